@@ -0,0 +1,73 @@
+//! Stream throughput with and without buffer pooling.
+//!
+//! Each iteration runs a batch of complete streams (parser setup, SSE parsing,
+//! content accumulation, teardown), the per-request lifecycle a busy service
+//! repeats thousands of times. Run with `cargo bench --bench streaming`.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use futures::executor::block_on;
+use rust_ai_sdk::providers::cerebras::CerebrasParser;
+use rust_ai_sdk::{pool, CompletionStream, Error};
+use std::fmt::Write;
+
+const STREAMS: usize = 64;
+
+/// Body of one chat-completions stream, split into network-sized reads.
+fn body(tokens: usize) -> Vec<Bytes> {
+    let mut sse = String::new();
+    for i in 0..tokens {
+        let _ = write!(
+            sse,
+            "data: {{\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\"token{i} \"}}}}]}}\n\n"
+        );
+    }
+    sse.push_str("data: [DONE]\n\n");
+    sse.into_bytes()
+        .chunks(1400)
+        .map(Bytes::copy_from_slice)
+        .collect()
+}
+
+fn run_streams(bodies: Vec<Vec<Bytes>>) -> usize {
+    block_on(async {
+        let mut total = 0;
+        for body in bodies {
+            let inner = futures::stream::iter(body.into_iter().map(Ok::<_, Error>));
+            let mut stream = CompletionStream::new(
+                inner,
+                Box::new(CerebrasParser::new()),
+                "llama3.1-8b".to_string(),
+            );
+            while let Some(chunk) = stream.next().await {
+                chunk.unwrap();
+            }
+            total += stream.current_content().len();
+        }
+        total
+    })
+}
+
+fn bench_pooling(c: &mut Criterion) {
+    // Short streams are dominated by per-stream setup, long ones by per-token work
+    for tokens in [4, 256] {
+        let body = body(tokens);
+        let mut group = c.benchmark_group(format!("streams/{tokens}_tokens"));
+        group.throughput(Throughput::Elements((STREAMS * tokens) as u64));
+
+        for (name, enabled) in [("unpooled", false), ("pooled", true)] {
+            pool::set_enabled(enabled);
+            group.bench_function(name, |b| {
+                b.iter_batched(
+                    || vec![body.clone(); STREAMS],
+                    run_streams,
+                    BatchSize::SmallInput,
+                );
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_pooling);
+criterion_main!(benches);
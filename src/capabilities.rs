@@ -0,0 +1,130 @@
+//! Model capability metadata.
+//!
+//! Answers "can this model do X?" before a request is made, so callers can
+//! route requests or disable UI features per model.
+
+use serde::{Deserialize, Serialize};
+
+/// Features and limits of a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ModelCapabilities {
+    /// Function/tool calling.
+    pub supports_tools: bool,
+    /// Image inputs.
+    pub supports_vision: bool,
+    /// Schema-constrained output (JSON schema / structured outputs).
+    pub supports_structured_output: bool,
+    /// Prompt caching (explicit or automatic).
+    pub supports_caching: bool,
+    /// Maximum output tokens per request, if known.
+    pub max_output_tokens: Option<u32>,
+    /// Total context window in tokens, if known.
+    pub context_window: Option<u32>,
+}
+
+impl ModelCapabilities {
+    /// No features and unknown limits.
+    pub const fn new() -> Self {
+        Self {
+            supports_tools: false,
+            supports_vision: false,
+            supports_structured_output: false,
+            supports_caching: false,
+            max_output_tokens: None,
+            context_window: None,
+        }
+    }
+
+    pub const fn with_tools(mut self) -> Self {
+        self.supports_tools = true;
+        self
+    }
+
+    pub const fn with_vision(mut self) -> Self {
+        self.supports_vision = true;
+        self
+    }
+
+    pub const fn with_structured_output(mut self) -> Self {
+        self.supports_structured_output = true;
+        self
+    }
+
+    pub const fn with_caching(mut self) -> Self {
+        self.supports_caching = true;
+        self
+    }
+
+    /// Set the context window and output token limits.
+    pub const fn with_limits(mut self, context_window: u32, max_output_tokens: u32) -> Self {
+        self.context_window = Some(context_window);
+        self.max_output_tokens = Some(max_output_tokens);
+        self
+    }
+
+    /// Whether these capabilities cover `required`: every feature it enables, and
+    /// limits at least as large as its (known) limits.
+    pub fn satisfies(&self, required: &ModelCapabilities) -> bool {
+        fn at_least(have: Option<u32>, need: Option<u32>) -> bool {
+            match (have, need) {
+                (_, None) => true,
+                (Some(have), Some(need)) => have >= need,
+                (None, Some(_)) => false,
+            }
+        }
+
+        (self.supports_tools || !required.supports_tools)
+            && (self.supports_vision || !required.supports_vision)
+            && (self.supports_structured_output || !required.supports_structured_output)
+            && (self.supports_caching || !required.supports_caching)
+            && at_least(self.max_output_tokens, required.max_output_tokens)
+            && at_least(self.context_window, required.context_window)
+    }
+}
+
+/// Capabilities shared by every model of a provider.
+///
+/// Used for models missing from the [`ModelRegistry`](crate::models::ModelRegistry);
+/// limits are unknown. Returns `None` for unknown providers.
+pub fn provider_defaults(provider: &str) -> Option<ModelCapabilities> {
+    let caps = match provider {
+        "claude" => ModelCapabilities::new()
+            .with_tools()
+            .with_vision()
+            .with_caching(),
+        "openai" | "gemini" => ModelCapabilities::new()
+            .with_tools()
+            .with_vision()
+            .with_structured_output()
+            .with_caching(),
+        "cerebras" | "deepseek" | "huggingface" | "openai-compatible" => {
+            ModelCapabilities::new().with_tools()
+        }
+        "ai21" => ModelCapabilities::new()
+            .with_tools()
+            .with_structured_output(),
+        // vLLM constrains output through guided decoding
+        "vllm" => ModelCapabilities::new()
+            .with_tools()
+            .with_structured_output(),
+        _ => return None,
+    };
+    Some(caps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_defaults() {
+        let caps = provider_defaults("openai").unwrap();
+        assert!(caps.supports_structured_output);
+        assert_eq!(caps.context_window, None);
+
+        assert!(!provider_defaults("cerebras").unwrap().supports_vision);
+        assert!(provider_defaults("nope").is_none());
+    }
+}
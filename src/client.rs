@@ -1,23 +1,126 @@
 //! HTTP client with retry logic and request builders.
 
-use crate::error::Error;
-use crate::providers::{get_provider_with_base_url, Provider, RequestConfig, ToolChoice};
-use crate::stream::CompletionStream;
+use crate::capabilities::ModelCapabilities;
+#[cfg(feature = "config")]
+use crate::config::ConfigFile;
+use crate::cost::{ModelPricing, PricingRegistry, RequestOutcome};
+use crate::error::{request_id_from_headers, Error, ErrorDetails, ValidationAttempt};
+use crate::eventstream::EventStreamDecoder;
+use crate::lifecycle::{InFlight, KillSwitch, Lifecycle};
+#[cfg(feature = "prometheus")]
+use crate::metrics::{Metrics, RequestMetrics};
+use crate::models::ModelRegistry;
+use crate::providers::claude::{ClaudePlatform, ClaudeProvider};
+use crate::providers::gemini::GeminiProvider;
+use crate::providers::openai::OpenAIProvider;
+use crate::providers::{
+    continue_prefill, get_embedding_provider_with_base_url, get_provider_with_base_url, merge_json,
+    AudioFormat, AudioOutput, BodyTemplate, EmbeddingConfig, EmbeddingInputType, EmbeddingProvider,
+    GuidedDecoding, Provider, ReasoningEffort, RequestBody, RequestConfig, ResponseFormat,
+    ServerTool, StreamFraming, ToolChoice,
+};
+use crate::ratelimit::{Priority, RateLimit, RateLimiter};
+use crate::sanitize::Sanitizer;
+use crate::sleep::{Sleeper, TokioSleeper};
+use crate::stream::{CompletionStream, RawStream};
+use crate::tenant::{Tenant, Tenants};
+use crate::tokens::{self, TruncateStrategy};
+use crate::transport::{
+    EncryptedTransport, HttpRequest, HttpResponse, HttpTransport, PayloadCipher, ReqwestTransport,
+};
 use crate::types::*;
-use reqwest::header::{HeaderMap, RETRY_AFTER};
+use crate::usage::{UsageRecorder, UsageStore};
+use crate::warning::Warning;
+use futures::future::{select, Either};
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::env;
+use std::future::Future;
+use std::pin::pin;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::time::sleep;
+use std::time::{Duration, Instant};
 
 /// Main client for making LLM API requests.
 #[derive(Clone)]
 pub struct Client {
-    http: reqwest::Client,
+    transport: Arc<dyn HttpTransport>,
+    sleeper: Arc<dyn Sleeper>,
     api_keys: Arc<HashMap<String, String>>,
     base_urls: Arc<HashMap<String, String>>,
+    query_params: Arc<HashMap<String, Vec<(String, String)>>>,
+    endpoints: Arc<HashMap<String, String>>,
+    stream_endpoints: Arc<HashMap<String, String>>,
+    models: Arc<ModelRegistry>,
+    presets: Arc<HashMap<String, Preset>>,
+    rate_limiters: Arc<HashMap<String, Arc<RateLimiter>>>,
+    usage_store: Option<Arc<dyn UsageStore>>,
+    pricing: Arc<PricingRegistry>,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<Metrics>,
     config: ClientConfig,
+    hooks: Hooks,
+    context_guard: Option<ContextGuard>,
+    sanitizer: Option<Sanitizer>,
+    dedup_streams: bool,
+    lifecycle: Arc<Lifecycle>,
+    tenants: Arc<Tenants>,
+    // Set on handles from `for_tenant`
+    tenant: Option<Arc<Tenant>>,
+}
+
+/// Named request configuration applied to a fresh [`RequestBuilder`].
+pub type Preset = Arc<dyn for<'a> Fn(RequestBuilder<'a>) -> RequestBuilder<'a> + Send + Sync>;
+
+/// Callback invoked before each retry with the failed attempt number, its error, and the delay.
+pub type RetryHook = Arc<dyn Fn(u32, &Error, Duration) + Send + Sync>;
+
+/// Callback invoked when a request fails terminally.
+pub type ErrorHook = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// Callback invoked with conditions that don't fail the request.
+pub type WarningHook = Arc<dyn Fn(&Warning) + Send + Sync>;
+
+/// What to do when a request's estimated input doesn't fit the model's context window.
+#[derive(Clone)]
+pub enum ContextGuard {
+    /// Fail with [`Error::ContextLengthExceeded`] without sending.
+    Reject,
+    /// Pass the error to the hook and send anyway, e.g. to log near misses.
+    Warn(ErrorHook),
+}
+
+/// Callback invoked with a request's usage so far whenever it changes.
+pub type UsageHook = Arc<dyn Fn(&Usage) + Send + Sync>;
+
+/// Callback invoked with the time since a stream last received data.
+pub type StallHook = Arc<dyn Fn(Duration) + Send + Sync>;
+
+/// Observer callbacks registered on the client.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    pub(crate) on_retry: Option<RetryHook>,
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) on_warning: Option<WarningHook>,
+}
+
+impl Hooks {
+    /// Notify the error hook and hand the error back.
+    pub(crate) fn report_error(&self, error: Error) -> Error {
+        if let Some(on_error) = &self.on_error {
+            on_error(&error);
+        }
+        error
+    }
+
+    /// Notify the warning hook.
+    pub(crate) fn warn(&self, warning: &Warning) {
+        if let Some(on_warning) = &self.on_warning {
+            on_warning(warning);
+        }
+    }
 }
 
 /// Client configuration.
@@ -33,6 +136,59 @@ pub struct ClientConfig {
     pub max_backoff: Duration,
     /// Backoff multiplier.
     pub backoff_multiplier: f32,
+    /// Minimum backoff after the provider reports it is overloaded.
+    pub overloaded_backoff: Duration,
+    /// Randomize each backoff by ±15% so clients that failed together don't
+    /// retry together.
+    pub jitter: bool,
+    /// Maximum idle connections kept in the pool per host.
+    pub pool_max_idle_per_host: usize,
+    /// How long an idle pooled connection is kept (`None` keeps it indefinitely).
+    pub pool_idle_timeout: Option<Duration>,
+    /// Interval between HTTP/2 keep-alive pings (`None` disables them).
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// How long to wait for a keep-alive ping to be acknowledged before closing
+    /// the connection (`None` uses the HTTP stack's default).
+    pub http2_keep_alive_timeout: Option<Duration>,
+    /// Also ping connections without open streams.
+    pub http2_keep_alive_while_idle: bool,
+    /// Idle time before TCP keepalive probes are sent (`None` disables them).
+    pub tcp_keepalive: Option<Duration>,
+    /// Preferred HTTP protocol version.
+    pub http_version: HttpVersion,
+    /// Platform serving `claude/...` models.
+    pub claude_platform: ClaudePlatform,
+    /// Drive `gemini/...` models through Gemini's OpenAI-compatible endpoint.
+    pub gemini_openai_compat: bool,
+    /// Parameters every request starts from, e.g. a default `max_tokens`.
+    pub defaults: RequestConfig,
+}
+
+impl ClientConfig {
+    /// Defaults without backoff jitter, so retries happen at exact, repeatable
+    /// delays.
+    ///
+    /// Backoff sleeps on tokio's clock: under `#[tokio::test(start_paused = true)]`
+    /// retries complete instantly while `tokio::time::Instant` still advances by
+    /// the full delays.
+    pub fn deterministic() -> Self {
+        Self {
+            jitter: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// HTTP protocol version preference.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// Negotiate via ALPN (HTTP/2 when the server supports it).
+    #[default]
+    Auto,
+    /// Only use HTTP/1.1.
+    Http1Only,
+    /// Use HTTP/2 without negotiation (prior knowledge), e.g. for h2c servers.
+    Http2PriorKnowledge,
 }
 
 impl Default for ClientConfig {
@@ -43,16 +199,68 @@ impl Default for ClientConfig {
             retry_backoff: Duration::from_millis(500),
             max_backoff: Duration::from_secs(30),
             backoff_multiplier: 2.0,
+            overloaded_backoff: Duration::from_secs(2),
+            jitter: true,
+            pool_max_idle_per_host: 10,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            http2_keep_alive_interval: None,
+            http2_keep_alive_timeout: None,
+            http2_keep_alive_while_idle: false,
+            tcp_keepalive: Some(Duration::from_secs(15)),
+            http_version: HttpVersion::Auto,
+            claude_platform: ClaudePlatform::Anthropic,
+            gemini_openai_compat: false,
+            defaults: RequestConfig::default(),
         }
     }
 }
 
+/// Shortest silence [`RequestBuilder::on_stall`] reports.
+const MIN_STALL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Standard `(provider, API key, base URL)` environment variables.
+const ENV_VARS: &[(&str, &str, &str)] = &[
+    ("ai21", "AI21_API_KEY", "AI21_BASE_URL"),
+    ("cerebras", "CEREBRAS_API_KEY", "CEREBRAS_BASE_URL"),
+    ("openai", "OPENAI_API_KEY", "OPENAI_BASE_URL"),
+    ("claude", "ANTHROPIC_API_KEY", "ANTHROPIC_BASE_URL"),
+    ("gemini", "GEMINI_API_KEY", "GEMINI_BASE_URL"),
+    ("deepseek", "DEEPSEEK_API_KEY", "DEEPSEEK_BASE_URL"),
+    ("huggingface", "HF_TOKEN", "HF_BASE_URL"),
+    ("vllm", "VLLM_API_KEY", "VLLM_BASE_URL"),
+    (
+        "openai-compatible",
+        "OPENAI_COMPATIBLE_API_KEY",
+        "OPENAI_COMPATIBLE_BASE_URL",
+    ),
+    ("voyage", "VOYAGE_API_KEY", "VOYAGE_BASE_URL"),
+    ("jina", "JINA_API_KEY", "JINA_BASE_URL"),
+];
+
 /// Builder for Client.
 pub struct ClientBuilder {
     api_keys: HashMap<String, String>,
     base_urls: HashMap<String, String>,
+    query_params: HashMap<String, Vec<(String, String)>>,
+    endpoints: HashMap<String, String>,
+    stream_endpoints: HashMap<String, String>,
+    models: ModelRegistry,
+    aliases: HashMap<String, String>,
+    presets: HashMap<String, Preset>,
+    rate_limits: HashMap<String, RateLimit>,
+    usage_store: Option<Arc<dyn UsageStore>>,
+    pricing: PricingRegistry,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<Metrics>,
     config: ClientConfig,
+    hooks: Hooks,
+    context_guard: Option<ContextGuard>,
+    sanitizer: Option<Sanitizer>,
+    dedup_streams: bool,
     http_builder: reqwest::ClientBuilder,
+    transport: Option<Arc<dyn HttpTransport>>,
+    cipher: Option<Arc<dyn PayloadCipher>>,
+    sleeper: Option<Arc<dyn Sleeper>>,
 }
 
 impl ClientBuilder {
@@ -61,11 +269,26 @@ impl ClientBuilder {
         Self {
             api_keys: HashMap::new(),
             base_urls: HashMap::new(),
+            query_params: HashMap::new(),
+            endpoints: HashMap::new(),
+            stream_endpoints: HashMap::new(),
+            models: ModelRegistry::new(),
+            aliases: HashMap::new(),
+            presets: HashMap::new(),
+            rate_limits: HashMap::new(),
+            usage_store: None,
+            pricing: PricingRegistry::new(),
+            #[cfg(feature = "prometheus")]
+            metrics: None,
             config: ClientConfig::default(),
-            http_builder: reqwest::Client::builder()
-                .pool_max_idle_per_host(10)
-                .pool_idle_timeout(Duration::from_secs(90))
-                .tcp_nodelay(true),
+            hooks: Hooks::default(),
+            context_guard: None,
+            sanitizer: None,
+            dedup_streams: false,
+            http_builder: reqwest::Client::builder().tcp_nodelay(true),
+            transport: None,
+            cipher: None,
+            sleeper: None,
         }
     }
 
@@ -81,6 +304,57 @@ impl ClientBuilder {
         self
     }
 
+    /// Override the chat endpoint path appended to a provider's base URL, e.g.
+    /// for gateways that mount the Messages API under `/anthropic/v1/messages`.
+    ///
+    /// `{model}` in `path` is replaced by the model name. The path replaces the
+    /// provider's URL path; its query string (Gemini's `alt=sse` and `key`) is
+    /// kept. Applies to streaming requests too, unless
+    /// [`stream_endpoint`](Self::stream_endpoint) is set.
+    pub fn endpoint(mut self, provider: &str, path: impl Into<String>) -> Self {
+        self.endpoints.insert(provider.to_string(), path.into());
+        self
+    }
+
+    /// Override the endpoint path of streaming requests only, for providers
+    /// whose streaming endpoint differs, e.g. Gemini's
+    /// `/models/{model}:streamGenerateContent`. Works like [`endpoint`](Self::endpoint).
+    pub fn stream_endpoint(mut self, provider: &str, path: impl Into<String>) -> Self {
+        self.stream_endpoints
+            .insert(provider.to_string(), path.into());
+        self
+    }
+
+    /// Add a query parameter to every request URL of a provider, e.g. Azure's
+    /// `api-version` or routing hints required by a gateway.
+    pub fn query_param(
+        mut self,
+        provider: &str,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.query_params
+            .entry(provider.to_string())
+            .or_default()
+            .push((key.into(), value.into()));
+        self
+    }
+
+    /// Resolve `alias` to `target` (both `provider/model`) on every request.
+    ///
+    /// Aliases are added to the model registry when the client is built, so
+    /// they survive a later [`model_registry`](Self::model_registry).
+    pub fn alias(mut self, alias: impl Into<String>, target: impl Into<String>) -> Self {
+        self.aliases.insert(alias.into(), target.into());
+        self
+    }
+
+    /// Replace the bundled model metadata registry.
+    pub fn model_registry(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
     /// Set request timeout.
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.config.timeout = timeout;
@@ -99,39 +373,409 @@ impl ClientBuilder {
         self
     }
 
-    /// Load API keys from environment variables.
-    pub fn from_env(mut self) -> Self {
-        let env_mappings = [
-            ("cerebras", "CEREBRAS_API_KEY"),
-            ("openai", "OPENAI_API_KEY"),
-            ("anthropic", "ANTHROPIC_API_KEY"),
-            ("gemini", "GEMINI_API_KEY"),
-        ];
+    /// Set the minimum backoff used after the provider reports it is overloaded.
+    pub fn overloaded_backoff(mut self, backoff: Duration) -> Self {
+        self.config.overloaded_backoff = backoff;
+        self
+    }
+
+    /// Back off by exactly the configured delays, without jitter (see
+    /// [`ClientConfig::deterministic`]).
+    pub fn deterministic(mut self) -> Self {
+        self.config.jitter = false;
+        self
+    }
+
+    /// Register a callback invoked before each retry.
+    ///
+    /// Receives the failed attempt number (starting at 1), the error, and the backoff delay.
+    pub fn on_retry(
+        mut self,
+        hook: impl Fn(u32, &Error, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_retry = Some(Arc::new(hook));
+        self
+    }
+
+    /// Register a named request preset, applied by [`Client::stream_with_preset`] and
+    /// [`Client::complete_with_preset`].
+    ///
+    /// ```no_run
+    /// # use rust_ai_sdk::ClientBuilder;
+    /// let client = ClientBuilder::new()
+    ///     .preset("creative", |b| b.temperature(1.0).top_p(0.95))
+    ///     .build();
+    /// ```
+    pub fn preset<F>(mut self, name: impl Into<String>, preset: F) -> Self
+    where
+        F: for<'a> Fn(RequestBuilder<'a>) -> RequestBuilder<'a> + Send + Sync + 'static,
+    {
+        self.presets.insert(name.into(), Arc::new(preset));
+        self
+    }
+
+    /// Limit request rate and concurrency for a provider.
+    ///
+    /// Requests over the limit fail with `Error::RateLimited` before reaching the
+    /// provider, or wait in line when the limit has a queue
+    /// ([`RateLimit::with_queue`]). [`build`](Self::build) fails with
+    /// `Error::Config` for a rate of 0 requests per minute.
+    pub fn rate_limit(mut self, provider: &str, limit: RateLimit) -> Self {
+        self.rate_limits.insert(provider.to_string(), limit);
+        self
+    }
+
+    /// Record every request's usage and cost in `store`.
+    ///
+    /// Records are written in the background once a request completes or a stream
+    /// ends; store failures are reported to the [`on_error`](Self::on_error) hook.
+    pub fn usage_store(mut self, store: impl UsageStore + 'static) -> Self {
+        self.usage_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Maintain Prometheus metrics for every request (`prometheus` feature).
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Replace the bundled pricing used to cost recorded usage.
+    pub fn pricing(mut self, pricing: PricingRegistry) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Price `model` for this client only, on top of the bundled prices; a
+    /// `model` ending in `*` prices every model it prefixes.
+    pub fn price(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.pricing.set(model, pricing);
+        self
+    }
+
+    /// Register a callback invoked when a request or stream fails terminally.
+    pub fn on_error(mut self, hook: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.hooks.on_error = Some(Arc::new(hook));
+        self
+    }
+
+    /// Register a callback for conditions that don't fail the request (see [`Warning`]).
+    pub fn on_warning(mut self, hook: impl Fn(&Warning) + Send + Sync + 'static) -> Self {
+        self.hooks.on_warning = Some(Arc::new(hook));
+        self
+    }
+
+    /// Check the estimated input size of each request against the model's
+    /// context window minus `max_tokens` before sending it.
+    ///
+    /// Estimates come from [`tokens::estimate_request`]; models without a known
+    /// context window are not checked.
+    pub fn context_guard(mut self, guard: ContextGuard) -> Self {
+        self.context_guard = Some(guard);
+        self
+    }
+
+    /// Clean the message text of each request with `sanitizer` before it is
+    /// serialized, e.g. for scraped or extracted documents.
+    pub fn sanitize(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitizer = Some(sanitizer);
+        self
+    }
+
+    /// Drop stream events that a gateway resends after reconnecting upstream,
+    /// so accumulated content isn't doubled.
+    ///
+    /// Resent events are recognized by their OpenAI `sequence_number` or their
+    /// SSE `id:`; events carrying neither can't be told apart and pass through.
+    pub fn dedup_streams(mut self) -> Self {
+        self.dedup_streams = true;
+        self
+    }
+
+    /// Set maximum idle connections kept in the pool per host.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.config.pool_max_idle_per_host = max;
+        self
+    }
+
+    /// Set how long idle pooled connections are kept (`None` keeps them indefinitely).
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.pool_idle_timeout = timeout;
+        self
+    }
+
+    /// Set the interval between HTTP/2 keep-alive pings.
+    ///
+    /// Pings keep a stream's connection busy while a reasoning model thinks
+    /// silently for minutes, so load balancers and NATs don't drop it as idle.
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.config.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Set how long a keep-alive ping may go unacknowledged before the
+    /// connection is closed.
+    pub fn http2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.config.http2_keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Also send keep-alive pings on pooled connections without open streams.
+    pub fn http2_keep_alive_while_idle(mut self, enabled: bool) -> Self {
+        self.config.http2_keep_alive_while_idle = enabled;
+        self
+    }
+
+    /// Set the idle time before TCP keepalive probes are sent (default 15s,
+    /// `None` disables them), for HTTP/1.1 connections that can't ping.
+    pub fn tcp_keepalive(mut self, idle: Option<Duration>) -> Self {
+        self.config.tcp_keepalive = idle;
+        self
+    }
+
+    /// Set the preferred HTTP protocol version.
+    pub fn http_version(mut self, version: HttpVersion) -> Self {
+        self.config.http_version = version;
+        self
+    }
+
+    /// Route `claude/...` models to Amazon Bedrock or Google Vertex AI instead of
+    /// the Anthropic API. The `claude` API key is then sent as a `Bearer` token.
+    pub fn claude_platform(mut self, platform: ClaudePlatform) -> Self {
+        self.config.claude_platform = platform;
+        self
+    }
+
+    /// Send `gemini/...` requests to Gemini's OpenAI-compatible endpoint
+    /// (`/v1beta/openai/chat/completions`) instead of the native API.
+    pub fn gemini_openai_compat(mut self, enabled: bool) -> Self {
+        self.config.gemini_openai_compat = enabled;
+        self
+    }
+
+    /// Send requests through a custom HTTP stack instead of `reqwest`.
+    ///
+    /// Pool and HTTP version settings only configure the default transport; a
+    /// custom one applies its own. The timeout comes with each request
+    /// ([`HttpRequest::timeout`]).
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Encrypt request bodies and decrypt response bodies with `cipher`, for
+    /// routing through a relay gateway that shouldn't read them.
+    ///
+    /// Wraps the transport, default or custom, in an [`EncryptedTransport`]:
+    /// the client's own logic (retries, parsing, usage) sees plaintext.
+    pub fn encryption(mut self, cipher: impl PayloadCipher + 'static) -> Self {
+        self.cipher = Some(Arc::new(cipher));
+        self
+    }
+
+    /// Wait out retry backoffs with a custom timer instead of `tokio::time::sleep`,
+    /// e.g. one that records the delays and returns at once in tests.
+    pub fn sleeper(mut self, sleeper: impl Sleeper + 'static) -> Self {
+        self.sleeper = Some(Arc::new(sleeper));
+        self
+    }
+
+    /// Load API keys and base URLs from the standard environment variables,
+    /// e.g. `OPENAI_API_KEY` and `OPENAI_BASE_URL`.
+    pub fn from_env(self) -> Self {
+        self.from_env_with("")
+    }
 
-        for (provider, env_var) in env_mappings {
-            if let Ok(key) = env::var(env_var) {
-                self.api_keys.insert(provider.to_string(), key);
+    /// Like [`from_env`](Self::from_env), with variable names prefixed by `prefix`,
+    /// e.g. `MYAPP_` for `MYAPP_OPENAI_API_KEY`.
+    pub fn from_env_with(mut self, prefix: &str) -> Self {
+        for (provider, key_var, url_var) in ENV_VARS {
+            if let Ok(key) = env::var(format!("{prefix}{key_var}")) {
+                self.api_keys.insert((*provider).to_string(), key);
+            }
+            if let Ok(url) = env::var(format!("{prefix}{url_var}")) {
+                self.base_urls.insert((*provider).to_string(), url);
             }
         }
+        self
+    }
+
+    /// Read a provider's API key from the environment variable `var`, if set,
+    /// e.g. `MYAPP_OPENAI_KEY`.
+    pub fn api_key_env(mut self, provider: &str, var: &str) -> Self {
+        if let Ok(key) = env::var(var) {
+            self.api_keys.insert(provider.to_string(), key);
+        }
+        self
+    }
 
+    /// Read a provider's base URL from the environment variable `var`, if set.
+    pub fn base_url_env(mut self, provider: &str, var: &str) -> Self {
+        if let Ok(url) = env::var(var) {
+            self.base_urls.insert(provider.to_string(), url);
+        }
         self
     }
 
+    /// Start from a TOML or YAML configuration file (`config` feature); see
+    /// [`config`](crate::config) for the format.
+    ///
+    /// API keys come from the standard environment variables ([`from_env`](Self::from_env))
+    /// unless a provider names its own with `api_key_env`.
+    #[cfg(feature = "config")]
+    pub fn from_config_path(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        Self::new()
+            .from_env()
+            .config_file(ConfigFile::from_path(path)?)
+    }
+
+    /// Apply the settings of a configuration file (`config` feature).
+    ///
+    /// Fails if a provider's `api_key_env` variable is not set.
+    #[cfg(feature = "config")]
+    pub fn config_file(mut self, file: ConfigFile) -> Result<Self, Error> {
+        use crate::config::secs;
+
+        if let Some(timeout) = file.timeout_secs {
+            self.config.timeout = secs("timeout_secs", timeout)?;
+        }
+        let retry = file.retry;
+        if let Some(max_retries) = retry.max_retries {
+            self.config.max_retries = max_retries;
+        }
+        if let Some(backoff) = retry.backoff_secs {
+            self.config.retry_backoff = secs("backoff_secs", backoff)?;
+        }
+        if let Some(max_backoff) = retry.max_backoff_secs {
+            self.config.max_backoff = secs("max_backoff_secs", max_backoff)?;
+        }
+        if let Some(multiplier) = retry.backoff_multiplier {
+            self.config.backoff_multiplier = multiplier;
+        }
+        if let Some(backoff) = retry.overloaded_backoff_secs {
+            self.config.overloaded_backoff = secs("overloaded_backoff_secs", backoff)?;
+        }
+        if let Some(jitter) = retry.jitter {
+            self.config.jitter = jitter;
+        }
+
+        for (provider, settings) in file.providers {
+            if let Some(var) = settings.api_key_env {
+                let key = env::var(&var).map_err(|_| {
+                    Error::Config(format!(
+                        "environment variable {var} for {provider} is not set"
+                    ))
+                })?;
+                self.api_keys.insert(provider.clone(), key);
+            }
+            if let Some(url) = settings.base_url {
+                self.base_urls.insert(provider.clone(), url);
+            }
+            if let Some(path) = settings.endpoint {
+                self.endpoints.insert(provider.clone(), path);
+            }
+            if let Some(path) = settings.stream_endpoint {
+                self.stream_endpoints.insert(provider.clone(), path);
+            }
+            if !settings.query.is_empty() {
+                self.query_params
+                    .entry(provider)
+                    .or_default()
+                    .extend(settings.query);
+            }
+        }
+        self.aliases.extend(file.aliases);
+        for (model, pricing) in file.pricing {
+            self.pricing.set(model, pricing);
+        }
+        Ok(self)
+    }
+
     /// Build the client.
     pub fn build(self) -> Result<Client, Error> {
-        let http = self
-            .http_builder
-            .timeout(self.config.timeout)
-            .build()
-            .map_err(|e| Error::Config(e.to_string()))?;
+        if let Some(provider) = self.rate_limits.iter().find_map(|(provider, limit)| {
+            (limit.requests_per_minute == Some(0)).then_some(provider)
+        }) {
+            return Err(Error::Config(format!(
+                "rate limit for {provider}: requests_per_minute must be at least 1"
+            )));
+        }
+        let mut transport: Arc<dyn HttpTransport> = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(ReqwestTransport::new(Self::build_http(
+                self.http_builder,
+                &self.config,
+            )?)),
+        };
+        if let Some(cipher) = self.cipher {
+            transport = Arc::new(EncryptedTransport::new(transport, cipher));
+        }
+        let mut models = self.models;
+        for (alias, target) in self.aliases {
+            models.set_alias(alias, target);
+        }
 
         Ok(Client {
-            http,
+            transport,
+            sleeper: self.sleeper.unwrap_or_else(|| Arc::new(TokioSleeper)),
             api_keys: Arc::new(self.api_keys),
             base_urls: Arc::new(self.base_urls),
+            query_params: Arc::new(self.query_params),
+            endpoints: Arc::new(self.endpoints),
+            stream_endpoints: Arc::new(self.stream_endpoints),
+            models: Arc::new(models),
+            presets: Arc::new(self.presets),
+            rate_limiters: Arc::new(
+                self.rate_limits
+                    .into_iter()
+                    .map(|(provider, limit)| (provider, Arc::new(RateLimiter::new(limit))))
+                    .collect(),
+            ),
+            usage_store: self.usage_store,
+            pricing: Arc::new(self.pricing),
+            #[cfg(feature = "prometheus")]
+            metrics: self.metrics,
             config: self.config,
+            hooks: self.hooks,
+            context_guard: self.context_guard,
+            sanitizer: self.sanitizer,
+            dedup_streams: self.dedup_streams,
+            lifecycle: Arc::new(Lifecycle::new()),
+            tenants: Arc::new(Tenants::default()),
+            tenant: None,
         })
     }
+
+    /// Build the default transport's `reqwest` client from the connection settings.
+    fn build_http(
+        http_builder: reqwest::ClientBuilder,
+        config: &ClientConfig,
+    ) -> Result<reqwest::Client, Error> {
+        let mut http_builder = http_builder
+            .timeout(config.timeout)
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(config.pool_idle_timeout)
+            .tcp_keepalive(config.tcp_keepalive)
+            .http2_keep_alive_while_idle(config.http2_keep_alive_while_idle);
+
+        if let Some(interval) = config.http2_keep_alive_interval {
+            http_builder = http_builder.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = config.http2_keep_alive_timeout {
+            http_builder = http_builder.http2_keep_alive_timeout(timeout);
+        }
+
+        http_builder = match config.http_version {
+            HttpVersion::Auto => http_builder,
+            HttpVersion::Http1Only => http_builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => http_builder.http2_prior_knowledge(),
+        };
+
+        http_builder
+            .build()
+            .map_err(|e| Error::Config(e.to_string()))
+    }
 }
 
 impl Default for ClientBuilder {
@@ -152,27 +796,274 @@ impl Client {
     }
 
     /// Start building a streaming request.
-    pub fn stream<'a>(&'a self, model: &str, messages: &'a [Message]) -> RequestBuilder<'a> {
+    ///
+    /// Messages can be borrowed (`&messages`) or owned (`messages`); passing an owned
+    /// `Vec` keeps the builder free of borrows across `.await` points.
+    pub fn stream<'a>(
+        &'a self,
+        model: &str,
+        messages: impl Into<Cow<'a, [Message]>>,
+    ) -> RequestBuilder<'a> {
         RequestBuilder {
             client: self,
             model: model.to_string(),
-            messages,
-            config: RequestConfig::default(),
+            messages: messages.into(),
+            config: self.config.defaults.clone(),
+            priority: Priority::default(),
+            on_usage: None,
+            on_stall: None,
+            hedge: None,
+            base_url: None,
+            truncate: None,
+            trim_stop: false,
+            token_mode: false,
             streaming: true,
         }
     }
 
     /// Start building a non-streaming request.
-    pub fn complete<'a>(&'a self, model: &str, messages: &'a [Message]) -> RequestBuilder<'a> {
+    pub fn complete<'a>(
+        &'a self,
+        model: &str,
+        messages: impl Into<Cow<'a, [Message]>>,
+    ) -> RequestBuilder<'a> {
         RequestBuilder {
             client: self,
             model: model.to_string(),
-            messages,
-            config: RequestConfig::default(),
+            messages: messages.into(),
+            config: self.config.defaults.clone(),
+            priority: Priority::default(),
+            on_usage: None,
+            on_stall: None,
+            hedge: None,
+            base_url: None,
+            truncate: None,
+            trim_stop: false,
+            token_mode: false,
             streaming: false,
         }
     }
 
+    /// Start building a streaming request from a named preset.
+    ///
+    /// Builder calls made afterwards override the preset's values.
+    pub fn stream_with_preset<'a>(
+        &'a self,
+        preset: &str,
+        model: &str,
+        messages: impl Into<Cow<'a, [Message]>>,
+    ) -> Result<RequestBuilder<'a>, Error> {
+        let preset = self.get_preset(preset)?;
+        Ok(preset(self.stream(model, messages)))
+    }
+
+    /// Start building a non-streaming request from a named preset.
+    pub fn complete_with_preset<'a>(
+        &'a self,
+        preset: &str,
+        model: &str,
+        messages: impl Into<Cow<'a, [Message]>>,
+    ) -> Result<RequestBuilder<'a>, Error> {
+        let preset = self.get_preset(preset)?;
+        Ok(preset(self.complete(model, messages)))
+    }
+
+    /// Start building an embedding request.
+    pub fn embed<I, S>(&self, model: &str, inputs: I) -> EmbeddingRequestBuilder<'_>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        EmbeddingRequestBuilder {
+            client: self,
+            model: model.to_string(),
+            inputs: inputs.into_iter().map(Into::into).collect(),
+            config: EmbeddingConfig::default(),
+        }
+    }
+
+    /// Send a provider-native request body to a model's streaming endpoint and
+    /// return the SSE events as received, without provider interpretation.
+    ///
+    /// Useful for proxies and for event types the SDK doesn't model yet. `body`
+    /// is sent unchanged, so it must include everything the provider expects
+    /// (including the streaming flag where the endpoint needs one).
+    pub async fn stream_raw(
+        &self,
+        model: &str,
+        body: serde_json::Value,
+    ) -> Result<RawStream<impl futures::Stream<Item = Result<bytes::Bytes, Error>> + Unpin>, Error>
+    {
+        let model_id = ModelId::parse(self.resolve_model(model))?;
+        let provider = self.resolve_provider(&model_id.provider)?;
+        let api_key = self.get_api_key_for(provider.as_ref())?;
+        let url = self.chat_url(
+            provider.as_ref(),
+            &model_id.model,
+            provider.stream_url(&model_id.model, api_key),
+            true,
+        );
+        let headers = provider.headers(api_key);
+        let body = RequestBody::json(&body)?;
+
+        let mut in_flight = self.begin()?;
+        let resp = in_flight
+            .or_abort(self.send_with_retry(&url, &headers, &body, &mut RequestTimings::default()))
+            .await??;
+        let request_id = request_id_from_headers(&resp.headers);
+
+        let mut stream = RawStream::new(decode_body(resp, provider.stream_framing()));
+        stream.set_request_id(request_id);
+        stream.set_in_flight(in_flight);
+        Ok(stream)
+    }
+
+    /// Open connections to providers ahead of the first request, e.g. right after
+    /// a deploy, so it doesn't pay for DNS resolution and the TLS handshake.
+    ///
+    /// Takes provider names (`&["openai", "claude"]`) and sends a `HEAD` request
+    /// to each configured base URL, concurrently. Any HTTP response counts as
+    /// success; the first connection failure is returned.
+    pub async fn warmup(&self, providers: &[&str]) -> Result<(), Error> {
+        let urls = providers
+            .iter()
+            .map(|name| self.provider_base_url(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        futures::future::join_all(urls.iter().map(|url| self.transport.warmup(url)))
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Capabilities of a model (e.g. `"claude/claude-3-5-sonnet-20241022"`).
+    ///
+    /// Models missing from the model registry get their provider's defaults, with
+    /// unknown limits.
+    pub fn capabilities(&self, model: &str) -> Result<ModelCapabilities, Error> {
+        let model_id = ModelId::parse(self.resolve_model(model))?;
+        self.models
+            .capabilities(model)
+            .ok_or_else(|| Error::InvalidModel(format!("unknown provider: {}", model_id.provider)))
+    }
+
+    /// Resolve a model alias (e.g. `"claude/sonnet-latest"`) to its pinned model ID.
+    pub fn resolve_model<'a>(&'a self, model: &'a str) -> &'a str {
+        self.models.resolve(model)
+    }
+
+    /// Model metadata registry consulted by this client.
+    pub fn models(&self) -> &ModelRegistry {
+        &self.models
+    }
+
+    /// Stop accepting requests and wait up to `deadline` for in-flight ones to finish.
+    ///
+    /// New requests fail with [`Error::ShuttingDown`]. Requests and streams still
+    /// running at the deadline are cancelled and yield [`Error::Aborted`]. Applies to
+    /// every clone of this client. Returns `true` if all requests finished in time.
+    pub async fn shutdown(&self, deadline: Duration) -> bool {
+        self.lifecycle.shutdown(deadline).await
+    }
+
+    /// Handle that aborts every request of this client (and its clones) when tripped.
+    pub fn kill_switch(&self) -> KillSwitch {
+        KillSwitch::new(Arc::clone(&self.lifecycle))
+    }
+
+    /// A handle scoped to `tenant_id`, sharing this client's connections,
+    /// rate limits, and settings.
+    ///
+    /// Usage and cost of its requests accumulate in the tenant's [`Tenant`],
+    /// which every handle for the same ID shares; see [`tenant`](crate::tenant).
+    pub fn for_tenant(&self, tenant_id: &str) -> Client {
+        let mut client = self.clone();
+        client.tenant = Some(self.tenants.get(tenant_id));
+        client
+    }
+
+    /// Use `key` for `provider` on this handle only, e.g. a tenant's own key.
+    pub fn with_api_key(mut self, provider: &str, key: impl Into<String>) -> Client {
+        Arc::make_mut(&mut self.api_keys).insert(provider.to_string(), key.into());
+        self
+    }
+
+    /// A client sharing this one's connections, rate limits, and hooks, with
+    /// its settings changed by `overrides`, e.g. a shorter timeout or default
+    /// parameters for one component.
+    ///
+    /// ```no_run
+    /// # fn f(client: &rust_ai_sdk::Client) {
+    /// use std::time::Duration;
+    ///
+    /// let background = client.with_overrides(|cfg| {
+    ///     cfg.timeout = Duration::from_secs(600);
+    ///     cfg.max_retries = 8;
+    ///     cfg.defaults.temperature = Some(0.0);
+    /// });
+    /// # }
+    /// ```
+    ///
+    /// Connection settings (pool, keep-alive, HTTP version) belong to the shared
+    /// connections and are not changed.
+    pub fn with_overrides(&self, overrides: impl FnOnce(&mut ClientConfig)) -> Client {
+        let mut client = self.clone();
+        overrides(&mut client.config);
+        client
+    }
+
+    /// Tenant this handle is scoped to, if any.
+    pub fn tenant(&self) -> Option<&Tenant> {
+        self.tenant.as_deref()
+    }
+
+    /// Register a request, unless the client is shutting down or the tenant is over budget.
+    fn begin(&self) -> Result<InFlight, Error> {
+        if let Some(tenant) = &self.tenant {
+            tenant.check_budget()?;
+        }
+        self.lifecycle.begin()
+    }
+
+    /// Recorder for requests to `provider`, if a usage store is configured, the
+    /// handle is scoped to a tenant, or missing pricing should be warned about.
+    fn usage_recorder(&self, provider: &str) -> Option<UsageRecorder> {
+        if self.usage_store.is_none() && self.tenant.is_none() && self.hooks.on_warning.is_none() {
+            return None;
+        }
+        Some(UsageRecorder {
+            store: self.usage_store.clone(),
+            tenant: self.tenant.clone(),
+            pricing: Arc::clone(&self.pricing),
+            on_error: self.hooks.on_error.clone(),
+            on_warning: self.hooks.on_warning.clone(),
+            provider: provider.to_string(),
+        })
+    }
+
+    /// Prometheus metrics maintained by this client, if configured.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    #[cfg(feature = "prometheus")]
+    fn request_metrics(&self, model_id: &ModelId) -> Option<RequestMetrics> {
+        self.metrics.as_ref().map(|metrics| {
+            RequestMetrics::start(metrics, &self.pricing, &model_id.provider, &model_id.model)
+        })
+    }
+
+    /// Number of requests and open streams currently running.
+    pub fn in_flight(&self) -> usize {
+        self.lifecycle.in_flight()
+    }
+
+    fn get_preset(&self, name: &str) -> Result<&Preset, Error> {
+        self.presets
+            .get(name)
+            .ok_or_else(|| Error::Config(format!("unknown preset: {name}")))
+    }
+
     /// Get API key for a provider.
     fn get_api_key(&self, provider: &str) -> Result<&str, Error> {
         self.api_keys
@@ -181,6 +1072,97 @@ impl Client {
             .ok_or_else(|| Error::MissingApiKey(provider.to_string()))
     }
 
+    /// Get API key for a provider, allowing keyless providers to proceed without one.
+    fn get_api_key_for(&self, provider: &dyn Provider) -> Result<&str, Error> {
+        match self.get_api_key(provider.name()) {
+            Err(Error::MissingApiKey(_)) if !provider.requires_api_key() => Ok(""),
+            other => other,
+        }
+    }
+
+    /// Resolve the provider for a model's provider prefix.
+    fn resolve_provider(&self, name: &str) -> Result<Box<dyn Provider>, Error> {
+        self.resolve_provider_at(name, None)
+    }
+
+    /// Resolve the provider, sending to `base_url` instead of the configured one if given.
+    fn resolve_provider_at(
+        &self,
+        name: &str,
+        base_url: Option<&str>,
+    ) -> Result<Box<dyn Provider>, Error> {
+        let base_url = base_url.or_else(|| self.get_base_url(name));
+        if name == "claude" && self.config.claude_platform != ClaudePlatform::Anthropic {
+            return Ok(Box::new(ClaudeProvider::for_platform(
+                self.config.claude_platform.clone(),
+                base_url,
+            )));
+        }
+        if name == "gemini" && self.config.gemini_openai_compat {
+            let provider = GeminiProvider::new().with_openai_compat();
+            return Ok(Box::new(match base_url {
+                Some(url) => provider.with_base_url(url),
+                None => provider,
+            }));
+        }
+        if name == "openai-compatible" && base_url.is_none() {
+            return Err(Error::Config(
+                "openai-compatible needs a base URL; set it with ClientBuilder::base_url or OPENAI_COMPATIBLE_BASE_URL".to_string(),
+            ));
+        }
+        get_provider_with_base_url(name, base_url)
+            .ok_or_else(|| Error::InvalidModel(format!("unknown provider: {name}")))
+    }
+
+    /// Base URL of a chat or embedding provider.
+    fn provider_base_url(&self, name: &str) -> Result<String, Error> {
+        if let Ok(provider) = self.resolve_provider(name) {
+            return Ok(provider.base_url().to_string());
+        }
+        get_embedding_provider_with_base_url(name, self.get_base_url(name))
+            .map(|provider| provider.base_url().to_string())
+            .ok_or_else(|| Error::InvalidModel(format!("unknown provider: {name}")))
+    }
+
+    /// Final URL of a chat request: `url` as built by the provider, with the
+    /// path replaced when the endpoint is overridden, plus default query
+    /// parameters.
+    fn chat_url(&self, provider: &dyn Provider, model: &str, url: String, stream: bool) -> String {
+        let name = provider.name();
+        let path = self
+            .stream_endpoints
+            .get(name)
+            .filter(|_| stream)
+            .or_else(|| self.endpoints.get(name));
+        let url = match path {
+            Some(path) => {
+                let mut overridden =
+                    format!("{}{}", provider.base_url(), path.replace("{model}", model));
+                // Keep the provider's query, e.g. Gemini's `alt=sse` and `key`
+                if let Some((_, query)) = url.split_once('?') {
+                    overridden.push(if overridden.contains('?') { '&' } else { '?' });
+                    overridden.push_str(query);
+                }
+                overridden
+            }
+            None => url,
+        };
+        self.with_query(name, url)
+    }
+
+    /// Append the provider's default query parameters to `url`.
+    fn with_query(&self, provider: &str, mut url: String) -> String {
+        if let Some(params) = self.query_params.get(provider) {
+            for (key, value) in params {
+                url.push(if url.contains('?') { '&' } else { '?' });
+                url.push_str(&percent_encode(key));
+                url.push('=');
+                url.push_str(&percent_encode(value));
+            }
+        }
+        url
+    }
+
     /// Get custom base URL for a provider, if configured.
     fn get_base_url(&self, provider: &str) -> Option<&str> {
         self.base_urls
@@ -193,146 +1175,183 @@ impl Client {
         &self,
         provider: &dyn Provider,
         api_key: &str,
-        body: serde_json::Value,
+        config: &RequestConfig,
+        body: RequestBody,
         model: String,
+        timings: &mut RequestTimings,
     ) -> Result<
-        CompletionStream<impl futures::Stream<Item = Result<bytes::Bytes, reqwest::Error>> + Unpin>,
+        CompletionStream<impl futures::Stream<Item = Result<bytes::Bytes, Error>> + Unpin>,
         Error,
     > {
-        let url = provider.stream_url(&model, api_key);
-        let headers = provider.headers(api_key);
-
-        let mut attempt = 0;
-        let mut backoff = self.config.retry_backoff;
+        let url = self.chat_url(provider, &model, provider.stream_url(&model, api_key), true);
+        let headers = provider.request_headers(api_key, config);
+
+        let resp = self.send_with_retry(&url, &headers, &body, timings).await?;
+        let request_id = request_id_from_headers(&resp.headers);
+        let parser = provider.create_parser();
+
+        let mut stream =
+            CompletionStream::new(decode_body(resp, provider.stream_framing()), parser, model);
+        stream.set_request_id(request_id);
+        stream.set_error_hook(self.hooks.on_error.clone());
+        stream.set_warning_hook(self.hooks.on_warning.clone());
+        Ok(stream)
+    }
 
-        loop {
-            attempt += 1;
+    /// Execute a non-streaming request with retry, returning the result with
+    /// the provider's request id.
+    async fn execute_complete(
+        &self,
+        provider: &dyn Provider,
+        api_key: &str,
+        config: &RequestConfig,
+        body: RequestBody,
+        model: &str,
+        timings: &mut RequestTimings,
+    ) -> Result<(CompletionResult, Option<String>), Error> {
+        let url = self.chat_url(
+            provider,
+            model,
+            provider.complete_url(model, api_key),
+            false,
+        );
+        let headers = provider.request_headers(api_key, config);
+
+        let resp = self.send_with_retry(&url, &headers, &body, timings).await?;
+        let request_id = request_id_from_headers(&resp.headers);
+        let response_at = Instant::now();
+        let result = self
+            .parse_body(resp, |text| provider.parse_response(text))
+            .await;
+        timings.stream = response_at.elapsed();
+        Ok((result?, request_id))
+    }
 
-            let response = self
-                .http
-                .post(&url)
-                .headers(headers.clone())
-                .json(&body)
-                .send()
-                .await;
-
-            match response {
-                Ok(resp) => {
-                    let status = resp.status();
-
-                    if status.is_success() {
-                        let stream = resp.bytes_stream();
-                        let parser = provider.create_parser();
-                        return Ok(CompletionStream::new(Box::pin(stream), parser, model));
-                    }
-
-                    // Handle errors
-                    let error = self.handle_error_response(resp).await;
-
-                    if !error.is_retryable() || attempt >= self.config.max_retries {
-                        return Err(error);
-                    }
-
-                    // Extract retry-after if available
-                    if let Error::RateLimited {
-                        retry_after: Some(duration),
-                    } = &error
-                    {
-                        backoff = *duration;
-                    }
-                }
-                Err(e) => {
-                    if e.is_timeout() {
-                        if attempt >= self.config.max_retries {
-                            return Err(Error::Timeout);
-                        }
-                    } else if e.is_connect() {
-                        if attempt >= self.config.max_retries {
-                            return Err(Error::Http(e));
-                        }
-                    } else {
-                        return Err(Error::Http(e));
-                    }
-                }
-            }
-
-            // Exponential backoff with jitter
-            let jitter = fastrand::f32() * 0.3 + 0.85; // 0.85-1.15
-            let sleep_duration = Duration::from_secs_f32(backoff.as_secs_f32() * jitter);
-            sleep(sleep_duration).await;
-
-            backoff = Duration::from_secs_f32(
-                (backoff.as_secs_f32() * self.config.backoff_multiplier)
-                    .min(self.config.max_backoff.as_secs_f32()),
-            );
-        }
-    }
-
-    /// Execute a non-streaming request with retry.
-    async fn execute_complete(
+    /// Execute an embedding request with retry.
+    async fn execute_embed(
         &self,
-        provider: &dyn Provider,
+        provider: &dyn EmbeddingProvider,
         api_key: &str,
-        body: serde_json::Value,
+        body: RequestBody,
         model: &str,
-    ) -> Result<CompletionResult, Error> {
-        let url = provider.complete_url(model, api_key);
+    ) -> Result<EmbeddingResult, Error> {
+        let url = self.with_query(provider.name(), provider.embed_url(model));
         let headers = provider.headers(api_key);
 
+        let resp = self
+            .send_with_retry(&url, &headers, &body, &mut RequestTimings::default())
+            .await?;
+        self.parse_body(resp, |text| provider.parse_embed_response(text))
+            .await
+    }
+
+    /// Read a successful response body and parse it, attaching response details on failure.
+    async fn parse_body<T>(
+        &self,
+        resp: HttpResponse,
+        parse: impl FnOnce(&str) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let status = resp.status;
+        let resp_headers = resp.headers.clone();
+        let text = resp.text().await?;
+
+        parse(&text).map_err(|e| {
+            self.hooks
+                .report_error(e.with_details(ErrorDetails::from_response(
+                    status,
+                    &resp_headers,
+                    text,
+                )))
+        })
+    }
+
+    /// Send a request, retrying transient failures with exponential backoff.
+    ///
+    /// Returns the first successful response, recording the attempts in `timings`.
+    async fn send_with_retry(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        body: &RequestBody,
+        timings: &mut RequestTimings,
+    ) -> Result<HttpResponse, Error> {
         let mut attempt = 0;
         let mut backoff = self.config.retry_backoff;
 
+        let mut headers = headers.clone();
+        headers
+            .entry(CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static("application/json"));
+        let body = body.clone().into_bytes();
+
+        let first_sent = Instant::now();
         loop {
             attempt += 1;
 
+            let sent = Instant::now();
             let response = self
-                .http
-                .post(&url)
-                .headers(headers.clone())
-                .json(&body)
-                .send()
+                .transport
+                .send(HttpRequest {
+                    url: url.to_string(),
+                    headers: headers.clone(),
+                    body: body.clone(),
+                    timeout: self.config.timeout,
+                })
                 .await;
 
-            match response {
+            let (error, retryable) = match response {
                 Ok(resp) => {
-                    let status = resp.status();
-
-                    if status.is_success() {
-                        let text = resp.text().await.map_err(Error::Http)?;
-                        return provider.parse_response(&text);
+                    if resp.is_success() {
+                        timings.retries = attempt - 1;
+                        timings.retry_time = sent - first_sent;
+                        timings.time_to_first_byte = sent.elapsed();
+                        return Ok(resp);
                     }
 
+                    // Handle errors
                     let error = self.handle_error_response(resp).await;
-
-                    if !error.is_retryable() || attempt >= self.config.max_retries {
-                        return Err(error);
-                    }
-
-                    if let Error::RateLimited {
-                        retry_after: Some(duration),
-                    } = &error
-                    {
-                        backoff = *duration;
-                    }
+                    let retryable = error.is_retryable();
+                    (error, retryable)
                 }
                 Err(e) => {
-                    if e.is_timeout() {
-                        if attempt >= self.config.max_retries {
-                            return Err(Error::Timeout);
-                        }
-                    } else if e.is_connect() {
-                        if attempt >= self.config.max_retries {
-                            return Err(Error::Http(e));
-                        }
-                    } else {
-                        return Err(Error::Http(e));
-                    }
+                    // Connection failures never reached the provider
+                    let retryable = match &e {
+                        Error::Http(e) => e.is_connect(),
+                        Error::Transport { retryable, .. } => *retryable,
+                        e => e.is_retryable(),
+                    };
+                    (e, retryable)
                 }
+            };
+
+            if !retryable || attempt >= self.config.max_retries {
+                return Err(self.hooks.report_error(error));
+            }
+
+            // Extract retry-after if available
+            match &error {
+                Error::RateLimited {
+                    retry_after: Some(duration),
+                    ..
+                } => backoff = *duration,
+                // Overload clears slowly; back off harder than for a plain 5xx
+                Error::Overloaded { .. } => {
+                    backoff = backoff.max(self.config.overloaded_backoff);
+                }
+                _ => {}
             }
 
-            let jitter = fastrand::f32() * 0.3 + 0.85;
-            let sleep_duration = Duration::from_secs_f32(backoff.as_secs_f32() * jitter);
-            sleep(sleep_duration).await;
+            // Exponential backoff with jitter
+            let sleep_duration = if self.config.jitter {
+                let jitter = fastrand::f32() * 0.3 + 0.85; // 0.85-1.15
+                Duration::from_secs_f32(backoff.as_secs_f32() * jitter)
+            } else {
+                backoff
+            };
+            if let Some(on_retry) = &self.hooks.on_retry {
+                on_retry(attempt, &error, sleep_duration);
+            }
+            self.sleeper.sleep(sleep_duration).await;
 
             backoff = Duration::from_secs_f32(
                 (backoff.as_secs_f32() * self.config.backoff_multiplier)
@@ -342,33 +1361,59 @@ impl Client {
     }
 
     /// Convert error response to Error type.
-    async fn handle_error_response(&self, resp: reqwest::Response) -> Error {
-        let status = resp.status().as_u16();
-        let headers = resp.headers().clone();
+    async fn handle_error_response(&self, resp: HttpResponse) -> Error {
+        let status = resp.status;
+        let headers = resp.headers.clone();
 
         let body = resp.text().await.unwrap_or_default();
 
-        match status {
-            401 => Error::Unauthorized,
-            429 => {
-                let retry_after = parse_retry_after(&headers);
-                Error::RateLimited { retry_after }
-            }
-            500..=599 => Error::Server(status),
-            _ => {
-                // Try to extract error message from JSON
-                let message = serde_json::from_str::<serde_json::Value>(&body)
-                    .ok()
-                    .and_then(|v| {
-                        v["error"]["message"]
-                            .as_str()
-                            .map(std::string::ToString::to_string)
-                    })
-                    .unwrap_or(body);
-                Error::api(status, message)
-            }
+        let error = match status {
+            401 => Error::Unauthorized { details: None },
+            429 => Error::RateLimited {
+                retry_after: parse_retry_after(&headers),
+                details: None,
+            },
+            529 => Error::Overloaded {
+                message: extract_error_message(&body),
+                details: None,
+            },
+            500..=599 => Error::Server {
+                status,
+                details: None,
+            },
+            _ => Error::api(status, extract_error_message(&body)),
+        };
+
+        error.with_details(ErrorDetails::from_response(status, &headers, body))
+    }
+}
+
+/// Percent-encode a query string component.
+fn percent_encode(component: &str) -> String {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let mut out = String::with_capacity(component.len());
+    for byte in component.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(char::from(byte));
+        } else {
+            out.push('%');
+            out.push(char::from(HEX[usize::from(byte >> 4)]));
+            out.push(char::from(HEX[usize::from(byte & 0xF)]));
         }
     }
+    out
+}
+
+/// Extract the error message from a JSON error body, falling back to the raw body.
+fn extract_error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| {
+            v["error"]["message"]
+                .as_str()
+                .map(std::string::ToString::to_string)
+        })
+        .unwrap_or_else(|| body.to_string())
 }
 
 /// Parse Retry-After header.
@@ -382,16 +1427,50 @@ fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
     })
 }
 
+/// The contents of a Markdown code fence around `text`, or `text` trimmed.
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    text.strip_prefix("```")
+        .and_then(|fenced| fenced.strip_suffix("```"))
+        .map_or(text, |fenced| {
+            // Drop the info string, e.g. `json`
+            fenced
+                .split_once('\n')
+                .map_or(fenced, |(_, body)| body)
+                .trim()
+        })
+}
+
+/// Response body as SSE bytes, unwrapping AWS event stream frames if needed.
+fn decode_body(
+    resp: HttpResponse,
+    framing: StreamFraming,
+) -> impl futures::Stream<Item = Result<bytes::Bytes, Error>> + Unpin {
+    let mut decoder = (framing == StreamFraming::AwsEventStream).then(EventStreamDecoder::new);
+    Box::pin(resp.body.map(move |chunk| match &mut decoder {
+        Some(decoder) => chunk.map(|bytes| decoder.decode(&bytes)),
+        None => chunk,
+    }))
+}
+
 /// Builder for individual requests.
 pub struct RequestBuilder<'a> {
     client: &'a Client,
     model: String,
-    messages: &'a [Message],
+    messages: Cow<'a, [Message]>,
     config: RequestConfig,
+    priority: Priority,
+    on_usage: Option<UsageHook>,
+    on_stall: Option<(Duration, StallHook)>,
+    hedge: Option<Duration>,
+    base_url: Option<String>,
+    truncate: Option<TruncateStrategy>,
+    trim_stop: bool,
+    token_mode: bool,
     streaming: bool,
 }
 
-impl RequestBuilder<'_> {
+impl<'a> RequestBuilder<'a> {
     /// Set maximum tokens to generate.
     pub fn max_tokens(mut self, tokens: u32) -> Self {
         self.config.max_tokens = Some(tokens);
@@ -411,32 +1490,238 @@ impl RequestBuilder<'_> {
     }
 
     /// Set stop sequences.
+    ///
+    /// The one that ended the output is reported in
+    /// [`CompletionResult::stop_sequence`] where the provider says so (Claude,
+    /// vLLM) or echoes it at the end of the content.
     pub fn stop(mut self, sequences: Vec<String>) -> Self {
         self.config.stop = Some(sequences);
         self
     }
 
+    /// Remove a stop sequence echoed at the end of the final content, as some
+    /// OpenAI-compatible servers do. Streamed chunks are passed on unchanged.
+    pub fn trim_stop_sequence(mut self) -> Self {
+        self.trim_stop = true;
+        self
+    }
+
     /// Set tools for function calling.
     pub fn tools(mut self, tools: Vec<Tool>) -> Self {
         self.config.tools = Some(tools);
         self
     }
 
+    /// Offer a tool the provider runs itself, e.g. a web search. Calls and
+    /// results come back in [`CompletionResult::server_tool_calls`].
+    pub fn server_tool(mut self, tool: ServerTool) -> Self {
+        self.config
+            .server_tools
+            .get_or_insert_with(Vec::new)
+            .push(tool);
+        self
+    }
+
     /// Set tool choice.
     pub fn tool_choice(mut self, choice: ToolChoice) -> Self {
         self.config.tool_choice = Some(choice);
         self
     }
 
+    /// Allow or forbid several tool calls in one turn.
+    ///
+    /// Maps to `parallel_tool_calls` (OpenAI-compatible) and
+    /// `disable_parallel_tool_use` (Claude). Ignored by Gemini.
+    pub fn parallel_tool_calls(mut self, enabled: bool) -> Self {
+        self.config.parallel_tool_calls = Some(enabled);
+        self
+    }
+
     /// Set system message (for providers that support it separately).
     pub fn system(mut self, system: impl Into<String>) -> Self {
         self.config.system = Some(system.into());
         self
     }
 
-    /// Add extra provider-specific fields.
+    /// Constrain output to a JSON schema (vLLM `guided_json`).
+    pub fn guided_json(mut self, schema: serde_json::Value) -> Self {
+        self.config.guided_decoding = Some(GuidedDecoding::Json(schema));
+        self
+    }
+
+    /// Constrain output to a regular expression (vLLM `guided_regex`).
+    pub fn guided_regex(mut self, pattern: impl Into<String>) -> Self {
+        self.config.guided_decoding = Some(GuidedDecoding::Regex(pattern.into()));
+        self
+    }
+
+    /// Constrain output to one of the given choices (vLLM `guided_choice`).
+    pub fn guided_choice<I, S>(mut self, choices: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.guided_decoding = Some(GuidedDecoding::Choice(
+            choices.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Constrain output to a context-free grammar (vLLM `guided_grammar`).
+    pub fn guided_grammar(mut self, grammar: impl Into<String>) -> Self {
+        self.config.guided_decoding = Some(GuidedDecoding::Grammar(grammar.into()));
+        self
+    }
+
+    /// Generate `n` candidates server-side and return the best (vLLM `best_of`).
+    pub fn best_of(mut self, n: u32) -> Self {
+        self.config.best_of = Some(n);
+        self
+    }
+
+    /// Ground the answer in the given documents (AI21 Jamba `documents`).
+    pub fn documents(mut self, documents: Vec<Document>) -> Self {
+        self.config.documents = Some(documents);
+        self
+    }
+
+    /// Stream tool arguments as they are generated (Claude
+    /// `fine-grained-tool-streaming` beta).
+    ///
+    /// Arguments arrive in larger, unvalidated chunks and may be invalid JSON if
+    /// generation stops early, e.g. at `max_tokens`.
+    pub fn fine_grained_tool_streaming(mut self) -> Self {
+        self.config.fine_grained_tool_streaming = true;
+        self
+    }
+
+    /// Generate spoken output alongside text (`modalities: ["text", "audio"]`).
+    ///
+    /// Streams yield [`ChunkKind::Audio`] chunks with base64 audio; streaming
+    /// requires [`AudioFormat::Pcm16`]. OpenAI requests go through Chat Completions.
+    pub fn audio(mut self, voice: impl Into<String>, format: AudioFormat) -> Self {
+        self.config.audio = Some(AudioOutput {
+            voice: voice.into(),
+            format,
+        });
+        self
+    }
+
+    /// Constrain the output format, e.g. JSON mode or a JSON schema.
+    ///
+    /// Claude has no equivalent; requests there fail with [`Error::Config`].
+    pub fn response_format(mut self, format: ResponseFormat) -> Self {
+        self.config.response_format = Some(format);
+        self
+    }
+
+    /// Return log probabilities of the generated tokens with up to `top`
+    /// alternatives per position, in [`CompletionResult::logprobs`] and
+    /// [`StreamChunk::logprobs`]. Not supported by Claude.
+    pub fn logprobs(mut self, top: u8) -> Self {
+        self.config.logprobs = Some(top);
+        self
+    }
+
+    /// Set how long reasoning models think before answering: `reasoning_effort`
+    /// in Chat Completions bodies, `reasoning.effort` for the OpenAI Responses
+    /// API, and a thinking budget for Gemini. Not supported by Claude and AI21.
+    pub fn reasoning_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.config.reasoning_effort = Some(effort);
+        self
+    }
+
+    /// Add extra provider-specific fields (a JSON object). They're deep-merged into
+    /// the body the SDK builds, and into fields added before.
     pub fn extra(mut self, extra: serde_json::Value) -> Self {
-        self.config.extra = Some(extra);
+        match &mut self.config.extra {
+            Some(existing) => merge_json(existing, &extra),
+            None => self.config.extra = Some(extra),
+        }
+        self
+    }
+
+    /// Add an extra provider-specific field at a dot-separated `path`, e.g.
+    /// `extra_at("generationConfig.thinkingConfig", json!({"thinkingBudget": 1024}))`
+    /// for Gemini. The SDK's own fields along the path are kept; `value` is
+    /// deep-merged into whatever is already at the path.
+    pub fn extra_at(self, path: &str, value: serde_json::Value) -> Self {
+        let nested = path.rsplit('.').fold(value, |value, key| {
+            serde_json::Value::Object([(key.to_string(), value)].into_iter().collect())
+        });
+        self.extra(nested)
+    }
+
+    /// Send this request to `url` instead of the provider's configured base URL,
+    /// e.g. to canary a new gateway region or target a staging deployment.
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Set the queue priority used when the provider's rate limit is saturated.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Register a callback invoked with the accumulated usage whenever a chunk
+    /// updates it, e.g. to enforce a token budget during a long generation.
+    ///
+    /// Non-streaming requests invoke it once, with the final usage.
+    pub fn on_usage(mut self, hook: impl Fn(&Usage) + Send + Sync + 'static) -> Self {
+        self.on_usage = Some(Arc::new(hook));
+        self
+    }
+
+    /// Register a callback invoked when a stream receives nothing for `after`,
+    /// and again every `after` while the silence lasts.
+    ///
+    /// Keepalives (Claude `ping` events, SSE comments) count as activity, so a
+    /// slow but healthy generation doesn't trigger it. The stream keeps waiting;
+    /// the callback decides whether to show progress or abort. `after` is
+    /// raised to at least 100 ms so the callback can't fire in a busy loop.
+    pub fn on_stall(
+        mut self,
+        after: Duration,
+        hook: impl Fn(Duration) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_stall = Some((after.max(MIN_STALL_INTERVAL), Arc::new(hook)));
+        self
+    }
+
+    /// Send a second, identical request when the first hasn't produced its
+    /// first chunk (or, without streaming, its response) after `after`, and
+    /// keep whichever is first; the other is cancelled, and recorded as such
+    /// in the usage store.
+    ///
+    /// Trades the cost of duplicate requests for lower tail latency on
+    /// interactive paths. If one request fails, the other is kept.
+    pub fn hedge(mut self, after: Duration) -> Self {
+        self.hedge = Some(after);
+        self
+    }
+
+    /// Fit the input into the model's context window, minus `max_tokens`,
+    /// with `strategy` when it doesn't fit.
+    ///
+    /// Sizes are estimates ([`tokens::estimate_request`]); models without a
+    /// known context window are sent as they are.
+    pub fn truncate(mut self, strategy: TruncateStrategy) -> Self {
+        self.truncate = Some(strategy);
+        self
+    }
+
+    /// Emit one chunk per model token instead of coalesced text.
+    ///
+    /// Requests log probabilities (without alternatives unless set with
+    /// [`logprobs`](Self::logprobs)) and splits every chunk along its tokens: each
+    /// chunk's text is one token, with its [`StreamChunk::logprobs`] entry and
+    /// [`StreamChunk::byte_offset`] in the content. Fails for providers without
+    /// log probabilities (Claude).
+    pub fn token_mode(mut self) -> Self {
+        self.token_mode = true;
+        self.config.logprobs.get_or_insert(0);
         self
     }
 
@@ -444,81 +1729,1980 @@ impl RequestBuilder<'_> {
     pub async fn send(
         self,
     ) -> Result<
-        CompletionStream<impl futures::Stream<Item = Result<bytes::Bytes, reqwest::Error>> + Unpin>,
+        CompletionStream<impl futures::Stream<Item = Result<bytes::Bytes, Error>> + Unpin>,
         Error,
     > {
-        let model_id = ModelId::parse(&self.model)?;
-        let base_url = self.client.get_base_url(&model_id.provider);
-        let provider =
-            get_provider_with_base_url(&model_id.provider, base_url).ok_or_else(|| {
-                Error::InvalidModel(format!("unknown provider: {}", model_id.provider))
-            })?;
-        let api_key = self.client.get_api_key(&model_id.provider)?;
-
-        if self.streaming {
-            let body = provider.build_stream_body(&model_id.model, self.messages, &self.config)?;
-            self.client
-                .execute_stream(provider.as_ref(), api_key, body, model_id.model)
-                .await
-        } else {
+        let started = Instant::now();
+        let model_id = ModelId::parse(self.client.resolve_model(&self.model))?;
+        let provider = self.resolve_provider(&model_id)?;
+        self.validate(provider.as_ref(), &model_id)?;
+        self.check_token_mode(provider.as_ref())?;
+        let messages = self.prepare_messages(provider.as_ref(), &model_id, &self.messages)?;
+        let api_key = self.client.get_api_key_for(provider.as_ref())?;
+
+        if !self.streaming {
             // For non-streaming, we'd need a different return type
             // This is a limitation of the current API design
-            Err(Error::Config(
+            return Err(Error::Config(
                 "use send_complete() for non-streaming".into(),
-            ))
+            ));
+        }
+        let body = provider.build_stream_body(&model_id.model, &messages, &self.config)?;
+        self.send_stream_body(provider.as_ref(), &model_id, api_key, body, started)
+            .await
+    }
+
+    /// Serialize everything but the messages (system prompt, tools, parameters)
+    /// once, for services sending many requests that differ only in their messages.
+    ///
+    /// Start from a builder without messages and pass them to each
+    /// [`PreparedRequest::stream`] or [`PreparedRequest::complete`] call:
+    ///
+    /// ```no_run
+    /// # async fn example(client: &rust_ai_sdk::Client, tools: Vec<rust_ai_sdk::Tool>) -> Result<(), rust_ai_sdk::Error> {
+    /// use rust_ai_sdk::Message;
+    ///
+    /// let prepared = client
+    ///     .complete("openai/gpt-4o", Vec::new())
+    ///     .system("You are a support agent.")
+    ///     .tools(tools)
+    ///     .prepare()?;
+    /// let result = prepared.complete(&[Message::user("Where is my order?")]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prepare(self) -> Result<PreparedRequest<'a>, Error> {
+        if !self.messages.is_empty() {
+            return Err(Error::Config(
+                "prepared requests take their messages per call".into(),
+            ));
+        }
+        let model_id = ModelId::parse(self.client.resolve_model(&self.model))?;
+        let provider = self.resolve_provider(&model_id)?;
+        self.validate(provider.as_ref(), &model_id)?;
+        self.check_token_mode(provider.as_ref())?;
+        self.client.get_api_key_for(provider.as_ref())?;
+
+        let template = |body: Result<RequestBody, Error>| match provider.build_messages(&[]) {
+            Some(Ok((field, _))) => Ok(BodyTemplate::new(body?, field)),
+            Some(Err(e)) => Err(e),
+            None => Ok(None),
+        };
+        let stream_body = template(provider.build_stream_body(&model_id.model, &[], &self.config))?;
+        let complete_body =
+            template(provider.build_complete_body(&model_id.model, &[], &self.config))?;
+
+        Ok(PreparedRequest {
+            builder: self,
+            model_id,
+            provider,
+            stream_body,
+            complete_body,
+        })
+    }
+
+    /// Send a streaming request with an already built body.
+    async fn send_stream_body(
+        &self,
+        provider: &dyn Provider,
+        model_id: &ModelId,
+        api_key: &str,
+        body: RequestBody,
+        started: Instant,
+    ) -> Result<
+        CompletionStream<impl futures::Stream<Item = Result<bytes::Bytes, Error>> + Unpin>,
+        Error,
+    > {
+        // A losing stream records its usage as cancelled when dropped
+        self.hedged(
+            || async {
+                let mut stream = self
+                    .open_stream(provider, model_id, api_key, body.clone(), started)
+                    .await?;
+                if self.hedge.is_some() {
+                    stream.read_ahead().await?;
+                }
+                Ok(stream)
+            },
+            || {},
+        )
+        .await
+    }
+
+    /// Send one streaming request.
+    async fn open_stream(
+        &self,
+        provider: &dyn Provider,
+        model_id: &ModelId,
+        api_key: &str,
+        body: RequestBody,
+        started: Instant,
+    ) -> Result<
+        CompletionStream<impl futures::Stream<Item = Result<bytes::Bytes, Error>> + Unpin>,
+        Error,
+    > {
+        #[cfg(feature = "prometheus")]
+        let metrics = self.client.request_metrics(model_id);
+
+        let mut timings = RequestTimings::default();
+        let sent = async {
+            let mut in_flight = self.begin(&model_id.provider, &mut timings).await?;
+            let mut stream = in_flight
+                .or_abort(self.client.execute_stream(
+                    provider,
+                    api_key,
+                    &self.config,
+                    body,
+                    model_id.model.clone(),
+                    &mut timings,
+                ))
+                .await??;
+            stream.set_in_flight(in_flight);
+            Ok::<_, Error>(stream)
+        }
+        .await;
+        #[cfg(feature = "prometheus")]
+        if let (Some(metrics), Err(e)) = (&metrics, &sent) {
+            metrics.error(e);
+        }
+
+        let mut stream = sent?;
+        stream.set_timings(timings, started);
+        stream.set_token_mode(self.token_mode);
+        stream.set_dedup(self.client.dedup_streams);
+        if let Some(stop) = &self.config.stop {
+            stream.set_stop_sequences(stop.clone(), self.trim_stop);
+        }
+        stream.set_usage_hook(self.on_usage.clone());
+        if let Some((after, hook)) = &self.on_stall {
+            stream.set_stall_hook(*after, Arc::clone(hook));
+        }
+        stream.set_usage_recorder(self.client.usage_recorder(&model_id.provider));
+        #[cfg(feature = "prometheus")]
+        stream.set_metrics(metrics);
+        Ok(stream)
+    }
+
+    /// Fail early if token mode is on but the provider has no log probabilities.
+    fn check_token_mode(&self, provider: &dyn Provider) -> Result<(), Error> {
+        if self.token_mode && !provider.supports_logprobs() {
+            return Err(Error::Config(format!(
+                "token mode needs log probabilities, which {} does not return",
+                provider.name()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Resolve the provider, switching OpenAI to Chat Completions for audio output.
+    fn resolve_provider(&self, model_id: &ModelId) -> Result<Box<dyn Provider>, Error> {
+        let provider = self
+            .client
+            .resolve_provider_at(&model_id.provider, self.base_url.as_deref())?;
+        if self.config.audio.is_some() && provider.name() == "openai" {
+            return Ok(Box::new(
+                OpenAIProvider::with_base_url(provider.base_url()).with_chat_completions(),
+            ));
+        }
+        Ok(provider)
+    }
+
+    /// Register the request with the client, waiting for the provider's rate limit.
+    async fn begin(&self, provider: &str, timings: &mut RequestTimings) -> Result<InFlight, Error> {
+        let mut in_flight = self.client.begin()?;
+        if let Some(limiter) = self.client.rate_limiters.get(provider) {
+            let queued_at = Instant::now();
+            let permit = in_flight.or_abort(limiter.acquire(self.priority)).await??;
+            timings.queued = queued_at.elapsed();
+            in_flight.hold(permit);
+        }
+        Ok(in_flight)
+    }
+
+    /// Check parameters against the provider's accepted ranges and the model's
+    /// output limit, so mistakes surface as `Error::Config` instead of a 400,
+    /// and warn about parameters the provider leaves out.
+    fn validate(&self, provider: &dyn Provider, model_id: &ModelId) -> Result<(), Error> {
+        let max_output_tokens = self
+            .client
+            .models
+            .get(&format!("{}/{}", model_id.provider, model_id.model))
+            .and_then(|info| info.capabilities.max_output_tokens);
+        provider
+            .parameter_limits()
+            .validate(provider.name(), &self.config, max_output_tokens)?;
+
+        for parameter in self.config.provider_parameters() {
+            if !provider.supports_parameter(parameter) {
+                self.client.hooks.warn(&Warning::ParameterDropped {
+                    provider: provider.name().to_string(),
+                    parameter,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the client's [`Sanitizer`], the prefill fallback of providers
+    /// that can't continue an assistant message, the request's truncation
+    /// strategy, and the client's [`ContextGuard`] to `messages`.
+    fn prepare_messages<'m>(
+        &self,
+        provider: &dyn Provider,
+        model_id: &ModelId,
+        messages: &'m [Message],
+    ) -> Result<Cow<'m, [Message]>, Error> {
+        let mut messages = match &self.client.sanitizer {
+            Some(sanitizer) => sanitizer.sanitize_messages(messages),
+            None => Cow::Borrowed(messages),
+        };
+        if !provider.supports_prefill() {
+            if let Cow::Owned(continued) = continue_prefill(&messages) {
+                messages = Cow::Owned(continued);
+            }
+        }
+        match messages {
+            Cow::Borrowed(messages) => self.fit_context(model_id, messages),
+            Cow::Owned(messages) => self
+                .fit_context(model_id, &messages)
+                .map(|fitted| Cow::Owned(fitted.into_owned())),
+        }
+    }
+
+    /// Apply the request's truncation strategy and the client's [`ContextGuard`]
+    /// to `messages`.
+    fn fit_context<'m>(
+        &self,
+        model_id: &ModelId,
+        messages: &'m [Message],
+    ) -> Result<Cow<'m, [Message]>, Error> {
+        if self.truncate.is_none() && self.client.context_guard.is_none() {
+            return Ok(Cow::Borrowed(messages));
+        }
+        let Some(context_window) = self
+            .client
+            .models
+            .get(&format!("{}/{}", model_id.provider, model_id.model))
+            .and_then(|info| info.capabilities.context_window)
+        else {
+            return Ok(Cow::Borrowed(messages));
+        };
+        let available = context_window.saturating_sub(self.config.max_tokens.unwrap_or(0));
+
+        let messages = match self.truncate {
+            Some(strategy) => tokens::truncate(messages, &self.config, available, strategy)?,
+            None => Cow::Borrowed(messages),
+        };
+        let estimated = tokens::estimate_request(&messages, &self.config);
+        if estimated <= available {
+            return Ok(messages);
+        }
+        let error = Error::ContextLengthExceeded {
+            estimated,
+            available,
+        };
+        match &self.client.context_guard {
+            Some(ContextGuard::Reject) => Err(error),
+            Some(ContextGuard::Warn(hook)) => {
+                hook(&error);
+                Ok(messages)
+            }
+            None => Ok(messages),
         }
     }
 
     /// Send a non-streaming request.
     pub async fn send_complete(self) -> Result<CompletionResult, Error> {
-        let model_id = ModelId::parse(&self.model)?;
-        let base_url = self.client.get_base_url(&model_id.provider);
-        let provider =
-            get_provider_with_base_url(&model_id.provider, base_url).ok_or_else(|| {
-                Error::InvalidModel(format!("unknown provider: {}", model_id.provider))
-            })?;
-        let api_key = self.client.get_api_key(&model_id.provider)?;
+        self.complete_messages(&self.messages).await
+    }
 
-        let body = provider.build_complete_body(&model_id.model, self.messages, &self.config)?;
-        self.client
-            .execute_complete(provider.as_ref(), api_key, body, &model_id.model)
+    /// Send non-streaming requests until the output parses as JSON into `T`,
+    /// at most `max_attempts` in all.
+    ///
+    /// After a failed attempt the model's output and the parse error are
+    /// appended to the conversation, asking for a corrected reply. Output in a
+    /// Markdown code fence is accepted. Set a [`ResponseFormat`] as well where
+    /// the provider supports one. When no attempt parses, fails with
+    /// [`Error::Validation`] listing every output and error.
+    pub async fn validated<T: DeserializeOwned>(self, max_attempts: u32) -> Result<T, Error> {
+        let mut messages = self.messages.to_vec();
+        let mut attempts = Vec::new();
+        for _ in 0..max_attempts.max(1) {
+            let result = self.complete_messages(&messages).await?;
+            match serde_json::from_str::<T>(strip_code_fence(&result.content)) {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    messages.push(Message::assistant(result.content.clone()));
+                    messages.push(Message::user(format!(
+                        "Your reply could not be parsed: {e}. Reply again with only the corrected JSON."
+                    )));
+                    attempts.push(ValidationAttempt {
+                        output: result.content,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        Err(Error::Validation { attempts })
+    }
+
+    /// Send a non-streaming request for `messages`.
+    async fn complete_messages(&self, messages: &[Message]) -> Result<CompletionResult, Error> {
+        let started = Instant::now();
+        let model_id = ModelId::parse(self.client.resolve_model(&self.model))?;
+        let provider = self.resolve_provider(&model_id)?;
+        self.validate(provider.as_ref(), &model_id)?;
+        let messages = self.prepare_messages(provider.as_ref(), &model_id, messages)?;
+        let api_key = self.client.get_api_key_for(provider.as_ref())?;
+
+        let body = provider.build_complete_body(&model_id.model, &messages, &self.config)?;
+        self.send_complete_body(provider.as_ref(), &model_id, api_key, body, started)
             .await
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Send a non-streaming request with an already built body.
+    async fn send_complete_body(
+        &self,
+        provider: &dyn Provider,
+        model_id: &ModelId,
+        api_key: &str,
+        body: RequestBody,
+        started: Instant,
+    ) -> Result<CompletionResult, Error> {
+        #[cfg(feature = "prometheus")]
+        let metrics = self.client.request_metrics(model_id);
+
+        let sent = self
+            .hedged(
+                || self.complete_attempt(provider, model_id, api_key, body.clone()),
+                // The losing request may still be billed
+                || {
+                    if let Some(recorder) = self.client.usage_recorder(&model_id.provider) {
+                        recorder.record(
+                            &model_id.model,
+                            &Usage::default(),
+                            None,
+                            None,
+                            false,
+                            RequestOutcome::Cancelled,
+                        );
+                    }
+                },
+            )
+            .await
+            .map(|(result, mut timings, request_id)| {
+                timings.total = started.elapsed();
+                (result, timings, request_id)
+            });
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &metrics {
+            match &sent {
+                Ok((result, timings, _)) => {
+                    metrics.usage(&result.usage);
+                    metrics.timings(timings);
+                }
+                Err(e) => metrics.error(e),
+            }
+        }
 
-    #[test]
-    fn test_client_builder() {
-        let client = Client::builder()
-            .api_key("cerebras", "test-key")
-            .timeout(Duration::from_secs(60))
-            .max_retries(5)
-            .build()
-            .unwrap();
+        let (mut result, timings, request_id) = sent?;
+        result.timings = Some(timings);
+        if let Some(stop) = &self.config.stop {
+            result.resolve_stop_sequence(stop, self.trim_stop);
+        }
+        if let Some(on_usage) = &self.on_usage {
+            on_usage(&result.usage);
+        }
+        if let Some(recorder) = self.client.usage_recorder(&model_id.provider) {
+            recorder.record(
+                &model_id.model,
+                &result.usage,
+                Some(result.finish_reason),
+                request_id.as_deref(),
+                false,
+                RequestOutcome::Success,
+            );
+        }
+        if result.model.is_empty() {
+            result.model.clone_from(&model_id.model);
+        }
+        Ok(result)
+    }
 
-        assert_eq!(client.config.max_retries, 5);
-        assert_eq!(client.api_keys.get("cerebras").unwrap(), "test-key");
+    /// Send one non-streaming request, returning the result with its timings
+    /// and request id.
+    async fn complete_attempt(
+        &self,
+        provider: &dyn Provider,
+        model_id: &ModelId,
+        api_key: &str,
+        body: RequestBody,
+    ) -> Result<(CompletionResult, RequestTimings, Option<String>), Error> {
+        let mut timings = RequestTimings::default();
+        let mut in_flight = self.begin(&model_id.provider, &mut timings).await?;
+        let (result, request_id) = in_flight
+            .or_abort(self.client.execute_complete(
+                provider,
+                api_key,
+                &self.config,
+                body,
+                &model_id.model,
+                &mut timings,
+            ))
+            .await??;
+        Ok((result, timings, request_id))
     }
 
-    #[test]
-    fn test_request_builder() {
-        let client = Client::builder()
-            .api_key("cerebras", "test")
-            .build()
-            .unwrap();
+    /// Run `attempt`, with a second one started alongside when the request is
+    /// hedged and the first hasn't finished in time. The first to succeed wins;
+    /// `abandoned` is called when the other one is dropped still in flight.
+    async fn hedged<T, F, Fut>(&self, attempt: F, abandoned: impl FnOnce()) -> Result<T, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let Some(after) = self.hedge else {
+            return attempt().await;
+        };
+        let mut primary = pin!(attempt());
+        if let Either::Left((result, _)) =
+            select(primary.as_mut(), self.client.sleeper.sleep(after)).await
+        {
+            return result;
+        }
+        let secondary = pin!(attempt());
+        match select(primary, secondary).await {
+            Either::Left((Ok(value), _)) | Either::Right((Ok(value), _)) => {
+                abandoned();
+                Ok(value)
+            }
+            Either::Left((Err(_), other)) | Either::Right((Err(_), other)) => other.await,
+        }
+    }
+}
 
-        let messages = vec![Message::user("Hi")];
-        let builder = client
-            .stream("cerebras/llama3.1-70b", &messages)
-            .max_tokens(100)
-            .temperature(0.7)
-            .top_p(0.9);
+/// A request whose body, apart from the messages, was serialized once.
+///
+/// Built with [`RequestBuilder::prepare`]. Each call splices its messages into
+/// the serialized body, so a large system prompt or tool schema isn't
+/// serialized again per request. Calls take `&self`, so one prepared request
+/// can serve many concurrent tasks.
+///
+/// Where other fields depend on the messages (system messages moved into a
+/// separate field, Gemini image resolution), the body is built in full instead.
+pub struct PreparedRequest<'a> {
+    builder: RequestBuilder<'a>,
+    model_id: ModelId,
+    provider: Box<dyn Provider>,
+    stream_body: Option<BodyTemplate>,
+    complete_body: Option<BodyTemplate>,
+}
+
+impl PreparedRequest<'_> {
+    /// Send a streaming request with `messages`.
+    pub async fn stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<
+        CompletionStream<impl futures::Stream<Item = Result<bytes::Bytes, Error>> + Unpin>,
+        Error,
+    > {
+        let started = Instant::now();
+        let messages =
+            self.builder
+                .prepare_messages(self.provider.as_ref(), &self.model_id, messages)?;
+        let body = match self.splice(self.stream_body.as_ref(), &messages)? {
+            Some(body) => body,
+            None => self.provider.build_stream_body(
+                &self.model_id.model,
+                &messages,
+                &self.builder.config,
+            )?,
+        };
+        let api_key = self
+            .builder
+            .client
+            .get_api_key_for(self.provider.as_ref())?;
+        self.builder
+            .send_stream_body(
+                self.provider.as_ref(),
+                &self.model_id,
+                api_key,
+                body,
+                started,
+            )
+            .await
+    }
+
+    /// Send a non-streaming request with `messages`.
+    pub async fn complete(&self, messages: &[Message]) -> Result<CompletionResult, Error> {
+        let started = Instant::now();
+        let messages =
+            self.builder
+                .prepare_messages(self.provider.as_ref(), &self.model_id, messages)?;
+        let body = match self.splice(self.complete_body.as_ref(), &messages)? {
+            Some(body) => body,
+            None => self.provider.build_complete_body(
+                &self.model_id.model,
+                &messages,
+                &self.builder.config,
+            )?,
+        };
+        let api_key = self
+            .builder
+            .client
+            .get_api_key_for(self.provider.as_ref())?;
+        self.builder
+            .send_complete_body(
+                self.provider.as_ref(),
+                &self.model_id,
+                api_key,
+                body,
+                started,
+            )
+            .await
+    }
+
+    /// The template with `messages` spliced in, if both allow it.
+    fn splice(
+        &self,
+        template: Option<&BodyTemplate>,
+        messages: &[Message],
+    ) -> Result<Option<RequestBody>, Error> {
+        let Some(template) = template else {
+            return Ok(None);
+        };
+        match self.provider.build_messages(messages) {
+            Some(spliced) => {
+                let (field, messages) = spliced?;
+                Ok(template.fill(field, &messages))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Builder for embedding requests.
+pub struct EmbeddingRequestBuilder<'a> {
+    client: &'a Client,
+    model: String,
+    inputs: Vec<String>,
+    config: EmbeddingConfig,
+}
+
+impl EmbeddingRequestBuilder<'_> {
+    /// Set output vector size (for models supporting flexible dimensions).
+    pub fn dimensions(mut self, dimensions: u32) -> Self {
+        self.config.dimensions = Some(dimensions);
+        self
+    }
+
+    /// Truncate over-long inputs instead of failing.
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.config.truncate = Some(truncate);
+        self
+    }
+
+    /// Set the retrieval role of the inputs (query vs. document).
+    pub fn input_type(mut self, input_type: EmbeddingInputType) -> Self {
+        self.config.input_type = Some(input_type);
+        self
+    }
+
+    /// Add extra provider-specific fields.
+    pub fn extra(mut self, extra: serde_json::Value) -> Self {
+        self.config.extra = Some(extra);
+        self
+    }
+
+    /// Send the embedding request.
+    pub async fn send(self) -> Result<EmbeddingResult, Error> {
+        let model_id = ModelId::parse(self.client.resolve_model(&self.model))?;
+        let base_url = self.client.get_base_url(&model_id.provider);
+        let provider = get_embedding_provider_with_base_url(&model_id.provider, base_url)
+            .ok_or_else(|| {
+                Error::InvalidModel(format!("unknown embedding provider: {}", model_id.provider))
+            })?;
+        let api_key = self.client.get_api_key(&model_id.provider)?;
+
+        let body = provider.build_embed_body(&model_id.model, &self.inputs, &self.config)?;
+        let mut in_flight = self.client.begin()?;
+        in_flight
+            .or_abort(
+                self.client
+                    .execute_embed(provider.as_ref(), api_key, body, &model_id.model),
+            )
+            .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_builder() {
+        let client = Client::builder()
+            .api_key("cerebras", "test-key")
+            .timeout(Duration::from_secs(60))
+            .max_retries(5)
+            .build()
+            .unwrap();
+
+        assert_eq!(client.config.max_retries, 5);
+        assert_eq!(client.api_keys.get("cerebras").unwrap(), "test-key");
+    }
+
+    #[test]
+    fn test_from_env_with() {
+        env::set_var("RUST_AI_SDK_TEST_ANTHROPIC_API_KEY", "claude-key");
+        env::set_var(
+            "RUST_AI_SDK_TEST_OPENAI_BASE_URL",
+            "http://localhost:8080/v1",
+        );
+        env::set_var("RUST_AI_SDK_TEST_CUSTOM_KEY", "custom-key");
+
+        let client = Client::builder()
+            .from_env_with("RUST_AI_SDK_TEST_")
+            .api_key_env("vllm", "RUST_AI_SDK_TEST_CUSTOM_KEY")
+            .api_key_env("jina", "RUST_AI_SDK_TEST_UNSET")
+            .build()
+            .unwrap();
+
+        assert_eq!(client.api_keys.get("claude").unwrap(), "claude-key");
+        assert_eq!(client.api_keys.get("vllm").unwrap(), "custom-key");
+        assert!(!client.api_keys.contains_key("jina"));
+        assert_eq!(
+            client.get_base_url("openai"),
+            Some("http://localhost:8080/v1")
+        );
+    }
+
+    #[test]
+    fn test_client_builder_pool_settings() {
+        let client = Client::builder()
+            .pool_max_idle_per_host(64)
+            .pool_idle_timeout(None)
+            .http2_keep_alive_interval(Duration::from_secs(20))
+            .http2_keep_alive_timeout(Duration::from_secs(5))
+            .http2_keep_alive_while_idle(true)
+            .tcp_keepalive(None)
+            .http_version(HttpVersion::Http2PriorKnowledge)
+            .build()
+            .unwrap();
+
+        assert_eq!(client.config.pool_max_idle_per_host, 64);
+        assert_eq!(client.config.pool_idle_timeout, None);
+        assert_eq!(
+            client.config.http2_keep_alive_interval,
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(
+            client.config.http2_keep_alive_timeout,
+            Some(Duration::from_secs(5))
+        );
+        assert!(client.config.http2_keep_alive_while_idle);
+        assert_eq!(client.config.tcp_keepalive, None);
+        assert_eq!(client.config.http_version, HttpVersion::Http2PriorKnowledge);
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .build()
+            .unwrap();
+
+        let messages = vec![Message::user("Hi")];
+        let builder = client
+            .stream("cerebras/llama3.1-70b", &messages)
+            .max_tokens(100)
+            .temperature(0.7)
+            .top_p(0.9)
+            .extra(serde_json::json!({"a": {"b": 1}, "seed": 1}))
+            .extra_at("a.c", serde_json::json!({"d": true}));
 
         assert_eq!(builder.config.max_tokens, Some(100));
         assert_eq!(builder.config.temperature, Some(0.7));
         assert_eq!(builder.config.top_p, Some(0.9));
+        assert_eq!(
+            builder.config.extra,
+            Some(serde_json::json!({"a": {"b": 1, "c": {"d": true}}, "seed": 1}))
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let client = Client::builder()
+            .preset("creative", |b| b.temperature(1.0).top_p(0.95))
+            .build()
+            .unwrap();
+        let messages = vec![Message::user("Hi")];
+
+        let builder = client
+            .stream_with_preset("creative", "openai/gpt-4o", &messages)
+            .unwrap()
+            .top_p(0.5);
+        assert_eq!(builder.config.temperature, Some(1.0));
+        assert_eq!(builder.config.top_p, Some(0.5));
+
+        assert!(matches!(
+            client.complete_with_preset("missing", "openai/gpt-4o", &messages),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_parameter_validation() {
+        let client = Client::builder().api_key("claude", "test").build().unwrap();
+        let messages = vec![Message::user("Hi")];
+
+        let err = client
+            .complete("claude/claude-3-5-sonnet-20241022", &messages)
+            .temperature(1.5)
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("temperature")));
+
+        let err = client
+            .complete("claude/claude-3-5-sonnet-20241022", &messages)
+            .max_tokens(100_000)
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("8192")));
+
+        let err = client
+            .complete("openai/gpt-4o", &messages)
+            .stop(vec![
+                "a".into(),
+                "b".into(),
+                "c".into(),
+                "d".into(),
+                "e".into(),
+            ])
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("stop sequences")));
+    }
+
+    #[tokio::test]
+    async fn test_retries_overloaded() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(529).set_body_string(
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            ))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"model":"claude-3-haiku","content":[{"type":"text","text":"ok"}],"stop_reason":"end_turn","usage":{"input_tokens":1,"output_tokens":1}}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("claude", "test")
+            .base_url("claude", server.uri())
+            .retry_backoff(Duration::from_millis(1))
+            .overloaded_backoff(Duration::from_millis(1))
+            .build()
+            .unwrap();
+
+        let messages = vec![Message::user("Hi")];
+        let result = client
+            .complete("claude/claude-3-haiku", &messages)
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "ok");
+
+        let timings = result.timings.unwrap();
+        assert_eq!(timings.retries, 1);
+        assert!(timings.retry_time >= Duration::from_micros(850));
+        assert!(timings.total >= timings.retry_time + timings.time_to_first_byte);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_keys_and_budget() {
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer acme-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id":"1","model":"llama3.1-8b","choices":[{"index":0,"message":{"role":"assistant","content":"ok"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1000000,"completion_tokens":0,"total_tokens":1000000}}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "shared-key")
+            .base_url("cerebras", server.uri())
+            .build()
+            .unwrap();
+        let acme = client
+            .for_tenant("acme")
+            .with_api_key("cerebras", "acme-key");
+        acme.tenant().unwrap().set_budget(Some(0.05));
+
+        let messages = vec![Message::user("Hi")];
+        acme.complete("cerebras/llama3.1-8b", &messages)
+            .send_complete()
+            .await
+            .unwrap();
+
+        // Another handle for the same tenant sees the spending and the budget
+        let tenant = client.for_tenant("acme");
+        let tenant = tenant.tenant().unwrap();
+        assert!((tenant.spent() - 0.10).abs() < 1e-9);
+        assert_eq!(tenant.costs().input_tokens(), 1_000_000);
+        assert!(tenant.remaining().unwrap() < 1e-9);
+        assert_eq!(client.api_keys.get("cerebras").unwrap(), "shared-key");
+
+        let err = acme
+            .complete("cerebras/llama3.1-8b", &messages)
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::BudgetExceeded { ref tenant, .. } if tenant == "acme"));
+        assert_eq!(
+            client
+                .for_tenant("other")
+                .tenant()
+                .unwrap()
+                .costs()
+                .request_count(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tenant_stream_outcomes() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(concat!(
+                        "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+                        "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}],",
+                        "\"usage\":{\"prompt_tokens\":10,\"completion_tokens\":2,\"total_tokens\":12}}\n\n",
+                        "data: [DONE]\n\n",
+                    )),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .build()
+            .unwrap();
+        let acme = client.for_tenant("acme");
+        let messages = vec![Message::user("Hi")];
+
+        let mut stream = acme
+            .stream("cerebras/llama3.1-8b", &messages)
+            .send()
+            .await
+            .unwrap();
+        while let Some(chunk) = stream.next().await {
+            chunk.unwrap();
+        }
+        drop(stream);
+
+        // Dropped after its first chunk: the user went away
+        let mut stream = acme
+            .stream("cerebras/llama3.1-8b", &messages)
+            .send()
+            .await
+            .unwrap();
+        stream.next().await.unwrap().unwrap();
+        drop(stream);
+
+        let costs = acme.tenant().unwrap().costs();
+        assert_eq!(costs.request_count(), 2);
+        assert_eq!(costs.outcome_count(RequestOutcome::Success), 1);
+        assert_eq!(costs.outcome_count(RequestOutcome::Cancelled), 1);
+        assert_eq!(costs.outcome_count(RequestOutcome::Error), 0);
+    }
+
+    #[tokio::test]
+    async fn test_with_overrides() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"max_completion_tokens": 16}),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(
+                        r#"{"id":"1","model":"llama3.1-8b","choices":[{"index":0,"message":{"role":"assistant","content":"ok"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+                    )
+                    .set_delay(Duration::from_millis(200)),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .build()
+            .unwrap();
+        let impatient = client.with_overrides(|cfg| {
+            cfg.timeout = Duration::from_millis(50);
+            cfg.max_retries = 1;
+            cfg.defaults.max_tokens = Some(16);
+        });
+
+        let messages = vec![Message::user("Hi")];
+        let err = impatient
+            .complete("cerebras/llama3.1-8b", &messages)
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Timeout));
+
+        // Defaults are only a starting point, and the parent keeps its settings
+        client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .max_tokens(16)
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(client.config.max_retries, 3);
+    }
+
+    #[test]
+    fn test_context_guard() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let warnings = Arc::new(AtomicU32::new(0));
+        let w = warnings.clone();
+        let reject = Client::builder()
+            .context_guard(ContextGuard::Reject)
+            .build()
+            .unwrap();
+        let warn = Client::builder()
+            .context_guard(ContextGuard::Warn(Arc::new(move |_| {
+                w.fetch_add(1, Ordering::SeqCst);
+            })))
+            .build()
+            .unwrap();
+
+        // gpt-4o has a 128k context window; ~100k tokens of input fit, unless
+        // the output needs 30k of it
+        let messages = vec![Message::user("word ".repeat(80_000))];
+        let model_id = ModelId::parse("openai/gpt-4o").unwrap();
+        let check = |client: &Client, max_tokens| {
+            client
+                .complete("openai/gpt-4o", &messages)
+                .max_tokens(max_tokens)
+                .fit_context(&model_id, &messages)
+                .map(|_| ())
+        };
+
+        check(&reject, 1_000).unwrap();
+        let err = check(&reject, 30_000).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ContextLengthExceeded {
+                available: 98_000,
+                ..
+            }
+        ));
+        check(&warn, 30_000).unwrap();
+        assert_eq!(warnings.load(Ordering::SeqCst), 1);
+
+        // Truncation fits the request before the guard sees it
+        let fitted = reject
+            .complete("openai/gpt-4o", &messages)
+            .max_tokens(30_000)
+            .truncate(TruncateStrategy::TrimMiddle)
+            .fit_context(&model_id, &messages)
+            .unwrap();
+        assert!(tokens::estimate_messages(&fitted) <= 98_000);
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_error_hooks() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let retries = Arc::new(AtomicU32::new(0));
+        let errors = Arc::new(AtomicU32::new(0));
+        let (r, e) = (retries.clone(), errors.clone());
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .max_retries(3)
+            .retry_backoff(Duration::from_millis(1))
+            .on_retry(move |attempt, err, _delay| {
+                assert!(matches!(err, Error::Server { status: 503, .. }));
+                assert_eq!(attempt, r.fetch_add(1, Ordering::SeqCst) + 1);
+            })
+            .on_error(move |_| {
+                e.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()
+            .unwrap();
+
+        let messages = vec![Message::user("Hi")];
+        let result = client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .send_complete()
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(retries.load(Ordering::SeqCst), 2);
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warnings() {
+        use crate::providers::Parameter;
+        use std::sync::Mutex;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id":"1","model":"unpriced","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let w = warnings.clone();
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .on_warning(move |warning| w.lock().unwrap().push(warning.clone()))
+            .build()
+            .unwrap();
+
+        let messages = vec![Message::user("Hi")];
+        let response = client
+            .complete("cerebras/unpriced", &messages)
+            .best_of(2)
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(response.content, "hi");
+
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [
+                Warning::ParameterDropped {
+                    provider: "cerebras".into(),
+                    parameter: Parameter::BestOf,
+                },
+                Warning::PricingMissing {
+                    model: "cerebras/unpriced".into(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validated() {
+        use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+        use futures::future::BoxFuture;
+        use std::sync::Mutex;
+
+        /// Replies with the queued outputs in turn, keeping the request bodies.
+        struct Replies(Mutex<(Vec<&'static str>, Vec<serde_json::Value>)>);
+
+        impl HttpTransport for Replies {
+            fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+                let mut state = self.0.lock().unwrap();
+                state.1.push(serde_json::from_slice(&request.body).unwrap());
+                let content = state.0.remove(0);
+                let body = serde_json::json!({
+                    "id": "1",
+                    "model": "llama3.1-8b",
+                    "choices": [{"index": 0, "message": {"role": "assistant", "content": content}, "finish_reason": "stop"}],
+                    "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2},
+                });
+                Box::pin(async move {
+                    Ok(HttpResponse::from_bytes(
+                        200,
+                        HeaderMap::new(),
+                        body.to_string(),
+                    ))
+                })
+            }
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct City {
+            name: String,
+            population: u64,
+        }
+
+        let replies = Arc::new(Replies(Mutex::new((
+            vec![
+                r#"{"name": "Paris"}"#,
+                "```json\n{\"name\": \"Paris\", \"population\": 2100000}\n```",
+            ],
+            Vec::new(),
+        ))));
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .transport(replies.clone())
+            .build()
+            .unwrap();
+
+        let messages = vec![Message::user("Largest city in France, as JSON?")];
+        let city: City = client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .validated(3)
+            .await
+            .unwrap();
+        assert_eq!(city.population, 2_100_000);
+
+        // The retry carries the failed output and the parse error
+        let retry = replies.0.lock().unwrap().1[1]["messages"].clone();
+        let retry = retry.as_array().unwrap();
+        assert_eq!(retry.len(), 3);
+        assert_eq!(retry[1]["content"], r#"{"name": "Paris"}"#);
+        assert!(retry[2]["content"]
+            .as_str()
+            .unwrap()
+            .contains("missing field `population`"));
+
+        replies.0.lock().unwrap().0 = vec!["Paris", "Paris!"];
+        let err = client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .validated::<City>(2)
+            .await
+            .unwrap_err();
+        let Error::Validation { attempts } = err else {
+            panic!("expected a validation error, got {err:?}");
+        };
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[1].output, "Paris!");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deterministic_retries() {
+        use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+        use futures::future::BoxFuture;
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Mutex;
+
+        /// Fails the first two attempts with a 503.
+        #[derive(Default)]
+        struct Flaky(AtomicU32);
+
+        impl HttpTransport for Flaky {
+            fn send(&self, _request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+                let attempt = self.0.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async move {
+                    Ok(if attempt < 2 {
+                        HttpResponse::from_bytes(503, HeaderMap::new(), "")
+                    } else {
+                        HttpResponse::from_bytes(
+                            200,
+                            HeaderMap::new(),
+                            r#"{"id":"1","model":"llama3.1-8b","choices":[{"index":0,"message":{"role":"assistant","content":"ok"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+                        )
+                    })
+                })
+            }
+        }
+
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let d = delays.clone();
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .transport(Flaky::default())
+            .retry_backoff(Duration::from_secs(1))
+            .deterministic()
+            .on_retry(move |_attempt, _err, delay| d.lock().unwrap().push(delay))
+            .build()
+            .unwrap();
+
+        let started = tokio::time::Instant::now();
+        let messages = vec![Message::user("Hi")];
+        let result = client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "ok");
+
+        // Exactly the exponential schedule, with paused time standing in for the waits
+        assert_eq!(
+            *delays.lock().unwrap(),
+            [Duration::from_secs(1), Duration::from_secs(2)]
+        );
+        assert_eq!(started.elapsed(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_continue_prefill() {
+        let prefilled = [
+            Message::user("List three colors as JSON."),
+            Message::assistant_prefill("{"),
+        ];
+        let continued = continue_prefill(&prefilled);
+        assert_eq!(continued.len(), 3);
+        assert_eq!(continued[2].role, Role::User);
+
+        // Conversations ending on a plain assistant turn go out unchanged
+        let finished = [Message::user("Hi"), Message::assistant("Hello!")];
+        assert!(matches!(continue_prefill(&finished), Cow::Borrowed(_)));
+    }
+
+    #[derive(Clone, Default)]
+    struct MemoryStore(Arc<std::sync::Mutex<Vec<crate::usage::RequestRecord>>>);
+
+    impl UsageStore for MemoryStore {
+        fn append(
+            &self,
+            record: crate::usage::RequestRecord,
+        ) -> futures::future::BoxFuture<'_, Result<(), Error>> {
+            self.0.lock().unwrap().push(record);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hedge() {
+        use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+        use futures::future::BoxFuture;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        /// Answers the first request after 10s and later ones at once.
+        #[derive(Default)]
+        struct SlowFirst(AtomicU32);
+
+        impl HttpTransport for SlowFirst {
+            fn send(&self, _request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+                let attempt = self.0.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async move {
+                    let content = if attempt == 0 {
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                        "slow"
+                    } else {
+                        "fast"
+                    };
+                    let body = serde_json::json!({
+                        "id": "1",
+                        "model": "llama3.1-8b",
+                        "choices": [{"index": 0, "message": {"role": "assistant", "content": content}, "finish_reason": "stop"}],
+                        "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2},
+                    });
+                    Ok(HttpResponse::from_bytes(
+                        200,
+                        HeaderMap::new(),
+                        body.to_string(),
+                    ))
+                })
+            }
+        }
+
+        let transport = Arc::new(SlowFirst::default());
+        let store = MemoryStore::default();
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .transport(transport.clone())
+            .usage_store(store.clone())
+            .build()
+            .unwrap();
+
+        let started = tokio::time::Instant::now();
+        let messages = vec![Message::user("Hi")];
+        let result = client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .hedge(Duration::from_secs(1))
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "fast");
+        assert_eq!(started.elapsed(), Duration::from_secs(1));
+        assert_eq!(transport.0.load(Ordering::SeqCst), 2);
+        // Both requests are recorded, the slow one as cancelled
+        while store.0.lock().unwrap().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        let outcomes: Vec<_> = store.0.lock().unwrap().iter().map(|r| r.outcome).collect();
+        assert!(outcomes.contains(&RequestOutcome::Cancelled));
+        assert!(outcomes.contains(&RequestOutcome::Success));
+
+        // A response within the threshold isn't hedged
+        let result = client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .hedge(Duration::from_secs(1))
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "fast");
+        assert_eq!(transport.0.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_custom_sleeper() {
+        use futures::future::BoxFuture;
+        use std::sync::Mutex;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        /// Records backoffs and returns at once.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<Duration>>);
+
+        impl Sleeper for Recorder {
+            fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+                self.0.lock().unwrap().push(duration);
+                Box::pin(async {})
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(4)
+            .mount(&server)
+            .await;
+
+        let sleeper = Arc::new(Recorder::default());
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .max_retries(4)
+            .retry_backoff(Duration::from_secs(10))
+            .deterministic()
+            .sleeper(Arc::clone(&sleeper))
+            .build()
+            .unwrap();
+
+        let messages = vec![Message::user("Hi")];
+        let started = Instant::now();
+        let result = client
+            .complete("cerebras/llama3.1-8b", &messages)
+            .send_complete()
+            .await;
+        assert!(matches!(result, Err(Error::Server { status: 503, .. })));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(
+            *sleeper.0.lock().unwrap(),
+            [10, 20, 30].map(Duration::from_secs)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_embed() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "voyage-3",
+                "input": ["a", "b"],
+                "input_type": "query"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"data":[{"embedding":[1.0],"index":0},{"embedding":[2.0],"index":1}],"model":"voyage-3","usage":{"total_tokens":2}}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("voyage", "test")
+            .base_url("voyage", server.uri())
+            .build()
+            .unwrap();
+
+        let result = client
+            .embed("voyage/voyage-3", ["a", "b"])
+            .input_type(EmbeddingInputType::Query)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(result.embeddings, vec![vec![1.0], vec![2.0]]);
+        assert_eq!(result.usage.input_tokens, 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_base_url() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let canary = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/canary/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id":"1","model":"llama-3.3-70b","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+            ))
+            .expect(1)
+            .mount(&canary)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", "http://127.0.0.1:1")
+            .max_retries(1)
+            .build()
+            .unwrap();
+
+        let result = client
+            .complete("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+            .base_url(format!("{}/canary", canary.uri()))
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "hi");
+    }
+
+    #[tokio::test]
+    async fn test_query_params() {
+        use wiremock::matchers::{method, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(query_param("api-version", "2024-10-21"))
+            .and(query_param("route", "eu west"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id":"1","model":"llama-3.3-70b","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .query_param("cerebras", "api-version", "2024-10-21")
+            .query_param("cerebras", "route", "eu west")
+            .build()
+            .unwrap();
+
+        client
+            .complete("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+            .send_complete()
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_stall_interval_clamped() {
+        let client = Client::builder().build().unwrap();
+        let builder = client
+            .stream("openai/gpt-4o", vec![Message::user("Hi")])
+            .on_stall(Duration::ZERO, |_| {});
+        assert_eq!(builder.on_stall.unwrap().0, MIN_STALL_INTERVAL);
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_base_url() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"model":"local-model","choices":[{"index":0,"message":{"role":"assistant","content":"ok"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1}}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder().build().unwrap();
+        let err = client
+            .complete("openai-compatible/local-model", vec![Message::user("Hi")])
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+
+        // No API key needed
+        let client = Client::builder()
+            .base_url("openai-compatible", format!("{}/v1", server.uri()))
+            .build()
+            .unwrap();
+        let result = client
+            .complete("openai-compatible/local-model", vec![Message::user("Hi")])
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_override() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/anthropic/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"model":"claude-3-haiku","content":[{"type":"text","text":"ok"}],"stop_reason":"end_turn","usage":{"input_tokens":1,"output_tokens":1}}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("claude", "test")
+            .base_url("claude", server.uri())
+            .endpoint("claude", "/anthropic/v1/messages")
+            .build()
+            .unwrap();
+
+        let result = client
+            .complete("claude/claude-3-haiku", vec![Message::user("Hi")])
+            .max_tokens(10)
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "ok");
+
+        // Gemini streams from another path; the query with the key is kept
+        let client = Client::builder()
+            .endpoint("gemini", "/gemini/models/{model}:generateContent")
+            .stream_endpoint("gemini", "/gemini/models/{model}:streamGenerateContent")
+            .build()
+            .unwrap();
+        let provider = GeminiProvider::new()
+            .with_base_url("https://gateway.example")
+            .with_query_auth();
+        let model = "gemini-2.0-flash";
+        assert_eq!(
+            client.chat_url(&provider, model, provider.stream_url(model, "k"), true),
+            "https://gateway.example/gemini/models/gemini-2.0-flash:streamGenerateContent?alt=sse&key=k"
+        );
+        assert_eq!(
+            client.chat_url(&provider, model, provider.complete_url(model, "k"), false),
+            "https://gateway.example/gemini/models/gemini-2.0-flash:generateContent?key=k"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warmup() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .base_url("cerebras", server.uri())
+            .base_url("voyage", server.uri())
+            .build()
+            .unwrap();
+
+        client.warmup(&["cerebras", "voyage"]).await.unwrap();
+        assert!(matches!(
+            client.warmup(&["nope"]).await,
+            Err(Error::InvalidModel(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_model_alias() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(serde_json::json!({"model": "llama-3.3-70b"})))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id":"1","model":"llama-3.3-70b","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .alias("app/fast", "cerebras/llama-3.3-70b")
+            .build()
+            .unwrap();
+
+        assert_eq!(client.resolve_model("app/fast"), "cerebras/llama-3.3-70b");
+        assert!(client.capabilities("app/fast").unwrap().supports_tools);
+
+        // A registry set after the alias keeps it
+        let replaced = Client::builder()
+            .alias("app/fast", "cerebras/llama-3.3-70b")
+            .model_registry(ModelRegistry::new())
+            .build()
+            .unwrap();
+        assert_eq!(replaced.resolve_model("app/fast"), "cerebras/llama-3.3-70b");
+
+        // Owned messages: no borrow held across the await
+        let result = client
+            .complete("app/fast", vec![Message::user("Hi")])
+            .send_complete()
+            .await
+            .unwrap();
+        assert_eq!(result.model, "llama-3.3-70b");
+    }
+
+    #[tokio::test]
+    async fn test_custom_transport() {
+        use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+        use futures::future::BoxFuture;
+        use std::sync::Mutex;
+
+        /// Replays a canned SSE body and records the request.
+        #[derive(Default)]
+        struct Canned(Mutex<Option<HttpRequest>>);
+
+        impl HttpTransport for Canned {
+            fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+                *self.0.lock().unwrap() = Some(request);
+                Box::pin(async {
+                    let chunks = [
+                        "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+                        "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}]}\n\ndata: [DONE]\n\n",
+                    ];
+                    Ok(HttpResponse {
+                        status: 200,
+                        headers: HeaderMap::new(),
+                        body: Box::pin(futures::stream::iter(
+                            chunks.map(|chunk| Ok(bytes::Bytes::from(chunk))),
+                        )),
+                    })
+                })
+            }
+        }
+
+        let transport = Arc::new(Canned::default());
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .transport(Arc::clone(&transport))
+            .build()
+            .unwrap();
+
+        let mut stream = client
+            .stream("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+            .send()
+            .await
+            .unwrap();
+        while let Some(chunk) = stream.next().await {
+            chunk.unwrap();
+        }
+        assert_eq!(stream.finalize().unwrap().content, "Hello");
+
+        let request = transport.0.lock().unwrap().take().unwrap();
+        assert!(request.url.ends_with("/chat/completions"));
+        assert_eq!(request.headers[CONTENT_TYPE], "application/json");
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["model"], "llama-3.3-70b");
+    }
+
+    #[tokio::test]
+    async fn test_encryption() {
+        use crate::transport::{HttpRequest, HttpResponse, HttpTransport, PayloadCipher};
+        use futures::future::BoxFuture;
+        use std::sync::Mutex;
+
+        fn xor(bytes: &[u8]) -> bytes::Bytes {
+            bytes.iter().map(|b| b ^ 0x5a).collect()
+        }
+
+        /// XORs every byte; a stand-in for a real cipher.
+        struct Xor;
+
+        impl PayloadCipher for Xor {
+            fn encrypt(&self, request: &mut HttpRequest) -> Result<(), Error> {
+                request.body = xor(&request.body);
+                request
+                    .headers
+                    .insert("x-encrypted", HeaderValue::from_static("xor"));
+                Ok(())
+            }
+
+            fn decrypt(&self, mut response: HttpResponse) -> Result<HttpResponse, Error> {
+                response.body = Box::pin(response.body.map(|chunk| chunk.map(|c| xor(&c))));
+                Ok(response)
+            }
+        }
+
+        /// A relay that only sees ciphertext.
+        #[derive(Default)]
+        struct Relay(Mutex<Option<HttpRequest>>);
+
+        impl HttpTransport for Relay {
+            fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+                *self.0.lock().unwrap() = Some(request);
+                Box::pin(async {
+                    let sse = "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\ndata: [DONE]\n\n";
+                    let (head, tail) = sse.split_at(17);
+                    Ok(HttpResponse {
+                        status: 200,
+                        headers: HeaderMap::new(),
+                        body: Box::pin(futures::stream::iter(
+                            [head, tail].map(|chunk| Ok(xor(chunk.as_bytes()))),
+                        )),
+                    })
+                })
+            }
+        }
+
+        let relay = Arc::new(Relay::default());
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .transport(Arc::clone(&relay))
+            .encryption(Xor)
+            .build()
+            .unwrap();
+
+        let result = client
+            .stream("cerebras/llama-3.3-70b", vec![Message::user("secret")])
+            .send()
+            .await
+            .unwrap()
+            .finalize_drained()
+            .await
+            .unwrap();
+        assert_eq!(result.content, "Hi");
+
+        let request = relay.0.lock().unwrap().take().unwrap();
+        assert_eq!(request.headers["x-encrypted"], "xor");
+        assert!(serde_json::from_slice::<serde_json::Value>(&request.body).is_err());
+        let body: serde_json::Value = serde_json::from_slice(&xor(&request.body)).unwrap();
+        assert_eq!(body["messages"][0]["content"], "secret");
+    }
+
+    #[tokio::test]
+    async fn test_prepared_request() {
+        use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+        use futures::future::BoxFuture;
+        use std::sync::Mutex;
+
+        /// Records request bodies and rejects every request.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<bytes::Bytes>>);
+
+        impl HttpTransport for Recorder {
+            fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+                self.0.lock().unwrap().push(request.body);
+                Box::pin(async { Ok(HttpResponse::from_bytes(400, HeaderMap::new(), "{}")) })
+            }
+        }
+
+        let transport = Arc::new(Recorder::default());
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .api_key("claude", "test")
+            .api_key("openai", "test")
+            .api_key("gemini", "test")
+            .transport(Arc::clone(&transport))
+            .build()
+            .unwrap();
+        let tool = Tool::function(
+            "search",
+            "Search the web",
+            serde_json::json!({"type": "object"}),
+        );
+        let conversations = [
+            vec![Message::user("Hi")],
+            vec![
+                Message::user("Hi"),
+                Message::assistant("Hello"),
+                Message::user("Search"),
+            ],
+            // System messages move into their own field; the body is built in full
+            vec![Message::system("Be brief"), Message::user("Hi")],
+        ];
+
+        for model in [
+            "cerebras/llama-3.3-70b",
+            "claude/claude-3-haiku",
+            "openai/gpt-4o",
+            "gemini/gemini-2.0-flash",
+        ] {
+            let build = |messages: Vec<Message>| {
+                client
+                    .complete(model, messages)
+                    .system("You are helpful")
+                    .tools(vec![tool.clone()])
+                    .max_tokens(100)
+            };
+            assert!(build(vec![Message::user("Hi")]).prepare().is_err());
+            let prepared = build(Vec::new()).prepare().unwrap();
+            assert!(prepared.complete_body.is_some(), "{model}");
+
+            for messages in &conversations {
+                assert!(prepared.complete(messages).await.is_err());
+                assert!(build(messages.clone()).send_complete().await.is_err());
+
+                let bodies = transport.0.lock().unwrap();
+                let [.., spliced, full] = bodies.as_slice() else {
+                    unreachable!()
+                };
+                assert_eq!(spliced, full, "{model}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .build()
+            .unwrap();
+
+        let pending = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .complete("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+                    .send_complete()
+                    .await
+            }
+        });
+        while client.in_flight() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(!client.shutdown(Duration::from_millis(50)).await);
+        assert!(matches!(pending.await.unwrap(), Err(Error::Aborted)));
+        assert_eq!(client.in_flight(), 0);
+
+        let err = client
+            .complete("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ShuttingDown));
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+            .mount(&server)
+            .await;
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .build()
+            .unwrap();
+        let kill_switch = client.kill_switch();
+
+        let pending = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .complete("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+                    .send_complete()
+                    .await
+            }
+        });
+        while client.in_flight() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        kill_switch.clone().trip();
+        assert!(matches!(pending.await.unwrap(), Err(Error::Aborted)));
+
+        let err = client
+            .complete("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+            .send_complete()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Aborted));
+
+        kill_switch.reset();
+        assert!(!kill_switch.is_tripped());
+    }
+
+    #[tokio::test]
+    async fn test_usage_store() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-request-id", "req_123")
+                    .set_body_string(
+                        r#"{"id":"1","model":"llama-3.3-70b","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1000,"completion_tokens":500,"total_tokens":1500}}"#,
+                    ),
+            )
+            .mount(&server)
+            .await;
+
+        let store = MemoryStore::default();
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .usage_store(store.clone())
+            .build()
+            .unwrap();
+
+        client
+            .complete("cerebras/llama-3.3-70b", vec![Message::user("Hi")])
+            .send_complete()
+            .await
+            .unwrap();
+        while store.0.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let records = store.0.lock().unwrap();
+        assert_eq!(records[0].provider, "cerebras");
+        assert_eq!(records[0].usage.input_tokens, 1000);
+        assert_eq!(records[0].request_id.as_deref(), Some("req_123"));
+        assert!((records[0].cost_usd.unwrap() - 0.0009).abs() < 1e-10);
     }
 }
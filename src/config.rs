@@ -0,0 +1,192 @@
+//! Client configuration files (`config` feature).
+//!
+//! [`ClientBuilder::from_config_path`](crate::ClientBuilder::from_config_path)
+//! reads a TOML or YAML file, chosen by extension, so deployments configure
+//! providers, retries, default models, and pricing declaratively:
+//!
+//! ```toml
+//! timeout_secs = 60
+//!
+//! [retry]
+//! max_retries = 5
+//! backoff_secs = 0.5
+//!
+//! [providers.openai]
+//! api_key_env = "GATEWAY_OPENAI_KEY"
+//! base_url = "https://gateway.internal/openai/v1"
+//! query = { api-version = "2024-10-21" }
+//!
+//! # Model aliases: client.stream("default", ...)
+//! [aliases]
+//! default = "openai/gpt-4o"
+//! fast = "cerebras/llama3.1-8b"
+//!
+//! # USD per 1M tokens, replacing the bundled prices
+//! [pricing."openai/gpt-4o"]
+//! input_per_million = 2.5
+//! output_per_million = 10.0
+//! ```
+//!
+//! Every setting is optional; unknown keys are rejected so typos don't go unnoticed.
+
+use crate::cost::ModelPricing;
+use crate::error::Error;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::Duration;
+
+/// Contents of a configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Request timeout in seconds.
+    pub timeout_secs: Option<f64>,
+    pub retry: RetryConfig,
+    /// Settings per provider name.
+    pub providers: HashMap<String, ProviderConfig>,
+    /// Model aliases, e.g. `default = "openai/gpt-4o"`.
+    pub aliases: HashMap<String, String>,
+    /// Pricing per `provider/model`, in USD per 1M tokens.
+    pub pricing: HashMap<String, ModelPricing>,
+}
+
+/// Retry policy; unset values keep the client defaults.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_retries: Option<u32>,
+    /// Initial backoff in seconds.
+    pub backoff_secs: Option<f64>,
+    /// Maximum backoff in seconds.
+    pub max_backoff_secs: Option<f64>,
+    pub backoff_multiplier: Option<f32>,
+    /// Minimum backoff in seconds after the provider reports it is overloaded.
+    pub overloaded_backoff_secs: Option<f64>,
+    /// Randomize backoffs (`false` for exact, repeatable delays).
+    pub jitter: Option<bool>,
+}
+
+/// Settings of one provider.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderConfig {
+    /// Environment variable holding the API key, instead of the standard one.
+    pub api_key_env: Option<String>,
+    pub base_url: Option<String>,
+    /// Chat endpoint path, see [`ClientBuilder::endpoint`](crate::ClientBuilder::endpoint).
+    pub endpoint: Option<String>,
+    /// Streaming endpoint path, see
+    /// [`ClientBuilder::stream_endpoint`](crate::ClientBuilder::stream_endpoint).
+    pub stream_endpoint: Option<String>,
+    /// Query parameters added to every request URL.
+    pub query: BTreeMap<String, String>,
+}
+
+impl ConfigFile {
+    /// Read a `.toml`, `.yaml`, or `.yml` file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("config file {}: {e}", path.display())))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&text),
+            Some("yaml" | "yml") => Self::from_yaml_str(&text),
+            _ => Err(Error::Config(format!(
+                "config file {}: expected a .toml, .yaml, or .yml extension",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Parse TOML.
+    pub fn from_toml_str(text: &str) -> Result<Self, Error> {
+        toml::from_str(text).map_err(|e| Error::Config(format!("config file: {e}")))
+    }
+
+    /// Parse YAML.
+    pub fn from_yaml_str(text: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(text).map_err(|e| Error::Config(format!("config file: {e}")))
+    }
+}
+
+/// Convert a setting in seconds, rejecting negative and non-finite values.
+pub(crate) fn secs(name: &str, value: f64) -> Result<Duration, Error> {
+    Duration::try_from_secs_f64(value)
+        .map_err(|_| Error::Config(format!("config file: invalid {name}: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClientBuilder;
+
+    #[test]
+    fn test_toml_and_yaml_agree() {
+        let toml = ConfigFile::from_toml_str(
+            r#"
+            timeout_secs = 30
+            [retry]
+            max_retries = 5
+            backoff_secs = 0.25
+            [providers.openai]
+            base_url = "http://localhost:8080/v1"
+            query = { api-version = "2024-10-21" }
+            [aliases]
+            default = "openai/gpt-4o"
+            [pricing."openai/gpt-4o"]
+            input_per_million = 2.0
+            output_per_million = 8.0
+            "#,
+        )
+        .unwrap();
+        let yaml = ConfigFile::from_yaml_str(
+            r#"
+            timeout_secs: 30
+            retry:
+              max_retries: 5
+              backoff_secs: 0.25
+            providers:
+              openai:
+                base_url: "http://localhost:8080/v1"
+                query: { api-version: "2024-10-21" }
+            aliases:
+              default: openai/gpt-4o
+            pricing:
+              openai/gpt-4o: { input_per_million: 2.0, output_per_million: 8.0 }
+            "#,
+        )
+        .unwrap();
+
+        for file in [toml, yaml] {
+            assert_eq!(file.timeout_secs, Some(30.0));
+            assert_eq!(file.retry.max_retries, Some(5));
+            assert_eq!(file.providers["openai"].query["api-version"], "2024-10-21");
+            assert!((file.pricing["openai/gpt-4o"].output_per_million - 8.0).abs() < f64::EPSILON);
+
+            let client = ClientBuilder::new()
+                .config_file(file)
+                .unwrap()
+                .build()
+                .unwrap();
+            assert_eq!(client.resolve_model("default"), "openai/gpt-4o");
+        }
+
+        assert!(ConfigFile::from_toml_str("timeout = 30").is_err());
+    }
+
+    #[test]
+    fn test_api_key_env() {
+        let file = ConfigFile::from_toml_str(
+            r#"
+            [providers.cerebras]
+            api_key_env = "RUST_AI_SDK_TEST_UNSET_KEY"
+            "#,
+        )
+        .unwrap();
+        let err = ClientBuilder::new().config_file(file).err().unwrap();
+        assert!(
+            matches!(err, Error::Config(ref msg) if msg.contains("RUST_AI_SDK_TEST_UNSET_KEY"))
+        );
+    }
+}
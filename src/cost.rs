@@ -3,6 +3,7 @@
 //! Provides pricing information and cost calculation for different providers and models.
 
 use crate::types::Usage;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Cost in USD for token usage.
@@ -16,26 +17,37 @@ pub struct Cost {
     pub cache_read_cost: f64,
     /// Cost for cache creation in USD (if applicable).
     pub cache_write_cost: f64,
+    /// Cost for web searches the provider ran in USD (if applicable).
+    pub web_search_cost: f64,
 }
 
 impl Cost {
     /// Total cost in USD.
     pub fn total(&self) -> f64 {
-        self.input_cost + self.output_cost + self.cache_read_cost + self.cache_write_cost
+        self.input_cost
+            + self.output_cost
+            + self.cache_read_cost
+            + self.cache_write_cost
+            + self.web_search_cost
     }
 }
 
 /// Pricing per 1M tokens for a model.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ModelPricing {
     /// Cost per 1M input tokens.
     pub input_per_million: f64,
     /// Cost per 1M output tokens.
     pub output_per_million: f64,
     /// Cost per 1M cached input tokens (if supported).
+    #[serde(default)]
     pub cache_read_per_million: Option<f64>,
     /// Cost per 1M tokens for cache creation (if supported).
+    #[serde(default)]
     pub cache_write_per_million: Option<f64>,
+    /// Cost per 1K web searches (if supported).
+    #[serde(default)]
+    pub web_search_per_thousand: Option<f64>,
 }
 
 impl ModelPricing {
@@ -45,6 +57,7 @@ impl ModelPricing {
             output_per_million: output,
             cache_read_per_million: None,
             cache_write_per_million: None,
+            web_search_per_thousand: None,
         }
     }
 
@@ -54,6 +67,11 @@ impl ModelPricing {
         self
     }
 
+    pub const fn with_web_search(mut self, per_thousand: f64) -> Self {
+        self.web_search_per_thousand = Some(per_thousand);
+        self
+    }
+
     /// Calculate cost for given usage.
     pub fn calculate(&self, usage: &Usage) -> Cost {
         let input_cost = (f64::from(usage.input_tokens) / 1_000_000.0) * self.input_per_million;
@@ -67,18 +85,30 @@ impl ModelPricing {
             (f64::from(usage.cache_creation_input_tokens) / 1_000_000.0) * rate
         });
 
+        let web_search_cost = self.web_search_per_thousand.map_or(0.0, |rate| {
+            (f64::from(usage.web_search_requests) / 1_000.0) * rate
+        });
+
         Cost {
             input_cost,
             output_cost,
             cache_read_cost,
             cache_write_cost,
+            web_search_cost,
         }
     }
 }
 
 /// Registry of model pricing.
+///
+/// Entries are keyed by `provider/model`. A key ending in `*` prices every model
+/// starting with the rest of it (`openai/gpt-4o*`, `cerebras/*`). Lookups try the
+/// exact model, then the model without a snapshot date (`gpt-4o-2024-08-06` is
+/// priced as `gpt-4o`), then the longest matching pattern.
 pub struct PricingRegistry {
     prices: HashMap<String, ModelPricing>,
+    // Patterns without their `*`, longest first
+    patterns: Vec<(String, ModelPricing)>,
 }
 
 impl PricingRegistry {
@@ -122,14 +152,18 @@ impl PricingRegistry {
             ModelPricing::new(0.075, 0.30),
         );
 
-        // Claude pricing (as of 2024)
+        // Claude pricing (as of 2024); web search is billed per 1K searches
         prices.insert(
             "claude/claude-3-5-sonnet-20241022".to_string(),
-            ModelPricing::new(3.00, 15.00).with_cache(0.30, 3.75),
+            ModelPricing::new(3.00, 15.00)
+                .with_cache(0.30, 3.75)
+                .with_web_search(10.00),
         );
         prices.insert(
             "claude/claude-3-5-haiku-20241022".to_string(),
-            ModelPricing::new(0.80, 4.00).with_cache(0.08, 1.00),
+            ModelPricing::new(0.80, 4.00)
+                .with_cache(0.08, 1.00)
+                .with_web_search(10.00),
         );
         prices.insert(
             "claude/claude-3-opus-20240229".to_string(),
@@ -158,12 +192,68 @@ impl PricingRegistry {
             ModelPricing::new(3.00, 12.00).with_cache(1.50, 3.00),
         );
 
-        Self { prices }
+        // DeepSeek pricing (as of 2025, cache misses)
+        prices.insert(
+            "deepseek/deepseek-chat".to_string(),
+            ModelPricing::new(0.27, 1.10),
+        );
+        prices.insert(
+            "deepseek/deepseek-reasoner".to_string(),
+            ModelPricing::new(0.55, 2.19),
+        );
+
+        // AI21 pricing (as of 2024)
+        prices.insert("ai21/jamba-mini".to_string(), ModelPricing::new(0.20, 0.40));
+        prices.insert(
+            "ai21/jamba-large".to_string(),
+            ModelPricing::new(2.00, 8.00),
+        );
+
+        Self::insert_embedding_prices(&mut prices);
+
+        Self {
+            prices,
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Default pricing of embedding models.
+    fn insert_embedding_prices(prices: &mut HashMap<String, ModelPricing>) {
+        // Embedding pricing (as of 2024, input tokens only)
+        prices.insert("voyage/voyage-3".to_string(), ModelPricing::new(0.06, 0.0));
+        prices.insert(
+            "voyage/voyage-3-lite".to_string(),
+            ModelPricing::new(0.02, 0.0),
+        );
+        prices.insert(
+            "voyage/voyage-3-large".to_string(),
+            ModelPricing::new(0.18, 0.0),
+        );
+        prices.insert(
+            "voyage/voyage-code-3".to_string(),
+            ModelPricing::new(0.18, 0.0),
+        );
+        prices.insert(
+            "jina/jina-embeddings-v3".to_string(),
+            ModelPricing::new(0.02, 0.0),
+        );
+        prices.insert(
+            "jina/jina-clip-v2".to_string(),
+            ModelPricing::new(0.02, 0.0),
+        );
     }
 
     /// Get pricing for a model.
     pub fn get(&self, model: &str) -> Option<&ModelPricing> {
-        self.prices.get(model)
+        self.prices
+            .get(model)
+            .or_else(|| undated(model).and_then(|base| self.prices.get(base)))
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(prefix, _)| model.starts_with(prefix.as_str()))
+                    .map(|(_, pricing)| pricing)
+            })
     }
 
     /// Calculate cost for a model and usage.
@@ -171,10 +261,36 @@ impl PricingRegistry {
         self.get(model).map(|p| p.calculate(usage))
     }
 
-    /// Add or update pricing for a model.
+    /// Add or update pricing for a model, or for a pattern ending in `*`.
     pub fn set(&mut self, model: impl Into<String>, pricing: ModelPricing) {
-        self.prices.insert(model.into(), pricing);
+        let model = model.into();
+        let Some(prefix) = model.strip_suffix('*') else {
+            self.prices.insert(model, pricing);
+            return;
+        };
+        match self.patterns.iter_mut().find(|(p, _)| p == prefix) {
+            Some((_, existing)) => *existing = pricing,
+            None => {
+                self.patterns.push((prefix.to_string(), pricing));
+                self.patterns
+                    .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+            }
+        }
+    }
+}
+
+/// `model` without a trailing snapshot date: `-2024-08-06`, `-20241022` or `@20241022`.
+fn undated(model: &str) -> Option<&str> {
+    fn digits(s: &str, len: usize) -> bool {
+        s.len() == len && s.bytes().all(|b| b.is_ascii_digit())
+    }
+    let (base, last) = model.rsplit_once(['-', '@'])?;
+    if digits(last, 8) {
+        return Some(base);
     }
+    let (rest, month) = base.rsplit_once('-')?;
+    let (base, year) = rest.rsplit_once('-')?;
+    (digits(last, 2) && digits(month, 2) && digits(year, 4)).then_some(base)
 }
 
 impl Default for PricingRegistry {
@@ -183,6 +299,31 @@ impl Default for PricingRegistry {
     }
 }
 
+/// How a request ended. Cancelled and failed streams are billed for the
+/// tokens generated before they stopped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestOutcome {
+    /// The response arrived in full.
+    #[default]
+    Success,
+    /// The caller stopped reading, aborted, or dropped the stream.
+    Cancelled,
+    /// The stream failed mid-way.
+    Error,
+}
+
+impl RequestOutcome {
+    /// Name used in records: `success`, `cancelled` or `error`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Cancelled => "cancelled",
+            Self::Error => "error",
+        }
+    }
+}
+
 /// Track cumulative costs across multiple requests.
 #[derive(Debug, Clone, Default)]
 pub struct CostTracker {
@@ -192,6 +333,9 @@ pub struct CostTracker {
     total_cache_write_tokens: u64,
     total_cost: f64,
     request_count: u32,
+    // Requests and cost per outcome, indexed by `RequestOutcome as usize`
+    outcome_counts: [u32; 3],
+    outcome_costs: [f64; 3],
 }
 
 impl CostTracker {
@@ -199,16 +343,23 @@ impl CostTracker {
         Self::default()
     }
 
-    /// Record usage and cost from a request.
+    /// Record usage and cost from a successful request.
     pub fn record(&mut self, usage: &Usage, cost: Option<&Cost>) {
+        self.record_outcome(usage, cost, RequestOutcome::Success);
+    }
+
+    /// Record usage and cost from a request that ended with `outcome`.
+    pub fn record_outcome(&mut self, usage: &Usage, cost: Option<&Cost>, outcome: RequestOutcome) {
         self.total_input_tokens += u64::from(usage.input_tokens);
         self.total_output_tokens += u64::from(usage.output_tokens);
         self.total_cache_read_tokens += u64::from(usage.cache_read_input_tokens);
         self.total_cache_write_tokens += u64::from(usage.cache_creation_input_tokens);
         if let Some(c) = cost {
             self.total_cost += c.total();
+            self.outcome_costs[outcome as usize] += c.total();
         }
         self.request_count += 1;
+        self.outcome_counts[outcome as usize] += 1;
     }
 
     /// Get total input tokens.
@@ -231,6 +382,16 @@ impl CostTracker {
         self.request_count
     }
 
+    /// Number of requests that ended with `outcome`.
+    pub fn outcome_count(&self, outcome: RequestOutcome) -> u32 {
+        self.outcome_counts[outcome as usize]
+    }
+
+    /// Cost in USD of the requests that ended with `outcome`.
+    pub fn outcome_cost(&self, outcome: RequestOutcome) -> f64 {
+        self.outcome_costs[outcome as usize]
+    }
+
     /// Reset the tracker.
     pub fn reset(&mut self) {
         *self = Self::default();
@@ -258,17 +419,21 @@ mod tests {
 
     #[test]
     fn test_cache_cost() {
-        let pricing = ModelPricing::new(1.0, 2.0).with_cache(0.25, 1.0);
+        let pricing = ModelPricing::new(1.0, 2.0)
+            .with_cache(0.25, 1.0)
+            .with_web_search(10.0);
         let usage = Usage {
             input_tokens: 1000,
             output_tokens: 500,
             cache_read_input_tokens: 2000,
             cache_creation_input_tokens: 500,
+            web_search_requests: 3,
         };
 
         let cost = pricing.calculate(&usage);
         assert!(cost.cache_read_cost > 0.0);
         assert!(cost.cache_write_cost > 0.0);
+        assert!((cost.web_search_cost - 0.03).abs() < 1e-10);
     }
 
     #[test]
@@ -278,6 +443,35 @@ mod tests {
         assert!(registry.get("gemini/gemini-1.5-pro").is_some());
     }
 
+    #[test]
+    fn test_registry_fallbacks() {
+        let mut registry = PricingRegistry::new();
+        registry.set("openai/gpt-4o", ModelPricing::new(2.5, 10.0));
+        registry.set("openai/gpt-4o*", ModelPricing::new(5.0, 15.0));
+        registry.set("openai/*", ModelPricing::new(1.0, 1.0));
+        registry.set("claude/claude-3-5-sonnet", ModelPricing::new(3.0, 15.0));
+
+        let input = |registry: &PricingRegistry, model: &str| {
+            registry.get(model).map(|p| p.input_per_million)
+        };
+        assert_eq!(input(&registry, "openai/gpt-4o-2024-08-06"), Some(2.5));
+        assert_eq!(
+            input(&registry, "claude/claude-3-5-sonnet-20241022"),
+            Some(3.0)
+        );
+        assert_eq!(
+            input(&registry, "claude/claude-3-5-sonnet@20241022"),
+            Some(3.0)
+        );
+        // The longest pattern wins
+        assert_eq!(input(&registry, "openai/gpt-4o-audio-preview"), Some(5.0));
+        assert_eq!(input(&registry, "openai/o3"), Some(1.0));
+        assert_eq!(input(&registry, "claude/claude-3-opus-2024"), None);
+
+        registry.set("openai/*", ModelPricing::new(2.0, 2.0));
+        assert_eq!(input(&registry, "openai/o3"), Some(2.0));
+    }
+
     #[test]
     fn test_cost_tracker() {
         let mut tracker = CostTracker::new();
@@ -297,5 +491,13 @@ mod tests {
         assert_eq!(tracker.output_tokens(), 50);
         assert!((tracker.total_cost() - 0.003).abs() < 1e-10);
         assert_eq!(tracker.request_count(), 1);
+
+        // Cancelled generations still count towards the totals
+        tracker.record_outcome(&usage, Some(&cost), RequestOutcome::Cancelled);
+        assert_eq!(tracker.output_tokens(), 100);
+        assert!((tracker.total_cost() - 0.006).abs() < 1e-10);
+        assert_eq!(tracker.outcome_count(RequestOutcome::Success), 1);
+        assert_eq!(tracker.outcome_count(RequestOutcome::Cancelled), 1);
+        assert!((tracker.outcome_cost(RequestOutcome::Cancelled) - 0.003).abs() < 1e-10);
     }
 }
@@ -1,32 +1,66 @@
+use crate::types::SafetyRating;
 use std::time::Duration;
 use thiserror::Error;
 
+/// Response headers that identify a request on the provider side.
+const REQUEST_ID_HEADERS: &[&str] = &["x-request-id", "request-id", "x-goog-request-id"];
+
 /// Unified error type across all providers.
 #[derive(Error, Debug)]
 pub enum Error {
     /// Rate limited by the provider. Check `retry_after` for suggested wait time.
     #[error("rate limited")]
-    RateLimited { retry_after: Option<Duration> },
+    RateLimited {
+        retry_after: Option<Duration>,
+        details: Option<Box<ErrorDetails>>,
+    },
 
     /// Invalid or missing API key.
     #[error("unauthorized")]
-    Unauthorized,
+    Unauthorized { details: Option<Box<ErrorDetails>> },
 
     /// Server error (5xx status codes).
-    #[error("server error ({0})")]
-    Server(u16),
+    #[error("server error ({status})")]
+    Server {
+        status: u16,
+        details: Option<Box<ErrorDetails>>,
+    },
+
+    /// Provider is temporarily overloaded (Anthropic 529 / `overloaded_error`).
+    #[error("overloaded: {message}")]
+    Overloaded {
+        message: String,
+        details: Option<Box<ErrorDetails>>,
+    },
 
     /// API error with provider-specific message.
     #[error("{message}")]
-    Api { status: u16, message: String },
+    Api {
+        status: u16,
+        message: String,
+        details: Option<Box<ErrorDetails>>,
+    },
+
+    /// The provider's safety filters blocked the prompt or returned no output
+    /// (Gemini `blockReason` or an empty candidate list).
+    #[error("content blocked: {reason}{}", flagged_categories(ratings))]
+    ContentBlocked {
+        /// Provider block reason, e.g. `SAFETY` or `PROHIBITED_CONTENT`.
+        reason: String,
+        /// Safety ratings of the prompt, if the provider reported them.
+        ratings: Vec<SafetyRating>,
+    },
 
     /// Request or connection timeout.
     #[error("timeout")]
     Timeout,
 
     /// JSON or SSE parsing error.
-    #[error("parse: {0}")]
-    Parse(String),
+    #[error("parse: {message}")]
+    Parse {
+        message: String,
+        details: Option<Box<ErrorDetails>>,
+    },
 
     /// Invalid model ID format.
     #[error("invalid model: {0}")]
@@ -37,9 +71,19 @@ pub enum Error {
     MissingApiKey(String),
 
     /// HTTP/network error.
+    #[cfg(feature = "client")]
     #[error("http: {0}")]
     Http(#[from] reqwest::Error),
 
+    /// Failure of a custom [`HttpTransport`](crate::transport::HttpTransport)
+    /// before a response arrived.
+    #[error("transport: {message}")]
+    Transport {
+        message: String,
+        /// The request never reached the server, so retrying is safe.
+        retryable: bool,
+    },
+
     /// Stream was already consumed.
     #[error("stream already finalized")]
     StreamConsumed,
@@ -47,6 +91,125 @@ pub enum Error {
     /// Invalid configuration.
     #[error("config: {0}")]
     Config(String),
+
+    /// Request was cancelled before it completed (client shutdown or kill switch).
+    #[error("request aborted")]
+    Aborted,
+
+    /// Client is shutting down and no longer accepts requests.
+    #[error("client is shutting down")]
+    ShuttingDown,
+
+    /// Rate limit queue is at its maximum depth.
+    #[error("rate limit queue full")]
+    QueueFull,
+
+    /// Request waited in the rate limit queue longer than its timeout.
+    #[error("timed out in rate limit queue")]
+    QueueTimeout,
+
+    /// The request's estimated input exceeds the model's context window minus
+    /// `max_tokens`; rejected before sending by the client's context guard.
+    #[error("input of ~{estimated} tokens exceeds the {available} tokens available")]
+    ContextLengthExceeded { estimated: u32, available: u32 },
+
+    /// The tenant has spent its budget.
+    #[error("tenant {tenant} exceeded its budget of ${budget:.2}")]
+    BudgetExceeded { tenant: String, budget: f64 },
+
+    /// No attempt of [`RequestBuilder::validated`](crate::RequestBuilder::validated)
+    /// produced output that parsed into the requested type.
+    #[error("output failed validation after {} attempts: {}", attempts.len(), last_error(attempts))]
+    Validation { attempts: Vec<ValidationAttempt> },
+
+    /// A tool-calling loop hit a [`ToolCallGuard`](crate::ToolCallGuard) limit:
+    /// too many calls, or the same call over and over.
+    #[error("agent loop detected: {0}")]
+    AgentLoopDetected(String),
+}
+
+/// An attempt at structured output that failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationAttempt {
+    /// The model's output.
+    pub output: String,
+    /// Why it didn't parse.
+    pub error: String,
+}
+
+/// Raw response context captured when a request fails.
+///
+/// Attached to provider errors so failures can be correlated with provider logs.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorDetails {
+    /// HTTP status code of the response.
+    pub status: u16,
+    /// Provider request ID, if the response carried one.
+    pub request_id: Option<String>,
+    /// Raw response body (or the SSE payload for mid-stream errors).
+    pub body: String,
+    /// Headers of interest: request IDs, rate-limit state, and `retry-after`.
+    pub headers: Vec<(String, String)>,
+}
+
+impl ErrorDetails {
+    /// Capture details from a response status, headers, and body.
+    pub fn from_response(status: u16, headers: &http::HeaderMap, body: impl Into<String>) -> Self {
+        let request_id = request_id_from_headers(headers);
+
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| is_header_of_interest(name.as_str()))
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|v| (name.as_str().to_string(), v.to_string()))
+            })
+            .collect();
+
+        Self {
+            status,
+            request_id,
+            body: body.into(),
+            headers,
+        }
+    }
+}
+
+/// Extract the provider request ID from response headers.
+pub(crate) fn request_id_from_headers(headers: &http::HeaderMap) -> Option<String> {
+    REQUEST_ID_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string)
+}
+
+fn is_header_of_interest(name: &str) -> bool {
+    REQUEST_ID_HEADERS.contains(&name)
+        || name == "retry-after"
+        || name.starts_with("x-ratelimit-")
+        || name.starts_with("anthropic-ratelimit-")
+}
+
+/// Validation error of the last attempt, empty if there was none.
+fn last_error(attempts: &[ValidationAttempt]) -> &str {
+    attempts.last().map_or("", |attempt| attempt.error.as_str())
+}
+
+/// ` (CATEGORY, ...)` listing ratings that were blocked or rated medium/high.
+fn flagged_categories(ratings: &[SafetyRating]) -> String {
+    let flagged: Vec<&str> = ratings
+        .iter()
+        .filter(|r| r.blocked || matches!(r.probability.as_str(), "MEDIUM" | "HIGH"))
+        .map(|r| r.category.as_str())
+        .collect();
+    if flagged.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", flagged.join(", "))
+    }
 }
 
 impl Error {
@@ -55,7 +218,10 @@ impl Error {
     pub fn is_retryable(&self) -> bool {
         matches!(
             self,
-            Error::RateLimited { .. } | Error::Server(_) | Error::Timeout
+            Error::RateLimited { .. }
+                | Error::Server { .. }
+                | Error::Overloaded { .. }
+                | Error::Timeout
         )
     }
 
@@ -64,11 +230,110 @@ impl Error {
         Self::Api {
             status,
             message: message.into(),
+            details: None,
         }
     }
 
     /// Create a parse error.
     pub fn parse(msg: impl Into<String>) -> Self {
-        Self::Parse(msg.into())
+        Self::Parse {
+            message: msg.into(),
+            details: None,
+        }
+    }
+
+    /// Attach response details, if this error kind carries them and has none yet.
+    pub fn with_details(mut self, new: ErrorDetails) -> Self {
+        match &mut self {
+            Error::RateLimited { details, .. }
+            | Error::Unauthorized { details }
+            | Error::Server { details, .. }
+            | Error::Overloaded { details, .. }
+            | Error::Api { details, .. }
+            | Error::Parse { details, .. }
+                if details.is_none() =>
+            {
+                *details = Some(Box::new(new));
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Raw response details, if captured.
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            Error::RateLimited { details, .. }
+            | Error::Unauthorized { details }
+            | Error::Server { details, .. }
+            | Error::Overloaded { details, .. }
+            | Error::Api { details, .. }
+            | Error::Parse { details, .. } => details.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Provider request ID, if captured.
+    pub fn request_id(&self) -> Option<&str> {
+        self.details().and_then(|d| d.request_id.as_deref())
+    }
+
+    /// HTTP status code associated with this error, if any.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::RateLimited { .. } => Some(429),
+            Error::Unauthorized { .. } => Some(401),
+            Error::Overloaded { .. } => Some(529),
+            Error::Server { status, .. } | Error::Api { status, .. } => Some(*status),
+            #[cfg(feature = "client")]
+            Error::Http(e) => e.status().map(|s| s.as_u16()),
+            _ => self.details().map(|d| d.status),
+        }
+    }
+
+    /// Raw response body, if captured.
+    pub fn body(&self) -> Option<&str> {
+        self.details().map(|d| d.body.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::{HeaderMap, HeaderValue};
+
+    #[test]
+    fn test_details_from_response() {
+        let mut headers = HeaderMap::new();
+        headers.insert("request-id", HeaderValue::from_static("req_123"));
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from_static("0"),
+        );
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+
+        let details = ErrorDetails::from_response(400, &headers, "{\"error\":{}}");
+        assert_eq!(details.request_id.as_deref(), Some("req_123"));
+        assert_eq!(details.headers.len(), 2);
+        assert!(details.headers.iter().all(|(k, _)| k != "content-type"));
+    }
+
+    #[test]
+    fn test_with_details() {
+        let details = ErrorDetails {
+            status: 200,
+            request_id: Some("req_1".into()),
+            body: "not json".into(),
+            headers: Vec::new(),
+        };
+
+        let err = Error::parse("expected value").with_details(details.clone());
+        assert_eq!(err.request_id(), Some("req_1"));
+        assert_eq!(err.body(), Some("not json"));
+        assert_eq!(err.status(), Some(200));
+
+        // Errors without a response context are left untouched
+        let err = Error::Timeout.with_details(details);
+        assert!(err.details().is_none());
     }
 }
@@ -0,0 +1,253 @@
+//! AWS event stream decoder.
+//!
+//! Bedrock streams responses as binary `application/vnd.amazon.eventstream`
+//! frames rather than SSE. Each `chunk` event wraps one provider event as
+//! base64 inside a small JSON envelope. This decoder unwraps those frames and
+//! re-emits the provider events as SSE `data:` lines, so the regular SSE parser
+//! and provider parsers handle them unchanged.
+//!
+//! Frame layout (all integers big-endian):
+//! ```text
+//! [total_len: u32][headers_len: u32][prelude_crc: u32][headers][payload][message_crc: u32]
+//! ```
+//! CRCs are not verified; the transport already guarantees integrity.
+
+use bytes::Bytes;
+use serde::Deserialize;
+
+/// Prelude (12 bytes) plus trailing message CRC (4 bytes).
+const FRAME_OVERHEAD: usize = 16;
+const PRELUDE_LEN: usize = 12;
+
+/// Incremental AWS event stream to SSE converter.
+#[derive(Debug, Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    /// Create a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed raw bytes and return SSE text for every frame completed by them.
+    pub fn decode(&mut self, bytes: &[u8]) -> Bytes {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut out = Vec::new();
+        let mut pos = 0;
+
+        while self.buffer.len() - pos >= PRELUDE_LEN {
+            let total_len = read_u32(&self.buffer[pos..]) as usize;
+            let headers_len = read_u32(&self.buffer[pos + 4..]) as usize;
+
+            if total_len < FRAME_OVERHEAD + headers_len {
+                // Unrecoverable: frame boundaries are lost
+                push_event(
+                    &mut out,
+                    &error_event("invalid_frame", "malformed event stream frame"),
+                );
+                pos = self.buffer.len();
+                break;
+            }
+            if self.buffer.len() - pos < total_len {
+                break;
+            }
+
+            let frame = &self.buffer[pos..pos + total_len];
+            let headers = &frame[PRELUDE_LEN..PRELUDE_LEN + headers_len];
+            let payload = &frame[PRELUDE_LEN + headers_len..total_len - 4];
+
+            if let Some(event) = frame_to_event(headers, payload) {
+                push_event(&mut out, &event);
+            }
+            pos += total_len;
+        }
+
+        self.buffer.drain(..pos);
+        Bytes::from(out)
+    }
+}
+
+/// Convert one frame to the JSON text of a provider event.
+fn frame_to_event(headers: &[u8], payload: &[u8]) -> Option<String> {
+    let message_type = header_value(headers, ":message-type").unwrap_or("event");
+
+    match message_type {
+        "event" => {
+            if header_value(headers, ":event-type") != Some("chunk") {
+                return None;
+            }
+            let envelope: ChunkEnvelope = serde_json::from_slice(payload).ok()?;
+            let decoded = base64_decode(&envelope.bytes)?;
+            String::from_utf8(decoded).ok()
+        }
+        "exception" => {
+            let exception_type = header_value(headers, ":exception-type").unwrap_or("exception");
+            let message = serde_json::from_slice::<ExceptionPayload>(payload).map_or_else(
+                |_| String::from_utf8_lossy(payload).into_owned(),
+                |p| p.message,
+            );
+            // Map onto the Claude error types the stream parser understands
+            let error_type = match exception_type {
+                "throttlingException" | "serviceUnavailableException" => "overloaded_error",
+                "internalServerException" | "modelStreamErrorException" => "api_error",
+                other => other,
+            };
+            Some(error_event(error_type, &message))
+        }
+        _ => {
+            let message = header_value(headers, ":error-message").unwrap_or("event stream error");
+            Some(error_event("api_error", message))
+        }
+    }
+}
+
+/// Find a string-typed header value by name.
+fn header_value<'a>(mut headers: &'a [u8], name: &str) -> Option<&'a str> {
+    while let Some((&name_len, rest)) = headers.split_first() {
+        let name_len = usize::from(name_len);
+        let header_name = rest.get(..name_len)?;
+        let (&value_type, rest) = rest.get(name_len..)?.split_first()?;
+
+        let value_len = match value_type {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => 2 + usize::from(u16::from_be_bytes([*rest.first()?, *rest.get(1)?])),
+            _ => return None,
+        };
+        let value = rest.get(..value_len)?;
+
+        if value_type == 7 && header_name == name.as_bytes() {
+            return std::str::from_utf8(&value[2..]).ok();
+        }
+        headers = &rest[value_len..];
+    }
+    None
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn push_event(out: &mut Vec<u8>, event: &str) {
+    for line in event.lines() {
+        out.extend_from_slice(b"data: ");
+        out.extend_from_slice(line.as_bytes());
+        out.push(b'\n');
+    }
+    out.push(b'\n');
+}
+
+fn error_event(error_type: &str, message: &str) -> String {
+    serde_json::json!({
+        "type": "error",
+        "error": {"type": error_type, "message": message}
+    })
+    .to_string()
+}
+
+/// Decode standard (padded) base64.
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    fn sextet(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some(u32::from(c - b'A')),
+            b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+            b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let input = input.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for &c in input {
+        // At most 14 pending bits are ever needed
+        acc = ((acc << 6) | sextet(c)?) & 0x3FFF;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits).to_le_bytes()[0]);
+        }
+    }
+    Some(out)
+}
+
+#[derive(Deserialize)]
+struct ChunkEnvelope {
+    bytes: String,
+}
+
+#[derive(Deserialize)]
+struct ExceptionPayload {
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut header_bytes = Vec::new();
+        for (name, value) in headers {
+            header_bytes.push(u8::try_from(name.len()).unwrap());
+            header_bytes.extend_from_slice(name.as_bytes());
+            header_bytes.push(7);
+            header_bytes.extend_from_slice(&u16::try_from(value.len()).unwrap().to_be_bytes());
+            header_bytes.extend_from_slice(value.as_bytes());
+        }
+        let total = FRAME_OVERHEAD + header_bytes.len() + payload.len();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&u32::try_from(total).unwrap().to_be_bytes());
+        out.extend_from_slice(&u32::try_from(header_bytes.len()).unwrap().to_be_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&header_bytes);
+        out.extend_from_slice(payload);
+        out.extend_from_slice(&[0; 4]);
+        out
+    }
+
+    #[test]
+    fn test_decode_chunk_across_reads() {
+        let event = r#"{"type":"message_stop"}"#;
+        let payload = r#"{"bytes":"eyJ0eXBlIjoibWVzc2FnZV9zdG9wIn0="}"#;
+        let bytes = frame(
+            &[(":message-type", "event"), (":event-type", "chunk")],
+            payload.as_bytes(),
+        );
+
+        let mut decoder = EventStreamDecoder::new();
+        let (head, tail) = bytes.split_at(20);
+        assert!(decoder.decode(head).is_empty());
+        assert_eq!(
+            &decoder.decode(tail)[..],
+            format!("data: {event}\n\n").as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_decode_exception() {
+        let bytes = frame(
+            &[
+                (":message-type", "exception"),
+                (":exception-type", "throttlingException"),
+            ],
+            br#"{"message":"Too many requests"}"#,
+        );
+
+        let out = EventStreamDecoder::new().decode(&bytes);
+        let text = std::str::from_utf8(&out).unwrap();
+        assert!(text.contains("overloaded_error"));
+        assert!(text.contains("Too many requests"));
+    }
+}
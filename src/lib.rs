@@ -1,9 +1,16 @@
 //! High-performance streaming LLM client with zero-copy parsing and usage tracking.
 //!
+//! The HTTP client lives behind the default `client` feature. Without it
+//! (`default-features = false, features = ["core"]`) the crate has no
+//! `reqwest`/`tokio` dependency and provides the types, SSE parser, provider
+//! request builders, and stream parsers for use with your own I/O.
+//!
 //! # Example
 //! ```no_run
+//! # #[cfg(feature = "client")]
 //! use rust_ai_sdk::{Client, Message, Role};
 //!
+//! # #[cfg(feature = "client")]
 //! #[tokio::main]
 //! async fn main() -> Result<(), rust_ai_sdk::Error> {
 //!     let client = Client::from_env()?;
@@ -26,21 +33,90 @@
 //!     println!("\nTokens: {} in, {} out", result.usage.input_tokens, result.usage.output_tokens);
 //!     Ok(())
 //! }
+//! # #[cfg(not(feature = "client"))]
+//! # fn main() {}
 //! ```
 
+pub mod capabilities;
+#[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "config")]
+pub mod config;
 pub mod cost;
 pub mod error;
+pub mod eventstream;
+#[cfg(feature = "client")]
+pub mod lifecycle;
+pub mod markdown;
+#[cfg(feature = "prometheus")]
+pub mod metrics;
+pub mod models;
+pub mod pool;
 pub mod providers;
+#[cfg(feature = "client")]
+pub mod ratelimit;
+#[cfg(feature = "client")]
+pub mod router;
+pub mod sanitize;
+pub mod schema;
+pub mod serve;
+#[cfg(feature = "client")]
+pub mod sleep;
 pub mod sse;
 pub mod stream;
+#[cfg(feature = "client")]
+pub mod tenant;
+pub mod tokens;
+pub mod toolguard;
+#[cfg(feature = "client")]
+pub mod transport;
 pub mod types;
+#[cfg(feature = "client")]
+pub mod usage;
+pub mod warning;
 
-pub use client::{Client, ClientBuilder, RequestBuilder};
-pub use cost::{Cost, CostTracker, ModelPricing, PricingRegistry};
-pub use error::Error;
-pub use stream::CompletionStream;
+pub use capabilities::ModelCapabilities;
+#[cfg(feature = "client")]
+pub use client::{
+    Client, ClientBuilder, ContextGuard, EmbeddingRequestBuilder, ErrorHook, HttpVersion,
+    PreparedRequest, Preset, RequestBuilder, RetryHook, StallHook, UsageHook, WarningHook,
+};
+#[cfg(feature = "config")]
+pub use config::ConfigFile;
+pub use cost::{Cost, CostTracker, ModelPricing, PricingRegistry, RequestOutcome};
+pub use error::{Error, ErrorDetails, ValidationAttempt};
+#[cfg(feature = "client")]
+pub use lifecycle::KillSwitch;
+#[cfg(feature = "prometheus")]
+pub use metrics::Metrics;
+pub use models::{Modality, ModelInfo, ModelRegistry, ModelStatus};
+pub use providers::claude::ClaudePlatform;
+pub use providers::{
+    AudioFormat, AudioOutput, EmbeddingInputType, FileSearch, Parameter, ReasoningEffort,
+    ResponseFormat, ServerTool, WebSearch,
+};
+#[cfg(feature = "client")]
+pub use ratelimit::{Priority, RateLimit};
+#[cfg(feature = "client")]
+pub use router::{CheapestModelRouter, TokenBudget};
+pub use sanitize::Sanitizer;
+#[cfg(feature = "client")]
+pub use sleep::{Sleeper, TokioSleeper};
+pub use sse::{OwnedSseEvent, SseStream};
+#[cfg(feature = "client")]
+pub use stream::{ChunkTransform, CompletionStream, RawStream, TrimTrailingWhitespace};
+#[cfg(feature = "client")]
+pub use tenant::Tenant;
+pub use tokens::TruncateStrategy;
+pub use toolguard::ToolCallGuard;
+#[cfg(feature = "client")]
+pub use transport::{
+    EncryptedTransport, HttpRequest, HttpResponse, HttpTransport, PayloadCipher, ReqwestTransport,
+};
 pub use types::*;
+#[cfg(feature = "client")]
+pub use usage::{FileUsageStore, RequestRecord, UsageStore};
+pub use warning::Warning;
 
 /// Result type alias for this crate.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,163 @@
+//! Request lifecycle: in-flight tracking and cancellation.
+//!
+//! Every request registers with its client's lifecycle while it runs, so
+//! [`Client::shutdown`](crate::Client::shutdown) can stop new requests, wait
+//! for in-flight ones, and cancel whatever is still running at the deadline.
+//! A [`KillSwitch`] cancels everything at once.
+
+use crate::error::Error;
+use crate::ratelimit::Permit;
+use futures::future::{select, Either};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+
+/// Shared request state of a client and all its clones.
+#[derive(Debug)]
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+    abort: watch::Sender<bool>,
+}
+
+impl Lifecycle {
+    pub(crate) fn new() -> Self {
+        Self {
+            closed: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+            abort: watch::channel(false).0,
+        }
+    }
+
+    /// Register a new request, failing once the client is shutting down.
+    pub(crate) fn begin(self: &Arc<Self>) -> Result<InFlight, Error> {
+        // Count first so a concurrent shutdown either sees this request or rejects it
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlight {
+            lifecycle: Arc::clone(self),
+            abort: self.abort.subscribe(),
+            permit: None,
+        };
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(Error::ShuttingDown);
+        }
+        if *self.abort.borrow() {
+            return Err(Error::Aborted);
+        }
+        Ok(guard)
+    }
+
+    /// Number of requests currently running.
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Stop accepting requests, wait up to `deadline` for in-flight ones, then
+    /// abort the rest. Returns `true` if everything finished in time.
+    pub(crate) async fn shutdown(&self, deadline: Duration) -> bool {
+        self.closed.store(true, Ordering::SeqCst);
+
+        let drained = tokio::time::timeout(deadline, self.wait_idle())
+            .await
+            .is_ok();
+        if !drained {
+            self.abort.send_replace(true);
+        }
+        drained
+    }
+
+    async fn wait_idle(&self) {
+        loop {
+            let mut notified = pin!(self.idle.notified());
+            notified.as_mut().enable();
+            if self.in_flight() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Emergency brake for every request of a client.
+///
+/// Obtained from [`Client::kill_switch`](crate::Client::kill_switch); cheap to
+/// clone and share with watchdogs such as budget monitors. Tripping it aborts
+/// all in-flight requests and streams with [`Error::Aborted`] and rejects new
+/// ones the same way until [`reset`](Self::reset).
+#[derive(Debug, Clone)]
+pub struct KillSwitch {
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl KillSwitch {
+    pub(crate) fn new(lifecycle: Arc<Lifecycle>) -> Self {
+        Self { lifecycle }
+    }
+
+    /// Abort all in-flight requests and reject new ones.
+    pub fn trip(&self) {
+        self.lifecycle.abort.send_replace(true);
+    }
+
+    /// Accept requests again after [`trip`](Self::trip).
+    pub fn reset(&self) {
+        self.lifecycle.abort.send_replace(false);
+    }
+
+    /// Whether the switch is currently tripped.
+    pub fn is_tripped(&self) -> bool {
+        *self.lifecycle.abort.borrow()
+    }
+}
+
+/// Registration of one running request; deregisters on drop.
+#[derive(Debug)]
+pub(crate) struct InFlight {
+    lifecycle: Arc<Lifecycle>,
+    abort: watch::Receiver<bool>,
+    permit: Option<Permit>,
+}
+
+impl InFlight {
+    /// Keep a rate limit slot for as long as the request runs.
+    pub(crate) fn hold(&mut self, permit: Permit) {
+        self.permit = Some(permit);
+    }
+
+    /// Drive `fut` to completion unless the request is aborted first.
+    pub(crate) async fn or_abort<F: Future>(&mut self, fut: F) -> Result<F::Output, Error> {
+        if *self.abort.borrow() {
+            return Err(Error::Aborted);
+        }
+
+        let abort = &mut self.abort;
+        let aborted = async move {
+            loop {
+                if *abort.borrow_and_update() {
+                    return;
+                }
+                // The sender outlives every guard, so this only fails during teardown
+                if abort.changed().await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        match select(pin!(fut), pin!(aborted)).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(Error::Aborted),
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.lifecycle.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.lifecycle.idle.notify_waiters();
+        }
+    }
+}
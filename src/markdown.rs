@@ -0,0 +1,244 @@
+//! Incremental splitting of streamed markdown into prose and code blocks.
+//!
+//! Renderers that highlight code as it streams need to know where fenced code
+//! blocks start and end, but a fence line (```` ```rust ````) can be split
+//! across chunks anywhere. A [`MarkdownSplitter`] tracks fences across the
+//! text pushed into it and emits [`MarkdownEvent`]s, holding back only the
+//! start of a line that may still turn out to be a fence.
+//!
+//! ```
+//! use rust_ai_sdk::markdown::{MarkdownEvent, MarkdownSplitter};
+//!
+//! let mut splitter = MarkdownSplitter::new();
+//! let mut events = splitter.push("Run:\n```sh\ncargo te");
+//! events.extend(splitter.push("st\n```\n"));
+//! events.extend(splitter.finish());
+//! assert_eq!(events[1], MarkdownEvent::CodeBlockStart { lang: Some("sh".into()) });
+//! assert_eq!(events[3], MarkdownEvent::CodeBlockDelta("st\n".into()));
+//! assert_eq!(events[4], MarkdownEvent::CodeBlockEnd);
+//! ```
+
+/// A piece of streamed markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownEvent {
+    /// Text outside code blocks, line breaks included.
+    Text(String),
+    /// A fenced code block opened; `lang` is the first word of its info string.
+    CodeBlockStart { lang: Option<String> },
+    /// Content of the open code block, without its fences.
+    CodeBlockDelta(String),
+    /// The open code block closed, or the stream ended inside it.
+    CodeBlockEnd,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fence {
+    marker: u8,
+    len: usize,
+}
+
+/// Tracks fenced code blocks across pieces of streamed markdown.
+#[derive(Debug, Default)]
+pub struct MarkdownSplitter {
+    // Fence of the open code block
+    fence: Option<Fence>,
+    // Start of the current line, held while it may be a fence
+    line: String,
+    // The current line is known not to be a fence and passes through
+    mid_line: bool,
+}
+
+impl MarkdownSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a code block is open.
+    pub fn in_code_block(&self) -> bool {
+        self.fence.is_some()
+    }
+
+    /// Split `text`, the next piece of the stream, e.g. a chunk's text.
+    pub fn push(&mut self, text: &str) -> Vec<MarkdownEvent> {
+        let mut events = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+            let (piece, tail) = rest.split_at(end);
+            rest = tail;
+
+            if self.mid_line {
+                self.content(piece, &mut events);
+                self.mid_line = !piece.ends_with('\n');
+                continue;
+            }
+            self.line.push_str(piece);
+            if self.line.ends_with('\n') {
+                let line = std::mem::take(&mut self.line);
+                self.complete_line(&line, &mut events);
+            } else if !may_be_fence(&self.line) {
+                let line = std::mem::take(&mut self.line);
+                self.content(&line, &mut events);
+                self.mid_line = true;
+            }
+        }
+        events
+    }
+
+    /// End the stream: hand out a held line and close an open code block.
+    pub fn finish(&mut self) -> Vec<MarkdownEvent> {
+        let mut events = Vec::new();
+        let line = std::mem::take(&mut self.line);
+        if !line.is_empty() {
+            self.complete_line(&line, &mut events);
+        }
+        self.mid_line = false;
+        if self.fence.take().is_some() {
+            events.push(MarkdownEvent::CodeBlockEnd);
+        }
+        events
+    }
+
+    /// Handle a whole line: a fence opens or closes a block, anything else is content.
+    fn complete_line(&mut self, line: &str, events: &mut Vec<MarkdownEvent>) {
+        if let Some((fence, info)) = parse_fence(line) {
+            match self.fence {
+                None if fence.marker != b'`' || !info.contains('`') => {
+                    self.fence = Some(fence);
+                    events.push(MarkdownEvent::CodeBlockStart {
+                        lang: info.split_whitespace().next().map(str::to_string),
+                    });
+                    return;
+                }
+                Some(open)
+                    if fence.marker == open.marker
+                        && fence.len >= open.len
+                        && info.trim().is_empty() =>
+                {
+                    self.fence = None;
+                    events.push(MarkdownEvent::CodeBlockEnd);
+                    return;
+                }
+                _ => {}
+            }
+        }
+        self.content(line, events);
+    }
+
+    /// Append prose or code, extending the last event when it's of the same kind.
+    fn content(&self, text: &str, events: &mut Vec<MarkdownEvent>) {
+        match (self.fence.is_some(), events.last_mut()) {
+            (false, Some(MarkdownEvent::Text(last)))
+            | (true, Some(MarkdownEvent::CodeBlockDelta(last))) => last.push_str(text),
+            (false, _) => events.push(MarkdownEvent::Text(text.to_string())),
+            (true, _) => events.push(MarkdownEvent::CodeBlockDelta(text.to_string())),
+        }
+    }
+}
+
+/// The fence a line starts with, and the rest of the line (the info string).
+fn parse_fence(line: &str) -> Option<(Fence, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = *trimmed.as_bytes().first()?;
+    if marker != b'`' && marker != b'~' {
+        return None;
+    }
+    let len = trimmed.bytes().take_while(|&b| b == marker).count();
+    (len >= 3).then(|| {
+        (
+            Fence { marker, len },
+            trimmed[len..].trim_end_matches(['\n', '\r']),
+        )
+    })
+}
+
+/// Whether the start of a line can still become a fence line.
+fn may_be_fence(partial: &str) -> bool {
+    let trimmed = partial.trim_start_matches(' ');
+    if partial.len() - trimmed.len() > 3 {
+        return false;
+    }
+    match trimmed.as_bytes().first() {
+        None => true,
+        Some(&marker @ (b'`' | b'~')) => {
+            let run = trimmed.bytes().take_while(|&b| b == marker).count();
+            // A fence's info string is only complete at the end of the line
+            run == trimmed.len() || run >= 3
+        }
+        Some(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Events with consecutive text or code pieces joined.
+    fn merged(events: Vec<MarkdownEvent>) -> Vec<MarkdownEvent> {
+        let mut merged: Vec<MarkdownEvent> = Vec::new();
+        for event in events {
+            match (merged.last_mut(), event) {
+                (Some(MarkdownEvent::Text(last)), MarkdownEvent::Text(text))
+                | (
+                    Some(MarkdownEvent::CodeBlockDelta(last)),
+                    MarkdownEvent::CodeBlockDelta(text),
+                ) => {
+                    last.push_str(&text);
+                }
+                (_, event) => merged.push(event),
+            }
+        }
+        merged
+    }
+
+    #[test]
+    fn test_split_across_chunks() {
+        let markdown = "Intro `code`:\n```rust\nfn main() {}\n```\n    ```\nDone.";
+        let expected = vec![
+            MarkdownEvent::Text("Intro `code`:\n".into()),
+            MarkdownEvent::CodeBlockStart {
+                lang: Some("rust".into()),
+            },
+            MarkdownEvent::CodeBlockDelta("fn main() {}\n".into()),
+            MarkdownEvent::CodeBlockEnd,
+            // Indented four spaces: not a fence
+            MarkdownEvent::Text("    ```\nDone.".into()),
+        ];
+
+        let mut splitter = MarkdownSplitter::new();
+        let mut events = splitter.push(markdown);
+        events.extend(splitter.finish());
+        assert_eq!(events, expected);
+
+        // Any split point gives the same events
+        let mut splitter = MarkdownSplitter::new();
+        let mut events = Vec::new();
+        for c in markdown.chars() {
+            events.extend(splitter.push(c.encode_utf8(&mut [0; 4])));
+        }
+        events.extend(splitter.finish());
+        assert_eq!(merged(events), expected);
+    }
+
+    #[test]
+    fn test_nested_and_unterminated_fences() {
+        let mut splitter = MarkdownSplitter::new();
+        let mut events = splitter.push("~~~~md\n```py\nx\n```\n~~~\n");
+        assert!(splitter.in_code_block());
+        events.extend(splitter.finish());
+        assert_eq!(
+            events,
+            [
+                MarkdownEvent::CodeBlockStart {
+                    lang: Some("md".into())
+                },
+                // Neither a backtick fence nor a shorter tilde fence closes the block
+                MarkdownEvent::CodeBlockDelta("```py\nx\n```\n~~~\n".into()),
+                MarkdownEvent::CodeBlockEnd,
+            ]
+        );
+    }
+}
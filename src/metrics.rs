@@ -0,0 +1,298 @@
+//! Prometheus metrics (`prometheus` feature).
+//!
+//! Register a [`Metrics`] with [`ClientBuilder::metrics`](crate::ClientBuilder::metrics)
+//! and the client maintains, labelled by provider and model:
+//!
+//! - `llm_requests_total` - requests sent
+//! - `llm_errors_total` - failed requests and streams, also labelled by error kind
+//! - `llm_input_tokens_total` / `llm_output_tokens_total` - token usage
+//! - `llm_cost_usd_total` - cost of models with known pricing
+//! - `llm_time_to_first_token_seconds` - latency until the first streamed chunk
+//! - `llm_request_phase_seconds` - time per [`RequestTimings`] phase, also
+//!   labelled by phase: `queued`, `retry`, `time_to_first_byte`, `stream`,
+//!   `overhead`, and `total`
+//!
+//! Scrape them with [`Metrics::gather`] or [`Metrics::encode_text`], or register
+//! them in an existing registry with [`Metrics::with_registry`].
+
+use crate::cost::PricingRegistry;
+use crate::error::Error;
+use crate::types::{RequestTimings, Usage};
+use prometheus::{
+    CounterVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::sync::Arc;
+use std::time::Instant;
+
+pub use prometheus::proto::MetricFamily;
+
+const LABELS: &[&str] = &["provider", "model"];
+
+/// SDK metrics, cheap to clone.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    errors: IntCounterVec,
+    input_tokens: IntCounterVec,
+    output_tokens: IntCounterVec,
+    cost: CounterVec,
+    time_to_first_token: HistogramVec,
+    request_phase: HistogramVec,
+}
+
+impl Metrics {
+    /// Create metrics in a fresh registry.
+    pub fn new() -> Self {
+        Self::with_registry(Registry::new()).expect("fresh registry has no conflicting metrics")
+    }
+
+    /// Create metrics registered in `registry`, e.g. a service's existing one.
+    ///
+    /// Fails if metrics with the same names are already registered there.
+    pub fn with_registry(registry: Registry) -> Result<Self, Error> {
+        let metrics = Self {
+            requests: IntCounterVec::new(
+                Opts::new("llm_requests_total", "LLM requests sent"),
+                LABELS,
+            )
+            .map_err(|e| metrics_error(&e))?,
+            errors: IntCounterVec::new(
+                Opts::new("llm_errors_total", "Failed LLM requests and streams"),
+                &["provider", "model", "kind"],
+            )
+            .map_err(|e| metrics_error(&e))?,
+            input_tokens: IntCounterVec::new(
+                Opts::new("llm_input_tokens_total", "Input tokens consumed"),
+                LABELS,
+            )
+            .map_err(|e| metrics_error(&e))?,
+            output_tokens: IntCounterVec::new(
+                Opts::new("llm_output_tokens_total", "Output tokens generated"),
+                LABELS,
+            )
+            .map_err(|e| metrics_error(&e))?,
+            cost: CounterVec::new(
+                Opts::new(
+                    "llm_cost_usd_total",
+                    "Cost in USD of models with known pricing",
+                ),
+                LABELS,
+            )
+            .map_err(|e| metrics_error(&e))?,
+            time_to_first_token: HistogramVec::new(
+                HistogramOpts::new(
+                    "llm_time_to_first_token_seconds",
+                    "Time from sending a streaming request to its first chunk",
+                ),
+                LABELS,
+            )
+            .map_err(|e| metrics_error(&e))?,
+            request_phase: HistogramVec::new(
+                HistogramOpts::new(
+                    "llm_request_phase_seconds",
+                    "Time spent in each phase of a successful request",
+                )
+                // Streams run for minutes
+                .buckets(vec![
+                    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+                    120.0, 300.0,
+                ]),
+                &["provider", "model", "phase"],
+            )
+            .map_err(|e| metrics_error(&e))?,
+            registry,
+        };
+
+        metrics.register_all().map_err(|e| metrics_error(&e))?;
+        Ok(metrics)
+    }
+
+    fn register_all(&self) -> prometheus::Result<()> {
+        self.registry.register(Box::new(self.requests.clone()))?;
+        self.registry.register(Box::new(self.errors.clone()))?;
+        self.registry
+            .register(Box::new(self.input_tokens.clone()))?;
+        self.registry
+            .register(Box::new(self.output_tokens.clone()))?;
+        self.registry.register(Box::new(self.cost.clone()))?;
+        self.registry
+            .register(Box::new(self.time_to_first_token.clone()))?;
+        self.registry.register(Box::new(self.request_phase.clone()))
+    }
+
+    /// Registry holding the metrics.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Current values of all metrics in the registry.
+    pub fn gather(&self) -> Vec<MetricFamily> {
+        self.registry.gather()
+    }
+
+    /// Current values in the Prometheus text exposition format.
+    pub fn encode_text(&self) -> String {
+        let mut out = String::new();
+        // Encoding into a String only fails on malformed metric families
+        let _ = TextEncoder::new().encode_utf8(&self.gather(), &mut out);
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn metrics_error(e: &prometheus::Error) -> Error {
+    Error::Config(format!("metrics: {e}"))
+}
+
+/// Metrics of one request.
+#[derive(Clone)]
+pub(crate) struct RequestMetrics {
+    metrics: Metrics,
+    pricing: Arc<PricingRegistry>,
+    provider: String,
+    model: String,
+    started: Instant,
+    first_chunk_seen: bool,
+}
+
+impl RequestMetrics {
+    /// Count a request being sent.
+    pub(crate) fn start(
+        metrics: &Metrics,
+        pricing: &Arc<PricingRegistry>,
+        provider: &str,
+        model: &str,
+    ) -> Self {
+        metrics.requests.with_label_values(&[provider, model]).inc();
+        Self {
+            metrics: metrics.clone(),
+            pricing: Arc::clone(pricing),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            started: Instant::now(),
+            first_chunk_seen: false,
+        }
+    }
+
+    /// Observe time to first token on the first streamed chunk.
+    pub(crate) fn chunk(&mut self) {
+        if !self.first_chunk_seen {
+            self.first_chunk_seen = true;
+            self.metrics
+                .time_to_first_token
+                .with_label_values(&[&self.provider, &self.model])
+                .observe(self.started.elapsed().as_secs_f64());
+        }
+    }
+
+    pub(crate) fn usage(&self, usage: &Usage) {
+        let labels = [self.provider.as_str(), self.model.as_str()];
+        self.metrics
+            .input_tokens
+            .with_label_values(&labels)
+            .inc_by(u64::from(usage.input_tokens));
+        self.metrics
+            .output_tokens
+            .with_label_values(&labels)
+            .inc_by(u64::from(usage.output_tokens));
+
+        let key = format!("{}/{}", self.provider, self.model);
+        if let Some(cost) = self.pricing.calculate_cost(&key, usage) {
+            self.metrics
+                .cost
+                .with_label_values(&labels)
+                .inc_by(cost.total());
+        }
+    }
+
+    /// Observe the phases of a finished request.
+    pub(crate) fn timings(&self, timings: &RequestTimings) {
+        for (phase, duration) in [
+            ("queued", timings.queued),
+            ("retry", timings.retry_time),
+            ("time_to_first_byte", timings.time_to_first_byte),
+            ("stream", timings.stream),
+            ("overhead", timings.overhead()),
+            ("total", timings.total),
+        ] {
+            self.metrics
+                .request_phase
+                .with_label_values(&[&self.provider, &self.model, phase])
+                .observe(duration.as_secs_f64());
+        }
+    }
+
+    pub(crate) fn error(&self, error: &Error) {
+        self.metrics
+            .errors
+            .with_label_values(&[&self.provider, &self.model, error_kind(error)])
+            .inc();
+    }
+}
+
+/// Low-cardinality label for an error.
+fn error_kind(error: &Error) -> &'static str {
+    match error {
+        Error::RateLimited { .. } => "rate_limited",
+        Error::Unauthorized { .. } => "unauthorized",
+        Error::Server { .. } => "server",
+        Error::Overloaded { .. } => "overloaded",
+        Error::Api { .. } => "api",
+        Error::ContentBlocked { .. } => "blocked",
+        Error::Timeout => "timeout",
+        Error::Parse { .. } => "parse",
+        Error::Http(_) | Error::Transport { .. } => "http",
+        Error::Aborted => "aborted",
+        Error::QueueFull | Error::QueueTimeout => "queue",
+        Error::BudgetExceeded { .. } => "budget",
+        Error::ContextLengthExceeded { .. } => "context_length",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_request_metrics() {
+        let metrics = Metrics::new();
+        let pricing = Arc::new(PricingRegistry::new());
+
+        let mut request = RequestMetrics::start(&metrics, &pricing, "openai", "gpt-4o");
+        request.chunk();
+        request.chunk();
+        request.usage(&Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 100,
+            ..Default::default()
+        });
+        request.timings(&RequestTimings {
+            time_to_first_byte: Duration::from_millis(300),
+            total: Duration::from_millis(400),
+            ..Default::default()
+        });
+        request.error(&Error::Timeout);
+
+        let text = metrics.encode_text();
+        assert!(text.contains(r#"llm_requests_total{model="gpt-4o",provider="openai"} 1"#));
+        assert!(
+            text.contains(r#"llm_input_tokens_total{model="gpt-4o",provider="openai"} 1000000"#)
+        );
+        assert!(text.contains(
+            r#"llm_time_to_first_token_seconds_count{model="gpt-4o",provider="openai"} 1"#
+        ));
+        assert!(text.contains(
+            r#"llm_request_phase_seconds_count{model="gpt-4o",phase="time_to_first_byte",provider="openai"} 1"#
+        ));
+        assert!(text.contains(r#"kind="timeout""#));
+        assert!(text.contains("llm_cost_usd_total"));
+    }
+}
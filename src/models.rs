@@ -0,0 +1,435 @@
+//! Model metadata registry.
+//!
+//! Context windows, knowledge cutoffs, modalities, and lifecycle status per
+//! model, keyed by `provider/model` like [`PricingRegistry`](crate::cost::PricingRegistry).
+//! Ships with a bundled table that can be extended or replaced from JSON.
+//!
+//! The registry also holds model aliases (`claude/sonnet-latest`) that resolve
+//! to pinned snapshot IDs, so results record exactly which model answered.
+
+use crate::capabilities::{self, ModelCapabilities};
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Input or output modality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modality {
+    Text,
+    Image,
+    Audio,
+    Video,
+    Pdf,
+}
+
+/// Lifecycle status of a model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelStatus {
+    #[default]
+    Active,
+    /// Still served, but scheduled for removal.
+    Deprecated,
+    /// No longer served.
+    Retired,
+}
+
+/// Metadata for a model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Features and token limits.
+    #[serde(flatten)]
+    pub capabilities: ModelCapabilities,
+    /// Training data cutoff (`YYYY-MM`), if published.
+    #[serde(default)]
+    pub knowledge_cutoff: Option<String>,
+    /// Accepted input modalities.
+    #[serde(default = "text_only")]
+    pub input_modalities: Vec<Modality>,
+    /// Produced output modalities.
+    #[serde(default = "text_only")]
+    pub output_modalities: Vec<Modality>,
+    /// Lifecycle status.
+    #[serde(default)]
+    pub status: ModelStatus,
+    /// Date the model stops being served (`YYYY-MM-DD`), if announced.
+    #[serde(default)]
+    pub retirement_date: Option<String>,
+    /// Suggested replacement model ID.
+    #[serde(default)]
+    pub replacement: Option<String>,
+}
+
+fn text_only() -> Vec<Modality> {
+    vec![Modality::Text]
+}
+
+impl ModelInfo {
+    /// Text-in, text-out active model with the given capabilities.
+    pub fn new(capabilities: ModelCapabilities) -> Self {
+        Self {
+            capabilities,
+            knowledge_cutoff: None,
+            input_modalities: text_only(),
+            output_modalities: text_only(),
+            status: ModelStatus::Active,
+            retirement_date: None,
+            replacement: None,
+        }
+    }
+
+    pub fn with_knowledge_cutoff(mut self, cutoff: impl Into<String>) -> Self {
+        self.knowledge_cutoff = Some(cutoff.into());
+        self
+    }
+
+    pub fn with_input_modalities(mut self, modalities: &[Modality]) -> Self {
+        self.input_modalities = modalities.to_vec();
+        self
+    }
+
+    /// Mark the model deprecated, with an optional replacement model ID.
+    pub fn deprecated(mut self, replacement: Option<&str>) -> Self {
+        self.status = ModelStatus::Deprecated;
+        self.replacement = replacement.map(ToString::to_string);
+        self
+    }
+
+    pub fn with_retirement_date(mut self, date: impl Into<String>) -> Self {
+        self.retirement_date = Some(date.into());
+        self
+    }
+
+    /// Whether the model accepts the given input modality.
+    pub fn accepts(&self, modality: Modality) -> bool {
+        self.input_modalities.contains(&modality)
+    }
+
+    /// Whether the model is deprecated or retired.
+    pub fn is_deprecated(&self) -> bool {
+        self.status != ModelStatus::Active
+    }
+}
+
+/// Longest alias chain followed before giving up (guards against cycles).
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// Registry of model metadata.
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    models: HashMap<String, ModelInfo>,
+    aliases: HashMap<String, String>,
+}
+
+impl ModelRegistry {
+    /// Create a new registry with the bundled model table.
+    pub fn new() -> Self {
+        let mut models = HashMap::new();
+        insert_claude_models(&mut models);
+        insert_openai_models(&mut models);
+        insert_gemini_models(&mut models);
+        insert_cerebras_models(&mut models);
+        insert_ai21_models(&mut models);
+
+        let aliases = [
+            ("claude/sonnet-latest", "claude/claude-3-5-sonnet-20241022"),
+            ("claude/haiku-latest", "claude/claude-3-5-haiku-20241022"),
+            ("claude/opus-latest", "claude/claude-3-opus-20240229"),
+            ("gemini/flash-latest", "gemini/gemini-2.0-flash"),
+            ("gemini/pro-latest", "gemini/gemini-1.5-pro"),
+        ]
+        .into_iter()
+        .map(|(alias, target)| (alias.to_string(), target.to_string()))
+        .collect();
+
+        Self { models, aliases }
+    }
+
+    /// Create an empty registry.
+    pub fn empty() -> Self {
+        Self {
+            models: HashMap::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Create a registry from a JSON object mapping model IDs to metadata.
+    ///
+    /// ```json
+    /// {"vllm/my-model": {"context_window": 32768, "supports_tools": true, "status": "active"}}
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let mut registry = Self::empty();
+        registry.extend_from_json(json)?;
+        Ok(registry)
+    }
+
+    /// Add or replace entries from a JSON object mapping model IDs to metadata.
+    pub fn extend_from_json(&mut self, json: &str) -> Result<(), Error> {
+        let models: HashMap<String, ModelInfo> = serde_json::from_str(json)
+            .map_err(|e| Error::Config(format!("model registry: {e}")))?;
+        self.models.extend(models);
+        Ok(())
+    }
+
+    /// Get metadata for a model or alias.
+    pub fn get(&self, model: &str) -> Option<&ModelInfo> {
+        self.models.get(self.resolve(model))
+    }
+
+    /// Map an alias to a concrete model ID (both in `provider/model` form).
+    ///
+    /// The target may itself be an alias, or belong to a different provider.
+    pub fn set_alias(&mut self, alias: impl Into<String>, target: impl Into<String>) {
+        self.aliases.insert(alias.into(), target.into());
+    }
+
+    /// Resolve an alias to its concrete model ID. Non-aliases are returned unchanged.
+    pub fn resolve<'a>(&'a self, model: &'a str) -> &'a str {
+        let mut resolved = model;
+        for _ in 0..MAX_ALIAS_DEPTH {
+            match self.aliases.get(resolved) {
+                Some(target) => resolved = target,
+                None => break,
+            }
+        }
+        resolved
+    }
+
+    /// Add or update metadata for a model.
+    pub fn set(&mut self, model: impl Into<String>, info: ModelInfo) {
+        self.models.insert(model.into(), info);
+    }
+
+    /// Context window of a model in tokens, if known.
+    pub fn context_window(&self, model: &str) -> Option<u32> {
+        self.get(model)?.capabilities.context_window
+    }
+
+    /// Capabilities of a model, falling back to its provider's defaults.
+    pub fn capabilities(&self, model: &str) -> Option<ModelCapabilities> {
+        if let Some(info) = self.get(model) {
+            return Some(info.capabilities);
+        }
+        let (provider, _) = self.resolve(model).split_once('/')?;
+        capabilities::provider_defaults(provider)
+    }
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bundled Claude models (structured output only via tool use).
+fn insert_claude_models(models: &mut HashMap<String, ModelInfo>) {
+    let claude = ModelCapabilities::new()
+        .with_tools()
+        .with_vision()
+        .with_caching();
+    models.insert(
+        "claude/claude-3-5-sonnet-20241022".to_string(),
+        ModelInfo::new(claude.with_limits(200_000, 8_192))
+            .with_knowledge_cutoff("2024-04")
+            .with_input_modalities(&[Modality::Text, Modality::Image, Modality::Pdf]),
+    );
+    models.insert(
+        "claude/claude-3-5-haiku-20241022".to_string(),
+        ModelInfo::new(claude.with_limits(200_000, 8_192))
+            .with_knowledge_cutoff("2024-07")
+            .with_input_modalities(&[Modality::Text, Modality::Image, Modality::Pdf]),
+    );
+    models.insert(
+        "claude/claude-3-opus-20240229".to_string(),
+        ModelInfo::new(claude.with_limits(200_000, 4_096))
+            .with_knowledge_cutoff("2023-08")
+            .with_input_modalities(&[Modality::Text, Modality::Image])
+            .deprecated(Some("claude/claude-3-5-sonnet-20241022"))
+            .with_retirement_date("2026-01-05"),
+    );
+    models.insert(
+        "claude/claude-3-haiku-20240307".to_string(),
+        ModelInfo::new(claude.with_limits(200_000, 4_096))
+            .with_knowledge_cutoff("2023-08")
+            .with_input_modalities(&[Modality::Text, Modality::Image]),
+    );
+}
+
+/// Bundled OpenAI models.
+fn insert_openai_models(models: &mut HashMap<String, ModelInfo>) {
+    let openai = ModelCapabilities::new()
+        .with_tools()
+        .with_vision()
+        .with_structured_output()
+        .with_caching();
+    for model in ["gpt-4o", "gpt-4o-mini"] {
+        models.insert(
+            format!("openai/{model}"),
+            ModelInfo::new(openai.with_limits(128_000, 16_384))
+                .with_knowledge_cutoff("2023-10")
+                .with_input_modalities(&[Modality::Text, Modality::Image]),
+        );
+    }
+    models.insert(
+        "openai/o1".to_string(),
+        ModelInfo::new(openai.with_limits(200_000, 100_000))
+            .with_knowledge_cutoff("2023-10")
+            .with_input_modalities(&[Modality::Text, Modality::Image]),
+    );
+    models.insert(
+        "openai/o1-mini".to_string(),
+        ModelInfo::new(
+            ModelCapabilities::new()
+                .with_caching()
+                .with_limits(128_000, 65_536),
+        )
+        .with_knowledge_cutoff("2023-10"),
+    );
+}
+
+/// Bundled Gemini models.
+fn insert_gemini_models(models: &mut HashMap<String, ModelInfo>) {
+    let gemini = ModelCapabilities::new()
+        .with_tools()
+        .with_vision()
+        .with_structured_output()
+        .with_caching();
+    let gemini_inputs = [
+        Modality::Text,
+        Modality::Image,
+        Modality::Audio,
+        Modality::Video,
+        Modality::Pdf,
+    ];
+    for model in ["gemini-2.0-flash", "gemini-2.0-flash-lite"] {
+        models.insert(
+            format!("gemini/{model}"),
+            ModelInfo::new(gemini.with_limits(1_048_576, 8_192))
+                .with_knowledge_cutoff("2024-08")
+                .with_input_modalities(&gemini_inputs),
+        );
+    }
+    models.insert(
+        "gemini/gemini-1.5-flash".to_string(),
+        ModelInfo::new(gemini.with_limits(1_048_576, 8_192))
+            .with_knowledge_cutoff("2023-11")
+            .with_input_modalities(&gemini_inputs)
+            .deprecated(Some("gemini/gemini-2.0-flash")),
+    );
+    models.insert(
+        "gemini/gemini-1.5-pro".to_string(),
+        ModelInfo::new(gemini.with_limits(2_097_152, 8_192))
+            .with_knowledge_cutoff("2023-11")
+            .with_input_modalities(&gemini_inputs)
+            .deprecated(Some("gemini/gemini-2.0-flash")),
+    );
+}
+
+/// Bundled Cerebras models.
+fn insert_cerebras_models(models: &mut HashMap<String, ModelInfo>) {
+    for model in ["llama3.1-8b", "llama3.1-70b", "llama-3.3-70b"] {
+        models.insert(
+            format!("cerebras/{model}"),
+            ModelInfo::new(
+                ModelCapabilities::new()
+                    .with_tools()
+                    .with_limits(8_192, 8_192),
+            )
+            .with_knowledge_cutoff("2023-12"),
+        );
+    }
+}
+
+/// Bundled AI21 models.
+fn insert_ai21_models(models: &mut HashMap<String, ModelInfo>) {
+    for model in ["jamba-large", "jamba-mini"] {
+        models.insert(
+            format!("ai21/{model}"),
+            ModelInfo::new(
+                ModelCapabilities::new()
+                    .with_tools()
+                    .with_structured_output()
+                    .with_limits(256_000, 4_096),
+            )
+            .with_knowledge_cutoff("2024-03"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_registry() {
+        let registry = ModelRegistry::new();
+
+        let info = registry.get("claude/claude-3-5-sonnet-20241022").unwrap();
+        assert_eq!(info.capabilities.context_window, Some(200_000));
+        assert!(info.accepts(Modality::Pdf));
+        assert!(!info.is_deprecated());
+
+        let opus = registry.get("claude/claude-3-opus-20240229").unwrap();
+        assert_eq!(opus.status, ModelStatus::Deprecated);
+        assert!(opus.replacement.is_some());
+
+        // Unknown models fall back to provider defaults
+        let caps = registry.capabilities("openai/gpt-5-preview").unwrap();
+        assert!(caps.supports_tools);
+        assert_eq!(caps.context_window, None);
+        assert!(registry.capabilities("nope/model").is_none());
+    }
+
+    #[test]
+    fn test_extend_from_json() {
+        let mut registry = ModelRegistry::new();
+        registry
+            .extend_from_json(
+                r#"{
+                    "vllm/Qwen/Qwen2.5-7B-Instruct": {
+                        "context_window": 32768,
+                        "supports_tools": true,
+                        "knowledge_cutoff": "2024-06"
+                    },
+                    "gemini/gemini-1.5-pro": {"status": "retired"}
+                }"#,
+            )
+            .unwrap();
+
+        let qwen = registry.get("vllm/Qwen/Qwen2.5-7B-Instruct").unwrap();
+        assert_eq!(
+            registry.context_window("vllm/Qwen/Qwen2.5-7B-Instruct"),
+            Some(32_768)
+        );
+        assert!(qwen.capabilities.supports_tools);
+        assert_eq!(qwen.input_modalities, vec![Modality::Text]);
+
+        assert_eq!(
+            registry.get("gemini/gemini-1.5-pro").unwrap().status,
+            ModelStatus::Retired
+        );
+        assert!(ModelRegistry::from_json("[]").is_err());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut registry = ModelRegistry::new();
+        assert_eq!(
+            registry.resolve("claude/sonnet-latest"),
+            "claude/claude-3-5-sonnet-20241022"
+        );
+        assert!(registry.get("claude/sonnet-latest").is_some());
+        assert_eq!(registry.resolve("openai/gpt-4o"), "openai/gpt-4o");
+
+        registry.set_alias("openai/gpt-4o", "openai/gpt-4o-2024-08-06");
+        registry.set_alias("openai/fast", "openai/gpt-4o");
+        assert_eq!(registry.resolve("openai/fast"), "openai/gpt-4o-2024-08-06");
+
+        // Cycles terminate
+        registry.set_alias("a/x", "a/y");
+        registry.set_alias("a/y", "a/x");
+        let _ = registry.resolve("a/x");
+    }
+}
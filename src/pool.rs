@@ -0,0 +1,161 @@
+//! Process-wide pool of reusable buffers.
+//!
+//! Every stream needs an SSE read buffer, scratch strings for event fields,
+//! and a content accumulator. With thousands of concurrent streams, allocating
+//! these per request puts noticeable pressure on the allocator, so parsers and
+//! streams take them from this pool and hand them back when dropped.
+//!
+//! Pooling is off by default; turn it on with [`set_enabled`]. The pool is
+//! bounded: it keeps at most [`MAX_POOLED`] buffers of each kind, strings are
+//! shrunk to [`MAX_RETAINED_CAPACITY`] and larger byte buffers are freed, so
+//! a burst of huge responses pins at most a few MiB. A pool that is busy is
+//! skipped rather than waited on, so concurrent streams never queue on it.
+
+use bytes::BytesMut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+/// Maximum number of idle buffers kept per kind.
+pub const MAX_POOLED: usize = 256;
+
+/// Capacity idle buffers are kept at: the default SSE buffer size, with room
+/// for it to have grown once.
+pub const MAX_RETAINED_CAPACITY: usize = 16 << 10;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STRINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static BYTES: Mutex<Vec<BytesMut>> = Mutex::new(Vec::new());
+
+/// Turn pooling on or off (off by default). Disabling also frees idle buffers.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        clear();
+    }
+}
+
+/// Whether buffers are currently pooled.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Free all idle buffers.
+pub fn clear() {
+    lock(&STRINGS).clear();
+    lock(&BYTES).clear();
+}
+
+/// Number of idle `(strings, byte buffers)` in the pool.
+pub fn idle() -> (usize, usize) {
+    (lock(&STRINGS).len(), lock(&BYTES).len())
+}
+
+/// An empty string with at least `capacity`, reused if possible.
+pub(crate) fn take_string(capacity: usize) -> String {
+    match try_lock(&STRINGS).and_then(|mut pool| pool.pop()) {
+        Some(mut s) => {
+            s.reserve(capacity);
+            s
+        }
+        None => String::with_capacity(capacity),
+    }
+}
+
+/// Return a string to the pool.
+pub(crate) fn give_string(mut s: String) {
+    if s.capacity() == 0 {
+        return;
+    }
+    let Some(mut pool) = try_lock(&STRINGS).filter(|pool| pool.len() < MAX_POOLED) else {
+        return;
+    };
+    s.clear();
+    s.shrink_to(MAX_RETAINED_CAPACITY);
+    pool.push(s);
+}
+
+/// An empty byte buffer with at least `capacity`, reused if possible.
+pub(crate) fn take_bytes(capacity: usize) -> BytesMut {
+    match try_lock(&BYTES).and_then(|mut pool| pool.pop()) {
+        Some(mut buf) => {
+            buf.reserve(capacity);
+            buf
+        }
+        None => BytesMut::with_capacity(capacity),
+    }
+}
+
+/// Return a byte buffer to the pool.
+pub(crate) fn give_bytes(mut buf: BytesMut) {
+    // `BytesMut` can't shrink in place; a grown buffer is freed instead
+    if buf.capacity() == 0 || buf.capacity() > MAX_RETAINED_CAPACITY {
+        return;
+    }
+    let Some(mut pool) = try_lock(&BYTES).filter(|pool| pool.len() < MAX_POOLED) else {
+        return;
+    };
+    buf.clear();
+    pool.push(buf);
+}
+
+/// The pool, unless pooling is off or another thread holds it.
+fn try_lock<T>(pool: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    if !is_enabled() {
+        return None;
+    }
+    match pool.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+fn lock<T>(pool: &Mutex<T>) -> MutexGuard<'_, T> {
+    pool.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reused_buffers_are_empty() {
+        // The pool is process-wide and shared with other tests, so only check
+        // what holds for any buffer handed out
+        let mut s = take_string(64);
+        s.push_str("leftover");
+        give_string(s);
+        let s = take_string(16);
+        assert!(s.is_empty());
+        assert!(s.capacity() >= 16);
+
+        let mut buf = take_bytes(128);
+        buf.extend_from_slice(b"data: x");
+        give_bytes(buf);
+        let buf = take_bytes(128);
+        assert!(buf.is_empty());
+        assert!(buf.capacity() >= 128);
+    }
+
+    /// Restores the process-wide pooling setting when dropped.
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            set_enabled(self.0);
+        }
+    }
+
+    #[test]
+    fn test_large_buffers_not_retained() {
+        let _restore = Restore(is_enabled());
+        set_enabled(true);
+        give_string(String::with_capacity(4 * MAX_RETAINED_CAPACITY));
+        give_bytes(BytesMut::with_capacity(4 * MAX_RETAINED_CAPACITY));
+        // Whatever is idle, from this test or another, was kept small
+        let (strings, bytes) = (take_string(0), take_bytes(0));
+        assert!(strings.capacity() <= MAX_RETAINED_CAPACITY);
+        assert!(bytes.capacity() <= MAX_RETAINED_CAPACITY);
+    }
+}
@@ -0,0 +1,170 @@
+//! AI21 Labs provider implementation.
+//!
+//! AI21 serves Jamba models through an OpenAI-compatible Chat Completions API:
+//! - Auth via `Bearer` API key
+//! - 256K context window, suited to long-document workloads
+//! - `documents` grounds the answer in caller-supplied texts (RAG without a retrieval step)
+//! - Streaming uses SSE with `[DONE]` marker; usage arrives on the final chunk
+
+use crate::error::Error;
+use crate::providers::cerebras::{
+    build_chat_body, build_chat_messages, parse_chat_response, CerebrasParser, ChatBody,
+};
+use crate::providers::{Parameter, Provider, RequestBody, RequestConfig};
+use crate::stream::ProviderParser;
+use crate::types::*;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+/// AI21 Labs (Jamba) provider.
+pub struct Ai21Provider {
+    base_url: String,
+}
+
+impl Ai21Provider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "https://api.ai21.com/studio/v1".to_string(),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+
+    fn build_base_body<'a>(
+        &self,
+        model: &'a str,
+        messages: &'a [Message],
+        config: &'a RequestConfig,
+    ) -> ChatBody<'a> {
+        let mut body = build_chat_body(model, messages, config);
+        body.documents = config.documents.as_deref();
+        // Jamba models don't reason
+        body.reasoning_effort = None;
+        body
+    }
+}
+
+impl Default for Ai21Provider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for Ai21Provider {
+    fn name(&self) -> &'static str {
+        "ai21"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self, api_key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+            headers.insert(AUTHORIZATION, auth);
+        }
+        headers
+    }
+
+    fn build_stream_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        // Usage arrives on the final chunk without `stream_options`
+        let mut body = self.build_base_body(model, messages, config);
+        body.stream = Some(true);
+        RequestBody::json_with_extra(&body, config.extra.as_ref())
+    }
+
+    fn build_complete_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        // Usage arrives on the final chunk without `stream_options`
+        let mut body = self.build_base_body(model, messages, config);
+        body.stream = Some(false);
+        RequestBody::json_with_extra(&body, config.extra.as_ref())
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        Some(build_chat_messages(messages))
+    }
+
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        matches!(
+            parameter,
+            Parameter::Documents | Parameter::Audio | Parameter::Logprobs
+        )
+    }
+
+    fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
+        Box::new(CerebrasParser::new())
+    }
+
+    fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
+        parse_chat_response(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_body_with_documents() {
+        let provider = Ai21Provider::new();
+        let config = RequestConfig {
+            documents: Some(vec![
+                Document::new("The Eiffel Tower is 330 metres tall.")
+                    .with_metadata("source", "wiki"),
+                Document::new("It was completed in 1889.").with_id("doc-2"),
+            ]),
+            ..Default::default()
+        };
+
+        let body = provider
+            .build_stream_body(
+                "jamba-large",
+                &[Message::user("How tall is the tower?")],
+                &config,
+            )
+            .unwrap()
+            .to_value();
+
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["documents"][0]["metadata"]["source"], "wiki");
+        assert!(body["documents"][0].get("id").is_none());
+        assert_eq!(body["documents"][1]["id"], "doc-2");
+    }
+
+    #[test]
+    fn test_build_body_without_documents() {
+        let provider = Ai21Provider::new();
+        let body = provider
+            .build_complete_body(
+                "jamba-mini",
+                &[Message::user("Hi")],
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+
+        assert!(body.get("documents").is_none());
+        assert_eq!(
+            provider.complete_url("jamba-mini", "key"),
+            "https://api.ai21.com/studio/v1/chat/completions"
+        );
+    }
+}
@@ -4,12 +4,18 @@
 //! Streaming uses SSE with `[DONE]` marker.
 
 use crate::error::Error;
-use crate::providers::{Provider, RequestConfig};
+use crate::providers::{
+    ParameterLimits, Provider, ReasoningEffort, RequestBody, RequestConfig, ResponseFormat,
+    ToolChoice,
+};
+use crate::sse::SseEvent;
 use crate::stream::ProviderParser;
 use crate::types::*;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
-use serde::Deserialize;
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 
 /// Cerebras API provider.
 pub struct CerebrasProvider {
@@ -59,16 +65,14 @@ impl Provider for CerebrasProvider {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        let mut body = self.build_base_body(model, messages, config)?;
-
+    ) -> Result<RequestBody, Error> {
         // Enable streaming with usage tracking
-        body["stream"] = Value::Bool(true);
-        body["stream_options"] = serde_json::json!({
-            "include_usage": true
-        });
-
-        Ok(body)
+        RequestBody::json_with_extra(
+            &build_chat_body(model, messages, config)
+                .with_stream(true)
+                .with_max_completion_tokens(),
+            config.extra.as_ref(),
+        )
     }
 
     fn build_complete_body(
@@ -76,10 +80,28 @@ impl Provider for CerebrasProvider {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        let mut body = self.build_base_body(model, messages, config)?;
-        body["stream"] = Value::Bool(false);
-        Ok(body)
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &build_chat_body(model, messages, config)
+                .with_stream(false)
+                .with_max_completion_tokens(),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        Some(build_chat_messages(messages))
+    }
+
+    fn parameter_limits(&self) -> ParameterLimits {
+        ParameterLimits {
+            temperature: 0.0..=1.5,
+            max_stop_sequences: Some(4),
+            ..Default::default()
+        }
     }
 
     fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
@@ -87,124 +109,219 @@ impl Provider for CerebrasProvider {
     }
 
     fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
-        let resp: CerebrasResponse =
-            serde_json::from_str(body).map_err(|e| Error::parse(e.to_string()))?;
-
-        let choice = resp
-            .choices
-            .first()
-            .ok_or_else(|| Error::parse("no choices"))?;
-
-        Ok(CompletionResult {
-            content: choice.message.content.clone().unwrap_or_default(),
-            usage: Usage {
-                input_tokens: resp.usage.prompt_tokens,
-                output_tokens: resp.usage.completion_tokens,
-                ..Default::default()
-            },
-            model: resp.model,
-            finish_reason: parse_finish_reason(choice.finish_reason.as_deref()),
-            tool_calls: choice.message.tool_calls.clone().unwrap_or_default(),
-        })
+        parse_chat_response(body)
     }
 }
 
-impl CerebrasProvider {
-    fn build_base_body(
-        &self,
-        model: &str,
-        messages: &[Message],
-        config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        // Convert messages to Cerebras format
-        let msgs: Vec<Value> = messages
-            .iter()
-            .map(|m| {
-                let mut obj = serde_json::json!({
-                    "role": m.role,
-                    "content": match &m.content {
-                        MessageContent::Text(s) => Value::String(s.clone()),
-                        MessageContent::Parts(parts) => serde_json::to_value(parts).unwrap_or(Value::Null),
-                    }
-                });
+/// OpenAI Chat Completions request body.
+///
+/// Shared by providers speaking the same dialect; they set their own fields
+/// (streaming, vLLM guided decoding, AI21 documents) before serializing.
+#[derive(Serialize)]
+pub(crate) struct ChatBody<'a> {
+    model: &'a str,
+    messages: &'a [Message],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<&'a Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<&'a ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallel_tool_calls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<&'a ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_logprobs: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) reasoning_effort: Option<ReasoningEffort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) modalities: Option<[&'static str; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) audio: Option<ChatAudio<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) guided_json: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) guided_regex: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) guided_choice: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) guided_grammar: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) best_of: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) continue_final_message: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) add_generation_prompt: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) documents: Option<&'a [Document]>,
+}
 
-                if let Some(name) = &m.name {
-                    obj["name"] = Value::String(name.clone());
-                }
-                if let Some(tool_call_id) = &m.tool_call_id {
-                    obj["tool_call_id"] = Value::String(tool_call_id.clone());
-                }
-                if let Some(tool_calls) = &m.tool_calls {
-                    obj["tool_calls"] = serde_json::to_value(tool_calls).unwrap_or(Value::Null);
-                }
-
-                obj
-            })
-            .collect();
-
-        let mut body = serde_json::json!({
-            "model": model,
-            "messages": msgs,
-        });
+#[derive(Serialize)]
+pub(crate) struct ChatAudio<'a> {
+    voice: &'a str,
+    format: &'static str,
+}
 
-        // Add optional parameters
-        if let Some(max_tokens) = config.max_tokens {
-            body["max_tokens"] = Value::Number(max_tokens.into());
-        }
-        if let Some(temperature) = config.temperature {
-            body["temperature"] = serde_json::json!(temperature);
-        }
-        if let Some(top_p) = config.top_p {
-            body["top_p"] = serde_json::json!(top_p);
-        }
-        if let Some(stop) = &config.stop {
-            body["stop"] = serde_json::to_value(stop).unwrap_or(Value::Null);
-        }
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
 
-        // Tool calling support
-        if let Some(tools) = &config.tools {
-            body["tools"] = serde_json::to_value(tools).unwrap_or(Value::Null);
-        }
-        if let Some(tool_choice) = &config.tool_choice {
-            body["tool_choice"] = tool_choice.to_value();
-        }
+impl ChatBody<'_> {
+    /// Set the streaming flag, requesting usage in the final chunk when streaming.
+    pub(crate) fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = Some(stream);
+        self.stream_options = stream.then_some(StreamOptions {
+            include_usage: true,
+        });
+        self
+    }
 
-        // Merge extra fields
-        if let Some(Value::Object(map)) = &config.extra {
-            if let Value::Object(ref mut body_map) = body {
-                for (k, v) in map {
-                    body_map.insert(k.clone(), v.clone());
-                }
-            }
-        }
+    /// Send the output limit as `max_completion_tokens`, which replaces the
+    /// deprecated `max_tokens` and is the only one reasoning models accept.
+    pub(crate) fn with_max_completion_tokens(mut self) -> Self {
+        self.max_completion_tokens = self.max_tokens.take();
+        self
+    }
+}
 
-        Ok(body)
+/// Build an OpenAI Chat Completions request body.
+///
+/// Shared by providers speaking the same dialect.
+pub(crate) fn build_chat_body<'a>(
+    model: &'a str,
+    messages: &'a [Message],
+    config: &'a RequestConfig,
+) -> ChatBody<'a> {
+    // Few compatible servers know `allowed_tools`; the tool list is filtered instead
+    static REQUIRED: ToolChoice = ToolChoice::Required;
+    let tool_choice = config.tool_choice.as_ref().map(|choice| match choice {
+        ToolChoice::AllowedTools(_) => &REQUIRED,
+        other => other,
+    });
+
+    ChatBody {
+        model,
+        messages,
+        max_tokens: config.max_tokens,
+        max_completion_tokens: None,
+        temperature: config.temperature,
+        top_p: config.top_p,
+        stop: config.stop.as_deref(),
+        tools: config
+            .tools
+            .is_some()
+            .then(|| config.offered_tools().collect()),
+        tool_choice,
+        parallel_tool_calls: config.parallel_tool_calls,
+        response_format: config.response_format.as_ref(),
+        logprobs: config.logprobs.map(|_| true),
+        top_logprobs: config.logprobs.filter(|&top| top > 0),
+        reasoning_effort: config.reasoning_effort,
+        modalities: config.audio.as_ref().map(|_| ["text", "audio"]),
+        audio: config.audio.as_ref().map(|audio| ChatAudio {
+            voice: &audio.voice,
+            format: audio.format.as_str(),
+        }),
+        stream: None,
+        stream_options: None,
+        guided_json: None,
+        guided_regex: None,
+        guided_choice: None,
+        guided_grammar: None,
+        best_of: None,
+        continue_final_message: None,
+        add_generation_prompt: None,
+        documents: None,
     }
 }
 
+/// Serialize messages for the `messages` field of a Chat Completions body.
+pub(crate) fn build_chat_messages(
+    messages: &[Message],
+) -> Result<(&'static str, RequestBody), Error> {
+    RequestBody::json(messages).map(|body| ("messages", body))
+}
+
+/// Parse a non-streaming OpenAI Chat Completions response.
+pub(crate) fn parse_chat_response(body: &str) -> Result<CompletionResult, Error> {
+    let resp: CerebrasResponse =
+        serde_json::from_str(body).map_err(|e| Error::parse(e.to_string()))?;
+
+    let choice = resp
+        .choices
+        .first()
+        .ok_or_else(|| Error::parse("no choices"))?;
+
+    Ok(CompletionResult {
+        content: choice.message.content.clone().unwrap_or_default(),
+        reasoning: choice.message.reasoning_content.clone(),
+        usage: Usage {
+            input_tokens: resp.usage.prompt_tokens,
+            output_tokens: resp.usage.completion_tokens,
+            ..Default::default()
+        },
+        model: resp.model,
+        finish_reason: parse_finish_reason(choice.finish_reason.as_deref()),
+        tool_calls: choice.message.tool_calls.clone().unwrap_or_default(),
+        server_tool_calls: Vec::new(),
+        logprobs: choice.logprobs.as_ref().and_then(|l| l.content.clone()),
+        refusal: choice.message.refusal.clone(),
+        safety_ratings: None,
+        citations: None,
+        stop_sequence: choice.stop_reason.as_ref().and_then(stop_sequence),
+        incomplete: false,
+        timings: None,
+    })
+}
+
 /// Streaming response parser for Cerebras.
 pub struct CerebrasParser {
-    // Empty struct - state tracked externally
+    // Answer text that arrived in the same delta as reasoning
+    pending: Option<StreamChunk>,
 }
 
 impl CerebrasParser {
     pub fn new() -> Self {
-        Self {}
+        Self { pending: None }
     }
 
-    /// Parse a streaming chunk.
-    fn parse_chunk_inner(&mut self, data: &str) -> Result<Option<StreamChunk>, Error> {
+    /// Parse a streaming chunk, sharing text with `frame` when given.
+    fn parse_chunk_inner(
+        &mut self,
+        data: &str,
+        frame: Option<&Bytes>,
+    ) -> Result<Option<StreamChunk>, Error> {
         let chunk: CerebrasStreamChunk =
             serde_json::from_str(data).map_err(|e| Error::parse(e.to_string()))?;
 
+        let extensions = chunk.extensions();
+
         // Check for usage-only chunk (no choices, just usage)
         if chunk.choices.is_empty() {
             if let Some(usage) = chunk.usage {
-                return Ok(Some(StreamChunk::usage(Usage {
+                let mut stream_chunk = StreamChunk::usage(Usage {
                     input_tokens: usage.prompt_tokens,
                     output_tokens: usage.completion_tokens,
                     ..Default::default()
-                })));
+                });
+                stream_chunk.extensions = extensions;
+                return Ok(Some(stream_chunk));
             }
             return Ok(None);
         }
@@ -212,15 +329,36 @@ impl CerebrasParser {
         let choice = &chunk.choices[0];
         let delta = &choice.delta;
 
-        // Build chunk based on content
-        let mut stream_chunk = if let Some(content) = &delta.content {
-            if content.is_empty() {
-                StreamChunk::empty(ChunkKind::Text)
-            } else {
-                StreamChunk::text_owned(content.clone())
+        // Build chunk based on content; reasoning models (DeepSeek, vLLM with a
+        // reasoning parser) send their reasoning in `reasoning_content` first
+        let reasoning = delta.reasoning_content.as_ref().filter(|r| !r.0.is_empty());
+        let thinking = |BorrowedText(reasoning): &BorrowedText| {
+            let mut chunk = match frame {
+                Some(frame) => StreamChunk::text_shared(frame, reasoning),
+                None => StreamChunk::text_owned(reasoning.to_string()),
+            };
+            chunk.kind = ChunkKind::Thinking;
+            chunk
+        };
+        // At the switch to the answer, both can arrive in one delta
+        let has_content = delta.content.as_ref().is_some_and(|c| !c.0.is_empty());
+        let mut stream_chunk = if let Some(reasoning) = reasoning.filter(|_| !has_content) {
+            thinking(reasoning)
+        } else if let Some(BorrowedText(content)) = &delta.content {
+            match frame {
+                _ if content.is_empty() => StreamChunk::empty(ChunkKind::Text),
+                // Escape-free strings borrow from `data`, which lies in the frame
+                Some(frame) => StreamChunk::text_shared(frame, content),
+                None => StreamChunk::text_owned(content.to_string()),
             }
         } else if delta.tool_calls.is_some() {
             StreamChunk::empty(ChunkKind::ToolDelta)
+        } else if let Some(refusal) = &delta.refusal {
+            StreamChunk::refusal(refusal.clone())
+        } else if let Some(audio) = &delta.audio {
+            let mut chunk = StreamChunk::empty(ChunkKind::Audio);
+            chunk.audio = Some(audio.clone());
+            chunk
         } else {
             StreamChunk::empty(ChunkKind::Unknown)
         };
@@ -233,6 +371,7 @@ impl CerebrasParser {
                     id: tc.id.clone(),
                     function_name: tc.function.as_ref().and_then(|f| f.name.clone()),
                     function_arguments: tc.function.as_ref().and_then(|f| f.arguments.clone()),
+                    final_arguments: None,
                 });
                 stream_chunk.kind = ChunkKind::ToolDelta;
             }
@@ -242,6 +381,9 @@ impl CerebrasParser {
         if let Some(reason) = &choice.finish_reason {
             stream_chunk.finish_reason = Some(parse_finish_reason(Some(reason)));
         }
+        stream_chunk.stop_sequence = choice.stop_reason.as_ref().and_then(stop_sequence);
+        stream_chunk.logprobs = choice.logprobs.as_ref().and_then(|l| l.content.clone());
+        stream_chunk.output_index = choice.index;
 
         // Set usage if present
         if let Some(usage) = chunk.usage {
@@ -251,7 +393,15 @@ impl CerebrasParser {
                 ..Default::default()
             });
         }
-
+        stream_chunk.extensions = extensions;
+
+        // The reasoning goes first, the answer text with the other fields after it
+        if let Some(reasoning) = reasoning.filter(|_| has_content) {
+            let mut reasoning = thinking(reasoning);
+            reasoning.output_index = stream_chunk.output_index;
+            self.pending = Some(stream_chunk);
+            return Ok(Some(reasoning));
+        }
         Ok(Some(stream_chunk))
     }
 }
@@ -264,12 +414,20 @@ impl Default for CerebrasParser {
 
 impl ProviderParser for CerebrasParser {
     fn parse_chunk(&mut self, data: &str) -> Result<Option<StreamChunk>, Error> {
-        self.parse_chunk_inner(data)
+        self.parse_chunk_inner(data, None)
+    }
+
+    fn parse_event(&mut self, event: &SseEvent<'_>) -> Result<Option<StreamChunk>, Error> {
+        self.parse_chunk_inner(event.data, event.frame)
     }
 
     fn is_end_of_stream(&self, data: &str) -> bool {
         data == "[DONE]"
     }
+
+    fn take_pending(&mut self) -> Option<StreamChunk> {
+        self.pending.take()
+    }
 }
 
 /// Parse finish reason string to enum.
@@ -283,6 +441,11 @@ fn parse_finish_reason(reason: Option<&str>) -> FinishReason {
     }
 }
 
+/// The stop sequence in a `stop_reason`, which is a token ID for stop tokens.
+fn stop_sequence(stop_reason: &Value) -> Option<String> {
+    stop_reason.as_str().map(str::to_string)
+}
+
 // --- Serde types for Cerebras API ---
 
 #[derive(Debug, Deserialize)]
@@ -296,12 +459,24 @@ struct CerebrasResponse {
 struct CerebrasChoice {
     message: CerebrasMessage,
     finish_reason: Option<String>,
+    /// Matched stop string or token ID (vLLM).
+    stop_reason: Option<Value>,
+    logprobs: Option<ChatLogProbs>,
+}
+
+/// Per-choice `logprobs` of Chat Completions.
+#[derive(Debug, Deserialize)]
+struct ChatLogProbs {
+    content: Option<Vec<TokenLogProb>>,
 }
 
 #[derive(Debug, Deserialize)]
 struct CerebrasMessage {
     content: Option<String>,
+    /// Reasoning of reasoning models (DeepSeek).
+    reasoning_content: Option<String>,
     tool_calls: Option<Vec<ToolCall>>,
+    refusal: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -311,23 +486,57 @@ struct CerebrasUsage {
 }
 
 #[derive(Debug, Deserialize)]
-struct CerebrasStreamChunk {
+struct CerebrasStreamChunk<'a> {
     #[allow(dead_code)]
     id: Option<String>,
-    choices: Vec<CerebrasStreamChoice>,
+    #[serde(borrow)]
+    choices: Vec<CerebrasStreamChoice<'a>>,
     usage: Option<CerebrasUsage>,
+    // Provider-specific fields, passed through as chunk extensions
+    x_groq: Option<Value>,
+    time_info: Option<Value>,
+    obfuscation: Option<Value>,
+}
+
+impl CerebrasStreamChunk<'_> {
+    fn extensions(&self) -> Option<serde_json::Map<String, Value>> {
+        let map: serde_json::Map<String, Value> = [
+            ("x_groq", &self.x_groq),
+            ("time_info", &self.time_info),
+            ("obfuscation", &self.obfuscation),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.clone()?)))
+        .collect();
+        (!map.is_empty()).then_some(map)
+    }
 }
 
 #[derive(Debug, Deserialize)]
-struct CerebrasStreamChoice {
-    delta: CerebrasStreamDelta,
+struct CerebrasStreamChoice<'a> {
+    index: Option<u32>,
+    #[serde(borrow)]
+    delta: CerebrasStreamDelta<'a>,
     finish_reason: Option<String>,
+    stop_reason: Option<Value>,
+    logprobs: Option<ChatLogProbs>,
 }
 
+/// String borrowed from the JSON input unless it has escapes.
+///
+/// A newtype because serde only borrows `Cow` fields that aren't wrapped in `Option`.
 #[derive(Debug, Deserialize)]
-struct CerebrasStreamDelta {
-    content: Option<String>,
+struct BorrowedText<'a>(#[serde(borrow)] Cow<'a, str>);
+
+#[derive(Debug, Deserialize)]
+struct CerebrasStreamDelta<'a> {
+    #[serde(borrow)]
+    content: Option<BorrowedText<'a>>,
+    #[serde(borrow)]
+    reasoning_content: Option<BorrowedText<'a>>,
     tool_calls: Option<Vec<CerebrasToolCallDelta>>,
+    refusal: Option<String>,
+    audio: Option<AudioDelta>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -346,6 +555,7 @@ struct CerebrasFunctionDelta {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::ResponseFormat;
 
     #[test]
     fn test_parse_text_chunk() {
@@ -355,6 +565,76 @@ mod tests {
         let chunk = parser.parse_chunk(data).unwrap().unwrap();
         assert_eq!(chunk.text().unwrap().as_ref(), "Hello");
         assert!(chunk.finish_reason.is_none());
+        assert_eq!(chunk.output_index, Some(0));
+    }
+
+    #[test]
+    fn test_text_shared_with_frame() {
+        let mut sse = crate::sse::SseParser::new();
+        sse.feed(
+            concat!(
+                r#"data: {"choices":[{"index":0,"delta":{"content":"Hello"}}]}"#,
+                "\n\n",
+                r#"data: {"choices":[{"index":0,"delta":{"content":"a\"b"}}]}"#,
+                "\n\n"
+            )
+            .as_bytes(),
+        );
+        let mut parser = CerebrasParser::new();
+
+        let event = sse.next_event().unwrap();
+        let frame = event.frame.unwrap().as_ptr_range();
+        let chunk = parser.parse_event(&event).unwrap().unwrap();
+        let text = chunk.text_bytes().unwrap();
+        assert_eq!(&text[..], b"Hello");
+        assert!(frame.contains(&text.as_ptr()));
+
+        // Unescaped text can't be shared
+        let event = sse.next_event().unwrap();
+        let chunk = parser.parse_event(&event).unwrap().unwrap();
+        assert_eq!(chunk.into_text().unwrap(), "a\"b");
+    }
+
+    #[test]
+    fn test_parse_logprobs() {
+        let mut parser = CerebrasParser::new();
+        let data = r#"{"id":"1","choices":[{"index":0,"delta":{"content":"Hi"},"logprobs":{"content":[{"token":"Hi","logprob":-0.2,"bytes":[72,105],"top_logprobs":[{"token":"Hi","logprob":-0.2,"bytes":null}]}]},"finish_reason":null}]}"#;
+        let chunk = parser.parse_chunk(data).unwrap().unwrap();
+        let logprobs = chunk.logprobs.unwrap();
+        assert_eq!(logprobs[0].token, "Hi");
+        assert_eq!(logprobs[0].top_alternatives.len(), 1);
+
+        let body = r#"{"model":"llama3.1-8b","choices":[{"message":{"content":"Hi"},"finish_reason":"stop","logprobs":{"content":[{"token":"Hi","logprob":-0.2,"top_logprobs":[]}]}}],"usage":{"prompt_tokens":1,"completion_tokens":1}}"#;
+        let result = parse_chat_response(body).unwrap();
+        assert!((result.logprobs.unwrap()[0].logprob + 0.2).abs() < 1e-9);
+
+        let config = RequestConfig {
+            logprobs: Some(0),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(build_chat_body(
+            "llama3.1-8b",
+            &[Message::user("Hi")],
+            &config,
+        ))
+        .unwrap();
+        assert_eq!(body["logprobs"], true);
+        assert!(body.get("top_logprobs").is_none());
+    }
+
+    #[test]
+    fn test_response_format() {
+        let config = RequestConfig {
+            response_format: Some(ResponseFormat::JsonObject),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(build_chat_body(
+            "llama3.1-8b",
+            &[Message::user("Hi")],
+            &config,
+        ))
+        .unwrap();
+        assert_eq!(body["response_format"]["type"], "json_object");
     }
 
     #[test]
@@ -379,6 +659,28 @@ mod tests {
         assert_eq!(usage.output_tokens, 20);
     }
 
+    #[test]
+    fn test_parse_extensions() {
+        let mut parser = CerebrasParser::new();
+        let data = r#"{"id":"123","choices":[],"usage":{"prompt_tokens":10,"completion_tokens":20},"x_groq":{"id":"req_1","usage":{"queue_time":0.02}}}"#;
+
+        let chunk = parser.parse_chunk(data).unwrap().unwrap();
+        assert_eq!(
+            chunk.extension("x_groq").unwrap()["usage"]["queue_time"],
+            0.02
+        );
+        assert!(chunk.extension("time_info").is_none());
+
+        let data =
+            r#"{"id":"123","choices":[{"index":0,"delta":{"content":"Hi"},"finish_reason":null}]}"#;
+        assert!(parser
+            .parse_chunk(data)
+            .unwrap()
+            .unwrap()
+            .extensions
+            .is_none());
+    }
+
     #[test]
     fn test_parse_tool_call_delta() {
         let mut parser = CerebrasParser::new();
@@ -418,15 +720,64 @@ mod tests {
         let config = RequestConfig {
             max_tokens: Some(100),
             tools: Some(tools),
+            reasoning_effort: Some(ReasoningEffort::High),
             ..Default::default()
         };
 
         let body = provider
             .build_stream_body("llama3.1-70b", &messages, &config)
-            .unwrap();
+            .unwrap()
+            .to_value();
 
+        assert_eq!(body["max_completion_tokens"], 100);
+        assert!(body.get("max_tokens").is_none());
+        assert_eq!(body["reasoning_effort"], "high");
         assert!(body["tools"].is_array());
         assert!(body["stream"].as_bool().unwrap());
         assert!(body["stream_options"]["include_usage"].as_bool().unwrap());
     }
+
+    #[test]
+    fn test_allowed_tools() {
+        let provider = CerebrasProvider::new();
+        let messages = vec![Message::user("Hi")];
+        let tool = |name: &str| Tool::function(name, name, serde_json::json!({"type": "object"}));
+        let config = RequestConfig {
+            tools: Some(vec![tool("search"), tool("fetch"), tool("delete")]),
+            tool_choice: Some(ToolChoice::AllowedTools(vec![
+                "search".to_string(),
+                "fetch".to_string(),
+            ])),
+            ..Default::default()
+        };
+
+        let body = provider
+            .build_complete_body("llama3.1-70b", &messages, &config)
+            .unwrap()
+            .to_value();
+
+        let tools = body["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["function"]["name"], "search");
+        assert_eq!(body["tool_choice"], "required");
+    }
+
+    #[test]
+    fn test_extra_fields() {
+        let provider = CerebrasProvider::new();
+        let config = RequestConfig {
+            extra: Some(serde_json::json!({"seed": 42, "user": "abc"})),
+            ..Default::default()
+        };
+
+        let body = provider
+            .build_stream_body("llama3.1-8b", &[Message::user("Hi")], &config)
+            .unwrap();
+        let text = std::str::from_utf8(body.as_bytes()).unwrap();
+        assert!(text.ends_with(r#""seed":42,"user":"abc"}"#));
+
+        let body = body.to_value();
+        assert_eq!(body["seed"], 42);
+        assert_eq!(body["model"], "llama3.1-8b");
+    }
 }
@@ -4,34 +4,139 @@
 //! - Auth via `x-api-key` header (not Bearer token)
 //! - Requires `anthropic-version` header
 //! - Streaming uses named SSE events: message_start, content_block_delta, etc.
-//! - Content blocks can be text, tool_use, or thinking
-
-use crate::error::Error;
-use crate::providers::{Provider, RequestConfig, ToolChoice};
+//! - Content blocks can be text, tool_use, thinking, or server tool calls and results
+//!
+//! The same models are also served by Amazon Bedrock and Google Vertex AI (see
+//! [`ClaudePlatform`]). Those take the model in the URL, `anthropic_version` in
+//! the body, and a `Bearer` token instead of `x-api-key`.
+
+use crate::error::{Error, ErrorDetails};
+use crate::providers::{
+    system_texts, Parameter, ParameterLimits, Provider, RequestBody, RequestConfig, ResponseFormat,
+    ServerTool, SkippedEvents, StreamFraming, ToolChoice, WebSearch,
+};
+use crate::schema::{self, SchemaDialect};
 use crate::stream::ProviderParser;
 use crate::types::*;
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
-use serde::Deserialize;
+use crate::warning::Warning;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 
 const ANTHROPIC_VERSION: &str = "2023-06-01";
+const BEDROCK_ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+const VERTEX_ANTHROPIC_VERSION: &str = "vertex-2023-10-16";
+const FINE_GRAINED_TOOL_STREAMING_BETA: &str = "fine-grained-tool-streaming-2025-05-14";
+const WEB_SEARCH_TOOL: &str = "web_search_20250305";
+
+/// Platform serving the Claude Messages API.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ClaudePlatform {
+    /// Anthropic API (`api.anthropic.com`).
+    #[default]
+    Anthropic,
+    /// Amazon Bedrock, authenticated with a Bedrock API key.
+    ///
+    /// Model IDs are Bedrock IDs, e.g. `anthropic.claude-3-5-sonnet-20241022-v2:0`.
+    Bedrock { region: String },
+    /// Google Vertex AI, authenticated with an OAuth access token.
+    ///
+    /// Model IDs are Vertex IDs, e.g. `claude-3-5-sonnet-v2@20241022`.
+    Vertex { project_id: String, region: String },
+}
+
+impl ClaudePlatform {
+    /// Default base URL for this platform.
+    pub fn default_base_url(&self) -> String {
+        match self {
+            ClaudePlatform::Anthropic => "https://api.anthropic.com".to_string(),
+            ClaudePlatform::Bedrock { region } => {
+                format!("https://bedrock-runtime.{region}.amazonaws.com")
+            }
+            ClaudePlatform::Vertex { region, .. } if region == "global" => {
+                "https://aiplatform.googleapis.com".to_string()
+            }
+            ClaudePlatform::Vertex { region, .. } => {
+                format!("https://{region}-aiplatform.googleapis.com")
+            }
+        }
+    }
+}
 
 /// Claude API provider.
 pub struct ClaudeProvider {
     base_url: String,
+    platform: ClaudePlatform,
 }
 
 impl ClaudeProvider {
     pub fn new() -> Self {
         Self {
-            base_url: "https://api.anthropic.com".to_string(),
+            base_url: ClaudePlatform::Anthropic.default_base_url(),
+            platform: ClaudePlatform::Anthropic,
         }
     }
 
     pub fn with_base_url(base_url: impl Into<String>) -> Self {
         Self {
             base_url: base_url.into(),
+            platform: ClaudePlatform::Anthropic,
+        }
+    }
+
+    /// Target Claude on another platform, optionally through a custom base URL.
+    pub fn for_platform(platform: ClaudePlatform, base_url: Option<&str>) -> Self {
+        Self {
+            base_url: base_url.map_or_else(|| platform.default_base_url(), ToString::to_string),
+            platform,
+        }
+    }
+
+    /// Build the request URL for a model on the configured platform.
+    fn url(&self, model: &str, stream: bool) -> String {
+        match &self.platform {
+            ClaudePlatform::Anthropic => format!("{}{}", self.base_url, self.chat_endpoint()),
+            ClaudePlatform::Bedrock { .. } => {
+                let action = if stream {
+                    "invoke-with-response-stream"
+                } else {
+                    "invoke"
+                };
+                format!(
+                    "{}/model/{}/{action}",
+                    self.base_url,
+                    model.replace(':', "%3A")
+                )
+            }
+            ClaudePlatform::Vertex { project_id, region } => {
+                let action = if stream {
+                    "streamRawPredict"
+                } else {
+                    "rawPredict"
+                };
+                format!(
+                    "{}/v1/projects/{project_id}/locations/{region}/publishers/anthropic/models/{model}:{action}",
+                    self.base_url
+                )
+            }
+        }
+    }
+
+    /// Adapt a Messages API body to the platform: the model moves to the URL and
+    /// the API version moves into the body.
+    fn adapt_body(&self, body: &mut ClaudeBody) {
+        let version = match self.platform {
+            ClaudePlatform::Anthropic => return,
+            ClaudePlatform::Bedrock { .. } => BEDROCK_ANTHROPIC_VERSION,
+            ClaudePlatform::Vertex { .. } => VERTEX_ANTHROPIC_VERSION,
+        };
+        body.model = None;
+        // Bedrock selects streaming by endpoint and rejects the field
+        if matches!(self.platform, ClaudePlatform::Bedrock { .. }) {
+            body.stream = None;
         }
+        body.anthropic_version = Some(version);
     }
 }
 
@@ -53,6 +158,12 @@ impl Provider for ClaudeProvider {
     fn headers(&self, api_key: &str) -> HeaderMap {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if self.platform != ClaudePlatform::Anthropic {
+            if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+                headers.insert(AUTHORIZATION, auth);
+            }
+            return headers;
+        }
         headers.insert(
             "anthropic-version",
             HeaderValue::from_static(ANTHROPIC_VERSION),
@@ -63,15 +174,28 @@ impl Provider for ClaudeProvider {
         headers
     }
 
+    fn request_headers(&self, api_key: &str, config: &RequestConfig) -> HeaderMap {
+        let mut headers = self.headers(api_key);
+        // Bedrock and Vertex take betas in the body instead
+        if config.fine_grained_tool_streaming && self.platform == ClaudePlatform::Anthropic {
+            headers.insert(
+                "anthropic-beta",
+                HeaderValue::from_static(FINE_GRAINED_TOOL_STREAMING_BETA),
+            );
+        }
+        headers
+    }
+
     fn build_stream_body(
         &self,
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
+    ) -> Result<RequestBody, Error> {
         let mut body = self.build_base_body(model, messages, config)?;
-        body["stream"] = Value::Bool(true);
-        Ok(body)
+        body.stream = Some(true);
+        self.adapt_body(&mut body);
+        RequestBody::json_with_extra(&body, config.extra.as_ref())
     }
 
     fn build_complete_body(
@@ -79,10 +203,45 @@ impl Provider for ClaudeProvider {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
+    ) -> Result<RequestBody, Error> {
         let mut body = self.build_base_body(model, messages, config)?;
-        body["stream"] = Value::Bool(false);
-        Ok(body)
+        body.stream = Some(false);
+        self.adapt_body(&mut body);
+        RequestBody::json_with_extra(&body, config.extra.as_ref())
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        // System messages become the system prompt
+        if messages.iter().any(|m| m.role == Role::System) {
+            return None;
+        }
+        let messages = self.convert_messages(messages.iter());
+        Some(RequestBody::json(&messages).map(|body| ("messages", body)))
+    }
+
+    fn supports_logprobs(&self) -> bool {
+        false
+    }
+
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        matches!(
+            parameter,
+            Parameter::FineGrainedToolStreaming | Parameter::ServerTools
+        )
+    }
+
+    fn supports_prefill(&self) -> bool {
+        true
+    }
+
+    fn parameter_limits(&self) -> ParameterLimits {
+        ParameterLimits {
+            temperature: 0.0..=1.0,
+            ..Default::default()
+        }
     }
 
     fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
@@ -95,11 +254,23 @@ impl Provider for ClaudeProvider {
 
         let mut content = String::new();
         let mut tool_calls = Vec::new();
+        let mut server_tool_calls: Vec<ServerToolCall> = Vec::new();
+        let mut citations = Vec::new();
 
         for block in &resp.content {
             match block {
-                ContentBlock::Text { text } => {
+                ContentBlock::Text {
+                    text,
+                    citations: cited,
+                } => {
+                    let start = content.len();
                     content.push_str(text);
+                    citations.extend(
+                        cited
+                            .iter()
+                            .flatten()
+                            .map(|c| c.to_citation(start, content.len())),
+                    );
                 }
                 ContentBlock::ToolUse { id, name, input } => {
                     tool_calls.push(ToolCall {
@@ -111,7 +282,28 @@ impl Provider for ClaudeProvider {
                         },
                     });
                 }
-                ContentBlock::Thinking { .. } => {
+                ContentBlock::ServerToolUse { id, name, input } => {
+                    server_tool_calls.push(ServerToolCall {
+                        id: id.clone(),
+                        name: name.clone(),
+                        input: input.clone(),
+                        results: Vec::new(),
+                        files: Vec::new(),
+                        error: None,
+                    });
+                }
+                ContentBlock::WebSearchToolResult {
+                    tool_use_id,
+                    content,
+                } => {
+                    if let Some(call) = server_tool_calls
+                        .iter_mut()
+                        .find(|call| call.id == *tool_use_id)
+                    {
+                        content.apply(call);
+                    }
+                }
+                ContentBlock::Thinking { .. } | ContentBlock::Unknown => {
                     // Thinking blocks are not included in content
                 }
             }
@@ -127,105 +319,171 @@ impl Provider for ClaudeProvider {
 
         Ok(CompletionResult {
             content,
-            usage: Usage {
-                input_tokens: resp.usage.input_tokens,
-                output_tokens: resp.usage.output_tokens,
-                cache_read_input_tokens: resp.usage.cache_read_input_tokens.unwrap_or(0),
-                cache_creation_input_tokens: resp.usage.cache_creation_input_tokens.unwrap_or(0),
-            },
+            reasoning: None,
+            usage: resp.usage.to_usage(),
             model: resp.model,
             finish_reason,
             tool_calls,
+            server_tool_calls,
+            logprobs: None,
+            refusal: None,
+            safety_ratings: None,
+            citations: (!citations.is_empty()).then_some(citations),
+            stop_sequence: resp.stop_sequence,
+            incomplete: false,
+            timings: None,
         })
     }
 
     fn chat_endpoint(&self) -> &'static str {
         "/v1/messages"
     }
-}
-
-impl ClaudeProvider {
-    fn build_base_body(
-        &self,
-        model: &str,
-        messages: &[Message],
-        config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        // Extract system message
-        let system_text = messages
-            .iter()
-            .find(|m| m.role == Role::System)
-            .and_then(|m| m.content.as_text())
-            .or(config.system.as_deref());
 
-        // Convert non-system messages
-        let msgs: Vec<Value> = messages
-            .iter()
-            .filter(|m| m.role != Role::System)
-            .map(|m| self.convert_message(m))
-            .collect();
+    fn stream_url(&self, model: &str, _api_key: &str) -> String {
+        self.url(model, true)
+    }
 
-        let mut body = serde_json::json!({
-            "model": model,
-            "messages": msgs,
-            "max_tokens": config.max_tokens.unwrap_or(4096),
-        });
+    fn complete_url(&self, model: &str, _api_key: &str) -> String {
+        self.url(model, false)
+    }
 
-        if let Some(system) = system_text {
-            body["system"] = Value::String(system.to_string());
+    fn stream_framing(&self) -> StreamFraming {
+        match self.platform {
+            ClaudePlatform::Bedrock { .. } => StreamFraming::AwsEventStream,
+            _ => StreamFraming::Sse,
         }
+    }
+}
 
-        if let Some(temp) = config.temperature {
-            body["temperature"] = serde_json::json!(temp);
-        }
-        if let Some(top_p) = config.top_p {
-            body["top_p"] = serde_json::json!(top_p);
+impl ClaudeProvider {
+    fn build_base_body<'a>(
+        &self,
+        model: &'a str,
+        messages: &'a [Message],
+        config: &'a RequestConfig,
+    ) -> Result<ClaudeBody<'a>, Error> {
+        // Every system message and part becomes a block of the system prompt
+        let mut system = system_texts(messages);
+        if system.is_empty() {
+            system.extend(config.system.as_deref());
         }
-        if let Some(stop) = &config.stop {
-            body["stop_sequences"] = serde_json::to_value(stop).unwrap_or(Value::Null);
+
+        // Claude has no JSON mode; structured output goes through a forced tool call
+        if matches!(
+            config.response_format,
+            Some(ResponseFormat::JsonObject | ResponseFormat::JsonSchema(_))
+        ) {
+            return Err(Error::Config(
+                "claude does not support response formats; use a tool with ToolChoice::Function"
+                    .into(),
+            ));
         }
 
-        // Tools
-        if let Some(tools) = &config.tools {
-            let claude_tools: Vec<Value> = tools
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "name": t.function.name,
-                        "description": t.function.description,
-                        "input_schema": t.function.parameters
-                    })
-                })
-                .collect();
-            body["tools"] = Value::Array(claude_tools);
+        if config
+            .server_tools
+            .iter()
+            .flatten()
+            .any(|tool| matches!(tool, ServerTool::FileSearch(_)))
+        {
+            return Err(Error::Config(
+                "claude does not support file search; pass documents in the messages".into(),
+            ));
         }
 
-        // Tool choice
-        if let Some(tool_choice) = &config.tool_choice {
-            body["tool_choice"] = match tool_choice {
-                ToolChoice::Auto => serde_json::json!({"type": "auto"}),
-                ToolChoice::None => serde_json::json!({"type": "none"}),
-                ToolChoice::Required => serde_json::json!({"type": "any"}),
-                ToolChoice::Function(name) => serde_json::json!({
-                    "type": "tool",
-                    "name": name
-                }),
+        let mut tool_choice = config.tool_choice.as_ref().map(|choice| {
+            let (kind, name) = match choice {
+                ToolChoice::Auto => ("auto", None),
+                ToolChoice::None => ("none", None),
+                ToolChoice::Required | ToolChoice::AllowedTools(_) => ("any", None),
+                ToolChoice::Function(name) => ("tool", Some(name.as_str())),
             };
-        }
+            ClaudeToolChoice {
+                kind,
+                name,
+                disable_parallel_tool_use: None,
+            }
+        });
 
-        // Merge extra fields
-        if let Some(Value::Object(map)) = &config.extra {
-            if let Value::Object(ref mut body_map) = body {
-                for (k, v) in map {
-                    body_map.insert(k.clone(), v.clone());
-                }
+        // Claude expresses the parallel flag as a tool_choice option
+        if let (Some(parallel), Some(_)) = (config.parallel_tool_calls, &config.tools) {
+            if !matches!(config.tool_choice, Some(ToolChoice::None)) {
+                let choice = tool_choice.get_or_insert(ClaudeToolChoice {
+                    kind: "auto",
+                    name: None,
+                    disable_parallel_tool_use: None,
+                });
+                choice.disable_parallel_tool_use = Some(!parallel);
             }
         }
 
-        Ok(body)
+        Ok(ClaudeBody {
+            model: Some(model),
+            anthropic_version: None,
+            messages: self.convert_messages(messages.iter().filter(|m| m.role != Role::System)),
+            max_tokens: config.max_tokens.unwrap_or(4096),
+            system: match system.as_slice() {
+                [] => None,
+                [text] => Some(ClaudeContent::Text(text)),
+                blocks => Some(ClaudeContent::Blocks(
+                    blocks
+                        .iter()
+                        .map(|text| InputBlock::Text { text })
+                        .collect(),
+                )),
+            },
+            temperature: config.temperature,
+            top_p: config.top_p,
+            stop_sequences: config.stop.as_deref(),
+            // Claude has no allow-list, so unlisted tools are left out
+            tools: (config.tools.is_some() || config.server_tools.is_some()).then(|| {
+                let functions = config.offered_tools().map(|t| {
+                    ClaudeTool::Function(FunctionTool {
+                        name: &t.function.name,
+                        description: t.function.description.as_deref(),
+                        input_schema: schema::tool_parameters(
+                            t.function.parameters.as_ref(),
+                            SchemaDialect::Claude,
+                        ),
+                    })
+                });
+                let server = config
+                    .server_tools
+                    .iter()
+                    .flatten()
+                    .filter_map(|tool| match tool {
+                        ServerTool::WebSearch(search) => {
+                            Some(ClaudeTool::WebSearch(WebSearchTool::new(search)))
+                        }
+                        ServerTool::FileSearch(_) => None,
+                    });
+                functions.chain(server).collect()
+            }),
+            tool_choice,
+            anthropic_beta: (config.fine_grained_tool_streaming
+                && self.platform != ClaudePlatform::Anthropic)
+                .then_some([FINE_GRAINED_TOOL_STREAMING_BETA]),
+            stream: None,
+        })
+    }
+
+    /// Convert `messages`, trimming the trailing whitespace Claude rejects at
+    /// the end of a prefill.
+    fn convert_messages<'a>(
+        &self,
+        messages: impl Iterator<Item = &'a Message>,
+    ) -> Vec<ClaudeMessage<'a>> {
+        let mut converted: Vec<ClaudeMessage> = messages.map(|m| self.convert_message(m)).collect();
+        if let Some(ClaudeMessage {
+            role: "assistant",
+            content: ClaudeContent::Text(text),
+        }) = converted.last_mut()
+        {
+            *text = text.trim_end();
+        }
+        converted
     }
 
-    fn convert_message(&self, msg: &Message) -> Value {
+    fn convert_message<'a>(&self, msg: &'a Message) -> ClaudeMessage<'a> {
         let role = match msg.role {
             Role::User | Role::System => "user",
             Role::Assistant => "assistant",
@@ -236,56 +494,71 @@ impl ClaudeProvider {
             MessageContent::Text(text) => {
                 if msg.role == Role::Tool {
                     // Tool result format
-                    serde_json::json!([{
-                        "type": "tool_result",
-                        "tool_use_id": msg.tool_call_id.as_deref().unwrap_or(""),
-                        "content": text
+                    ClaudeContent::Blocks(vec![InputBlock::ToolResult {
+                        tool_use_id: msg.tool_call_id.as_deref().unwrap_or(""),
+                        content: text,
                     }])
                 } else {
-                    Value::String(text.clone())
+                    ClaudeContent::Text(text)
                 }
             }
-            MessageContent::Parts(parts) => {
-                let blocks: Vec<Value> = parts
+            MessageContent::Parts(parts) => ClaudeContent::Blocks(
+                parts
                     .iter()
                     .map(|p| match p {
-                        ContentPart::Text { text } => {
-                            serde_json::json!({"type": "text", "text": text})
-                        }
+                        ContentPart::Text { text } => InputBlock::Text { text },
                         ContentPart::ImageUrl { image_url } => {
                             // Extract base64 data and media type
                             let url = &image_url.url;
                             if let Some(rest) = url.strip_prefix("data:") {
                                 if let Some((media_type, data)) = rest.split_once(";base64,") {
-                                    return serde_json::json!({
-                                        "type": "image",
-                                        "source": {
-                                            "type": "base64",
-                                            "media_type": media_type,
-                                            "data": data
-                                        }
-                                    });
+                                    return InputBlock::Image {
+                                        source: ImageSource::Base64 { media_type, data },
+                                    };
                                 }
                             }
                             // Fallback to URL (Claude supports this too)
-                            serde_json::json!({
-                                "type": "image",
-                                "source": {
-                                    "type": "url",
-                                    "url": url
-                                }
-                            })
+                            InputBlock::Image {
+                                source: ImageSource::Url { url },
+                            }
                         }
                     })
-                    .collect();
-                Value::Array(blocks)
+                    .collect(),
+            ),
+        };
+
+        // Replay tool calls made by the assistant as `tool_use` blocks
+        let content = match (&msg.tool_calls, msg.role) {
+            (Some(tool_calls), Role::Assistant) if !tool_calls.is_empty() => {
+                let mut blocks = match content {
+                    ClaudeContent::Text("") => Vec::new(),
+                    ClaudeContent::Text(text) => vec![InputBlock::Text { text }],
+                    ClaudeContent::Blocks(blocks) => blocks,
+                };
+                blocks.extend(tool_calls.iter().map(|tc| InputBlock::ToolUse {
+                    id: &tc.id,
+                    name: &tc.function.name,
+                    input: tool_call_input(tc),
+                }));
+                ClaudeContent::Blocks(blocks)
             }
+            _ => content,
         };
 
-        serde_json::json!({
-            "role": role,
-            "content": content
-        })
+        ClaudeMessage { role, content }
+    }
+}
+
+/// Tool call arguments as a JSON object (`{}` when empty).
+///
+/// Malformed arguments, e.g. fine-grained tool streaming cut off at `max_tokens`,
+/// are wrapped as `{"INVALID_JSON": "..."}` so the model sees what it produced.
+fn tool_call_input(tool_call: &ToolCall) -> Value {
+    let arguments = &tool_call.function.arguments;
+    match serde_json::from_str(arguments) {
+        Ok(value @ Value::Object(_)) => value,
+        _ if arguments.trim().is_empty() => Value::Object(serde_json::Map::new()),
+        _ => serde_json::json!({ "INVALID_JSON": arguments }),
     }
 }
 
@@ -296,6 +569,16 @@ pub struct ClaudeParser {
     current_tool_id: Option<String>,
     current_tool_name: Option<String>,
     tool_index: usize,
+    // Server tool call whose input is streaming, and the input so far
+    server_call: Option<ServerToolCall>,
+    server_input: String,
+    // Server tool calls made, for their results to be added to
+    server_calls: Vec<ServerToolCall>,
+    // Bytes of text emitted, where the open text block started, and its citations
+    text_len: usize,
+    block_start: usize,
+    block_citations: Vec<ClaudeCitation>,
+    skipped: SkippedEvents,
 }
 
 impl ClaudeParser {
@@ -306,10 +589,113 @@ impl ClaudeParser {
             current_tool_id: None,
             current_tool_name: None,
             tool_index: 0,
+            server_call: None,
+            server_input: String::new(),
+            server_calls: Vec::new(),
+            text_len: 0,
+            block_start: 0,
+            block_citations: Vec::new(),
+            skipped: SkippedEvents::default(),
+        }
+    }
+
+    /// Handle a `content_block_start` event.
+    fn start_block(&mut self, block: StreamContentBlock) -> Option<StreamChunk> {
+        match block {
+            StreamContentBlock::Text { citations, .. } => {
+                self.current_block_type = Some("text".to_string());
+                self.block_start = self.text_len;
+                self.block_citations = citations.unwrap_or_default();
+            }
+            StreamContentBlock::ToolUse { id, name, input } => {
+                self.current_block_type = Some("tool_use".to_string());
+                self.current_tool_id = Some(id);
+                self.current_tool_name = Some(name);
+                // Input is normally streamed as deltas after an empty
+                // object; when the block starts with it, it's final
+                if input.as_object().is_some_and(|input| !input.is_empty()) {
+                    let mut chunk = StreamChunk::empty(ChunkKind::ToolDelta);
+                    chunk.tool_call_delta = Some(ToolCallDelta {
+                        index: self.tool_index,
+                        id: self.current_tool_id.clone(),
+                        function_name: self.current_tool_name.clone(),
+                        final_arguments: Some(input.to_string()),
+                        ..Default::default()
+                    });
+                    return Some(chunk);
+                }
+            }
+            StreamContentBlock::ServerToolUse { id, name, input } => {
+                self.current_block_type = Some("server_tool_use".to_string());
+                self.server_input.clear();
+                self.server_call = Some(ServerToolCall {
+                    id,
+                    name,
+                    input,
+                    results: Vec::new(),
+                    files: Vec::new(),
+                    error: None,
+                });
+            }
+            StreamContentBlock::WebSearchToolResult {
+                tool_use_id,
+                content,
+            } => {
+                let call = self
+                    .server_calls
+                    .iter_mut()
+                    .find(|call| call.id == tool_use_id)?;
+                content.apply(call);
+                return Some(server_tool_chunk(call.clone()));
+            }
+            StreamContentBlock::Thinking { .. } => {
+                self.current_block_type = Some("thinking".to_string());
+            }
+            StreamContentBlock::Unknown => {}
+        }
+        None
+    }
+
+    /// Handle a `content_block_stop` event.
+    fn stop_block(&mut self) -> Option<StreamChunk> {
+        let block_type = self.current_block_type.take();
+        self.current_tool_id = None;
+        self.current_tool_name = None;
+        match block_type.as_deref() {
+            Some("tool_use") => {
+                self.tool_index += 1;
+                None
+            }
+            Some("server_tool_use") => {
+                let mut call = self.server_call.take()?;
+                if let Ok(input) = serde_json::from_str(&self.server_input) {
+                    call.input = input;
+                }
+                self.server_calls.push(call.clone());
+                Some(server_tool_chunk(call))
+            }
+            // Citations cover the whole text block
+            Some("text") if !self.block_citations.is_empty() => {
+                let mut chunk = StreamChunk::empty(ChunkKind::Unknown);
+                chunk.citations = Some(
+                    self.block_citations
+                        .drain(..)
+                        .map(|c| c.to_citation(self.block_start, self.text_len))
+                        .collect(),
+                );
+                Some(chunk)
+            }
+            _ => None,
         }
     }
 }
 
+fn server_tool_chunk(call: ServerToolCall) -> StreamChunk {
+    let mut chunk = StreamChunk::empty(ChunkKind::ServerTool);
+    chunk.server_tool_call = Some(call);
+    chunk
+}
+
 impl Default for ClaudeParser {
     fn default() -> Self {
         Self::new()
@@ -324,35 +710,31 @@ impl ProviderParser for ClaudeParser {
         match event {
             ClaudeStreamEvent::MessageStart { message } => {
                 // Initial usage from message_start
-                self.current_usage = Some(Usage {
-                    input_tokens: message.usage.input_tokens,
-                    output_tokens: message.usage.output_tokens,
-                    cache_read_input_tokens: message.usage.cache_read_input_tokens.unwrap_or(0),
-                    cache_creation_input_tokens: message
-                        .usage
-                        .cache_creation_input_tokens
-                        .unwrap_or(0),
-                });
+                self.current_usage = Some(message.usage.to_usage());
                 Ok(None)
             }
-            ClaudeStreamEvent::ContentBlockStart { content_block, .. } => {
-                match content_block {
-                    StreamContentBlock::Text { .. } => {
-                        self.current_block_type = Some("text".to_string());
-                    }
-                    StreamContentBlock::ToolUse { id, name, .. } => {
-                        self.current_block_type = Some("tool_use".to_string());
-                        self.current_tool_id = Some(id);
-                        self.current_tool_name = Some(name);
-                    }
-                    StreamContentBlock::Thinking { .. } => {
-                        self.current_block_type = Some("thinking".to_string());
-                    }
+            ClaudeStreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => Ok(self
+                .start_block(content_block)
+                .map(|chunk| chunk.with_output_index(Some(index)))),
+            ClaudeStreamEvent::ContentBlockDelta { index, delta } => match delta {
+                StreamDelta::TextDelta { text } => {
+                    self.text_len += text.len();
+                    Ok(Some(
+                        StreamChunk::text_owned(text).with_output_index(Some(index)),
+                    ))
                 }
-                Ok(None)
-            }
-            ClaudeStreamEvent::ContentBlockDelta { delta, .. } => match delta {
-                StreamDelta::TextDelta { text } => Ok(Some(StreamChunk::text_owned(text))),
+                // Blocks open with an empty delta; skip it rather than emit a no-op
+                StreamDelta::InputJsonDelta { partial_json } if partial_json.is_empty() => Ok(None),
+                // The server runs its own tools; their input arrives whole at block stop
+                StreamDelta::InputJsonDelta { partial_json } if self.server_call.is_some() => {
+                    self.server_input.push_str(&partial_json);
+                    Ok(None)
+                }
+                // With fine-grained tool streaming, fragments are larger and not
+                // split on JSON token boundaries; they are passed through as-is
                 StreamDelta::InputJsonDelta { partial_json } => {
                     let mut chunk = StreamChunk::empty(ChunkKind::ToolDelta);
                     chunk.tool_call_delta = Some(ToolCallDelta {
@@ -360,23 +742,22 @@ impl ProviderParser for ClaudeParser {
                         id: self.current_tool_id.clone(),
                         function_name: self.current_tool_name.clone(),
                         function_arguments: Some(partial_json),
+                        final_arguments: None,
                     });
-                    Ok(Some(chunk))
+                    Ok(Some(chunk.with_output_index(Some(index))))
+                }
+                StreamDelta::CitationsDelta { citation } => {
+                    self.block_citations.push(citation);
+                    Ok(None)
                 }
                 StreamDelta::ThinkingDelta { .. } | StreamDelta::SignatureDelta { .. } => {
                     // Skip thinking deltas for now
                     Ok(None)
                 }
             },
-            ClaudeStreamEvent::ContentBlockStop { .. } => {
-                if self.current_block_type.as_deref() == Some("tool_use") {
-                    self.tool_index += 1;
-                }
-                self.current_block_type = None;
-                self.current_tool_id = None;
-                self.current_tool_name = None;
-                Ok(None)
-            }
+            ClaudeStreamEvent::ContentBlockStop { index } => Ok(self
+                .stop_block()
+                .map(|chunk| chunk.with_output_index(Some(index)))),
             ClaudeStreamEvent::MessageDelta { delta, usage } => {
                 let finish_reason = match delta.stop_reason.as_deref() {
                     Some("end_turn") => Some(FinishReason::Stop),
@@ -389,19 +770,25 @@ impl ProviderParser for ClaudeParser {
                 // Update output tokens from message_delta
                 if let Some(ref mut u) = self.current_usage {
                     u.output_tokens = usage.output_tokens;
+                    if let Some(server) = &usage.server_tool_use {
+                        u.web_search_requests = server.web_search_requests;
+                    }
                 }
 
                 let mut chunk = StreamChunk::empty(ChunkKind::Unknown);
                 chunk.usage = self.current_usage.clone();
                 chunk.finish_reason = finish_reason;
+                chunk.stop_sequence = delta.stop_sequence;
                 Ok(Some(chunk))
             }
             ClaudeStreamEvent::MessageStop => Ok(None),
             ClaudeStreamEvent::Ping => Ok(None),
-            ClaudeStreamEvent::Error { error } => Err(Error::Api {
-                status: 0,
-                message: error.message,
-            }),
+            ClaudeStreamEvent::Error { error } => Err(stream_error(error)),
+            // Anthropic adds event types over time; clients are to skip unknown ones
+            ClaudeStreamEvent::Unknown => {
+                self.skipped.skip("claude", data);
+                Ok(None)
+            }
         }
     }
 
@@ -409,15 +796,160 @@ impl ProviderParser for ClaudeParser {
         // Claude uses message_stop event, handled in parse_chunk
         false
     }
+
+    fn take_warning(&mut self) -> Option<Warning> {
+        self.skipped.take_warning()
+    }
+}
+
+/// Classify a mid-stream `error` event.
+///
+/// Overload and internal errors are transient and surface as retryable errors.
+fn stream_error(error: ClaudeError) -> Error {
+    match error.error_type.as_deref() {
+        Some("overloaded_error") => Error::Overloaded {
+            message: error.message,
+            details: None,
+        },
+        // Keep Anthropic's message where callers and the error hook can see it
+        Some("api_error") => Error::Server {
+            status: 500,
+            details: Some(Box::new(ErrorDetails {
+                status: 500,
+                body: error.message,
+                ..Default::default()
+            })),
+        },
+        _ => Error::api(0, error.message),
+    }
 }
 
 // --- Serde types for Claude API ---
 
+/// Messages API request body.
+#[derive(Serialize)]
+struct ClaudeBody<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anthropic_version: Option<&'static str>,
+    messages: Vec<ClaudeMessage<'a>>,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<ClaudeContent<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<ClaudeTool<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ClaudeToolChoice<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anthropic_beta: Option<[&'static str; 1]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ClaudeMessage<'a> {
+    role: &'static str,
+    content: ClaudeContent<'a>,
+}
+
+/// Message content or system prompt: a plain string or content blocks.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ClaudeContent<'a> {
+    Text(&'a str),
+    Blocks(Vec<InputBlock<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum InputBlock<'a> {
+    Text {
+        text: &'a str,
+    },
+    Image {
+        source: ImageSource<'a>,
+    },
+    ToolUse {
+        id: &'a str,
+        name: &'a str,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: &'a str,
+        content: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ImageSource<'a> {
+    Base64 { media_type: &'a str, data: &'a str },
+    Url { url: &'a str },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ClaudeTool<'a> {
+    Function(FunctionTool<'a>),
+    WebSearch(WebSearchTool<'a>),
+}
+
+#[derive(Serialize)]
+struct FunctionTool<'a> {
+    name: &'a str,
+    description: Option<&'a str>,
+    input_schema: Cow<'a, Value>,
+}
+
+/// The server-side `web_search` tool.
+#[derive(Serialize)]
+struct WebSearchTool<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_uses: Option<u32>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    allowed_domains: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    blocked_domains: &'a [String],
+}
+
+impl<'a> WebSearchTool<'a> {
+    fn new(search: &'a WebSearch) -> Self {
+        Self {
+            kind: WEB_SEARCH_TOOL,
+            name: "web_search",
+            max_uses: search.max_uses,
+            allowed_domains: &search.allowed_domains,
+            blocked_domains: &search.blocked_domains,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ClaudeToolChoice<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disable_parallel_tool_use: Option<bool>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ClaudeResponse {
     model: String,
     content: Vec<ContentBlock>,
     stop_reason: Option<String>,
+    stop_sequence: Option<String>,
     usage: ClaudeUsage,
 }
 
@@ -426,7 +958,11 @@ struct ClaudeResponse {
 #[serde(tag = "type")]
 enum ContentBlock {
     #[serde(rename = "text")]
-    Text { text: String },
+    Text {
+        text: String,
+        #[serde(default)]
+        citations: Option<Vec<ClaudeCitation>>,
+    },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -435,6 +971,57 @@ enum ContentBlock {
     },
     #[serde(rename = "thinking")]
     Thinking { thinking: String, signature: String },
+    #[serde(rename = "server_tool_use")]
+    ServerToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    #[serde(rename = "web_search_tool_result")]
+    WebSearchToolResult {
+        tool_use_id: String,
+        content: WebSearchContent,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Source of a text block's claim; web search results carry a URL.
+#[derive(Debug, Clone, Deserialize)]
+struct ClaudeCitation {
+    url: Option<String>,
+    title: Option<String>,
+}
+
+impl ClaudeCitation {
+    /// The citation of the text block at `start..end` of the content.
+    fn to_citation(&self, start: usize, end: usize) -> Citation {
+        Citation {
+            start_index: u32::try_from(start).ok(),
+            end_index: u32::try_from(end).ok(),
+            uri: self.url.clone(),
+            title: self.title.clone(),
+            ..Citation::default()
+        }
+    }
+}
+
+/// Content of a `web_search_tool_result` block.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum WebSearchContent {
+    Results(Vec<WebSearchResult>),
+    Error { error_code: String },
+}
+
+impl WebSearchContent {
+    /// Record the outcome on the call it answers.
+    fn apply(&self, call: &mut ServerToolCall) {
+        match self {
+            Self::Results(results) => call.results.clone_from(results),
+            Self::Error { error_code } => call.error = Some(error_code.clone()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -445,6 +1032,30 @@ struct ClaudeUsage {
     cache_read_input_tokens: Option<u32>,
     #[serde(default)]
     cache_creation_input_tokens: Option<u32>,
+    #[serde(default)]
+    server_tool_use: Option<ServerToolUsage>,
+}
+
+impl ClaudeUsage {
+    fn to_usage(&self) -> Usage {
+        Usage {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cache_read_input_tokens: self.cache_read_input_tokens.unwrap_or(0),
+            cache_creation_input_tokens: self.cache_creation_input_tokens.unwrap_or(0),
+            web_search_requests: self
+                .server_tool_use
+                .as_ref()
+                .map_or(0, |s| s.web_search_requests),
+        }
+    }
+}
+
+/// Server tool calls billed for the request.
+#[derive(Debug, Deserialize, Clone)]
+struct ServerToolUsage {
+    #[serde(default)]
+    web_search_requests: u32,
 }
 
 #[allow(dead_code)]
@@ -455,13 +1066,13 @@ enum ClaudeStreamEvent {
     MessageStart { message: StreamMessage },
     #[serde(rename = "content_block_start")]
     ContentBlockStart {
-        index: usize,
+        index: u32,
         content_block: StreamContentBlock,
     },
     #[serde(rename = "content_block_delta")]
-    ContentBlockDelta { index: usize, delta: StreamDelta },
+    ContentBlockDelta { index: u32, delta: StreamDelta },
     #[serde(rename = "content_block_stop")]
-    ContentBlockStop { index: usize },
+    ContentBlockStop { index: u32 },
     #[serde(rename = "message_delta")]
     MessageDelta {
         delta: MessageDeltaData,
@@ -473,6 +1084,8 @@ enum ClaudeStreamEvent {
     Ping,
     #[serde(rename = "error")]
     Error { error: ClaudeError },
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Deserialize)]
@@ -485,7 +1098,11 @@ struct StreamMessage {
 #[serde(tag = "type")]
 enum StreamContentBlock {
     #[serde(rename = "text")]
-    Text { text: String },
+    Text {
+        text: String,
+        #[serde(default)]
+        citations: Option<Vec<ClaudeCitation>>,
+    },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -494,6 +1111,19 @@ enum StreamContentBlock {
     },
     #[serde(rename = "thinking")]
     Thinking { thinking: String, signature: String },
+    #[serde(rename = "server_tool_use")]
+    ServerToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    #[serde(rename = "web_search_tool_result")]
+    WebSearchToolResult {
+        tool_use_id: String,
+        content: WebSearchContent,
+    },
+    #[serde(other)]
+    Unknown,
 }
 
 #[allow(dead_code, clippy::enum_variant_names)]
@@ -508,20 +1138,27 @@ enum StreamDelta {
     ThinkingDelta { thinking: String },
     #[serde(rename = "signature_delta")]
     SignatureDelta { signature: String },
+    #[serde(rename = "citations_delta")]
+    CitationsDelta { citation: ClaudeCitation },
 }
 
 #[derive(Debug, Deserialize)]
 struct MessageDeltaData {
     stop_reason: Option<String>,
+    stop_sequence: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct MessageDeltaUsage {
     output_tokens: u32,
+    #[serde(default)]
+    server_tool_use: Option<ServerToolUsage>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ClaudeError {
+    #[serde(rename = "type")]
+    error_type: Option<String>,
     message: String,
 }
 
@@ -547,6 +1184,14 @@ mod tests {
         assert_eq!(result.usage.input_tokens, 10);
         assert_eq!(result.usage.output_tokens, 5);
         assert_eq!(result.finish_reason, FinishReason::Stop);
+        assert_eq!(result.stop_sequence, None);
+
+        let json = json.replace(
+            r#""stop_reason": "end_turn""#,
+            r#""stop_reason": "stop_sequence", "stop_sequence": "END""#,
+        );
+        let result = provider.parse_response(&json).unwrap();
+        assert_eq!(result.stop_sequence.as_deref(), Some("END"));
     }
 
     #[test]
@@ -591,6 +1236,7 @@ mod tests {
         let delta = r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#;
         let chunk = parser.parse_chunk(delta).unwrap().unwrap();
         assert_eq!(chunk.text().unwrap().as_ref(), "Hello");
+        assert_eq!(chunk.output_index, Some(0));
     }
 
     #[test]
@@ -614,6 +1260,97 @@ mod tests {
         assert_eq!(tool_delta.function_name, Some("get_weather".to_string()));
     }
 
+    #[test]
+    fn test_fine_grained_tool_streaming() {
+        let provider = ClaudeProvider::new();
+        let config = RequestConfig {
+            fine_grained_tool_streaming: true,
+            ..Default::default()
+        };
+        let headers = provider.request_headers("key", &config);
+        assert_eq!(headers["anthropic-beta"], FINE_GRAINED_TOOL_STREAMING_BETA);
+        assert!(!provider
+            .request_headers("key", &RequestConfig::default())
+            .contains_key("anthropic-beta"));
+
+        let bedrock = ClaudeProvider::for_platform(
+            ClaudePlatform::Bedrock {
+                region: "us-east-1".to_string(),
+            },
+            None,
+        );
+        let body = bedrock
+            .build_stream_body("anthropic.claude-sonnet-4", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["anthropic_beta"][0], FINE_GRAINED_TOOL_STREAMING_BETA);
+
+        // Large unvalidated fragments accumulate into the full arguments
+        let mut parser = ClaudeParser::new();
+        parser
+            .parse_chunk(r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_1","name":"write_file","input":{}}}"#)
+            .unwrap();
+        let empty = r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":""}}"#;
+        assert!(parser.parse_chunk(empty).unwrap().is_none());
+
+        let mut tool_calls = ToolCallAccumulator::default();
+        for fragment in [r#"{\"path\": \"a.txt\", \"con"#, r#"tent\": \"long te"#] {
+            let delta = format!(
+                r#"{{"type":"content_block_delta","index":0,"delta":{{"type":"input_json_delta","partial_json":"{fragment}"}}}}"#
+            );
+            let chunk = parser.parse_chunk(&delta).unwrap().unwrap();
+            tool_calls.apply(chunk.tool_call_delta.as_ref().unwrap());
+        }
+        let call = &tool_calls.finalize()[0];
+        assert_eq!(call.id, "toolu_1");
+        assert_eq!(
+            call.function.arguments,
+            r#"{"path": "a.txt", "content": "long te"#
+        );
+
+        // Cut-off arguments are replayed wrapped rather than dropped
+        assert_eq!(
+            tool_call_input(call)["INVALID_JSON"],
+            r#"{"path": "a.txt", "content": "long te"#
+        );
+    }
+
+    #[test]
+    fn test_parse_stream_final_input() {
+        // Blocks that start with their input carry the final arguments
+        let mut parser = ClaudeParser::new();
+        let chunk = parser
+            .parse_chunk(r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_1","name":"search","input":{"query":"rust"}}}"#)
+            .unwrap()
+            .unwrap();
+        let delta = chunk.tool_call_delta.unwrap();
+        assert_eq!(
+            delta.final_arguments.as_deref(),
+            Some(r#"{"query":"rust"}"#)
+        );
+        assert!(delta.function_arguments.is_none());
+    }
+
+    #[test]
+    fn test_parse_stream_overloaded_error() {
+        let mut parser = ClaudeParser::new();
+        let data = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+
+        let err = parser.parse_chunk(data).unwrap_err();
+        assert!(matches!(err, Error::Overloaded { .. }));
+        assert!(err.is_retryable());
+
+        let data =
+            r#"{"type":"error","error":{"type":"api_error","message":"Internal server error"}}"#;
+        let err = parser.parse_chunk(data).unwrap_err();
+        assert!(err.is_retryable());
+        assert_eq!(err.details().unwrap().body, "Internal server error");
+
+        let data = r#"{"type":"error","error":{"type":"invalid_request_error","message":"bad"}}"#;
+        let err = parser.parse_chunk(data).unwrap_err();
+        assert!(!err.is_retryable());
+    }
+
     #[test]
     fn test_build_body_with_tools() {
         let provider = ClaudeProvider::new();
@@ -632,13 +1369,189 @@ mod tests {
 
         let body = provider
             .build_stream_body("claude-3-haiku", &messages, &config)
-            .unwrap();
+            .unwrap()
+            .to_value();
 
         assert!(body["tools"].is_array());
         assert!(body["stream"].as_bool().unwrap());
         assert_eq!(body["max_tokens"], 100);
     }
 
+    #[test]
+    fn test_web_search() {
+        let provider = ClaudeProvider::new();
+        let config = RequestConfig {
+            tools: Some(vec![Tool::function(
+                "get_weather",
+                "Get weather",
+                serde_json::json!({"type": "object"}),
+            )]),
+            server_tools: Some(vec![ServerTool::WebSearch(WebSearch {
+                max_uses: Some(2),
+                allowed_domains: vec!["example.com".into()],
+                ..Default::default()
+            })]),
+            ..Default::default()
+        };
+        let body = provider
+            .build_stream_body("claude-3-5-haiku", &[Message::user("News?")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["tools"][0]["name"], "get_weather");
+        assert_eq!(
+            body["tools"][1],
+            serde_json::json!({
+                "type": "web_search_20250305",
+                "name": "web_search",
+                "max_uses": 2,
+                "allowed_domains": ["example.com"]
+            })
+        );
+
+        let json = r#"{
+            "model": "claude-3-5-haiku-20241022",
+            "content": [
+                {"type": "text", "text": "Searching. "},
+                {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "rust news"}},
+                {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_1", "content": [
+                    {"type": "web_search_result", "url": "https://example.com/a", "title": "A", "encrypted_content": "x", "page_age": "1 day ago"}
+                ]},
+                {"type": "text", "text": "Rust 2.0 shipped.", "citations": [
+                    {"type": "web_search_result_location", "url": "https://example.com/a", "title": "A", "cited_text": "...", "encrypted_index": "y"}
+                ]}
+            ],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 5, "server_tool_use": {"web_search_requests": 1}}
+        }"#;
+        let result = provider.parse_response(json).unwrap();
+        assert_eq!(result.content, "Searching. Rust 2.0 shipped.");
+        assert_eq!(result.usage.web_search_requests, 1);
+        let call = &result.server_tool_calls[0];
+        assert_eq!(call.input["query"], "rust news");
+        assert_eq!(call.results[0].page_age.as_deref(), Some("1 day ago"));
+        let citation = &result.citations.unwrap()[0];
+        assert_eq!(
+            (citation.start_index, citation.end_index),
+            (Some(11), Some(28))
+        );
+        assert_eq!(citation.uri.as_deref(), Some("https://example.com/a"));
+    }
+
+    #[test]
+    fn test_parse_stream_web_search() {
+        let mut parser = ClaudeParser::new();
+        let events = [
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":10,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"server_tool_use","id":"srvtoolu_1","name":"web_search","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"query\": \"rust\"}"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"web_search_tool_result","tool_use_id":"srvtoolu_1","content":{"type":"web_search_tool_result_error","error_code":"max_uses_exceeded"}}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"content_block_start","index":2,"content_block":{"type":"text","text":"","citations":[]}}"#,
+            r#"{"type":"content_block_delta","index":2,"delta":{"type":"citations_delta","citation":{"type":"web_search_result_location","url":"https://example.com","title":"Ex"}}}"#,
+            r#"{"type":"content_block_delta","index":2,"delta":{"type":"text_delta","text":"Rust"}}"#,
+            r#"{"type":"content_block_stop","index":2}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":9,"server_tool_use":{"web_search_requests":1}}}"#,
+        ];
+        let chunks: Vec<StreamChunk> = events
+            .iter()
+            .filter_map(|event| parser.parse_chunk(event).unwrap())
+            .collect();
+
+        // The call, then the call with its outcome
+        let calls: Vec<&ServerToolCall> = chunks
+            .iter()
+            .filter_map(|c| c.server_tool_call.as_ref())
+            .collect();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].input["query"], "rust");
+        assert_eq!(calls[1].error.as_deref(), Some("max_uses_exceeded"));
+
+        let citations = chunks.iter().find_map(|c| c.citations.as_ref()).unwrap();
+        assert_eq!(citations[0].end_index, Some(4));
+        let usage = chunks.last().unwrap().usage.as_ref().unwrap();
+        assert_eq!(usage.web_search_requests, 1);
+    }
+
+    #[test]
+    fn test_prefill() {
+        let provider = ClaudeProvider::new();
+        let messages = vec![
+            Message::user("List three colors as JSON."),
+            Message::assistant_prefill("{\"colors\": [ "),
+        ];
+
+        let body = provider
+            .build_complete_body("claude-3-haiku", &messages, &RequestConfig::default())
+            .unwrap()
+            .to_value();
+        assert_eq!(body["messages"][1]["role"], "assistant");
+        assert_eq!(body["messages"][1]["content"], "{\"colors\": [");
+    }
+
+    #[test]
+    fn test_multiple_system_messages() {
+        let provider = ClaudeProvider::new();
+        let messages = vec![
+            Message::system("You are a support agent."),
+            Message {
+                content: MessageContent::Parts(vec![
+                    ContentPart::Text {
+                        text: "Product manual: ...".to_string(),
+                    },
+                    ContentPart::Text {
+                        text: "Answer briefly.".to_string(),
+                    },
+                ]),
+                ..Message::system("")
+            },
+            Message::user("Hi"),
+        ];
+
+        let body = provider
+            .build_complete_body("claude-3-haiku", &messages, &RequestConfig::default())
+            .unwrap()
+            .to_value();
+        let system = body["system"].as_array().unwrap();
+        assert_eq!(system.len(), 3);
+        assert_eq!(system[0]["type"], "text");
+        assert_eq!(system[1]["text"], "Product manual: ...");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+
+        // A single system message stays a plain string
+        let body = provider
+            .build_complete_body(
+                "claude-3-haiku",
+                &[Message::system("Be brief"), Message::user("Hi")],
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert_eq!(body["system"], "Be brief");
+    }
+
+    #[test]
+    fn test_disable_parallel_tool_use() {
+        let provider = ClaudeProvider::new();
+        let messages = vec![Message::user("What's the weather?")];
+        let config = RequestConfig {
+            tools: Some(vec![Tool::function(
+                "get_weather",
+                "Get weather",
+                serde_json::json!({"type": "object", "properties": {}}),
+            )]),
+            parallel_tool_calls: Some(false),
+            ..Default::default()
+        };
+
+        let body = provider
+            .build_complete_body("claude-3-haiku", &messages, &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["tool_choice"]["type"], "auto");
+        assert_eq!(body["tool_choice"]["disable_parallel_tool_use"], true);
+    }
+
     #[test]
     fn test_headers() {
         let provider = ClaudeProvider::new();
@@ -646,4 +1559,94 @@ mod tests {
         assert!(headers.contains_key("x-api-key"));
         assert!(headers.contains_key("anthropic-version"));
     }
+
+    #[test]
+    fn test_bedrock_platform() {
+        let provider = ClaudeProvider::for_platform(
+            ClaudePlatform::Bedrock {
+                region: "us-east-1".to_string(),
+            },
+            None,
+        );
+        let model = "anthropic.claude-3-5-sonnet-20241022-v2:0";
+
+        assert_eq!(
+            provider.stream_url(model, "key"),
+            "https://bedrock-runtime.us-east-1.amazonaws.com/model/anthropic.claude-3-5-sonnet-20241022-v2%3A0/invoke-with-response-stream"
+        );
+        assert_eq!(provider.stream_framing(), StreamFraming::AwsEventStream);
+
+        let body = provider
+            .build_stream_body(model, &[Message::user("Hi")], &RequestConfig::default())
+            .unwrap()
+            .to_value();
+        assert_eq!(body["anthropic_version"], BEDROCK_ANTHROPIC_VERSION);
+        assert!(body.get("model").is_none());
+        assert!(body.get("stream").is_none());
+
+        let headers = provider.headers("bedrock-key");
+        assert_eq!(headers["authorization"], "Bearer bedrock-key");
+        assert!(!headers.contains_key("anthropic-version"));
+    }
+
+    #[test]
+    fn test_vertex_platform() {
+        let provider = ClaudeProvider::for_platform(
+            ClaudePlatform::Vertex {
+                project_id: "my-project".to_string(),
+                region: "us-east5".to_string(),
+            },
+            None,
+        );
+        let model = "claude-3-5-sonnet-v2@20241022";
+
+        assert_eq!(
+            provider.complete_url(model, "token"),
+            "https://us-east5-aiplatform.googleapis.com/v1/projects/my-project/locations/us-east5/publishers/anthropic/models/claude-3-5-sonnet-v2@20241022:rawPredict"
+        );
+        assert_eq!(provider.stream_framing(), StreamFraming::Sse);
+
+        let body = provider
+            .build_stream_body(model, &[Message::user("Hi")], &RequestConfig::default())
+            .unwrap()
+            .to_value();
+        assert_eq!(body["anthropic_version"], VERTEX_ANTHROPIC_VERSION);
+        assert_eq!(body["stream"], true);
+        assert!(body.get("model").is_none());
+    }
+
+    #[test]
+    fn test_replay_assistant_tool_calls() {
+        let provider = ClaudeProvider::new();
+        let messages = vec![
+            Message::user("Weather in Paris?"),
+            Message::assistant_with_tool_calls(
+                "Let me check.",
+                vec![ToolCall {
+                    id: "toolu_1".to_string(),
+                    tool_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "get_weather".to_string(),
+                        arguments: r#"{"city":"Paris"}"#.to_string(),
+                    },
+                }],
+            ),
+            Message::tool_result("toolu_1", "18C"),
+        ];
+
+        let body = provider
+            .build_complete_body(
+                "claude-3-5-sonnet-20241022",
+                &messages,
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        let assistant = &body["messages"][1]["content"];
+        assert_eq!(assistant[0]["type"], "text");
+        assert_eq!(assistant[1]["type"], "tool_use");
+        assert_eq!(assistant[1]["id"], "toolu_1");
+        assert_eq!(assistant[1]["input"]["city"], "Paris");
+        assert_eq!(body["messages"][2]["content"][0]["tool_use_id"], "toolu_1");
+    }
 }
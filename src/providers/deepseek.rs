@@ -0,0 +1,179 @@
+//! DeepSeek API implementation.
+//!
+//! DeepSeek serves an OpenAI-compatible Chat Completions API.
+//! - Auth via `Bearer` API key
+//! - `deepseek-reasoner` streams its reasoning in `reasoning_content` before the
+//!   answer; it arrives as [`ChunkKind::Thinking`] chunks and in
+//!   [`CompletionResult::reasoning`]
+//! - Streaming uses SSE with `[DONE]` marker
+
+use crate::error::Error;
+use crate::providers::cerebras::{
+    build_chat_body, build_chat_messages, parse_chat_response, CerebrasParser, ChatBody,
+};
+use crate::providers::{Parameter, Provider, RequestBody, RequestConfig};
+use crate::stream::ProviderParser;
+use crate::types::*;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+/// DeepSeek API provider.
+pub struct DeepSeekProvider {
+    base_url: String,
+}
+
+impl DeepSeekProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "https://api.deepseek.com".to_string(),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+
+    fn build_base_body<'a>(
+        &self,
+        model: &'a str,
+        messages: &'a [Message],
+        config: &'a RequestConfig,
+    ) -> ChatBody<'a> {
+        let mut body = build_chat_body(model, messages, config);
+        // The reasoner always reasons, and no model speaks
+        body.reasoning_effort = None;
+        body.modalities = None;
+        body.audio = None;
+        body
+    }
+}
+
+impl Default for DeepSeekProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for DeepSeekProvider {
+    fn name(&self) -> &'static str {
+        "deepseek"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self, api_key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+            headers.insert(AUTHORIZATION, auth);
+        }
+        headers
+    }
+
+    fn build_stream_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &self
+                .build_base_body(model, messages, config)
+                .with_stream(true),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_complete_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &self
+                .build_base_body(model, messages, config)
+                .with_stream(false),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        Some(build_chat_messages(messages))
+    }
+
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        parameter == Parameter::Logprobs
+    }
+
+    fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
+        // Same chunk format as other OpenAI-compatible servers
+        Box::new(CerebrasParser::new())
+    }
+
+    fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
+        parse_chat_response(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ReasoningEffort;
+
+    #[test]
+    fn test_parse_reasoning_chunks() {
+        let mut parser = DeepSeekProvider::new().create_parser();
+
+        let data = r#"{"id":"1","model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":null,"reasoning_content":"Compare digits"},"finish_reason":null}]}"#;
+        let chunk = parser.parse_chunk(data).unwrap().unwrap();
+        assert_eq!(chunk.kind, ChunkKind::Thinking);
+        assert_eq!(chunk.text().unwrap(), "Compare digits");
+
+        let data = r#"{"id":"1","model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":"9.11","reasoning_content":null},"finish_reason":null}]}"#;
+        let chunk = parser.parse_chunk(data).unwrap().unwrap();
+        assert_eq!(chunk.kind, ChunkKind::Text);
+        assert_eq!(chunk.text().unwrap(), "9.11");
+    }
+
+    #[test]
+    fn test_parse_response_reasoning() {
+        let body = r#"{
+            "model": "deepseek-reasoner",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "9.9", "reasoning_content": "0.9 > 0.11"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 40}
+        }"#;
+        let result = DeepSeekProvider::new().parse_response(body).unwrap();
+        assert_eq!(result.content, "9.9");
+        assert_eq!(result.reasoning.as_deref(), Some("0.9 > 0.11"));
+        assert_eq!(result.finish_reason, FinishReason::Stop);
+    }
+
+    #[test]
+    fn test_body_leaves_out_reasoning_effort() {
+        let config = RequestConfig {
+            reasoning_effort: Some(ReasoningEffort::High),
+            ..Default::default()
+        };
+        let provider = DeepSeekProvider::new();
+        let body = provider
+            .build_stream_body("deepseek-reasoner", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert!(body.get("reasoning_effort").is_none());
+        assert_eq!(
+            provider.stream_url("deepseek-reasoner", "sk-test"),
+            "https://api.deepseek.com/chat/completions"
+        );
+    }
+}
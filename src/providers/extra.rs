@@ -0,0 +1,282 @@
+//! Merging `extra` fields into a typed body while it serializes.
+
+use super::merge_json;
+use serde::ser::{Error as _, Impossible, SerializeMap, SerializeStruct, Serializer};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// `body` with the fields of `extra` merged in as [`merge_json`] would: an
+/// extra field replaces the body's field of the same name, or merges into it
+/// when both are objects.
+///
+/// Only the fields named in `extra` go through a `Value`; the rest of the body
+/// is written straight to the output, and no key is written twice.
+pub(super) struct WithExtra<'a, T: ?Sized> {
+    pub(super) body: &'a T,
+    pub(super) extra: &'a Map<String, Value>,
+}
+
+impl<T: Serialize + ?Sized> Serialize for WithExtra<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.body.serialize(Body {
+            inner: serializer,
+            extra: self.extra,
+        })
+    }
+}
+
+/// Serializer for the body's top level, which must be a struct or map.
+struct Body<'a, S> {
+    inner: S,
+    extra: &'a Map<String, Value>,
+}
+
+/// The body's fields as they are written.
+struct Fields<'a, M> {
+    map: M,
+    extra: &'a Map<String, Value>,
+    // Extra keys the body had a field for
+    merged: Vec<&'a str>,
+    // Key of a map entry whose value comes next
+    key: Option<String>,
+}
+
+impl<'a, M: SerializeMap> Fields<'a, M> {
+    fn new(map: M, extra: &'a Map<String, Value>) -> Self {
+        Self {
+            map,
+            extra,
+            merged: Vec::new(),
+            key: None,
+        }
+    }
+
+    fn field<V: Serialize + ?Sized>(&mut self, key: &str, value: &V) -> Result<(), M::Error> {
+        let Some((name, patch)) = self.extra.get_key_value(key) else {
+            return self.map.serialize_entry(key, value);
+        };
+        self.merged.push(name);
+        if patch.is_object() {
+            let mut merged = serde_json::to_value(value).map_err(M::Error::custom)?;
+            merge_json(&mut merged, patch);
+            self.map.serialize_entry(key, &merged)
+        } else {
+            self.map.serialize_entry(key, patch)
+        }
+    }
+
+    fn finish(mut self) -> Result<M::Ok, M::Error> {
+        for (key, value) in self.extra {
+            if !self.merged.contains(&key.as_str()) {
+                self.map.serialize_entry(key, value)?;
+            }
+        }
+        self.map.end()
+    }
+}
+
+impl<M: SerializeMap> SerializeStruct for Fields<'_, M> {
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn serialize_field<V: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), M::Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<M::Ok, M::Error> {
+        self.finish()
+    }
+}
+
+// Structs with `#[serde(flatten)]` fields serialize as maps
+impl<M: SerializeMap> SerializeMap for Fields<'_, M> {
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn serialize_key<K: Serialize + ?Sized>(&mut self, key: &K) -> Result<(), M::Error> {
+        match serde_json::to_value(key).map_err(M::Error::custom)? {
+            Value::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(M::Error::custom("request body keys must be strings")),
+        }
+    }
+
+    fn serialize_value<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), M::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| M::Error::custom("map value without a key"))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<M::Ok, M::Error> {
+        self.finish()
+    }
+}
+
+fn not_an_object<E: serde::ser::Error>() -> E {
+    E::custom("request body must be a JSON object")
+}
+
+macro_rules! reject {
+    ($($method:ident($($ty:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<S::Ok, S::Error> {
+                Err(not_an_object())
+            }
+        )*
+    };
+}
+
+impl<'a, S: Serializer> Serializer for Body<'a, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Impossible<S::Ok, S::Error>;
+    type SerializeMap = Fields<'a, S::SerializeMap>;
+    type SerializeStruct = Fields<'a, S::SerializeMap>;
+    type SerializeStructVariant = Impossible<S::Ok, S::Error>;
+
+    reject! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<S::Ok, S::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &V,
+    ) -> Result<S::Ok, S::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &V,
+    ) -> Result<S::Ok, S::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Ok(Fields::new(self.inner.serialize_map(None)?, self.extra))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(Fields::new(self.inner.serialize_map(None)?, self.extra))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        Err(not_an_object())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Request<'a> {
+        model: &'a str,
+        max_tokens: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stop: Option<&'a str>,
+        options: Value,
+        #[serde(flatten)]
+        vendor: Map<String, Value>,
+    }
+
+    #[test]
+    fn test_extra_replaces_and_merges_fields() {
+        let body = Request {
+            model: "m",
+            max_tokens: 10,
+            stop: None,
+            options: serde_json::json!({"a": 1, "b": 2}),
+            vendor: serde_json::json!({"seed": 1}).as_object().unwrap().clone(),
+        };
+        let extra = serde_json::json!({
+            "max_tokens": 20,
+            "options": {"b": 3},
+            "seed": 2,
+            "stop": "\n"
+        });
+        let json = serde_json::to_string(&WithExtra {
+            body: &body,
+            extra: extra.as_object().unwrap(),
+        })
+        .unwrap();
+
+        // Each key once, typed fields first in their order
+        assert_eq!(
+            json,
+            r#"{"model":"m","max_tokens":20,"options":{"a":1,"b":3},"seed":2,"stop":"\n"}"#
+        );
+    }
+}
@@ -6,20 +6,34 @@
 //! - Streaming via SSE with `?alt=sse`
 //! - Usage in every chunk (keep last)
 //! - No `[DONE]` marker - stream ends on connection close
+//!
+//! Alternatively, [`GeminiProvider::with_openai_compat`] drives Gemini through its
+//! OpenAI-compatible endpoint (`/v1beta/openai/`), for gateways that only speak
+//! the OpenAI Chat Completions dialect.
 
 use crate::error::Error;
-use crate::providers::{Provider, RequestConfig};
+use crate::providers::cerebras::{
+    build_chat_body, build_chat_messages, parse_chat_response, CerebrasParser,
+};
+use crate::providers::{
+    system_texts, Parameter, ParameterLimits, Provider, ReasoningEffort, RequestBody,
+    RequestConfig, ResponseFormat, ToolChoice,
+};
+use crate::schema::{self, SchemaDialect};
 use crate::stream::ProviderParser;
 use crate::types::*;
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
-use serde::Deserialize;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 
 /// Gemini API provider.
 pub struct GeminiProvider {
     base_url: String,
     /// API key stored for query param auth
     api_key_in_query: bool,
+    /// Use the OpenAI-compatible Chat Completions endpoint
+    openai_compat: bool,
 }
 
 impl GeminiProvider {
@@ -27,9 +41,16 @@ impl GeminiProvider {
         Self {
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
             api_key_in_query: false,
+            openai_compat: false,
         }
     }
 
+    /// Use the OpenAI-compatible endpoint (`{base_url}/openai/chat/completions`).
+    pub fn with_openai_compat(mut self) -> Self {
+        self.openai_compat = true;
+        self
+    }
+
     /// Use query parameter for API key instead of header.
     pub fn with_query_auth(mut self) -> Self {
         self.api_key_in_query = true;
@@ -42,76 +63,153 @@ impl GeminiProvider {
     }
 
     /// Convert messages to Gemini format.
-    fn convert_messages(&self, messages: &[Message]) -> Vec<Value> {
-        messages
-            .iter()
-            .filter_map(|m| {
-                let role = match m.role {
-                    Role::User => "user",
-                    Role::Assistant => "model",
-                    Role::System => return None, // Handled separately
-                    Role::Tool => "function", // Tool responses
-                };
+    fn convert_messages<'a>(&self, messages: &'a [Message]) -> Vec<Content<'a>> {
+        let mut contents: Vec<Content> = Vec::with_capacity(messages.len());
+        let mut previous_role = None;
+
+        for (i, m) in messages.iter().enumerate() {
+            let role = match m.role {
+                Role::User => "user",
+                Role::Assistant => "model",
+                Role::System => continue, // Handled separately
+                Role::Tool => {
+                    let part = Self::function_response(&messages[..i], m);
+                    // Responses to parallel calls must share a single turn
+                    match contents.last_mut() {
+                        Some(last) if previous_role == Some(Role::Tool) => last.parts.push(part),
+                        _ => contents.push(Content {
+                            role: "user",
+                            parts: vec![part],
+                        }),
+                    }
+                    previous_role = Some(Role::Tool);
+                    continue;
+                }
+            };
+            previous_role = Some(m.role);
 
-                let parts = match &m.content {
-                    MessageContent::Text(text) => {
-                        vec![serde_json::json!({"text": text})]
+            let mut parts = match &m.content {
+                // Tool-calling turns often carry no text
+                MessageContent::Text(text) if text.is_empty() && m.tool_calls.is_some() => {
+                    Vec::new()
+                }
+                MessageContent::Text(text) => vec![Part::Text(text)],
+                MessageContent::Parts(parts) => parts
+                    .iter()
+                    .map(|p| match p {
+                        ContentPart::Text { text } => Part::Text(text),
+                        ContentPart::ImageUrl { image_url } => Part::InlineData {
+                            mime_type: "image/jpeg",
+                            data: image_url
+                                .url
+                                .strip_prefix("data:image/jpeg;base64,")
+                                .unwrap_or(&image_url.url),
+                        },
+                    })
+                    .collect(),
+            };
+
+            // Replay tool calls made by the model as `functionCall` parts
+            if let (Some(tool_calls), Role::Assistant) = (&m.tool_calls, m.role) {
+                parts.extend(tool_calls.iter().map(|tc| {
+                    Part::FunctionCall {
+                        name: &tc.function.name,
+                        args: serde_json::from_str(&tc.function.arguments)
+                            .unwrap_or_else(|_| Value::Object(serde_json::Map::new())),
                     }
-                    MessageContent::Parts(parts) => parts
-                        .iter()
-                        .map(|p| match p {
-                            ContentPart::Text { text } => serde_json::json!({"text": text}),
-                            ContentPart::ImageUrl { image_url } => {
-                                serde_json::json!({
-                                    "inline_data": {
-                                        "mime_type": "image/jpeg",
-                                        "data": image_url.url.strip_prefix("data:image/jpeg;base64,")
-                                            .unwrap_or(&image_url.url)
-                                    }
-                                })
-                            }
-                        })
-                        .collect(),
-                };
+                }));
+            }
 
-                Some(serde_json::json!({
-                    "role": role,
-                    "parts": parts
-                }))
-            })
-            .collect()
+            contents.push(Content { role, parts });
+        }
+
+        contents
     }
 
-    /// Extract system instruction from messages.
-    fn extract_system(&self, messages: &[Message]) -> Option<Value> {
-        messages
-            .iter()
-            .find(|m| m.role == Role::System)
-            .and_then(|m| m.content.as_text())
-            .map(|text| {
-                serde_json::json!({
-                    "parts": [{"text": text}]
+    /// Build a `functionResponse` part for a tool result.
+    ///
+    /// Gemini matches responses to calls by function name, so the name is
+    /// taken from the message or looked up in the preceding tool calls.
+    fn function_response<'a>(history: &'a [Message], m: &'a Message) -> Part<'a> {
+        let name = m.name.as_deref().or_else(|| {
+            let id = m.tool_call_id.as_deref()?;
+            history
+                .iter()
+                .rev()
+                .filter_map(|h| h.tool_calls.as_deref())
+                .flatten()
+                .find(|tc| tc.id == id)
+                .map(|tc| tc.function.name.as_str())
+        });
+
+        let text: Cow<str> = match &m.content {
+            MessageContent::Text(text) => Cow::Borrowed(text),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
                 })
-            })
+                .collect::<String>()
+                .into(),
+        };
+
+        // The response must be an object; wrap anything else
+        let response = match serde_json::from_str::<Value>(&text) {
+            Ok(value @ Value::Object(_)) => FunctionResult::Object(value),
+            _ => FunctionResult::Wrapped { content: text },
+        };
+
+        Part::FunctionResponse {
+            name: name.unwrap_or_default(),
+            response,
+        }
+    }
+
+    /// Extract the system instruction from all system messages, one part per text.
+    fn extract_system<'a>(&self, messages: &'a [Message]) -> Option<SystemInstruction<'a>> {
+        let parts: Vec<Part> = system_texts(messages).into_iter().map(Part::Text).collect();
+        (!parts.is_empty()).then_some(SystemInstruction { parts })
     }
 
     /// Convert tools to Gemini format.
-    fn convert_tools(&self, tools: &[Tool]) -> Value {
-        let function_declarations: Vec<Value> = tools
-            .iter()
-            .map(|t| {
-                serde_json::json!({
-                    "name": t.function.name,
-                    "description": t.function.description,
-                    "parameters": t.function.parameters
+    fn convert_tools<'a>(&self, tools: &'a [Tool]) -> [ToolDeclarations<'a>; 1] {
+        [ToolDeclarations {
+            function_declarations: tools
+                .iter()
+                .map(|t| FunctionDeclaration {
+                    name: &t.function.name,
+                    description: t.function.description.as_deref(),
+                    parameters: t
+                        .function
+                        .parameters
+                        .as_ref()
+                        .map(|p| schema::normalized(p, SchemaDialect::Gemini)),
                 })
-            })
-            .collect();
+                .collect(),
+        }]
+    }
+}
 
-        serde_json::json!([{
-            "function_declarations": function_declarations
-        }])
+/// Request-wide `mediaResolution` for the images' detail levels.
+///
+/// Gemini sets resolution per request, so any `"high"` image wins over `"low"` ones.
+fn media_resolution(messages: &[Message]) -> Option<&'static str> {
+    let details = messages.iter().flat_map(|m| match &m.content {
+        MessageContent::Parts(parts) => parts.as_slice(),
+        MessageContent::Text(_) => &[],
+    });
+    let mut resolution = None;
+    for part in details {
+        if let ContentPart::ImageUrl { image_url } = part {
+            match image_url.detail.as_deref() {
+                Some("high") => return Some("MEDIA_RESOLUTION_HIGH"),
+                Some("low") => resolution = Some("MEDIA_RESOLUTION_LOW"),
+                _ => {}
+            }
+        }
     }
+    resolution
 }
 
 impl Default for GeminiProvider {
@@ -132,6 +230,12 @@ impl Provider for GeminiProvider {
     fn headers(&self, api_key: &str) -> HeaderMap {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if self.openai_compat {
+            if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+                headers.insert(AUTHORIZATION, auth);
+            }
+            return headers;
+        }
         // Use header auth by default
         if !self.api_key_in_query {
             if let Ok(key) = HeaderValue::from_str(api_key) {
@@ -146,8 +250,14 @@ impl Provider for GeminiProvider {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        self.build_body(model, messages, config)
+    ) -> Result<RequestBody, Error> {
+        if self.openai_compat {
+            return RequestBody::json_with_extra(
+                &build_chat_body(model, messages, config).with_stream(true),
+                config.extra.as_ref(),
+            );
+        }
+        RequestBody::json_with_extra(&self.build_body(messages, config), config.extra.as_ref())
     }
 
     fn build_complete_body(
@@ -155,22 +265,70 @@ impl Provider for GeminiProvider {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        self.build_body(model, messages, config)
+    ) -> Result<RequestBody, Error> {
+        if self.openai_compat {
+            return RequestBody::json_with_extra(
+                &build_chat_body(model, messages, config).with_stream(false),
+                config.extra.as_ref(),
+            );
+        }
+        RequestBody::json_with_extra(&self.build_body(messages, config), config.extra.as_ref())
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        if self.openai_compat {
+            return Some(build_chat_messages(messages));
+        }
+        // System messages and image detail shape the system instruction and generation config
+        if messages.iter().any(|m| m.role == Role::System) || media_resolution(messages).is_some() {
+            return None;
+        }
+        Some(RequestBody::json(&self.convert_messages(messages)).map(|body| ("contents", body)))
+    }
+
+    fn parameter_limits(&self) -> ParameterLimits {
+        ParameterLimits {
+            max_stop_sequences: Some(5),
+            ..Default::default()
+        }
+    }
+
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        match parameter {
+            Parameter::Logprobs | Parameter::ReasoningEffort => true,
+            Parameter::Audio => self.openai_compat,
+            _ => false,
+        }
     }
 
     fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
+        if self.openai_compat {
+            return Box::new(CerebrasParser::new());
+        }
         Box::new(GeminiParser::new())
     }
 
     fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
+        if self.openai_compat {
+            return parse_chat_response(body);
+        }
+
         let resp: GeminiResponse =
             serde_json::from_str(body).map_err(|e| Error::parse(e.to_string()))?;
+        if let Some(feedback) = &resp.prompt_feedback {
+            feedback.check()?;
+        }
 
         let candidate = resp
             .candidates
             .first()
-            .ok_or_else(|| Error::parse("no candidates"))?;
+            .ok_or_else(|| Error::ContentBlocked {
+                reason: "NO_CANDIDATES".to_string(),
+                ratings: Vec::new(),
+            })?;
 
         let content = candidate
             .content
@@ -213,18 +371,40 @@ impl Provider for GeminiProvider {
 
         Ok(CompletionResult {
             content,
+            reasoning: None,
             usage,
             model: resp.model_version.unwrap_or_default(),
             finish_reason,
             tool_calls,
+            server_tool_calls: Vec::new(),
+            logprobs: candidate
+                .logprobs_result
+                .as_ref()
+                .map(LogprobsResult::to_logprobs),
+            refusal: None,
+            safety_ratings: candidate.safety_ratings.as_deref().map(to_safety_ratings),
+            citations: candidate
+                .citation_metadata
+                .as_ref()
+                .map(CitationMetadata::to_citations),
+            stop_sequence: None,
+            incomplete: false,
+            timings: None,
         })
     }
 
     fn chat_endpoint(&self) -> &'static str {
-        "" // Gemini uses model-specific endpoints
+        if self.openai_compat {
+            "/openai/chat/completions"
+        } else {
+            "" // Gemini uses model-specific endpoints
+        }
     }
 
     fn stream_url(&self, model: &str, api_key: &str) -> String {
+        if self.openai_compat {
+            return format!("{}{}", self.base_url, self.chat_endpoint());
+        }
         let base = format!(
             "{}/models/{}:streamGenerateContent?alt=sse",
             self.base_url, model
@@ -237,6 +417,9 @@ impl Provider for GeminiProvider {
     }
 
     fn complete_url(&self, model: &str, api_key: &str) -> String {
+        if self.openai_compat {
+            return format!("{}{}", self.base_url, self.chat_endpoint());
+        }
         let base = format!("{}/models/{}:generateContent", self.base_url, model);
         if self.api_key_in_query {
             format!("{}?key={}", base, api_key)
@@ -247,82 +430,56 @@ impl Provider for GeminiProvider {
 }
 
 impl GeminiProvider {
-    fn build_body(
-        &self,
-        _model: &str,
-        messages: &[Message],
-        config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        let contents = self.convert_messages(messages);
-
-        let mut body = serde_json::json!({
-            "contents": contents
-        });
-
-        // System instruction
-        if let Some(system) = self.extract_system(messages) {
-            body["system_instruction"] = system;
-        }
-        if let Some(system) = &config.system {
-            body["system_instruction"] = serde_json::json!({
-                "parts": [{"text": system}]
-            });
-        }
-
-        // Generation config
-        let mut gen_config = serde_json::json!({});
-        if let Some(max_tokens) = config.max_tokens {
-            gen_config["maxOutputTokens"] = Value::Number(max_tokens.into());
-        }
-        if let Some(temp) = config.temperature {
-            gen_config["temperature"] = serde_json::json!(temp);
-        }
-        if let Some(top_p) = config.top_p {
-            gen_config["topP"] = serde_json::json!(top_p);
-        }
-        if let Some(stop) = &config.stop {
-            gen_config["stopSequences"] = serde_json::to_value(stop).unwrap_or(Value::Null);
-        }
-        if gen_config.as_object().is_some_and(|o| !o.is_empty()) {
-            body["generationConfig"] = gen_config;
-        }
-
-        // Tools
-        if let Some(tools) = &config.tools {
-            body["tools"] = self.convert_tools(tools);
-        }
+    fn build_body<'a>(&self, messages: &'a [Message], config: &'a RequestConfig) -> GeminiBody<'a> {
+        let (response_mime_type, response_json_schema) = match &config.response_format {
+            Some(ResponseFormat::Text) => (Some("text/plain"), None),
+            Some(ResponseFormat::JsonObject) => (Some("application/json"), None),
+            Some(ResponseFormat::JsonSchema(schema)) => (Some("application/json"), Some(schema)),
+            None => (None, None),
+        };
 
-        // Tool config (tool choice)
-        if let Some(tool_choice) = &config.tool_choice {
-            body["tool_config"] = match tool_choice {
-                crate::providers::ToolChoice::Auto => serde_json::json!({
-                    "function_calling_config": {"mode": "AUTO"}
-                }),
-                crate::providers::ToolChoice::None => serde_json::json!({
-                    "function_calling_config": {"mode": "NONE"}
+        GeminiBody {
+            contents: self.convert_messages(messages),
+            system_instruction: match &config.system {
+                Some(system) => Some(SystemInstruction {
+                    parts: vec![Part::Text(system)],
                 }),
-                crate::providers::ToolChoice::Required => serde_json::json!({
-                    "function_calling_config": {"mode": "ANY"}
+                None => self.extract_system(messages),
+            },
+            generation_config: GenerationConfig {
+                max_output_tokens: config.max_tokens,
+                temperature: config.temperature,
+                top_p: config.top_p,
+                stop_sequences: config.stop.as_deref(),
+                response_mime_type,
+                response_json_schema,
+                response_logprobs: config.logprobs.map(|_| true),
+                logprobs: config.logprobs.filter(|&top| top > 0),
+                media_resolution: media_resolution(messages),
+                thinking_config: config.reasoning_effort.map(|effort| ThinkingConfig {
+                    thinking_budget: thinking_budget(effort),
                 }),
-                crate::providers::ToolChoice::Function(name) => serde_json::json!({
-                    "function_calling_config": {
-                        "mode": "ANY",
-                        "allowed_function_names": [name]
-                    }
-                }),
-            };
-        }
-
-        // Merge extra fields
-        if let Some(Value::Object(map)) = &config.extra {
-            if let Value::Object(ref mut body_map) = body {
-                for (k, v) in map {
-                    body_map.insert(k.clone(), v.clone());
+            },
+            tools: config
+                .tools
+                .as_deref()
+                .map(|tools| self.convert_tools(tools)),
+            tool_config: config.tool_choice.as_ref().map(|choice| {
+                let (mode, allowed_function_names) = match choice {
+                    ToolChoice::Auto => ("AUTO", None),
+                    ToolChoice::None => ("NONE", None),
+                    ToolChoice::Required => ("ANY", None),
+                    ToolChoice::Function(name) => ("ANY", Some(std::slice::from_ref(name))),
+                    ToolChoice::AllowedTools(names) => ("ANY", Some(names.as_slice())),
+                };
+                ToolConfig {
+                    function_calling_config: FunctionCallingConfig {
+                        mode,
+                        allowed_function_names,
+                    },
                 }
-            }
+            }),
         }
-
-        Ok(body)
     }
 }
 
@@ -348,6 +505,10 @@ impl ProviderParser for GeminiParser {
         let chunk: GeminiStreamChunk =
             serde_json::from_str(data).map_err(|e| Error::parse(e.to_string()))?;
 
+        if let Some(feedback) = &chunk.prompt_feedback {
+            feedback.check()?;
+        }
+
         // Update usage (keep last)
         if let Some(usage) = &chunk.usage_metadata {
             self.last_usage = Some(Usage {
@@ -389,6 +550,7 @@ impl ProviderParser for GeminiParser {
                     id: Some(format!("call_{}", fastrand::u32(..))),
                     function_name: Some(fc.name.clone()),
                     function_arguments: Some(serde_json::to_string(&fc.args).unwrap_or_default()),
+                    final_arguments: None,
                 })
             })
         });
@@ -413,6 +575,19 @@ impl ProviderParser for GeminiParser {
             });
         }
 
+        chunk.logprobs = candidate
+            .logprobs_result
+            .as_ref()
+            .map(LogprobsResult::to_logprobs);
+
+        chunk.safety_ratings = candidate.safety_ratings.as_deref().map(to_safety_ratings);
+        chunk.citations = candidate
+            .citation_metadata
+            .as_ref()
+            .map(CitationMetadata::to_citations);
+
+        chunk.output_index = candidate.index;
+
         // Attach usage
         chunk.usage = self.last_usage.clone();
 
@@ -427,19 +602,276 @@ impl ProviderParser for GeminiParser {
 
 // --- Serde types for Gemini API ---
 
+/// `generateContent` request body.
+#[derive(Serialize)]
+struct GeminiBody<'a> {
+    contents: Vec<Content<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<SystemInstruction<'a>>,
+    #[serde(
+        rename = "generationConfig",
+        skip_serializing_if = "GenerationConfig::is_empty"
+    )]
+    generation_config: GenerationConfig<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<[ToolDeclarations<'a>; 1]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<ToolConfig<'a>>,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    role: &'static str,
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Serialize)]
+struct SystemInstruction<'a> {
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Serialize)]
+enum Part<'a> {
+    #[serde(rename = "text")]
+    Text(&'a str),
+    #[serde(rename = "inline_data")]
+    InlineData {
+        mime_type: &'static str,
+        data: &'a str,
+    },
+    #[serde(rename = "functionCall")]
+    FunctionCall { name: &'a str, args: Value },
+    #[serde(rename = "functionResponse")]
+    FunctionResponse {
+        name: &'a str,
+        response: FunctionResult<'a>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum FunctionResult<'a> {
+    Object(Value),
+    Wrapped { content: Cow<'a, str> },
+}
+
+#[derive(Serialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_json_schema: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logprobs: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media_resolution: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking_config: Option<ThinkingConfig>,
+}
+
+#[derive(Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ThinkingConfig {
+    thinking_budget: u32,
+}
+
+/// Thinking tokens for a reasoning effort, as Gemini's OpenAI-compatible
+/// endpoint maps `reasoning_effort`.
+fn thinking_budget(effort: ReasoningEffort) -> u32 {
+    match effort {
+        ReasoningEffort::Low => 1024,
+        ReasoningEffort::Medium => 8192,
+        ReasoningEffort::High => 24_576,
+    }
+}
+
+impl GenerationConfig<'_> {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Serialize)]
+struct ToolDeclarations<'a> {
+    function_declarations: Vec<FunctionDeclaration<'a>>,
+}
+
+#[derive(Serialize)]
+struct FunctionDeclaration<'a> {
+    name: &'a str,
+    description: Option<&'a str>,
+    parameters: Option<Cow<'a, Value>>,
+}
+
+#[derive(Serialize)]
+struct ToolConfig<'a> {
+    function_calling_config: FunctionCallingConfig<'a>,
+}
+
+#[derive(Serialize)]
+struct FunctionCallingConfig<'a> {
+    mode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_function_names: Option<&'a [String]>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GeminiResponse {
+    // Absent when the prompt is blocked
+    #[serde(default)]
     candidates: Vec<GeminiCandidate>,
+    prompt_feedback: Option<PromptFeedback>,
     usage_metadata: Option<GeminiUsage>,
     model_version: Option<String>,
 }
 
+/// Verdict on the prompt; `block_reason` is set when it was rejected.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<GeminiSafetyRating>,
+}
+
+impl PromptFeedback {
+    /// Fail with [`Error::ContentBlocked`] if the prompt was blocked.
+    fn check(&self) -> Result<(), Error> {
+        match &self.block_reason {
+            Some(reason) => Err(Error::ContentBlocked {
+                reason: reason.clone(),
+                ratings: to_safety_ratings(&self.safety_ratings),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiSafetyRating {
+    category: String,
+    probability: String,
+    #[serde(default)]
+    blocked: bool,
+}
+
+fn to_safety_ratings(ratings: &[GeminiSafetyRating]) -> Vec<SafetyRating> {
+    ratings
+        .iter()
+        .map(|r| SafetyRating {
+            category: r.category.clone(),
+            probability: r.probability.clone(),
+            blocked: r.blocked,
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CitationMetadata {
+    // `citations` on Vertex AI
+    #[serde(default, alias = "citations")]
+    citation_sources: Vec<GeminiCitation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCitation {
+    start_index: Option<u32>,
+    end_index: Option<u32>,
+    uri: Option<String>,
+    title: Option<String>,
+    license: Option<String>,
+}
+
+impl CitationMetadata {
+    fn to_citations(&self) -> Vec<Citation> {
+        self.citation_sources
+            .iter()
+            .map(|c| Citation {
+                start_index: c.start_index,
+                end_index: c.end_index,
+                uri: c.uri.clone(),
+                title: c.title.clone(),
+                license: c.license.clone(),
+                file_id: None,
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GeminiCandidate {
     content: GeminiContent,
     finish_reason: Option<String>,
+    safety_ratings: Option<Vec<GeminiSafetyRating>>,
+    citation_metadata: Option<CitationMetadata>,
+    logprobs_result: Option<LogprobsResult>,
+}
+
+/// Chosen tokens and, per position, the top candidates.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogprobsResult {
+    #[serde(default)]
+    chosen_candidates: Vec<LogprobsCandidate>,
+    #[serde(default)]
+    top_candidates: Vec<TopCandidates>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogprobsCandidate {
+    #[serde(default)]
+    token: String,
+    #[serde(default)]
+    log_probability: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopCandidates {
+    #[serde(default)]
+    candidates: Vec<LogprobsCandidate>,
+}
+
+impl LogprobsResult {
+    fn to_logprobs(&self) -> Vec<TokenLogProb> {
+        self.chosen_candidates
+            .iter()
+            .enumerate()
+            .map(|(i, chosen)| TokenLogProb {
+                token: chosen.token.clone(),
+                logprob: chosen.log_probability,
+                top_alternatives: self
+                    .top_candidates
+                    .get(i)
+                    .map(|top| {
+                        top.candidates
+                            .iter()
+                            .map(|c| TopLogProb {
+                                token: c.token.clone(),
+                                logprob: c.log_probability,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -475,15 +907,21 @@ struct GeminiUsage {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GeminiStreamChunk {
+    #[serde(default)]
     candidates: Vec<GeminiStreamCandidate>,
+    prompt_feedback: Option<PromptFeedback>,
     usage_metadata: Option<GeminiUsage>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GeminiStreamCandidate {
+    index: Option<u32>,
     content: Option<GeminiContent>,
     finish_reason: Option<String>,
+    safety_ratings: Option<Vec<GeminiSafetyRating>>,
+    citation_metadata: Option<CitationMetadata>,
+    logprobs_result: Option<LogprobsResult>,
 }
 
 #[cfg(test)]
@@ -499,9 +937,9 @@ mod tests {
             Message::assistant("Hi there!"),
         ];
 
-        let contents = provider.convert_messages(&messages);
+        let contents = serde_json::to_value(provider.convert_messages(&messages)).unwrap();
         // System message is filtered out
-        assert_eq!(contents.len(), 2);
+        assert_eq!(contents.as_array().unwrap().len(), 2);
         assert_eq!(contents[0]["role"], "user");
         assert_eq!(contents[1]["role"], "model");
     }
@@ -513,6 +951,14 @@ mod tests {
 
         let system = provider.extract_system(&messages);
         assert!(system.is_some());
+
+        let messages = vec![
+            Message::system("Be helpful"),
+            Message::system("Answer in French"),
+            Message::user("Hi"),
+        ];
+        let system = serde_json::to_value(provider.extract_system(&messages)).unwrap();
+        assert_eq!(system["parts"][1]["text"], "Answer in French");
     }
 
     #[test]
@@ -539,6 +985,149 @@ mod tests {
         assert_eq!(result.finish_reason, FinishReason::Stop);
     }
 
+    #[test]
+    fn test_response_format() {
+        let provider = GeminiProvider::new();
+        let schema = serde_json::json!({"type": "object"});
+        let config = RequestConfig {
+            response_format: Some(ResponseFormat::JsonSchema(schema.clone())),
+            ..Default::default()
+        };
+        let body = provider
+            .build_complete_body("gemini-2.0-flash", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(
+            body["generationConfig"]["responseMimeType"],
+            "application/json"
+        );
+        assert_eq!(body["generationConfig"]["responseJsonSchema"], schema);
+    }
+
+    #[test]
+    fn test_extra_merges_into_generation_config() {
+        let provider = GeminiProvider::new();
+        let config = RequestConfig {
+            max_tokens: Some(100),
+            reasoning_effort: Some(ReasoningEffort::Low),
+            extra: Some(serde_json::json!({
+                "generationConfig": {"thinkingConfig": {"thinkingBudget": 512, "includeThoughts": true}},
+                "labels": {"team": "search"}
+            })),
+            ..Default::default()
+        };
+        let body = provider
+            .build_stream_body("gemini-2.5-flash", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 100);
+        // The extra budget replaces the one for the reasoning effort
+        assert_eq!(
+            body["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            512
+        );
+        assert_eq!(
+            body["generationConfig"]["thinkingConfig"]["includeThoughts"],
+            true
+        );
+        assert_eq!(body["labels"]["team"], "search");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Hi");
+    }
+
+    #[test]
+    fn test_safety_and_citations() {
+        let provider = GeminiProvider::new();
+        let json = r#"{
+            "candidates": [{
+                "content": {"parts": [{"text": "To be or not to be"}], "role": "model"},
+                "finishReason": "STOP",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE"}
+                ],
+                "citationMetadata": {"citationSources": [
+                    {"startIndex": 0, "endIndex": 18, "uri": "https://example.com/hamlet"}
+                ]}
+            }]
+        }"#;
+        let result = provider.parse_response(json).unwrap();
+        let ratings = result.safety_ratings.unwrap();
+        assert_eq!(ratings[0].category, "HARM_CATEGORY_HARASSMENT");
+        assert!(!ratings[0].blocked);
+        let citations = result.citations.unwrap();
+        assert_eq!(citations[0].end_index, Some(18));
+        assert_eq!(
+            citations[0].uri.as_deref(),
+            Some("https://example.com/hamlet")
+        );
+
+        let blocked = r#"{
+            "promptFeedback": {
+                "blockReason": "SAFETY",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH"},
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE"}
+                ]
+            }
+        }"#;
+        let err = provider.parse_response(blocked).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "content blocked: SAFETY (HARM_CATEGORY_DANGEROUS_CONTENT)"
+        );
+        match err {
+            Error::ContentBlocked { reason, ratings } => {
+                assert_eq!(reason, "SAFETY");
+                assert_eq!(ratings.len(), 2);
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+
+        let mut parser = GeminiParser::new();
+        assert!(matches!(
+            parser.parse_chunk(blocked),
+            Err(Error::ContentBlocked { .. })
+        ));
+
+        let empty = provider
+            .parse_response(r#"{"candidates": []}"#)
+            .unwrap_err();
+        assert!(matches!(empty, Error::ContentBlocked { reason, .. } if reason == "NO_CANDIDATES"));
+    }
+
+    #[test]
+    fn test_parse_logprobs() {
+        let provider = GeminiProvider::new();
+        let config = RequestConfig {
+            logprobs: Some(3),
+            ..Default::default()
+        };
+        let body = provider
+            .build_complete_body("gemini-2.0-flash", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["generationConfig"]["responseLogprobs"], true);
+        assert_eq!(body["generationConfig"]["logprobs"], 3);
+
+        let json = r#"{
+            "candidates": [{
+                "content": {"parts": [{"text": "Yes"}], "role": "model"},
+                "finishReason": "STOP",
+                "logprobsResult": {
+                    "topCandidates": [{"candidates": [
+                        {"token": "Yes", "logProbability": -0.05},
+                        {"token": "No", "logProbability": -3.1}
+                    ]}],
+                    "chosenCandidates": [{"token": "Yes", "logProbability": -0.05}]
+                }
+            }]
+        }"#;
+        let logprobs = provider.parse_response(json).unwrap().logprobs.unwrap();
+        assert_eq!(logprobs.len(), 1);
+        assert_eq!(logprobs[0].token, "Yes");
+        assert!((logprobs[0].logprob + 0.05).abs() < 1e-9);
+        assert_eq!(logprobs[0].top_alternatives[1].token, "No");
+    }
+
     #[test]
     fn test_parse_stream_chunk() {
         let mut parser = GeminiParser::new();
@@ -547,7 +1136,8 @@ mod tests {
                 "content": {
                     "parts": [{"text": "Hi"}],
                     "role": "model"
-                }
+                },
+                "index": 0
             }],
             "usageMetadata": {
                 "promptTokenCount": 5,
@@ -558,6 +1148,7 @@ mod tests {
         let chunk = parser.parse_chunk(json).unwrap().unwrap();
         assert_eq!(chunk.text().unwrap().as_ref(), "Hi");
         assert!(chunk.usage.is_some());
+        assert_eq!(chunk.output_index, Some(0));
     }
 
     #[test]
@@ -599,10 +1190,150 @@ mod tests {
             ..Default::default()
         };
 
-        let body = provider
-            .build_body("gemini-pro", &messages, &config)
-            .unwrap();
+        let body = serde_json::to_value(provider.build_body(&messages, &config)).unwrap();
         assert!(body["tools"].is_array());
         assert!(body["generationConfig"]["maxOutputTokens"].is_number());
     }
+
+    #[test]
+    fn test_media_resolution_from_image_detail() {
+        let provider = GeminiProvider::new();
+        let image = |detail: &str| ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: "data:image/jpeg;base64,AAAA".to_string(),
+                detail: Some(detail.to_string()),
+            },
+        };
+        let message = |parts| Message {
+            content: MessageContent::Parts(parts),
+            ..Message::user("")
+        };
+
+        let body = provider
+            .build_stream_body(
+                "gemini-2.0-flash",
+                &[message(vec![image("low")])],
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert_eq!(
+            body["generationConfig"]["mediaResolution"],
+            "MEDIA_RESOLUTION_LOW"
+        );
+
+        let body = provider
+            .build_stream_body(
+                "gemini-2.0-flash",
+                &[message(vec![image("low"), image("high")])],
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert_eq!(
+            body["generationConfig"]["mediaResolution"],
+            "MEDIA_RESOLUTION_HIGH"
+        );
+
+        let body = provider
+            .build_stream_body(
+                "gemini-2.0-flash",
+                &[Message::user("Hi")],
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert!(body.get("generationConfig").is_none());
+    }
+
+    #[test]
+    fn test_openai_compat_mode() {
+        let provider = GeminiProvider::new().with_openai_compat();
+
+        assert_eq!(
+            provider.stream_url("gemini-2.0-flash", "key"),
+            "https://generativelanguage.googleapis.com/v1beta/openai/chat/completions"
+        );
+        let headers = provider.headers("key");
+        assert_eq!(headers["authorization"], "Bearer key");
+        assert!(!headers.contains_key("x-goog-api-key"));
+
+        let body = provider
+            .build_stream_body(
+                "gemini-2.0-flash",
+                &[Message::system("Be brief"), Message::user("Hi")],
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert_eq!(body["model"], "gemini-2.0-flash");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert!(body.get("contents").is_none());
+
+        let mut parser = provider.create_parser();
+        let chunk = parser
+            .parse_chunk(r#"{"choices":[{"index":0,"delta":{"content":"Hello"}}]}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk.text().as_deref(), Some("Hello"));
+    }
+
+    #[test]
+    fn test_replay_assistant_tool_calls() {
+        let provider = GeminiProvider::new();
+        let messages = vec![
+            Message::user("Weather in Paris?"),
+            Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall {
+                    id: "call_0".to_string(),
+                    tool_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "get_weather".to_string(),
+                        arguments: r#"{"city":"Paris"}"#.to_string(),
+                    },
+                }],
+            ),
+        ];
+
+        let contents = serde_json::to_value(provider.convert_messages(&messages)).unwrap();
+        let parts = contents[1]["parts"].as_array().unwrap();
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0]["functionCall"]["name"], "get_weather");
+        assert_eq!(parts[0]["functionCall"]["args"]["city"], "Paris");
+    }
+
+    #[test]
+    fn test_function_response() {
+        let provider = GeminiProvider::new();
+        let call = |id: &str, name: &str| ToolCall {
+            id: id.to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: "{}".to_string(),
+            },
+        };
+        let messages = vec![
+            Message::user("Weather and time in Paris?"),
+            Message::assistant_with_tool_calls(
+                "",
+                vec![call("call_0", "get_weather"), call("call_1", "get_time")],
+            ),
+            Message::tool_result("call_0", r#"{"temp":18}"#),
+            Message::tool_result("call_1", "12:00"),
+        ];
+
+        let contents = serde_json::to_value(provider.convert_messages(&messages)).unwrap();
+        assert_eq!(contents.as_array().unwrap().len(), 3);
+        assert_eq!(contents[2]["role"], "user");
+
+        let parts = contents[2]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0]["functionResponse"]["name"], "get_weather");
+        assert_eq!(parts[0]["functionResponse"]["response"]["temp"], 18);
+        assert_eq!(parts[1]["functionResponse"]["name"], "get_time");
+        assert_eq!(parts[1]["functionResponse"]["response"]["content"], "12:00");
+    }
 }
@@ -0,0 +1,132 @@
+//! Hugging Face Inference Providers implementation.
+//!
+//! The HF router exposes an OpenAI-compatible Chat Completions API that fans out
+//! to partner inference providers.
+//! - Auth via `Bearer hf_...` user access token
+//! - Model IDs are Hub repo IDs and contain a slash (`meta-llama/Llama-3.3-70B-Instruct`),
+//!   optionally suffixed with `:provider` to pin a backend (`...:together`)
+//! - Streaming uses SSE with `[DONE]` marker
+
+use crate::error::Error;
+use crate::providers::cerebras::{
+    build_chat_body, build_chat_messages, parse_chat_response, CerebrasParser,
+};
+use crate::providers::{Provider, RequestBody, RequestConfig};
+use crate::stream::ProviderParser;
+use crate::types::*;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+/// Hugging Face Inference router provider.
+pub struct HuggingFaceProvider {
+    base_url: String,
+}
+
+impl HuggingFaceProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "https://router.huggingface.co/v1".to_string(),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+}
+
+impl Default for HuggingFaceProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for HuggingFaceProvider {
+    fn name(&self) -> &'static str {
+        "huggingface"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self, api_key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+            headers.insert(AUTHORIZATION, auth);
+        }
+        headers
+    }
+
+    fn build_stream_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &build_chat_body(model, messages, config).with_stream(true),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_complete_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &build_chat_body(model, messages, config).with_stream(false),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        Some(build_chat_messages(messages))
+    }
+
+    fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
+        // Same chunk format as other OpenAI-compatible servers
+        Box::new(CerebrasParser::new())
+    }
+
+    fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
+        parse_chat_response(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_model_id() {
+        let id = ModelId::parse("huggingface/meta-llama/Llama-3.3-70B-Instruct").unwrap();
+        assert_eq!(id.provider, "huggingface");
+        assert_eq!(id.model, "meta-llama/Llama-3.3-70B-Instruct");
+
+        let provider = HuggingFaceProvider::new();
+        let body = provider
+            .build_stream_body(&id.model, &[Message::user("Hi")], &RequestConfig::default())
+            .unwrap()
+            .to_value();
+        assert_eq!(body["model"], "meta-llama/Llama-3.3-70B-Instruct");
+        assert_eq!(
+            provider.stream_url(&id.model, "hf_test"),
+            "https://router.huggingface.co/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn test_headers() {
+        let provider = HuggingFaceProvider::new();
+        let headers = provider.headers("hf_test");
+        let auth = headers.get("authorization").unwrap().to_str().unwrap();
+        assert_eq!(auth, "Bearer hf_test");
+    }
+}
@@ -0,0 +1,167 @@
+//! Jina AI embedding provider implementation.
+//!
+//! Jina exposes an OpenAI-style `/v1/embeddings` endpoint with extensions:
+//! - `task` selects a task-specific LoRA adapter ("retrieval.query" / "retrieval.passage")
+//! - `dimensions` for Matryoshka truncation (jina-embeddings-v3)
+//! - `truncate` to cut over-long inputs instead of failing
+
+use crate::error::Error;
+use crate::providers::{EmbeddingConfig, EmbeddingInputType, EmbeddingProvider, RequestBody};
+use crate::types::*;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+
+/// Jina AI embedding provider.
+pub struct JinaProvider {
+    base_url: String,
+}
+
+impl JinaProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "https://api.jina.ai/v1".to_string(),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+}
+
+impl Default for JinaProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmbeddingProvider for JinaProvider {
+    fn name(&self) -> &'static str {
+        "jina"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self, api_key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+            headers.insert(AUTHORIZATION, auth);
+        }
+        headers
+    }
+
+    fn build_embed_body(
+        &self,
+        model: &str,
+        inputs: &[String],
+        config: &EmbeddingConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &EmbedBody {
+                model,
+                input: inputs,
+                task: config.input_type.map(|input_type| match input_type {
+                    EmbeddingInputType::Query => "retrieval.query",
+                    EmbeddingInputType::Document => "retrieval.passage",
+                }),
+                dimensions: config.dimensions,
+                truncate: config.truncate,
+            },
+            config.extra.as_ref(),
+        )
+    }
+
+    fn parse_embed_response(&self, body: &str) -> Result<EmbeddingResult, Error> {
+        let mut resp: JinaResponse =
+            serde_json::from_str(body).map_err(|e| Error::parse(e.to_string()))?;
+
+        resp.data.sort_by_key(|d| d.index);
+
+        Ok(EmbeddingResult {
+            embeddings: resp.data.into_iter().map(|d| d.embedding).collect(),
+            usage: Usage {
+                input_tokens: resp.usage.prompt_tokens.unwrap_or(resp.usage.total_tokens),
+                ..Default::default()
+            },
+            model: resp.model,
+        })
+    }
+}
+
+// --- Serde types for Jina API ---
+
+/// Embedding request body.
+#[derive(Serialize)]
+struct EmbedBody<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    truncate: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JinaResponse {
+    data: Vec<JinaEmbedding>,
+    model: String,
+    usage: JinaUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct JinaEmbedding {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct JinaUsage {
+    total_tokens: u32,
+    prompt_tokens: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_embed_body() {
+        let provider = JinaProvider::new();
+        let config = EmbeddingConfig {
+            dimensions: Some(256),
+            truncate: Some(true),
+            input_type: Some(EmbeddingInputType::Query),
+            ..Default::default()
+        };
+
+        let body = provider
+            .build_embed_body("jina-embeddings-v3", &["hello".to_string()], &config)
+            .unwrap()
+            .to_value();
+
+        assert_eq!(body["task"], "retrieval.query");
+        assert_eq!(body["dimensions"], 256);
+        assert_eq!(body["truncate"], true);
+    }
+
+    #[test]
+    fn test_parse_embed_response() {
+        let provider = JinaProvider::new();
+        let json = r#"{
+            "model": "jina-embeddings-v3",
+            "object": "list",
+            "usage": {"total_tokens": 7, "prompt_tokens": 7},
+            "data": [{"object": "embedding", "index": 0, "embedding": [0.5, -0.5]}]
+        }"#;
+
+        let result = provider.parse_embed_response(json).unwrap();
+        assert_eq!(result.embeddings.len(), 1);
+        assert_eq!(result.usage.input_tokens, 7);
+    }
+}
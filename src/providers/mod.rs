@@ -1,15 +1,29 @@
 //! Provider implementations for different LLM APIs.
 
+pub mod ai21;
 pub mod cerebras;
 pub mod claude;
+pub mod deepseek;
+mod extra;
 pub mod gemini;
+pub mod huggingface;
+pub mod jina;
 pub mod openai;
+pub mod openai_compatible;
+pub mod vllm;
+pub mod voyage;
 
 use crate::error::Error;
+use crate::schema::{self, SchemaDialect};
 use crate::stream::ProviderParser;
-use crate::types::{Message, Tool};
-use reqwest::header::HeaderMap;
+use crate::types::{ContentPart, Document, EmbeddingResult, Message, MessageContent, Role, Tool};
+use crate::warning::Warning;
+use bytes::Bytes;
+use http::header::HeaderMap;
+use serde::Serialize;
 use serde_json::Value;
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
 
 /// Provider configuration and behavior.
 pub trait Provider: Send + Sync {
@@ -22,13 +36,18 @@ pub trait Provider: Send + Sync {
     /// Build request headers including auth.
     fn headers(&self, api_key: &str) -> HeaderMap;
 
+    /// Headers for a chat request, for options sent as headers (e.g. beta flags).
+    fn request_headers(&self, api_key: &str, _config: &RequestConfig) -> HeaderMap {
+        self.headers(api_key)
+    }
+
     /// Build request body for streaming completion.
     fn build_stream_body(
         &self,
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error>;
+    ) -> Result<RequestBody, Error>;
 
     /// Build request body for non-streaming completion.
     fn build_complete_body(
@@ -36,7 +55,57 @@ pub trait Provider: Send + Sync {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error>;
+    ) -> Result<RequestBody, Error>;
+
+    /// Serialize `messages` on their own, with the top-level body field they go in,
+    /// for splicing into a [`PreparedRequest`](crate::PreparedRequest)'s body.
+    ///
+    /// Returns `None` when other fields depend on these messages (e.g. system
+    /// messages moved into a separate field); the whole body is built instead.
+    fn build_messages(
+        &self,
+        _messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        None
+    }
+
+    /// Whether requests must carry an API key (self-hosted servers often run without one).
+    fn requires_api_key(&self) -> bool {
+        true
+    }
+
+    /// Whether a trailing [`Message::assistant_prefill`] is continued as is; if
+    /// not, the client adds an instruction to continue it (see [`continue_prefill`]).
+    fn supports_prefill(&self) -> bool {
+        false
+    }
+
+    /// Whether responses can carry token log probabilities.
+    fn supports_logprobs(&self) -> bool {
+        true
+    }
+
+    /// Whether request bodies carry `parameter`; unsupported ones are left out
+    /// with a [`Warning::ParameterDropped`].
+    ///
+    /// Defaults to what OpenAI-compatible Chat Completions bodies carry.
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        match parameter {
+            Parameter::Audio | Parameter::ReasoningEffort => true,
+            Parameter::Logprobs => self.supports_logprobs(),
+            _ => false,
+        }
+    }
+
+    /// Accepted ranges for sampling parameters, checked before sending.
+    fn parameter_limits(&self) -> ParameterLimits {
+        ParameterLimits::default()
+    }
+
+    /// Wire framing of streaming responses.
+    fn stream_framing(&self) -> StreamFraming {
+        StreamFraming::Sse
+    }
 
     /// Create a parser for streaming responses.
     fn create_parser(&self) -> Box<dyn ProviderParser + Send>;
@@ -60,6 +129,318 @@ pub trait Provider: Send + Sync {
     }
 }
 
+/// A request body, serialized once from the provider's typed body struct.
+#[derive(Debug, Clone)]
+pub struct RequestBody(Bytes);
+
+impl RequestBody {
+    /// Serialize `body` as JSON.
+    pub fn json<T: Serialize + ?Sized>(body: &T) -> Result<Self, Error> {
+        serde_json::to_vec(body)
+            .map(|json| Self(json.into()))
+            .map_err(|e| Error::Config(format!("request body: {e}")))
+    }
+
+    /// Serialize `body` as JSON with the fields of `extra` (a JSON object) merged
+    /// into it; see [`merge_json`]. Fields not named in `extra` keep their
+    /// order and are never buffered as a `Value`.
+    pub fn json_with_extra<T: Serialize + ?Sized>(
+        body: &T,
+        extra: Option<&Value>,
+    ) -> Result<Self, Error> {
+        let Some(extra) = extra.and_then(Value::as_object) else {
+            return Self::json(body);
+        };
+        Self::json(&extra::WithExtra { body, extra })
+    }
+
+    /// The serialized JSON.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+
+    /// Parse the body back into a JSON value, e.g. to inspect it in tests.
+    pub fn to_value(&self) -> Value {
+        serde_json::from_slice(&self.0).unwrap_or(Value::Null)
+    }
+}
+
+/// A serialized body with a gap for its messages.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub(crate) struct BodyTemplate {
+    field: &'static str,
+    prefix: Bytes,
+    suffix: Bytes,
+}
+
+#[cfg(feature = "client")]
+impl BodyTemplate {
+    /// Split a body built without messages around its empty `field` array.
+    ///
+    /// Returns `None` if the array can't be located unambiguously, e.g. because
+    /// an extra field holds an empty array under the same key.
+    pub(crate) fn new(body: RequestBody, field: &'static str) -> Option<Self> {
+        let needle = format!("\"{field}\":[]");
+        let body = body.into_bytes();
+        let mut found = body
+            .windows(needle.len())
+            .enumerate()
+            .filter(|(_, window)| *window == needle.as_bytes())
+            .map(|(at, _)| at);
+        let at = found.next()?;
+        if found.next().is_some() {
+            return None;
+        }
+
+        let gap = at + needle.len() - 2;
+        Some(Self {
+            field,
+            prefix: body.slice(..gap),
+            suffix: body.slice(gap + 2..),
+        })
+    }
+
+    /// The body with `messages`, serialized for `field`, in the gap.
+    ///
+    /// Returns `None` if the messages belong in a different field.
+    pub(crate) fn fill(&self, field: &str, messages: &RequestBody) -> Option<RequestBody> {
+        if field != self.field {
+            return None;
+        }
+        let mut body = bytes::BytesMut::with_capacity(
+            self.prefix.len() + messages.0.len() + self.suffix.len(),
+        );
+        body.extend_from_slice(&self.prefix);
+        body.extend_from_slice(&messages.0);
+        body.extend_from_slice(&self.suffix);
+        Some(RequestBody(body.freeze()))
+    }
+}
+
+/// Deep-merge `patch` into `target`: objects merge key by key, any other value
+/// (arrays included) replaces the one it lands on.
+pub fn merge_json(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+/// Accepted ranges for request parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterLimits {
+    /// Allowed `temperature` values.
+    pub temperature: RangeInclusive<f32>,
+    /// Allowed `top_p` values.
+    pub top_p: RangeInclusive<f32>,
+    /// Maximum number of stop sequences, if limited.
+    pub max_stop_sequences: Option<usize>,
+}
+
+impl Default for ParameterLimits {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0..=2.0,
+            top_p: 0.0..=1.0,
+            max_stop_sequences: None,
+        }
+    }
+}
+
+impl ParameterLimits {
+    /// Check a request configuration against these limits.
+    ///
+    /// `max_output_tokens` is the model's output limit, if known.
+    pub fn validate(
+        &self,
+        provider: &str,
+        config: &RequestConfig,
+        max_output_tokens: Option<u32>,
+    ) -> Result<(), Error> {
+        if let Some(temperature) = config.temperature {
+            if !self.temperature.contains(&temperature) {
+                return Err(Error::Config(format!(
+                    "temperature {temperature} is out of range for {provider} ({}..={})",
+                    self.temperature.start(),
+                    self.temperature.end()
+                )));
+            }
+        }
+        if let Some(top_p) = config.top_p {
+            if !self.top_p.contains(&top_p) {
+                return Err(Error::Config(format!(
+                    "top_p {top_p} is out of range for {provider} ({}..={})",
+                    self.top_p.start(),
+                    self.top_p.end()
+                )));
+            }
+        }
+        if let Some(max_tokens) = config.max_tokens {
+            if max_tokens == 0 {
+                return Err(Error::Config("max_tokens must be at least 1".into()));
+            }
+            if let Some(limit) = max_output_tokens.filter(|&limit| max_tokens > limit) {
+                return Err(Error::Config(format!(
+                    "max_tokens {max_tokens} exceeds the model's output limit of {limit}"
+                )));
+            }
+        }
+        if let (Some(stop), Some(limit)) = (&config.stop, self.max_stop_sequences) {
+            if stop.len() > limit {
+                return Err(Error::Config(format!(
+                    "{provider} accepts at most {limit} stop sequences, got {}",
+                    stop.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a streaming response body is framed on the wire.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamFraming {
+    /// Server-sent events.
+    #[default]
+    Sse,
+    /// AWS event stream (`application/vnd.amazon.eventstream`), as used by Bedrock.
+    AwsEventStream,
+}
+
+/// Embedding provider configuration and behavior.
+pub trait EmbeddingProvider: Send + Sync {
+    /// Provider name (e.g., "voyage", "jina").
+    fn name(&self) -> &str;
+
+    /// Base URL for API requests.
+    fn base_url(&self) -> &str;
+
+    /// Build request headers including auth.
+    fn headers(&self, api_key: &str) -> HeaderMap;
+
+    /// Build request body for an embedding request.
+    fn build_embed_body(
+        &self,
+        model: &str,
+        inputs: &[String],
+        config: &EmbeddingConfig,
+    ) -> Result<RequestBody, Error>;
+
+    /// Parse an embedding response.
+    fn parse_embed_response(&self, body: &str) -> Result<EmbeddingResult, Error>;
+
+    /// API endpoint path for embeddings.
+    fn embed_endpoint(&self) -> &'static str {
+        "/embeddings"
+    }
+
+    /// Build full URL for an embedding request.
+    fn embed_url(&self, _model: &str) -> String {
+        format!("{}{}", self.base_url(), self.embed_endpoint())
+    }
+}
+
+/// Embedding request configuration shared across providers.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddingConfig {
+    /// Output vector size, for models supporting flexible dimensions.
+    pub dimensions: Option<u32>,
+    /// Truncate over-long inputs instead of failing.
+    pub truncate: Option<bool>,
+    /// What the embeddings will be used for.
+    pub input_type: Option<EmbeddingInputType>,
+    /// Extra provider-specific fields, deep-merged into the body the SDK builds.
+    pub extra: Option<Value>,
+}
+
+/// Retrieval role of embedded inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingInputType {
+    /// Search queries.
+    Query,
+    /// Documents being indexed.
+    Document,
+}
+
+/// Stream event types a parser skipped as unknown, each reported once.
+#[derive(Debug, Default)]
+pub(crate) struct SkippedEvents {
+    seen: Vec<String>,
+    pending: Option<Warning>,
+}
+
+impl SkippedEvents {
+    /// Note the skipped event in `data`, a JSON object with a `type` field.
+    pub(crate) fn skip(&mut self, provider: &str, data: &str) {
+        #[derive(serde::Deserialize)]
+        struct Typed {
+            #[serde(rename = "type")]
+            kind: String,
+        }
+        let event = serde_json::from_str::<Typed>(data).map_or_else(|_| String::new(), |t| t.kind);
+        if !self.seen.contains(&event) {
+            self.seen.push(event.clone());
+            self.pending = Some(Warning::UnknownEvent {
+                provider: provider.to_string(),
+                event,
+            });
+        }
+    }
+
+    pub(crate) fn take_warning(&mut self) -> Option<Warning> {
+        self.pending.take()
+    }
+}
+
+/// A [`RequestConfig`] parameter only some providers send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    GuidedDecoding,
+    BestOf,
+    Documents,
+    FineGrainedToolStreaming,
+    Audio,
+    Logprobs,
+    ReasoningEffort,
+    ServerTools,
+}
+
+impl Parameter {
+    /// Field name in [`RequestConfig`].
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::GuidedDecoding => "guided_decoding",
+            Self::BestOf => "best_of",
+            Self::Documents => "documents",
+            Self::FineGrainedToolStreaming => "fine_grained_tool_streaming",
+            Self::Audio => "audio",
+            Self::Logprobs => "logprobs",
+            Self::ReasoningEffort => "reasoning_effort",
+            Self::ServerTools => "server_tools",
+        }
+    }
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Request configuration shared across providers.
 #[derive(Debug, Clone, Default)]
 pub struct RequestConfig {
@@ -69,11 +450,209 @@ pub struct RequestConfig {
     pub stop: Option<Vec<String>>,
     pub tools: Option<Vec<Tool>>,
     pub tool_choice: Option<ToolChoice>,
+    /// Allow the model to call several tools in one turn. `None` keeps the provider default.
+    pub parallel_tool_calls: Option<bool>,
     pub system: Option<String>,
-    /// Extra provider-specific fields.
+    /// Output format (JSON mode, JSON schema).
+    pub response_format: Option<ResponseFormat>,
+    /// Constrained decoding (vLLM guided decoding).
+    pub guided_decoding: Option<GuidedDecoding>,
+    /// Number of candidate sequences to generate server-side, returning the best (vLLM).
+    pub best_of: Option<u32>,
+    /// Grounding documents for RAG-style answers (AI21 Jamba).
+    pub documents: Option<Vec<Document>>,
+    /// Stream tool arguments without server-side buffering (Claude
+    /// `fine-grained-tool-streaming` beta).
+    pub fine_grained_tool_streaming: bool,
+    /// Spoken output alongside text (OpenAI audio models).
+    pub audio: Option<AudioOutput>,
+    /// Return log probabilities of generated tokens, with this many top
+    /// alternatives per position (0 for the chosen tokens only). Claude ignores it.
+    pub logprobs: Option<u8>,
+    /// How long reasoning models think before answering (Chat Completions
+    /// `reasoning_effort`, OpenAI `reasoning.effort`, a Gemini thinking budget).
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Tools the provider runs itself, offered alongside [`tools`](Self::tools)
+    /// (Claude web search).
+    pub server_tools: Option<Vec<ServerTool>>,
+    /// Extra provider-specific fields (a JSON object), deep-merged into the body the
+    /// SDK builds: nested objects merge key by key, other values replace the SDK's.
     pub extra: Option<Value>,
 }
 
+impl RequestConfig {
+    /// Provider-specific parameters that are set.
+    pub fn provider_parameters(&self) -> impl Iterator<Item = Parameter> {
+        [
+            (Parameter::GuidedDecoding, self.guided_decoding.is_some()),
+            (Parameter::BestOf, self.best_of.is_some()),
+            (Parameter::Documents, self.documents.is_some()),
+            (
+                Parameter::FineGrainedToolStreaming,
+                self.fine_grained_tool_streaming,
+            ),
+            (Parameter::Audio, self.audio.is_some()),
+            (Parameter::Logprobs, self.logprobs.is_some()),
+            (Parameter::ReasoningEffort, self.reasoning_effort.is_some()),
+            (Parameter::ServerTools, self.server_tools.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(parameter, set)| set.then_some(parameter))
+    }
+
+    /// Tools to send, narrowed by [`ToolChoice::AllowedTools`] when set.
+    pub(crate) fn offered_tools(&self) -> impl Iterator<Item = &Tool> {
+        let allowed = match &self.tool_choice {
+            Some(ToolChoice::AllowedTools(names)) => Some(names),
+            _ => None,
+        };
+        self.tools.iter().flatten().filter(move |t| match allowed {
+            Some(names) => names.contains(&t.function.name),
+            None => true,
+        })
+    }
+}
+
+/// Spoken output settings, sent as `modalities: ["text", "audio"]` plus `audio`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioOutput {
+    /// Voice name, e.g. `"alloy"`.
+    pub voice: String,
+    pub format: AudioFormat,
+}
+
+/// Encoding of generated audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Mp3,
+    Flac,
+    Opus,
+    /// Raw 16-bit little-endian PCM at 24 kHz, the only format supported when streaming.
+    #[default]
+    Pcm16,
+}
+
+impl AudioFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Pcm16 => "pcm16",
+        }
+    }
+}
+
+/// A tool the provider runs itself during generation; its calls and results
+/// come back as [`ServerToolCall`](crate::types::ServerToolCall)s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerTool {
+    /// Search the web and cite the pages found (Claude `web_search`, OpenAI
+    /// `web_search`).
+    WebSearch(WebSearch),
+    /// Search uploaded files in vector stores (OpenAI `file_search`).
+    FileSearch(FileSearch),
+}
+
+/// Web search settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebSearch {
+    /// Most searches per request.
+    pub max_uses: Option<u32>,
+    /// Only search these domains.
+    pub allowed_domains: Vec<String>,
+    /// Never search these domains.
+    pub blocked_domains: Vec<String>,
+}
+
+/// File search settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSearch {
+    /// Vector stores to search.
+    pub vector_store_ids: Vec<String>,
+    /// Most passages to retrieve.
+    pub max_num_results: Option<u32>,
+    /// Return the retrieved passages in [`ServerToolCall::files`](crate::types::ServerToolCall::files).
+    pub include_results: bool,
+}
+
+impl FileSearch {
+    /// Search the given vector stores.
+    pub fn new<S: Into<String>>(vector_store_ids: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            vector_store_ids: vector_store_ids.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// How much a reasoning model thinks before answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+/// Format the model must answer in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseFormat {
+    /// Free-form text (the default).
+    Text,
+    /// Any valid JSON object.
+    JsonObject,
+    /// JSON matching a schema.
+    JsonSchema(Value),
+}
+
+/// Serializes as the Chat Completions `response_format` value.
+impl Serialize for ResponseFormat {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum Format<'a> {
+            Text,
+            JsonObject,
+            JsonSchema { json_schema: JsonSchema<'a> },
+        }
+        #[derive(Serialize)]
+        struct JsonSchema<'a> {
+            name: &'static str,
+            schema: Cow<'a, Value>,
+            strict: bool,
+        }
+
+        match self {
+            ResponseFormat::Text => Format::Text,
+            ResponseFormat::JsonObject => Format::JsonObject,
+            ResponseFormat::JsonSchema(schema) => Format::JsonSchema {
+                json_schema: JsonSchema {
+                    name: "response",
+                    schema: schema::normalized(schema, SchemaDialect::OpenAIStrict),
+                    strict: true,
+                },
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Constraint applied to generated output by servers supporting guided decoding.
+#[derive(Debug, Clone)]
+pub enum GuidedDecoding {
+    /// Output must validate against a JSON schema.
+    Json(Value),
+    /// Output must match a regular expression.
+    Regex(String),
+    /// Output must be exactly one of the given strings.
+    Choice(Vec<String>),
+    /// Output must match an EBNF grammar.
+    Grammar(String),
+}
+
 /// Tool choice configuration.
 #[derive(Debug, Clone)]
 pub enum ToolChoice {
@@ -81,22 +660,104 @@ pub enum ToolChoice {
     None,
     Required,
     Function(String),
+    /// Require a call to one of the named tools; other tools are hidden from the model.
+    AllowedTools(Vec<String>),
 }
 
 impl ToolChoice {
+    /// The Chat Completions `tool_choice` value.
     pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// Serializes as the Chat Completions `tool_choice` value.
+impl Serialize for ToolChoice {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum Choice<'a> {
+            Function { function: Name<'a> },
+            AllowedTools { allowed_tools: Allowed<'a> },
+        }
+        #[derive(Serialize)]
+        struct Name<'a> {
+            name: &'a str,
+        }
+        #[derive(Serialize)]
+        struct Allowed<'a> {
+            mode: &'static str,
+            tools: Vec<Choice<'a>>,
+        }
+
         match self {
-            ToolChoice::Auto => Value::String("auto".to_string()),
-            ToolChoice::None => Value::String("none".to_string()),
-            ToolChoice::Required => Value::String("required".to_string()),
-            ToolChoice::Function(name) => serde_json::json!({
-                "type": "function",
-                "function": {"name": name}
-            }),
+            ToolChoice::Auto => serializer.serialize_str("auto"),
+            ToolChoice::None => serializer.serialize_str("none"),
+            ToolChoice::Required => serializer.serialize_str("required"),
+            ToolChoice::Function(name) => Choice::Function {
+                function: Name { name },
+            }
+            .serialize(serializer),
+            ToolChoice::AllowedTools(names) => Choice::AllowedTools {
+                allowed_tools: Allowed {
+                    mode: "required",
+                    tools: names
+                        .iter()
+                        .map(|name| Choice::Function {
+                            function: Name { name },
+                        })
+                        .collect(),
+                },
+            }
+            .serialize(serializer),
         }
     }
 }
 
+/// The text of a trailing [`Message::assistant_prefill`], which the model
+/// should continue.
+pub(crate) fn prefill(messages: &[Message]) -> Option<&str> {
+    let last = messages.last()?;
+    if !last.prefill || last.role != Role::Assistant || last.tool_calls.is_some() {
+        return None;
+    }
+    last.content.as_text()
+}
+
+/// `messages` with an instruction to continue a trailing
+/// [`Message::assistant_prefill`], for providers that can't continue it
+/// themselves. Other messages are left as they are.
+pub fn continue_prefill(messages: &[Message]) -> Cow<'_, [Message]> {
+    if prefill(messages).is_none() {
+        return Cow::Borrowed(messages);
+    }
+    let mut messages = messages.to_vec();
+    messages.push(Message::user(
+        "Continue your last message exactly where it stops, without repeating any of it.",
+    ));
+    Cow::Owned(messages)
+}
+
+/// Text segments of all system messages, in order: the whole text of plain
+/// messages and each text part of multi-part ones.
+pub(crate) fn system_texts(messages: &[Message]) -> Vec<&str> {
+    messages
+        .iter()
+        .filter(|m| m.role == Role::System)
+        .flat_map(|m| match &m.content {
+            MessageContent::Text(text) => vec![text.as_str()],
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect(),
+        })
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
 /// Get provider by name.
 pub fn get_provider(name: &str) -> Option<Box<dyn Provider>> {
     get_provider_with_base_url(name, None)
@@ -105,6 +766,10 @@ pub fn get_provider(name: &str) -> Option<Box<dyn Provider>> {
 /// Get provider by name with optional custom base URL.
 pub fn get_provider_with_base_url(name: &str, base_url: Option<&str>) -> Option<Box<dyn Provider>> {
     match name {
+        "ai21" => Some(Box::new(match base_url {
+            Some(url) => ai21::Ai21Provider::with_base_url(url),
+            None => ai21::Ai21Provider::new(),
+        })),
         "cerebras" => Some(Box::new(match base_url {
             Some(url) => cerebras::CerebrasProvider::with_base_url(url),
             None => cerebras::CerebrasProvider::new(),
@@ -113,14 +778,48 @@ pub fn get_provider_with_base_url(name: &str, base_url: Option<&str>) -> Option<
             Some(url) => claude::ClaudeProvider::with_base_url(url),
             None => claude::ClaudeProvider::new(),
         })),
+        "deepseek" => Some(Box::new(match base_url {
+            Some(url) => deepseek::DeepSeekProvider::with_base_url(url),
+            None => deepseek::DeepSeekProvider::new(),
+        })),
         "gemini" => Some(Box::new(match base_url {
             Some(url) => gemini::GeminiProvider::new().with_base_url(url),
             None => gemini::GeminiProvider::new(),
         })),
+        "huggingface" => Some(Box::new(match base_url {
+            Some(url) => huggingface::HuggingFaceProvider::with_base_url(url),
+            None => huggingface::HuggingFaceProvider::new(),
+        })),
         "openai" => Some(Box::new(match base_url {
             Some(url) => openai::OpenAIProvider::with_base_url(url),
             None => openai::OpenAIProvider::new(),
         })),
+        // No default endpoint to fall back on
+        "openai-compatible" => base_url.map(|url| {
+            Box::new(openai_compatible::OpenAICompatibleProvider::new(url)) as Box<dyn Provider>
+        }),
+        "vllm" => Some(Box::new(match base_url {
+            Some(url) => vllm::VllmProvider::with_base_url(url),
+            None => vllm::VllmProvider::new(),
+        })),
+        _ => None,
+    }
+}
+
+/// Get embedding provider by name with optional custom base URL.
+pub fn get_embedding_provider_with_base_url(
+    name: &str,
+    base_url: Option<&str>,
+) -> Option<Box<dyn EmbeddingProvider>> {
+    match name {
+        "jina" => Some(Box::new(match base_url {
+            Some(url) => jina::JinaProvider::with_base_url(url),
+            None => jina::JinaProvider::new(),
+        })),
+        "voyage" => Some(Box::new(match base_url {
+            Some(url) => voyage::VoyageProvider::with_base_url(url),
+            None => voyage::VoyageProvider::new(),
+        })),
         _ => None,
     }
 }
@@ -4,32 +4,54 @@
 //! - Input as string or messages array
 //! - Streaming with named events: response.created, response.output_text.delta, etc.
 //! - Rich metadata including billing, reasoning, and service tier
+//!
+//! Audio output is only served by Chat Completions; requests asking for it go
+//! through [`OpenAIProvider::with_chat_completions`].
 
 use crate::error::Error;
-use crate::providers::{Provider, RequestConfig, ToolChoice};
+use crate::providers::cerebras::{
+    build_chat_body, build_chat_messages, parse_chat_response, CerebrasParser,
+};
+use crate::providers::{
+    system_texts, Parameter, ParameterLimits, Provider, ReasoningEffort, RequestBody,
+    RequestConfig, ResponseFormat, ServerTool, SkippedEvents, ToolChoice,
+};
+use crate::schema::{self, SchemaDialect};
 use crate::stream::ProviderParser;
 use crate::types::*;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
-use serde::Deserialize;
+use crate::warning::Warning;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 
 /// OpenAI Responses API provider.
 pub struct OpenAIProvider {
     base_url: String,
+    /// Use the Chat Completions endpoint instead of the Responses API
+    chat_completions: bool,
 }
 
 impl OpenAIProvider {
     pub fn new() -> Self {
         Self {
             base_url: "https://api.openai.com".to_string(),
+            chat_completions: false,
         }
     }
 
     pub fn with_base_url(base_url: impl Into<String>) -> Self {
         Self {
             base_url: base_url.into(),
+            chat_completions: false,
         }
     }
+
+    /// Use the Chat Completions endpoint (`/v1/chat/completions`), e.g. for audio output.
+    pub fn with_chat_completions(mut self) -> Self {
+        self.chat_completions = true;
+        self
+    }
 }
 
 impl Default for OpenAIProvider {
@@ -61,10 +83,18 @@ impl Provider for OpenAIProvider {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
+    ) -> Result<RequestBody, Error> {
+        if self.chat_completions {
+            return RequestBody::json_with_extra(
+                &build_chat_body(model, messages, config)
+                    .with_stream(true)
+                    .with_max_completion_tokens(),
+                config.extra.as_ref(),
+            );
+        }
         let mut body = self.build_base_body(model, messages, config)?;
-        body["stream"] = Value::Bool(true);
-        Ok(body)
+        body.stream = true;
+        RequestBody::json_with_extra(&body, config.extra.as_ref())
     }
 
     fn build_complete_body(
@@ -72,32 +102,97 @@ impl Provider for OpenAIProvider {
         model: &str,
         messages: &[Message],
         config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        let mut body = self.build_base_body(model, messages, config)?;
-        body["stream"] = Value::Bool(false);
-        Ok(body)
+    ) -> Result<RequestBody, Error> {
+        if self.chat_completions {
+            return RequestBody::json_with_extra(
+                &build_chat_body(model, messages, config)
+                    .with_stream(false)
+                    .with_max_completion_tokens(),
+                config.extra.as_ref(),
+            );
+        }
+        RequestBody::json_with_extra(
+            &self.build_base_body(model, messages, config)?,
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        if self.chat_completions {
+            return Some(build_chat_messages(messages));
+        }
+        // System messages become the instructions
+        if messages.iter().any(|m| m.role == Role::System) {
+            return None;
+        }
+        Some(RequestBody::json(&self.convert_messages(messages)).map(|body| ("input", body)))
+    }
+
+    fn parameter_limits(&self) -> ParameterLimits {
+        ParameterLimits {
+            max_stop_sequences: Some(4),
+            ..Default::default()
+        }
+    }
+
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        match parameter {
+            Parameter::Logprobs | Parameter::ReasoningEffort => true,
+            Parameter::Audio => self.chat_completions,
+            Parameter::ServerTools => !self.chat_completions,
+            _ => false,
+        }
     }
 
     fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
+        if self.chat_completions {
+            return Box::new(CerebrasParser::new());
+        }
         Box::new(OpenAIParser::new())
     }
 
     fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
+        if self.chat_completions {
+            return parse_chat_response(body);
+        }
+
         let resp: ResponsesResponse =
             serde_json::from_str(body).map_err(|e| Error::parse(e.to_string()))?;
 
         let mut content = String::new();
         let mut tool_calls = Vec::new();
+        let mut logprobs: Option<Vec<TokenLogProb>> = None;
+        let mut refusal: Option<String> = None;
+        let mut citations = Vec::new();
+        let mut server_tool_calls = Vec::new();
 
-        for item in &resp.output {
+        for item in resp.output {
             match item {
                 OutputItem::Message { content: parts, .. } => {
                     for part in parts {
+                        if let Some(text) = part.refusal {
+                            refusal.get_or_insert_with(String::new).push_str(&text);
+                        }
                         if part.content_type == "output_text" {
+                            let start = content.len();
                             content.push_str(&part.text);
+                            if let Some(part_logprobs) = part.logprobs {
+                                logprobs.get_or_insert_with(Vec::new).extend(part_logprobs);
+                            }
+                            citations.extend(
+                                part.annotations
+                                    .into_iter()
+                                    .filter_map(|a| a.into_citation(&part.text, start)),
+                            );
                         }
                     }
                 }
+                OutputItem::FileSearchCall(call) => server_tool_calls.push(call.into()),
+                OutputItem::WebSearchCall(call) => server_tool_calls.push(call.into()),
+                OutputItem::Other => {}
                 OutputItem::FunctionCall {
                     call_id,
                     name,
@@ -105,12 +200,9 @@ impl Provider for OpenAIProvider {
                     ..
                 } => {
                     tool_calls.push(ToolCall {
-                        id: call_id.clone(),
+                        id: call_id,
                         tool_type: "function".to_string(),
-                        function: FunctionCall {
-                            name: name.clone(),
-                            arguments: arguments.clone(),
-                        },
+                        function: FunctionCall { name, arguments },
                     });
                 }
             }
@@ -128,142 +220,382 @@ impl Provider for OpenAIProvider {
 
         Ok(CompletionResult {
             content,
+            reasoning: None,
             usage: Usage {
                 input_tokens: resp.usage.input_tokens,
                 output_tokens: resp.usage.output_tokens,
                 cache_read_input_tokens: resp.usage.input_tokens_details.cached_tokens,
+                web_search_requests: web_searches(&server_tool_calls),
                 ..Default::default()
             },
             model: resp.model,
             finish_reason,
             tool_calls,
+            server_tool_calls,
+            logprobs,
+            refusal,
+            safety_ratings: None,
+            citations: (!citations.is_empty()).then_some(citations),
+            stop_sequence: None,
+            incomplete: false,
+            timings: None,
         })
     }
 
     fn chat_endpoint(&self) -> &'static str {
-        "/v1/responses"
+        if self.chat_completions {
+            "/v1/chat/completions"
+        } else {
+            "/v1/responses"
+        }
     }
 }
 
 impl OpenAIProvider {
-    fn build_base_body(
+    fn build_base_body<'a>(
         &self,
-        model: &str,
-        messages: &[Message],
-        config: &RequestConfig,
-    ) -> Result<Value, Error> {
-        // Extract system as instructions
-        let instructions = messages
-            .iter()
-            .find(|m| m.role == Role::System)
-            .and_then(|m| m.content.as_text())
-            .or(config.system.as_deref());
-
-        // Convert messages to input format
-        let input = self.convert_messages(messages);
-
-        let mut body = serde_json::json!({
-            "model": model,
-            "input": input,
+        model: &'a str,
+        messages: &'a [Message],
+        config: &'a RequestConfig,
+    ) -> Result<ResponsesBody<'a>, Error> {
+        // The web search tool has no domain blocklist or search limit
+        let unsupported = config.server_tools.iter().flatten().any(|tool| {
+            matches!(tool, ServerTool::WebSearch(search)
+                if !search.blocked_domains.is_empty() || search.max_uses.is_some())
         });
-
-        if let Some(instructions) = instructions {
-            body["instructions"] = Value::String(instructions.to_string());
+        if unsupported {
+            return Err(Error::Config(
+                "openai web search does not support blocked_domains or max_uses".into(),
+            ));
         }
 
-        if let Some(max_tokens) = config.max_tokens {
-            body["max_output_tokens"] = Value::Number(max_tokens.into());
+        // System messages are joined into the instructions
+        let system = system_texts(messages);
+        let instructions = if system.is_empty() {
+            config.system.as_deref().map(Cow::Borrowed)
+        } else {
+            Some(Cow::Owned(system.join("\n\n")))
+        };
+
+        Ok(ResponsesBody {
+            model,
+            input: self.convert_messages(messages),
+            instructions,
+            max_output_tokens: config.max_tokens,
+            temperature: config.temperature,
+            top_p: config.top_p,
+            text: config.response_format.as_ref().map(|format| TextOptions {
+                format: match format {
+                    ResponseFormat::Text => TextFormat::Text,
+                    ResponseFormat::JsonObject => TextFormat::JsonObject,
+                    ResponseFormat::JsonSchema(schema) => TextFormat::JsonSchema {
+                        name: "response",
+                        schema: schema::normalized(schema, SchemaDialect::OpenAIStrict),
+                        strict: true,
+                    },
+                },
+            }),
+            include: Self::includes(config),
+            top_logprobs: config.logprobs,
+            reasoning: config
+                .reasoning_effort
+                .map(|effort| ReasoningOptions { effort }),
+            tools: Self::build_tools(config),
+            tool_choice: config.tool_choice.as_ref().map(|choice| match choice {
+                ToolChoice::Auto => ResponsesToolChoice::Mode("auto"),
+                ToolChoice::None => ResponsesToolChoice::Mode("none"),
+                ToolChoice::Required => ResponsesToolChoice::Mode("required"),
+                ToolChoice::Function(name) => {
+                    ResponsesToolChoice::Typed(TypedToolChoice::Function { name })
+                }
+                ToolChoice::AllowedTools(names) => {
+                    ResponsesToolChoice::Typed(TypedToolChoice::AllowedTools {
+                        mode: "required",
+                        tools: names
+                            .iter()
+                            .map(|name| TypedToolChoice::Function { name })
+                            .collect(),
+                    })
+                }
+            }),
+            parallel_tool_calls: config.parallel_tool_calls,
+            stream: false,
+        })
+    }
+
+    /// Function tools followed by built-in tools.
+    fn build_tools(config: &RequestConfig) -> Option<Vec<ResponsesTool<'_>>> {
+        if config.tools.is_none() && config.server_tools.is_none() {
+            return None;
         }
-        if let Some(temp) = config.temperature {
-            body["temperature"] = serde_json::json!(temp);
+        let functions = config.tools.iter().flatten().map(|t| {
+            ResponsesTool::Function(FunctionTool {
+                kind: "function",
+                name: &t.function.name,
+                description: t.function.description.as_deref(),
+                parameters: t.function.parameters.as_ref().map(|p| {
+                    let dialect = if t.function.strict == Some(true) {
+                        SchemaDialect::OpenAIStrict
+                    } else {
+                        SchemaDialect::OpenAI
+                    };
+                    schema::normalized(p, dialect)
+                }),
+                strict: t.function.strict,
+            })
+        });
+        let built_in = config.server_tools.iter().flatten().map(|tool| match tool {
+            ServerTool::WebSearch(search) => ResponsesTool::BuiltIn(BuiltInTool::WebSearch {
+                filters: (!search.allowed_domains.is_empty()).then_some(WebSearchFilters {
+                    allowed_domains: &search.allowed_domains,
+                }),
+            }),
+            ServerTool::FileSearch(search) => ResponsesTool::BuiltIn(BuiltInTool::FileSearch {
+                vector_store_ids: &search.vector_store_ids,
+                max_num_results: search.max_num_results,
+            }),
+        });
+        Some(functions.chain(built_in).collect())
+    }
+
+    /// Extra output to include in the response.
+    fn includes(config: &RequestConfig) -> Vec<&'static str> {
+        let mut include = Vec::new();
+        if config.logprobs.is_some() {
+            include.push("message.output_text.logprobs");
         }
-        if let Some(top_p) = config.top_p {
-            body["top_p"] = serde_json::json!(top_p);
+        let file_results =
+            config.server_tools.iter().flatten().any(
+                |tool| matches!(tool, ServerTool::FileSearch(search) if search.include_results),
+            );
+        if file_results {
+            include.push("file_search_call.results");
         }
+        include
+    }
 
-        // Tools
-        if let Some(tools) = &config.tools {
-            let openai_tools: Vec<Value> = tools
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "type": "function",
-                        "name": t.function.name,
-                        "description": t.function.description,
-                        "parameters": t.function.parameters
-                    })
-                })
-                .collect();
-            body["tools"] = Value::Array(openai_tools);
-        }
+    fn convert_messages<'a>(&self, messages: &'a [Message]) -> Vec<InputItem<'a>> {
+        let mut items = Vec::with_capacity(messages.len());
+
+        for m in messages.iter().filter(|m| m.role != Role::System) {
+            // Tool results are standalone input items keyed by call ID
+            if m.role == Role::Tool {
+                items.push(InputItem::Typed(TypedItem::FunctionCallOutput {
+                    call_id: m.tool_call_id.as_deref().unwrap_or(""),
+                    output: Self::text_of(&m.content),
+                }));
+                continue;
+            }
 
-        // Tool choice
-        if let Some(tool_choice) = &config.tool_choice {
-            body["tool_choice"] = match tool_choice {
-                ToolChoice::Auto => Value::String("auto".to_string()),
-                ToolChoice::None => Value::String("none".to_string()),
-                ToolChoice::Required => Value::String("required".to_string()),
-                ToolChoice::Function(name) => serde_json::json!({
-                    "type": "function",
-                    "name": name
-                }),
+            let role = match m.role {
+                Role::Assistant => "assistant",
+                _ => "user",
             };
-        }
 
-        // Merge extra fields
-        if let Some(Value::Object(map)) = &config.extra {
-            if let Value::Object(ref mut body_map) = body {
-                for (k, v) in map {
-                    body_map.insert(k.clone(), v.clone());
-                }
+            let content = match &m.content {
+                MessageContent::Text(text) => InputContent::Text(text),
+                MessageContent::Parts(parts) => InputContent::Parts(
+                    parts
+                        .iter()
+                        .map(|p| match p {
+                            ContentPart::Text { text } => InputPart::Text { text },
+                            ContentPart::ImageUrl { image_url } => InputPart::InputImage {
+                                image_url: &image_url.url,
+                                detail: image_url.detail.as_deref(),
+                            },
+                        })
+                        .collect(),
+                ),
+            };
+
+            let tool_calls = m.tool_calls.as_deref().unwrap_or_default();
+            let empty = matches!(content, InputContent::Text(text) if text.is_empty());
+            if !empty || tool_calls.is_empty() {
+                items.push(InputItem::Message { role, content });
             }
+
+            // Prior tool calls are replayed as `function_call` items
+            items.extend(tool_calls.iter().map(|tc| {
+                InputItem::Typed(TypedItem::FunctionCall {
+                    call_id: &tc.id,
+                    name: &tc.function.name,
+                    arguments: &tc.function.arguments,
+                })
+            }));
         }
 
-        Ok(body)
+        items
     }
 
-    fn convert_messages(&self, messages: &[Message]) -> Value {
-        let msgs: Vec<Value> = messages
-            .iter()
-            .filter(|m| m.role != Role::System)
-            .map(|m| {
-                let role = match m.role {
-                    Role::User => "user",
-                    Role::Assistant => "assistant",
-                    Role::System => "system",
-                    Role::Tool => "tool",
-                };
+    /// Flatten message content to plain text.
+    fn text_of(content: &MessageContent) -> Cow<'_, str> {
+        match content {
+            MessageContent::Text(text) => Cow::Borrowed(text),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect::<String>()
+                .into(),
+        }
+    }
+}
 
-                let content = match &m.content {
-                    MessageContent::Text(text) => Value::String(text.clone()),
-                    MessageContent::Parts(parts) => {
-                        let arr: Vec<Value> = parts
-                            .iter()
-                            .map(|p| match p {
-                                ContentPart::Text { text } => {
-                                    serde_json::json!({"type": "text", "text": text})
-                                }
-                                ContentPart::ImageUrl { image_url } => {
-                                    serde_json::json!({
-                                        "type": "image_url",
-                                        "image_url": {"url": image_url.url}
-                                    })
-                                }
-                            })
-                            .collect();
-                        Value::Array(arr)
-                    }
-                };
+/// Responses API request body.
+#[derive(Serialize)]
+struct ResponsesBody<'a> {
+    model: &'a str,
+    input: Vec<InputItem<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<TextOptions<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    include: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_logprobs: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<ReasoningOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<ResponsesTool<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ResponsesToolChoice<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallel_tool_calls: Option<bool>,
+    stream: bool,
+}
 
-                serde_json::json!({
-                    "role": role,
-                    "content": content
-                })
-            })
-            .collect();
+/// An `input` item: a message, a tool result, or a replayed tool call.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum InputItem<'a> {
+    Message {
+        role: &'static str,
+        content: InputContent<'a>,
+    },
+    Typed(TypedItem<'a>),
+}
 
-        Value::Array(msgs)
-    }
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TypedItem<'a> {
+    FunctionCallOutput {
+        call_id: &'a str,
+        output: Cow<'a, str>,
+    },
+    FunctionCall {
+        call_id: &'a str,
+        name: &'a str,
+        arguments: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum InputContent<'a> {
+    Text(&'a str),
+    Parts(Vec<InputPart<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum InputPart<'a> {
+    Text {
+        text: &'a str,
+    },
+    InputImage {
+        image_url: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<&'a str>,
+    },
+}
+
+#[derive(Serialize)]
+struct ReasoningOptions {
+    effort: ReasoningEffort,
+}
+
+#[derive(Serialize)]
+struct TextOptions<'a> {
+    format: TextFormat<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TextFormat<'a> {
+    Text,
+    JsonObject,
+    JsonSchema {
+        name: &'static str,
+        schema: Cow<'a, Value>,
+        strict: bool,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ResponsesTool<'a> {
+    Function(FunctionTool<'a>),
+    BuiltIn(BuiltInTool<'a>),
+}
+
+#[derive(Serialize)]
+struct FunctionTool<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: &'a str,
+    description: Option<&'a str>,
+    parameters: Option<Cow<'a, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strict: Option<bool>,
+}
+
+/// A tool OpenAI runs itself.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BuiltInTool<'a> {
+    WebSearch {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filters: Option<WebSearchFilters<'a>>,
+    },
+    FileSearch {
+        vector_store_ids: &'a [String],
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_num_results: Option<u32>,
+    },
+}
+
+#[derive(Serialize)]
+struct WebSearchFilters<'a> {
+    allowed_domains: &'a [String],
+}
+
+/// Responses API `tool_choice`: a mode string or a typed object.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ResponsesToolChoice<'a> {
+    Mode(&'static str),
+    Typed(TypedToolChoice<'a>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TypedToolChoice<'a> {
+    Function {
+        name: &'a str,
+    },
+    AllowedTools {
+        mode: &'static str,
+        tools: Vec<TypedToolChoice<'a>>,
+    },
 }
 
 /// Streaming response parser for OpenAI Responses API.
@@ -273,6 +605,14 @@ pub struct OpenAIParser {
     current_tool_id: Option<String>,
     current_tool_name: Option<String>,
     tool_index: usize,
+    /// Web searches finished so far, reported with the usage.
+    web_searches: u32,
+    // The output text part being written (output and content index), where
+    // it starts in the content and its text so far, to place citations
+    part: Option<(Option<u32>, Option<u32>)>,
+    part_start: usize,
+    part_text: String,
+    skipped: SkippedEvents,
 }
 
 impl OpenAIParser {
@@ -282,66 +622,148 @@ impl OpenAIParser {
             current_tool_id: None,
             current_tool_name: None,
             tool_index: 0,
+            web_searches: 0,
+            part: None,
+            part_start: 0,
+            part_text: String::new(),
+            skipped: SkippedEvents::default(),
         }
     }
-}
 
-impl Default for OpenAIParser {
-    fn default() -> Self {
-        Self::new()
+    /// The chunk for a finished output item: the final image, or a built-in tool's outcome.
+    fn item_done(&mut self, item: StreamOutputItem) -> Option<StreamChunk> {
+        match item {
+            StreamOutputItem::ImageGenerationCall {
+                id,
+                result: Some(data),
+            } => Some(StreamChunk::image(ImageDelta {
+                id,
+                data,
+                partial_index: None,
+            })),
+            item => {
+                if matches!(item, StreamOutputItem::WebSearchCall(_)) {
+                    self.web_searches += 1;
+                }
+                server_tool_chunk(item)
+            }
+        }
     }
-}
 
-impl ProviderParser for OpenAIParser {
-    fn parse_chunk(&mut self, data: &str) -> Result<Option<StreamChunk>, Error> {
-        let event: OpenAIStreamEvent =
-            serde_json::from_str(data).map_err(|e| Error::parse(e.to_string()))?;
+    /// Note output text of the given part, to place later citations.
+    fn track_text(&mut self, part: (Option<u32>, Option<u32>), delta: &str) {
+        if self.part != Some(part) {
+            self.part = Some(part);
+            self.part_start += self.part_text.len();
+            self.part_text.clear();
+        }
+        self.part_text.push_str(delta);
+    }
+
+    /// The chunk for a citation of the part being written.
+    fn citation_chunk(&self, annotation: OutputAnnotation) -> Option<StreamChunk> {
+        let citation = annotation.into_citation(&self.part_text, self.part_start)?;
+        let mut chunk = StreamChunk::empty(ChunkKind::Unknown);
+        chunk.citations = Some(vec![citation]);
+        Some(chunk)
+    }
 
+    /// The chunk for an event, before its position is attached.
+    fn chunk_for(
+        &mut self,
+        event: OpenAIStreamEvent,
+        data: &str,
+    ) -> Result<Option<StreamChunk>, Error> {
         match event {
             OpenAIStreamEvent::ResponseCreated { .. }
             | OpenAIStreamEvent::ResponseInProgress { .. } => Ok(None),
 
-            OpenAIStreamEvent::OutputItemAdded { item, .. } => {
-                if let Some(StreamOutputItem::FunctionCall { call_id, name, .. }) = item {
+            OpenAIStreamEvent::OutputItemAdded { item, .. } => match item {
+                Some(StreamOutputItem::FunctionCall { call_id, name, .. }) => {
                     self.current_tool_id = Some(call_id);
                     self.current_tool_name = Some(name);
+                    Ok(None)
                 }
-                Ok(None)
-            }
+                Some(item) => Ok(server_tool_chunk(item)),
+                None => Ok(None),
+            },
 
             OpenAIStreamEvent::ContentPartAdded { .. } => Ok(None),
 
-            OpenAIStreamEvent::OutputTextDelta { delta, .. } => {
-                Ok(Some(StreamChunk::text_owned(delta)))
+            OpenAIStreamEvent::OutputTextDelta {
+                output_index,
+                content_index,
+                delta,
+                obfuscation,
+                logprobs,
+                ..
+            } => {
+                self.track_text((output_index, content_index), &delta);
+                let mut chunk = with_obfuscation(StreamChunk::text_owned(delta), obfuscation);
+                chunk.logprobs = logprobs;
+                Ok(Some(chunk))
             }
 
-            OpenAIStreamEvent::FunctionCallArgumentsDelta { delta, .. } => {
-                let mut chunk = StreamChunk::empty(ChunkKind::ToolDelta);
+            OpenAIStreamEvent::RefusalDelta { delta, .. } => Ok(Some(StreamChunk::refusal(delta))),
+
+            OpenAIStreamEvent::AnnotationAdded { annotation, .. } => {
+                Ok(self.citation_chunk(annotation))
+            }
+
+            OpenAIStreamEvent::FunctionCallArgumentsDelta {
+                delta, obfuscation, ..
+            } => {
+                let mut chunk =
+                    with_obfuscation(StreamChunk::empty(ChunkKind::ToolDelta), obfuscation);
                 chunk.tool_call_delta = Some(ToolCallDelta {
                     index: self.tool_index,
                     id: self.current_tool_id.clone(),
                     function_name: self.current_tool_name.clone(),
                     function_arguments: Some(delta),
+                    final_arguments: None,
                 });
                 Ok(Some(chunk))
             }
 
-            OpenAIStreamEvent::FunctionCallArgumentsDone { .. } => {
+            OpenAIStreamEvent::FunctionCallArgumentsDone { arguments, .. } => {
+                let mut chunk = StreamChunk::empty(ChunkKind::ToolDelta);
+                chunk.tool_call_delta = Some(ToolCallDelta {
+                    index: self.tool_index,
+                    id: self.current_tool_id.take(),
+                    function_name: self.current_tool_name.take(),
+                    final_arguments: Some(arguments),
+                    ..Default::default()
+                });
                 self.tool_index += 1;
-                self.current_tool_id = None;
-                self.current_tool_name = None;
-                Ok(None)
+                Ok(Some(chunk))
             }
 
             OpenAIStreamEvent::OutputTextDone { .. }
+            | OpenAIStreamEvent::RefusalDone {}
             | OpenAIStreamEvent::ContentPartDone { .. }
-            | OpenAIStreamEvent::OutputItemDone { .. } => Ok(None),
+            | OpenAIStreamEvent::ImageGenerationProgress {} => Ok(None),
+
+            OpenAIStreamEvent::ImageGenerationPartialImage {
+                item_id,
+                partial_image_index,
+                partial_image_b64,
+                ..
+            } => Ok(Some(StreamChunk::image(ImageDelta {
+                id: item_id,
+                data: partial_image_b64,
+                partial_index: Some(partial_image_index),
+            }))),
+
+            OpenAIStreamEvent::OutputItemDone { item, .. } => {
+                Ok(item.and_then(|i| self.item_done(i)))
+            }
 
             OpenAIStreamEvent::ResponseCompleted { response } => {
                 let usage = response.usage.map(|u| Usage {
                     input_tokens: u.input_tokens,
                     output_tokens: u.output_tokens,
                     cache_read_input_tokens: u.input_tokens_details.cached_tokens,
+                    web_search_requests: self.web_searches,
                     ..Default::default()
                 });
 
@@ -357,18 +779,66 @@ impl ProviderParser for OpenAIParser {
                 Ok(Some(chunk))
             }
 
-            OpenAIStreamEvent::Error { error } => Err(Error::Api {
-                status: 0,
-                message: error.message,
-            }),
+            OpenAIStreamEvent::Error { error } => Err(Error::api(0, error.message)),
 
-            OpenAIStreamEvent::Unknown => Ok(None),
+            OpenAIStreamEvent::Unknown => {
+                self.skipped.skip("openai", data);
+                Ok(None)
+            }
         }
     }
+}
+
+impl Default for OpenAIParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProviderParser for OpenAIParser {
+    fn parse_chunk(&mut self, data: &str) -> Result<Option<StreamChunk>, Error> {
+        let event: OpenAIStreamEvent =
+            serde_json::from_str(data).map_err(|e| Error::parse(e.to_string()))?;
+        let sequence_number = event.sequence_number();
+        let output_index = event.output_index();
+
+        let chunk = self.chunk_for(event, data);
+        chunk.map(|chunk| {
+            chunk.map(|chunk| {
+                chunk
+                    .with_sequence_number(sequence_number)
+                    .with_output_index(output_index)
+            })
+        })
+    }
 
     fn is_end_of_stream(&self, _data: &str) -> bool {
         false
     }
+
+    fn take_warning(&mut self) -> Option<Warning> {
+        self.skipped.take_warning()
+    }
+}
+
+/// Keep the stream obfuscation padding (`include_obfuscation`) as an extension.
+fn with_obfuscation(chunk: StreamChunk, obfuscation: Option<String>) -> StreamChunk {
+    match obfuscation {
+        Some(padding) => chunk.with_extension("obfuscation", Value::String(padding)),
+        None => chunk,
+    }
+}
+
+/// A chunk announcing a built-in tool call, once when it starts and again when it's done.
+fn server_tool_chunk(item: StreamOutputItem) -> Option<StreamChunk> {
+    let call = match item {
+        StreamOutputItem::FileSearchCall(call) => call.into(),
+        StreamOutputItem::WebSearchCall(call) => call.into(),
+        _ => return None,
+    };
+    let mut chunk = StreamChunk::empty(ChunkKind::ServerTool);
+    chunk.server_tool_call = Some(call);
+    Some(chunk)
 }
 
 // --- Serde types for OpenAI Responses API ---
@@ -398,6 +868,13 @@ enum OutputItem {
         arguments: String,
         call_id: String,
     },
+    #[serde(rename = "file_search_call")]
+    FileSearchCall(FileSearchCall),
+    #[serde(rename = "web_search_call")]
+    WebSearchCall(WebSearchCall),
+    /// Reasoning items and other built-in tool calls.
+    #[serde(other)]
+    Other,
 }
 
 #[derive(Debug, Deserialize)]
@@ -406,6 +883,121 @@ struct OutputContent {
     content_type: String,
     #[serde(default)]
     text: String,
+    logprobs: Option<Vec<TokenLogProb>>,
+    refusal: Option<String>,
+    #[serde(default)]
+    annotations: Vec<OutputAnnotation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputAnnotation {
+    UrlCitation {
+        url: String,
+        title: Option<String>,
+        start_index: u32,
+        end_index: u32,
+    },
+    FileCitation {
+        file_id: String,
+        filename: Option<String>,
+        index: u32,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl OutputAnnotation {
+    /// The citation of the output text part `text`, which starts at byte
+    /// `start` of the content. The provider counts characters of the part.
+    fn into_citation(self, text: &str, start: usize) -> Option<Citation> {
+        let at = |chars: u32| {
+            let byte = text
+                .char_indices()
+                .nth(chars as usize)
+                .map_or(text.len(), |(i, _)| i);
+            u32::try_from(start + byte).ok()
+        };
+        match self {
+            Self::UrlCitation {
+                url,
+                title,
+                start_index,
+                end_index,
+            } => Some(Citation {
+                start_index: at(start_index),
+                end_index: at(end_index),
+                uri: Some(url),
+                title,
+                ..Citation::default()
+            }),
+            Self::FileCitation {
+                file_id,
+                filename,
+                index,
+            } => Some(Citation {
+                start_index: at(index),
+                end_index: at(index),
+                title: filename,
+                file_id: Some(file_id),
+                ..Citation::default()
+            }),
+            Self::Other => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FileSearchCall {
+    id: String,
+    #[serde(default)]
+    queries: Vec<String>,
+    /// Only present when `file_search_call.results` was included.
+    results: Option<Vec<FileSearchResult>>,
+}
+
+impl From<FileSearchCall> for ServerToolCall {
+    fn from(call: FileSearchCall) -> Self {
+        Self {
+            id: call.id,
+            name: "file_search".to_string(),
+            input: serde_json::json!({ "queries": call.queries }),
+            results: Vec::new(),
+            files: call.results.unwrap_or_default(),
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WebSearchCall {
+    id: String,
+    action: Option<WebSearchAction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebSearchAction {
+    query: Option<String>,
+}
+
+impl From<WebSearchCall> for ServerToolCall {
+    fn from(call: WebSearchCall) -> Self {
+        let query = call.action.and_then(|a| a.query);
+        Self {
+            id: call.id,
+            name: "web_search".to_string(),
+            input: serde_json::json!({ "query": query }),
+            results: Vec::new(),
+            files: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+/// Number of web searches among the built-in tool calls.
+fn web_searches(calls: &[ServerToolCall]) -> u32 {
+    let count = calls.iter().filter(|c| c.name == "web_search").count();
+    u32::try_from(count).unwrap_or(u32::MAX)
 }
 
 #[derive(Debug, Deserialize)]
@@ -448,30 +1040,65 @@ enum OpenAIStreamEvent {
     },
     #[serde(rename = "response.output_text.delta")]
     OutputTextDelta {
-        #[allow(dead_code)]
-        output_index: usize,
+        output_index: Option<u32>,
+        content_index: Option<u32>,
         delta: String,
+        obfuscation: Option<String>,
+        logprobs: Option<Vec<TokenLogProb>>,
+        sequence_number: Option<u64>,
+    },
+    #[serde(rename = "response.output_text.annotation.added")]
+    AnnotationAdded {
+        output_index: Option<u32>,
+        annotation: OutputAnnotation,
     },
     #[serde(rename = "response.output_text.done")]
     OutputTextDone {
         #[allow(dead_code)]
         text: String,
     },
+    #[serde(rename = "response.refusal.delta")]
+    RefusalDelta {
+        output_index: Option<u32>,
+        delta: String,
+        sequence_number: Option<u64>,
+    },
+    #[serde(rename = "response.refusal.done")]
+    RefusalDone {},
     #[serde(rename = "response.function_call_arguments.delta")]
     FunctionCallArgumentsDelta {
         #[allow(dead_code)]
         item_id: String,
+        output_index: Option<u32>,
         delta: String,
+        obfuscation: Option<String>,
+        sequence_number: Option<u64>,
     },
     #[serde(rename = "response.function_call_arguments.done")]
     FunctionCallArgumentsDone {
-        #[allow(dead_code)]
+        output_index: Option<u32>,
         arguments: String,
     },
     #[serde(rename = "response.content_part.done")]
     ContentPartDone {},
     #[serde(rename = "response.output_item.done")]
-    OutputItemDone {},
+    OutputItemDone {
+        output_index: Option<u32>,
+        item: Option<StreamOutputItem>,
+    },
+    #[serde(rename = "response.image_generation_call.partial_image")]
+    ImageGenerationPartialImage {
+        item_id: String,
+        output_index: Option<u32>,
+        partial_image_index: u32,
+        partial_image_b64: String,
+    },
+    #[serde(
+        rename = "response.image_generation_call.in_progress",
+        alias = "response.image_generation_call.generating",
+        alias = "response.image_generation_call.completed"
+    )]
+    ImageGenerationProgress {},
     #[serde(rename = "response.completed")]
     ResponseCompleted { response: CompletedResponse },
     #[serde(rename = "error")]
@@ -480,6 +1107,38 @@ enum OpenAIStreamEvent {
     Unknown,
 }
 
+impl OpenAIStreamEvent {
+    /// Position of a delta event in the stream.
+    fn sequence_number(&self) -> Option<u64> {
+        match self {
+            Self::OutputTextDelta {
+                sequence_number, ..
+            }
+            | Self::RefusalDelta {
+                sequence_number, ..
+            }
+            | Self::FunctionCallArgumentsDelta {
+                sequence_number, ..
+            } => *sequence_number,
+            _ => None,
+        }
+    }
+
+    /// Output item a chunk-producing event belongs to.
+    fn output_index(&self) -> Option<u32> {
+        match self {
+            Self::OutputTextDelta { output_index, .. }
+            | Self::RefusalDelta { output_index, .. }
+            | Self::FunctionCallArgumentsDelta { output_index, .. }
+            | Self::FunctionCallArgumentsDone { output_index, .. }
+            | Self::AnnotationAdded { output_index, .. }
+            | Self::OutputItemDone { output_index, .. }
+            | Self::ImageGenerationPartialImage { output_index, .. } => *output_index,
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct StreamResponse {
     #[allow(dead_code)]
@@ -495,6 +1154,15 @@ enum StreamOutputItem {
     Message {},
     #[serde(rename = "function_call")]
     FunctionCall { call_id: String, name: String },
+    #[serde(rename = "image_generation_call")]
+    ImageGenerationCall { id: String, result: Option<String> },
+    #[serde(rename = "file_search_call")]
+    FileSearchCall(FileSearchCall),
+    #[serde(rename = "web_search_call")]
+    WebSearchCall(WebSearchCall),
+    /// Other built-in tool calls and reasoning items.
+    #[serde(other)]
+    Other,
 }
 
 #[derive(Debug, Deserialize)]
@@ -511,6 +1179,7 @@ struct OpenAIError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::{AudioFormat, AudioOutput, FileSearch, WebSearch};
 
     #[test]
     fn test_parse_message_response() {
@@ -577,6 +1246,34 @@ mod tests {
         assert_eq!(result.finish_reason, FinishReason::ToolCalls);
     }
 
+    #[test]
+    fn test_parse_refusal() {
+        let provider = OpenAIProvider::new();
+        let json = r#"{
+            "model": "gpt-4o",
+            "status": "completed",
+            "output": [{
+                "type": "message",
+                "id": "msg_1",
+                "content": [{"type": "refusal", "refusal": "I can't help with that."}]
+            }],
+            "usage": {"input_tokens": 10, "output_tokens": 5, "input_tokens_details": {"cached_tokens": 0}}
+        }"#;
+        let result = provider.parse_response(json).unwrap();
+        assert_eq!(result.content, "");
+        assert_eq!(result.refusal.as_deref(), Some("I can't help with that."));
+
+        let mut parser = OpenAIParser::new();
+        let delta = r#"{"type":"response.refusal.delta","item_id":"msg_1","output_index":0,"content_index":0,"delta":"I can't"}"#;
+        let chunk = parser.parse_chunk(delta).unwrap().unwrap();
+        assert_eq!(chunk.kind, ChunkKind::Refusal);
+        assert_eq!(chunk.refusal.as_deref(), Some("I can't"));
+        assert!(chunk.text().is_none());
+
+        let done = r#"{"type":"response.refusal.done","item_id":"msg_1","output_index":0,"content_index":0,"refusal":"I can't"}"#;
+        assert!(parser.parse_chunk(done).unwrap().is_none());
+    }
+
     #[test]
     fn test_parse_stream_text_delta() {
         let mut parser = OpenAIParser::new();
@@ -589,6 +1286,16 @@ mod tests {
         let delta = r#"{"type":"response.output_text.delta","sequence_number":5,"output_index":0,"content_index":0,"delta":"Hello"}"#;
         let chunk = parser.parse_chunk(delta).unwrap().unwrap();
         assert_eq!(chunk.text().unwrap().as_ref(), "Hello");
+        assert_eq!(chunk.sequence_number, Some(5));
+    }
+
+    #[test]
+    fn test_parse_stream_output_index() {
+        let mut parser = OpenAIParser::new();
+
+        let delta = r#"{"type":"response.output_text.delta","sequence_number":1,"output_index":1,"content_index":0,"delta":"Hi"}"#;
+        let chunk = parser.parse_chunk(delta).unwrap().unwrap();
+        assert_eq!(chunk.output_index, Some(1));
     }
 
     #[test]
@@ -605,6 +1312,114 @@ mod tests {
         assert_eq!(chunk.kind, ChunkKind::ToolDelta);
         let tool_delta = chunk.tool_call_delta.unwrap();
         assert_eq!(tool_delta.function_name, Some("get_weather".to_string()));
+
+        // A repeated delta corrupts the fragments; the done event's arguments win
+        let mut tool_calls = ToolCallAccumulator::default();
+        tool_calls.apply(&tool_delta);
+        tool_calls.apply(&tool_delta);
+        let done = r#"{"type":"response.function_call_arguments.done","sequence_number":4,"item_id":"fc_123","output_index":0,"arguments":"{\"location\":\"Paris\"}"}"#;
+        let chunk = parser.parse_chunk(done).unwrap().unwrap();
+        tool_calls.apply(chunk.tool_call_delta.as_ref().unwrap());
+        let calls = tool_calls.finalize();
+        assert_eq!(calls[0].id, "call_123");
+        assert_eq!(calls[0].function.arguments, r#"{"location":"Paris"}"#);
+    }
+
+    #[test]
+    fn test_parse_stream_image_generation() {
+        let mut parser = OpenAIParser::new();
+        let added = r#"{"type":"response.output_item.added","output_index":0,"item":{"type":"image_generation_call","id":"ig_1","status":"in_progress"}}"#;
+        assert!(parser.parse_chunk(added).unwrap().is_none());
+        let generating = r#"{"type":"response.image_generation_call.generating","item_id":"ig_1","output_index":0}"#;
+        assert!(parser.parse_chunk(generating).unwrap().is_none());
+
+        let partial = r#"{"type":"response.image_generation_call.partial_image","item_id":"ig_1","output_index":0,"partial_image_index":0,"partial_image_b64":"iVBORw0K"}"#;
+        let chunk = parser.parse_chunk(partial).unwrap().unwrap();
+        assert_eq!(chunk.kind, ChunkKind::ImageDelta);
+        let image = chunk.image.unwrap();
+        assert_eq!(
+            (image.id.as_str(), image.data.as_str()),
+            ("ig_1", "iVBORw0K")
+        );
+        assert_eq!(image.partial_index, Some(0));
+
+        let done = r#"{"type":"response.output_item.done","output_index":0,"item":{"type":"image_generation_call","id":"ig_1","status":"completed","result":"iVBORw0KGgo"}}"#;
+        let image = parser.parse_chunk(done).unwrap().unwrap().image.unwrap();
+        assert!(image.is_final());
+        assert_eq!(image.data, "iVBORw0KGgo");
+        assert_eq!(parser.take_warning(), None);
+    }
+
+    #[test]
+    fn test_parse_stream_unknown_event() {
+        let mut parser = OpenAIParser::new();
+        let unknown = r#"{"type":"response.future_feature.delta","sequence_number":1}"#;
+        assert!(parser.parse_chunk(unknown).unwrap().is_none());
+        assert_eq!(
+            parser.take_warning(),
+            Some(Warning::UnknownEvent {
+                provider: "openai".into(),
+                event: "response.future_feature.delta".into(),
+            })
+        );
+
+        // Each event type is reported once per stream
+        parser.parse_chunk(unknown).unwrap();
+        assert_eq!(parser.take_warning(), None);
+    }
+
+    #[test]
+    fn test_response_format() {
+        let provider = OpenAIProvider::new();
+        let schema =
+            serde_json::json!({"type": "object", "properties": {"name": {"type": "string"}}});
+        let config = RequestConfig {
+            response_format: Some(ResponseFormat::JsonSchema(schema.clone())),
+            ..Default::default()
+        };
+        let body = provider
+            .build_complete_body("gpt-4o", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["text"]["format"]["type"], "json_schema");
+        // Response schemas are sent in strict mode, which requires closed objects
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"name": {"type": ["string", "null"]}},
+            "required": ["name"],
+            "additionalProperties": false
+        });
+        assert_eq!(body["text"]["format"]["schema"], schema);
+
+        let body = OpenAIProvider::new()
+            .with_chat_completions()
+            .build_complete_body("gpt-4o", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(body["response_format"]["json_schema"]["schema"], schema);
+    }
+
+    #[test]
+    fn test_logprobs() {
+        let provider = OpenAIProvider::new();
+        let config = RequestConfig {
+            logprobs: Some(2),
+            ..Default::default()
+        };
+        let body = provider
+            .build_complete_body("gpt-4o", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["include"][0], "message.output_text.logprobs");
+        assert_eq!(body["top_logprobs"], 2);
+
+        let mut parser = OpenAIParser::new();
+        let delta = r#"{"type":"response.output_text.delta","output_index":0,"delta":"Hi","logprobs":[{"token":"Hi","logprob":-0.1,"top_logprobs":[{"token":"Hi","logprob":-0.1},{"token":"Hey","logprob":-2.5}]}]}"#;
+        let chunk = parser.parse_chunk(delta).unwrap().unwrap();
+        let logprobs = chunk.logprobs.unwrap();
+        assert_eq!(logprobs[0].token, "Hi");
+        assert_eq!(logprobs[0].top_alternatives[1].token, "Hey");
     }
 
     #[test]
@@ -620,16 +1435,213 @@ mod tests {
         let config = RequestConfig {
             max_tokens: Some(100),
             tools: Some(tools),
+            reasoning_effort: Some(ReasoningEffort::Low),
             ..Default::default()
         };
 
         let body = provider
             .build_stream_body("gpt-4o-mini", &messages, &config)
-            .unwrap();
+            .unwrap()
+            .to_value();
 
         assert!(body["tools"].is_array());
         assert!(body["stream"].as_bool().unwrap());
         assert_eq!(body["max_output_tokens"], 100);
+        assert_eq!(body["reasoning"]["effort"], "low");
+    }
+
+    #[test]
+    fn test_file_search() {
+        let provider = OpenAIProvider::new();
+        let config = RequestConfig {
+            server_tools: Some(vec![ServerTool::FileSearch(FileSearch {
+                max_num_results: Some(5),
+                include_results: true,
+                ..FileSearch::new(["vs_1"])
+            })]),
+            ..Default::default()
+        };
+        let body = provider
+            .build_complete_body("gpt-4o", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(
+            body["tools"][0],
+            serde_json::json!({"type": "file_search", "vector_store_ids": ["vs_1"], "max_num_results": 5})
+        );
+        assert_eq!(body["include"][0], "file_search_call.results");
+
+        let json = r#"{
+            "model": "gpt-4o",
+            "status": "completed",
+            "output": [
+                {"type": "file_search_call", "id": "fs_1", "status": "completed", "queries": ["refund policy"],
+                 "results": [{"file_id": "file_1", "filename": "policy.pdf", "score": 0.9, "text": "Refunds within 30 days."}]},
+                {"type": "message", "id": "msg_1", "content": [{
+                    "type": "output_text", "text": "Within 30 days.",
+                    "annotations": [{"type": "file_citation", "file_id": "file_1", "filename": "policy.pdf", "index": 15}]
+                }, {
+                    "type": "output_text", "text": " Ça dure.",
+                    "annotations": [{"type": "file_citation", "file_id": "file_2", "index": 3}]
+                }]}
+            ],
+            "usage": {"input_tokens": 10, "output_tokens": 5, "input_tokens_details": {}}
+        }"#;
+        let result = provider.parse_response(json).unwrap();
+        let call = &result.server_tool_calls[0];
+        assert_eq!(call.name, "file_search");
+        assert_eq!(call.input["queries"][0], "refund policy");
+        assert_eq!(call.files[0].filename.as_deref(), Some("policy.pdf"));
+        // Positions count bytes of the whole content
+        let citations = result.citations.unwrap();
+        assert_eq!(citations[0].file_id.as_deref(), Some("file_1"));
+        assert_eq!(citations[0].title.as_deref(), Some("policy.pdf"));
+        assert_eq!(
+            (citations[0].start_index, citations[0].end_index),
+            (Some(15), Some(15))
+        );
+        assert_eq!(&result.content[..19], "Within 30 days. Ça");
+        assert_eq!(citations[1].start_index, Some(19));
+    }
+
+    #[test]
+    fn test_web_search_unsupported_fields() {
+        let provider = OpenAIProvider::new();
+        let mut config = RequestConfig {
+            server_tools: Some(vec![ServerTool::WebSearch(WebSearch {
+                allowed_domains: vec!["rust-lang.org".into()],
+                ..Default::default()
+            })]),
+            ..Default::default()
+        };
+        let body = provider
+            .build_stream_body("gpt-4o", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(
+            body["tools"][0],
+            serde_json::json!({"type": "web_search", "filters": {"allowed_domains": ["rust-lang.org"]}})
+        );
+
+        // Fields the tool has no counterpart for fail rather than being dropped
+        config.server_tools = Some(vec![ServerTool::WebSearch(WebSearch {
+            blocked_domains: vec!["example.com".into()],
+            ..Default::default()
+        })]);
+        assert!(matches!(
+            provider.build_stream_body("gpt-4o", &[Message::user("Hi")], &config),
+            Err(Error::Config(_))
+        ));
+        config.server_tools = Some(vec![ServerTool::WebSearch(WebSearch {
+            max_uses: Some(3),
+            ..Default::default()
+        })]);
+        assert!(matches!(
+            provider.build_complete_body("gpt-4o", &[Message::user("Hi")], &config),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[cfg(feature = "client")]
+    #[tokio::test]
+    async fn test_stream_url_citations() {
+        use crate::stream::CompletionStream;
+
+        let events = [
+            r#"{"type":"response.output_item.added","output_index":0,"item":{"type":"web_search_call","id":"ws_1","status":"in_progress"}}"#,
+            r#"{"type":"response.output_item.done","output_index":0,"item":{"type":"web_search_call","id":"ws_1","status":"completed","action":{"type":"search","query":"rust release"}}}"#,
+            r#"{"type":"response.output_text.delta","output_index":1,"delta":"Rust 1.90 is out."}"#,
+            r#"{"type":"response.output_text.annotation.added","output_index":1,"annotation_index":0,"annotation":{"type":"url_citation","url":"https://blog.rust-lang.org/","title":"Rust Blog","start_index":0,"end_index":17}}"#,
+            r#"{"type":"response.output_text.annotation.added","output_index":1,"annotation_index":1,"annotation":{"type":"container_file_citation","container_id":"c_1"}}"#,
+            r#"{"type":"response.output_text.delta","output_index":2,"content_index":0,"delta":"Déjà là."}"#,
+            r#"{"type":"response.output_text.annotation.added","output_index":2,"annotation_index":0,"annotation":{"type":"url_citation","url":"https://example.com/","start_index":0,"end_index":8}}"#,
+            r#"{"type":"response.completed","response":{"status":"completed","usage":{"input_tokens":10,"output_tokens":5,"input_tokens_details":{}}}}"#,
+        ];
+        let frames = events.map(|e| Ok(bytes::Bytes::from(format!("data: {e}\n\n"))));
+        let mut stream = CompletionStream::new(
+            futures::stream::iter(frames),
+            Box::new(OpenAIParser::new()),
+            "gpt-4o".to_string(),
+        );
+        let mut kinds = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            kinds.push(chunk.unwrap().kind);
+        }
+        assert_eq!(kinds[..2], [ChunkKind::ServerTool, ChunkKind::ServerTool]);
+
+        let result = stream.finalize().unwrap();
+        assert_eq!(result.server_tool_calls[0].input["query"], "rust release");
+        assert_eq!(result.usage.web_search_requests, 1);
+        let citations = result.citations.unwrap();
+        assert_eq!(
+            citations[0],
+            Citation {
+                start_index: Some(0),
+                end_index: Some(17),
+                uri: Some("https://blog.rust-lang.org/".into()),
+                title: Some("Rust Blog".into()),
+                ..Citation::default()
+            }
+        );
+        // The second message's characters, placed after the first's bytes
+        assert_eq!(
+            (citations[1].start_index, citations[1].end_index),
+            (Some(17), Some(28))
+        );
+        assert_eq!(&result.content[17..28], "Déjà là.");
+    }
+
+    #[test]
+    fn test_image_detail() {
+        let provider = OpenAIProvider::new();
+        let message = Message {
+            content: MessageContent::Parts(vec![ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: "https://example.com/cat.png".to_string(),
+                    detail: Some("low".to_string()),
+                },
+            }]),
+            ..Message::user("")
+        };
+
+        let body = provider
+            .build_complete_body("gpt-4o", &[message], &RequestConfig::default())
+            .unwrap()
+            .to_value();
+        let image = &body["input"][0]["content"][0];
+        assert_eq!(image["type"], "input_image");
+        assert_eq!(image["image_url"], "https://example.com/cat.png");
+        assert_eq!(image["detail"], "low");
+    }
+
+    #[test]
+    fn test_audio_output() {
+        let provider = OpenAIProvider::new().with_chat_completions();
+        assert_eq!(provider.chat_endpoint(), "/v1/chat/completions");
+
+        let config = RequestConfig {
+            audio: Some(AudioOutput {
+                voice: "alloy".to_string(),
+                format: AudioFormat::Pcm16,
+            }),
+            ..Default::default()
+        };
+        let body = provider
+            .build_stream_body("gpt-4o-audio-preview", &[Message::user("Hi")], &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["modalities"], serde_json::json!(["text", "audio"]));
+        assert_eq!(body["audio"]["voice"], "alloy");
+        assert_eq!(body["audio"]["format"], "pcm16");
+
+        let mut parser = provider.create_parser();
+        let data = r#"{"id":"1","choices":[{"index":0,"delta":{"audio":{"id":"audio_1","data":"AAAA","transcript":"Hel"}},"finish_reason":null}]}"#;
+        let chunk = parser.parse_chunk(data).unwrap().unwrap();
+        assert_eq!(chunk.kind, ChunkKind::Audio);
+        let audio = chunk.audio.unwrap();
+        assert_eq!(audio.id.as_deref(), Some("audio_1"));
+        assert_eq!(audio.data.as_deref(), Some("AAAA"));
+        assert_eq!(audio.transcript.as_deref(), Some("Hel"));
     }
 
     #[test]
@@ -640,4 +1652,37 @@ mod tests {
         let auth = headers.get("authorization").unwrap().to_str().unwrap();
         assert!(auth.starts_with("Bearer "));
     }
+
+    #[test]
+    fn test_tool_loop_input_items() {
+        let provider = OpenAIProvider::new();
+        let messages = vec![
+            Message::user("Weather in Paris?"),
+            Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall {
+                    id: "call_123".to_string(),
+                    tool_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "get_weather".to_string(),
+                        arguments: r#"{"city":"Paris"}"#.to_string(),
+                    },
+                }],
+            ),
+            Message::tool_result("call_123", "18C"),
+        ];
+
+        let body = provider
+            .build_complete_body("gpt-4o", &messages, &RequestConfig::default())
+            .unwrap()
+            .to_value();
+        let input = body["input"].as_array().unwrap();
+        assert_eq!(input.len(), 3);
+        assert_eq!(input[1]["type"], "function_call");
+        assert_eq!(input[1]["call_id"], "call_123");
+        assert_eq!(input[2]["type"], "function_call_output");
+        assert_eq!(input[2]["call_id"], "call_123");
+        assert_eq!(input[2]["output"], "18C");
+        assert!(input[2].get("role").is_none());
+    }
 }
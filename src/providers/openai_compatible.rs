@@ -0,0 +1,149 @@
+//! Generic OpenAI-compatible provider for self-hosted servers.
+//!
+//! Targets servers that speak classic Chat Completions (`/chat/completions`)
+//! without vendor extensions: LM Studio, text-generation-inference, LiteLLM
+//! proxies, or vLLM when its guided decoding isn't needed.
+//! - There is no default endpoint; the base URL (including `/v1`) must be
+//!   configured with [`ClientBuilder::base_url`](crate::ClientBuilder::base_url)
+//!   or `OPENAI_COMPATIBLE_BASE_URL`
+//! - API key is optional; sent as a `Bearer` token when set
+//! - Streaming uses SSE with `[DONE]` marker
+
+use crate::error::Error;
+use crate::providers::cerebras::{
+    build_chat_body, build_chat_messages, parse_chat_response, CerebrasParser, ChatBody,
+};
+use crate::providers::{Parameter, Provider, RequestBody, RequestConfig};
+use crate::stream::ProviderParser;
+use crate::types::*;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+/// Provider for any OpenAI-compatible Chat Completions server.
+pub struct OpenAICompatibleProvider {
+    base_url: String,
+}
+
+impl OpenAICompatibleProvider {
+    /// Provider for the server at `base_url`, e.g. `http://localhost:1234/v1`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+
+    fn build_base_body<'a>(
+        &self,
+        model: &'a str,
+        messages: &'a [Message],
+        config: &'a RequestConfig,
+    ) -> ChatBody<'a> {
+        let mut body = build_chat_body(model, messages, config);
+        // Audio output is an OpenAI extension few servers implement
+        body.modalities = None;
+        body.audio = None;
+        body
+    }
+}
+
+impl Provider for OpenAICompatibleProvider {
+    fn name(&self) -> &'static str {
+        "openai-compatible"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self, api_key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if !api_key.is_empty() {
+            if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+                headers.insert(AUTHORIZATION, auth);
+            }
+        }
+        headers
+    }
+
+    fn build_stream_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &self
+                .build_base_body(model, messages, config)
+                .with_stream(true),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_complete_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &self
+                .build_base_body(model, messages, config)
+                .with_stream(false),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        Some(build_chat_messages(messages))
+    }
+
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        matches!(parameter, Parameter::Logprobs | Parameter::ReasoningEffort)
+    }
+
+    fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
+        Box::new(CerebrasParser::new())
+    }
+
+    fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
+        parse_chat_response(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_and_headers() {
+        let provider = OpenAICompatibleProvider::new("http://localhost:1234/v1");
+        assert_eq!(
+            provider.stream_url("qwen2.5-7b-instruct", ""),
+            "http://localhost:1234/v1/chat/completions"
+        );
+        assert!(provider.headers("").get(AUTHORIZATION).is_none());
+        assert_eq!(
+            provider.headers("sk-local")[AUTHORIZATION],
+            "Bearer sk-local"
+        );
+
+        let body = provider
+            .build_stream_body(
+                "qwen2.5-7b-instruct",
+                &[Message::user("Hi")],
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert_eq!(body["model"], "qwen2.5-7b-instruct");
+        assert_eq!(body["stream"], true);
+        assert!(body.get("modalities").is_none());
+    }
+}
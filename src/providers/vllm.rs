@@ -0,0 +1,234 @@
+//! vLLM provider implementation.
+//!
+//! vLLM serves an OpenAI-compatible Chat Completions API with extensions:
+//! - Guided decoding via `guided_json`, `guided_regex`, `guided_choice`, `guided_grammar`
+//! - `best_of` to sample several candidates server-side
+//! - `continue_final_message` to continue a trailing assistant message (prefill)
+//! - API key is optional (only enforced when the server runs with `--api-key`)
+//!
+//! There is no hosted endpoint; configure the base URL of your deployment
+//! (defaults to `http://localhost:8000/v1`).
+
+use crate::error::Error;
+use crate::providers::cerebras::{
+    build_chat_body, build_chat_messages, parse_chat_response, CerebrasParser, ChatBody,
+};
+use crate::providers::{prefill, GuidedDecoding, Parameter, Provider, RequestBody, RequestConfig};
+use crate::stream::ProviderParser;
+use crate::types::*;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+/// vLLM OpenAI-compatible server provider.
+pub struct VllmProvider {
+    base_url: String,
+}
+
+impl VllmProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "http://localhost:8000/v1".to_string(),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+
+    fn build_base_body<'a>(
+        &self,
+        model: &'a str,
+        messages: &'a [Message],
+        config: &'a RequestConfig,
+    ) -> ChatBody<'a> {
+        let mut body = build_chat_body(model, messages, config);
+
+        if let Some(guided) = &config.guided_decoding {
+            match guided {
+                GuidedDecoding::Json(schema) => body.guided_json = Some(schema),
+                GuidedDecoding::Regex(pattern) => body.guided_regex = Some(pattern),
+                GuidedDecoding::Choice(choices) => body.guided_choice = Some(choices),
+                GuidedDecoding::Grammar(grammar) => body.guided_grammar = Some(grammar),
+            }
+        }
+        body.best_of = config.best_of;
+        if prefill(messages).is_some() {
+            body.continue_final_message = Some(true);
+            body.add_generation_prompt = Some(false);
+        }
+
+        body
+    }
+}
+
+impl Default for VllmProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for VllmProvider {
+    fn name(&self) -> &'static str {
+        "vllm"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self, api_key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if !api_key.is_empty() {
+            if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+                headers.insert(AUTHORIZATION, auth);
+            }
+        }
+        headers
+    }
+
+    fn build_stream_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &self
+                .build_base_body(model, messages, config)
+                .with_stream(true),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_complete_body(
+        &self,
+        model: &str,
+        messages: &[Message],
+        config: &RequestConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &self
+                .build_base_body(model, messages, config)
+                .with_stream(false),
+            config.extra.as_ref(),
+        )
+    }
+
+    fn build_messages(
+        &self,
+        messages: &[Message],
+    ) -> Option<Result<(&'static str, RequestBody), Error>> {
+        // A prefill sets other fields of the body
+        if prefill(messages).is_some() {
+            return None;
+        }
+        Some(build_chat_messages(messages))
+    }
+
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    fn supports_prefill(&self) -> bool {
+        true
+    }
+
+    fn supports_parameter(&self, parameter: Parameter) -> bool {
+        matches!(
+            parameter,
+            Parameter::GuidedDecoding
+                | Parameter::BestOf
+                | Parameter::Audio
+                | Parameter::Logprobs
+                | Parameter::ReasoningEffort
+        )
+    }
+
+    fn create_parser(&self) -> Box<dyn ProviderParser + Send> {
+        Box::new(CerebrasParser::new())
+    }
+
+    fn parse_response(&self, body: &str) -> Result<CompletionResult, Error> {
+        parse_chat_response(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guided_decoding_body() {
+        let provider = VllmProvider::new();
+        let messages = vec![Message::user("Is Rust memory safe?")];
+
+        let config = RequestConfig {
+            guided_decoding: Some(GuidedDecoding::Choice(vec![
+                "yes".to_string(),
+                "no".to_string(),
+            ])),
+            best_of: Some(3),
+            ..Default::default()
+        };
+        let body = provider
+            .build_stream_body("Qwen/Qwen2.5-7B-Instruct", &messages, &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["guided_choice"], serde_json::json!(["yes", "no"]));
+        assert_eq!(body["best_of"], 3);
+
+        let config = RequestConfig {
+            guided_decoding: Some(GuidedDecoding::Json(serde_json::json!({"type": "object"}))),
+            ..Default::default()
+        };
+        let body = provider
+            .build_complete_body("Qwen/Qwen2.5-7B-Instruct", &messages, &config)
+            .unwrap()
+            .to_value();
+        assert_eq!(body["guided_json"]["type"], "object");
+        assert!(body.get("best_of").is_none());
+    }
+
+    #[test]
+    fn test_prefill_body() {
+        let provider = VllmProvider::new();
+        let messages = vec![
+            Message::user("List three colors as JSON."),
+            Message::assistant_prefill("{\"colors\": ["),
+        ];
+
+        let body = provider
+            .build_complete_body(
+                "Qwen/Qwen2.5-7B-Instruct",
+                &messages,
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert_eq!(body["continue_final_message"], true);
+        assert_eq!(body["add_generation_prompt"], false);
+        assert!(provider.build_messages(&messages).is_none());
+        assert!(provider.build_messages(&messages[..1]).is_some());
+
+        // A plain assistant message is a finished turn
+        let finished = [messages[0].clone(), Message::assistant("{\"colors\": []}")];
+        let body = provider
+            .build_complete_body(
+                "Qwen/Qwen2.5-7B-Instruct",
+                &finished,
+                &RequestConfig::default(),
+            )
+            .unwrap()
+            .to_value();
+        assert!(body.get("continue_final_message").is_none());
+    }
+
+    #[test]
+    fn test_headers_without_key() {
+        let provider = VllmProvider::new();
+        assert!(!provider.headers("").contains_key("authorization"));
+        assert!(provider.headers("secret").contains_key("authorization"));
+    }
+}
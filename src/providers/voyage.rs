@@ -0,0 +1,173 @@
+//! Voyage AI embedding provider implementation.
+//!
+//! Voyage exposes an OpenAI-style `/v1/embeddings` endpoint with extensions:
+//! - `input_type` ("query" / "document") to prepend retrieval prompts
+//! - `output_dimension` for models with flexible dimensions (voyage-3-large, voyage-code-3)
+//! - `truncation` to cut over-long inputs instead of failing
+//! - Usage reported as `total_tokens` only
+
+use crate::error::Error;
+use crate::providers::{EmbeddingConfig, EmbeddingInputType, EmbeddingProvider, RequestBody};
+use crate::types::*;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+
+/// Voyage AI embedding provider.
+pub struct VoyageProvider {
+    base_url: String,
+}
+
+impl VoyageProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "https://api.voyageai.com/v1".to_string(),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+}
+
+impl Default for VoyageProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmbeddingProvider for VoyageProvider {
+    fn name(&self) -> &'static str {
+        "voyage"
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self, api_key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(auth) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+            headers.insert(AUTHORIZATION, auth);
+        }
+        headers
+    }
+
+    fn build_embed_body(
+        &self,
+        model: &str,
+        inputs: &[String],
+        config: &EmbeddingConfig,
+    ) -> Result<RequestBody, Error> {
+        RequestBody::json_with_extra(
+            &EmbedBody {
+                model,
+                input: inputs,
+                input_type: config.input_type.map(|input_type| match input_type {
+                    EmbeddingInputType::Query => "query",
+                    EmbeddingInputType::Document => "document",
+                }),
+                output_dimension: config.dimensions,
+                truncation: config.truncate,
+            },
+            config.extra.as_ref(),
+        )
+    }
+
+    fn parse_embed_response(&self, body: &str) -> Result<EmbeddingResult, Error> {
+        let mut resp: VoyageResponse =
+            serde_json::from_str(body).map_err(|e| Error::parse(e.to_string()))?;
+
+        // Data is documented as ordered, but sort by index to be safe
+        resp.data.sort_by_key(|d| d.index);
+
+        Ok(EmbeddingResult {
+            embeddings: resp.data.into_iter().map(|d| d.embedding).collect(),
+            usage: Usage {
+                input_tokens: resp.usage.total_tokens,
+                ..Default::default()
+            },
+            model: resp.model,
+        })
+    }
+}
+
+// --- Serde types for Voyage API ---
+
+/// Embedding request body.
+#[derive(Serialize)]
+struct EmbedBody<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dimension: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    truncation: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageResponse {
+    data: Vec<VoyageEmbedding>,
+    model: String,
+    usage: VoyageUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageEmbedding {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageUsage {
+    total_tokens: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_embed_body() {
+        let provider = VoyageProvider::new();
+        let config = EmbeddingConfig {
+            dimensions: Some(512),
+            truncate: Some(false),
+            input_type: Some(EmbeddingInputType::Document),
+            ..Default::default()
+        };
+
+        let body = provider
+            .build_embed_body("voyage-3-large", &["hello".to_string()], &config)
+            .unwrap()
+            .to_value();
+
+        assert_eq!(body["input"][0], "hello");
+        assert_eq!(body["input_type"], "document");
+        assert_eq!(body["output_dimension"], 512);
+        assert_eq!(body["truncation"], false);
+    }
+
+    #[test]
+    fn test_parse_embed_response() {
+        let provider = VoyageProvider::new();
+        let json = r#"{
+            "object": "list",
+            "data": [
+                {"object": "embedding", "embedding": [0.3, 0.4], "index": 1},
+                {"object": "embedding", "embedding": [0.1, 0.2], "index": 0}
+            ],
+            "model": "voyage-3",
+            "usage": {"total_tokens": 12}
+        }"#;
+
+        let result = provider.parse_embed_response(json).unwrap();
+        assert_eq!(result.embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+        assert_eq!(result.usage.input_tokens, 12);
+        assert_eq!(result.model, "voyage-3");
+    }
+}
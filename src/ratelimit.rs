@@ -0,0 +1,312 @@
+//! Client-side rate limiting and request scheduling.
+//!
+//! A per-provider [`RateLimit`] caps the request rate (token bucket) and the
+//! number of concurrent requests. Requests over the limit are rejected with
+//! [`Error::RateLimited`], or, in queue mode, parked until capacity frees up
+//! and served by [`Priority`], then arrival order.
+
+use crate::error::Error;
+use futures::future::select;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::pin::pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Scheduling priority of a request waiting in a rate limit queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Per-provider request limits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimit {
+    /// Sustained request rate; bursts of up to this many requests are allowed.
+    pub requests_per_minute: Option<u32>,
+    /// Maximum requests (including open streams) running at once.
+    pub max_concurrent: Option<usize>,
+    /// Park requests over the limit instead of rejecting them.
+    pub queue: Option<QueueConfig>,
+}
+
+/// Queue mode settings.
+#[derive(Debug, Clone, Copy)]
+pub struct QueueConfig {
+    /// Maximum parked requests; further requests fail with [`Error::QueueFull`].
+    pub max_depth: usize,
+    /// Maximum time a request waits before failing with [`Error::QueueTimeout`].
+    pub timeout: Duration,
+}
+
+impl RateLimit {
+    /// No limits.
+    pub const fn new() -> Self {
+        Self {
+            requests_per_minute: None,
+            max_concurrent: None,
+            queue: None,
+        }
+    }
+
+    /// Must be at least 1.
+    pub const fn with_requests_per_minute(mut self, rpm: u32) -> Self {
+        self.requests_per_minute = Some(rpm);
+        self
+    }
+
+    pub const fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.max_concurrent = Some(max);
+        self
+    }
+
+    /// Enable queue mode.
+    pub const fn with_queue(mut self, max_depth: usize, timeout: Duration) -> Self {
+        self.queue = Some(QueueConfig { max_depth, timeout });
+        self
+    }
+}
+
+/// Queue position: higher priority first, then arrival order.
+type QueueKey = (Reverse<Priority>, u64);
+
+/// Enforces one [`RateLimit`].
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+#[derive(Debug)]
+struct State {
+    tokens: f64,
+    refilled: Instant,
+    active: usize,
+    waiting: BTreeSet<QueueKey>,
+    next_seq: u64,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(State {
+                tokens: limit.requests_per_minute.map_or(0.0, f64::from),
+                refilled: Instant::now(),
+                active: 0,
+                waiting: BTreeSet::new(),
+                next_seq: 0,
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Wait for (or, outside queue mode, demand) permission to send a request.
+    pub(crate) async fn acquire(self: &Arc<Self>, priority: Priority) -> Result<Permit, Error> {
+        let (key, timeout) = {
+            let mut state = self.lock();
+            let ready = self.check(&mut state);
+
+            let Some(queue) = self.limit.queue else {
+                return match ready {
+                    Ok(()) => Ok(self.take(&mut state)),
+                    Err(retry_after) => Err(Error::RateLimited {
+                        retry_after,
+                        details: None,
+                    }),
+                };
+            };
+            if ready.is_ok() && state.waiting.is_empty() {
+                return Ok(self.take(&mut state));
+            }
+            if state.waiting.len() >= queue.max_depth {
+                return Err(Error::QueueFull);
+            }
+
+            let key = (Reverse(priority), state.next_seq);
+            state.next_seq += 1;
+            state.waiting.insert(key);
+            (key, queue.timeout)
+        };
+
+        let _entry = QueueEntry { limiter: self, key };
+        tokio::time::timeout(timeout, self.wait_turn(key))
+            .await
+            .map_err(|_| Error::QueueTimeout)
+    }
+
+    async fn wait_turn(self: &Arc<Self>, key: QueueKey) -> Permit {
+        loop {
+            let mut changed = pin!(self.changed.notified());
+            changed.as_mut().enable();
+
+            let wait = {
+                let mut state = self.lock();
+                if state.waiting.first() == Some(&key) {
+                    match self.check(&mut state) {
+                        Ok(()) => {
+                            state.waiting.remove(&key);
+                            let permit = self.take(&mut state);
+                            drop(state);
+                            // Let the next request in line re-check
+                            self.changed.notify_waiters();
+                            return permit;
+                        }
+                        Err(wait) => wait,
+                    }
+                } else {
+                    None
+                }
+            };
+
+            match wait {
+                Some(wait) => {
+                    select(changed, pin!(tokio::time::sleep(wait))).await;
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    /// Refill the bucket and check capacity. On failure, returns how long until
+    /// a token is due (`None` when waiting on a concurrency slot).
+    fn check(&self, state: &mut State) -> Result<(), Option<Duration>> {
+        if let Some(max) = self.limit.max_concurrent {
+            if state.active >= max {
+                return Err(None);
+            }
+        }
+
+        if let Some(rpm) = self.limit.requests_per_minute {
+            let rate = f64::from(rpm) / 60.0;
+            let now = Instant::now();
+            let elapsed = now.duration_since(state.refilled).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * rate).min(f64::from(rpm));
+            state.refilled = now;
+
+            if state.tokens < 1.0 {
+                return Err(Some(Duration::from_secs_f64((1.0 - state.tokens) / rate)));
+            }
+        }
+        Ok(())
+    }
+
+    fn take(self: &Arc<Self>, state: &mut State) -> Permit {
+        if self.limit.requests_per_minute.is_some() {
+            state.tokens -= 1.0;
+        }
+        state.active += 1;
+        Permit {
+            limiter: Arc::clone(self),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Removes an abandoned request (timed out or cancelled) from the queue.
+struct QueueEntry<'a> {
+    limiter: &'a RateLimiter,
+    key: QueueKey,
+}
+
+impl Drop for QueueEntry<'_> {
+    fn drop(&mut self) {
+        if self.limiter.lock().waiting.remove(&self.key) {
+            self.limiter.changed.notify_waiters();
+        }
+    }
+}
+
+/// Concurrency slot held while a request runs.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    limiter: Arc<RateLimiter>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.lock().active -= 1;
+        self.limiter.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reject_without_queue() {
+        let limiter = Arc::new(RateLimiter::new(
+            RateLimit::new().with_requests_per_minute(60),
+        ));
+
+        let mut permits = Vec::new();
+        for _ in 0..60 {
+            permits.push(limiter.acquire(Priority::Normal).await.unwrap());
+        }
+        let err = limiter.acquire(Priority::Normal).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::RateLimited {
+                retry_after: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_zero_rate_rejected() {
+        let result = crate::ClientBuilder::new()
+            .rate_limit("openai", RateLimit::new().with_requests_per_minute(0))
+            .build();
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_queue_priority() {
+        let limiter = Arc::new(RateLimiter::new(
+            RateLimit::new()
+                .with_max_concurrent(1)
+                .with_queue(2, Duration::from_secs(5)),
+        ));
+        let held = limiter.acquire(Priority::Normal).await.unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for priority in [Priority::Low, Priority::High] {
+            tasks.push(tokio::spawn({
+                let limiter = Arc::clone(&limiter);
+                let order = Arc::clone(&order);
+                async move {
+                    let _permit = limiter.acquire(priority).await.unwrap();
+                    order.lock().unwrap().push(priority);
+                }
+            }));
+            // Both requests must be parked before capacity frees up
+            while limiter.lock().waiting.len() < tasks.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        assert!(matches!(
+            limiter.acquire(Priority::High).await,
+            Err(Error::QueueFull)
+        ));
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), [Priority::High, Priority::Low]);
+    }
+}
@@ -0,0 +1,242 @@
+//! Cost-based model routing.
+//!
+//! [`CheapestModelRouter`] picks the cheapest model from a candidate pool that
+//! has the required capabilities and fits the expected token budget, and can
+//! escalate to the next (more expensive) candidate when a response finishes
+//! unsatisfactorily, e.g. truncated by the output limit.
+
+use crate::capabilities::ModelCapabilities;
+use crate::client::{Client, RequestBuilder};
+use crate::cost::PricingRegistry;
+use crate::error::Error;
+use crate::models::ModelRegistry;
+use crate::types::{CompletionResult, FinishReason, Message, Usage};
+
+/// Expected size of a request, used to estimate its cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenBudget {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl TokenBudget {
+    pub const fn new(input_tokens: u32, output_tokens: u32) -> Self {
+        Self {
+            input_tokens,
+            output_tokens,
+        }
+    }
+}
+
+/// Routes each request to the cheapest capable model of a candidate pool.
+pub struct CheapestModelRouter {
+    candidates: Vec<String>,
+    pricing: PricingRegistry,
+    escalate_on: Vec<FinishReason>,
+}
+
+impl CheapestModelRouter {
+    /// Create a router over `provider/model` candidates (aliases allowed).
+    ///
+    /// Escalates on [`FinishReason::Length`] by default.
+    pub fn new<I, S>(candidates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            candidates: candidates.into_iter().map(Into::into).collect(),
+            pricing: PricingRegistry::new(),
+            escalate_on: vec![FinishReason::Length],
+        }
+    }
+
+    /// Use custom pricing instead of the bundled prices.
+    pub fn with_pricing(mut self, pricing: PricingRegistry) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Finish reasons that trigger a retry on the next candidate.
+    pub fn escalate_on(mut self, reasons: &[FinishReason]) -> Self {
+        self.escalate_on = reasons.to_vec();
+        self
+    }
+
+    /// Candidates that satisfy `required` and fit `budget`, cheapest first.
+    ///
+    /// Models without pricing are skipped, as their cost can't be compared.
+    pub fn rank(
+        &self,
+        models: &ModelRegistry,
+        required: &ModelCapabilities,
+        budget: TokenBudget,
+    ) -> Vec<&str> {
+        let usage = Usage {
+            input_tokens: budget.input_tokens,
+            output_tokens: budget.output_tokens,
+            ..Default::default()
+        };
+        let total = budget.input_tokens.saturating_add(budget.output_tokens);
+
+        let mut ranked: Vec<(&str, f64)> = self
+            .candidates
+            .iter()
+            .filter_map(|candidate| {
+                let caps = models.capabilities(candidate)?;
+                let fits = caps.satisfies(required)
+                    && caps.context_window.map_or(true, |ctx| ctx >= total)
+                    && caps
+                        .max_output_tokens
+                        .map_or(true, |max| max >= budget.output_tokens);
+                if !fits {
+                    return None;
+                }
+                let cost = self
+                    .pricing
+                    .calculate_cost(models.resolve(candidate), &usage)?;
+                Some((candidate.as_str(), cost.total()))
+            })
+            .collect();
+
+        // Stable sort keeps the pool order for equally priced models
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked.into_iter().map(|(model, _)| model).collect()
+    }
+
+    /// The cheapest candidate satisfying `required` and fitting `budget`.
+    pub fn select(
+        &self,
+        models: &ModelRegistry,
+        required: &ModelCapabilities,
+        budget: TokenBudget,
+    ) -> Option<&str> {
+        self.rank(models, required, budget).into_iter().next()
+    }
+
+    /// Complete on the cheapest capable model, escalating to the next candidate
+    /// while the finish reason is one of [`escalate_on`](Self::escalate_on).
+    ///
+    /// `configure` applies request options (tools, temperature, ...) to each attempt.
+    /// Returns the last result if every candidate finishes unsatisfactorily.
+    pub async fn complete<F>(
+        &self,
+        client: &Client,
+        messages: &[Message],
+        required: &ModelCapabilities,
+        budget: TokenBudget,
+        configure: F,
+    ) -> Result<CompletionResult, Error>
+    where
+        F: for<'b> Fn(RequestBuilder<'b>) -> RequestBuilder<'b>,
+    {
+        let ranked = self.rank(client.models(), required, budget);
+        let Some((last, rest)) = ranked.split_last() else {
+            return Err(Error::Config(
+                "no candidate model satisfies the requirements".into(),
+            ));
+        };
+
+        for model in rest {
+            let result = configure(client.complete(model, messages))
+                .send_complete()
+                .await?;
+            if !self.escalate_on.contains(&result.finish_reason) {
+                return Ok(result);
+            }
+        }
+        configure(client.complete(last, messages))
+            .send_complete()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_by_price_and_capability() {
+        let router = CheapestModelRouter::new([
+            "openai/gpt-4o",
+            "cerebras/llama-3.3-70b",
+            "cerebras/llama3.1-8b",
+            "openai/gpt-4o-mini",
+        ]);
+        let models = ModelRegistry::new();
+        let budget = TokenBudget::new(1_000, 500);
+
+        let tools = ModelCapabilities::new().with_tools();
+        assert_eq!(
+            router.rank(&models, &tools, budget),
+            [
+                "cerebras/llama3.1-8b",
+                "openai/gpt-4o-mini",
+                "cerebras/llama-3.3-70b",
+                "openai/gpt-4o"
+            ]
+        );
+
+        let vision = ModelCapabilities::new().with_vision();
+        assert_eq!(
+            router.select(&models, &vision, budget),
+            Some("openai/gpt-4o-mini")
+        );
+
+        // Cerebras models can't fit a 20k-token prompt
+        let long = TokenBudget::new(20_000, 500);
+        assert_eq!(
+            router.select(&models, &tools, long),
+            Some("openai/gpt-4o-mini")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_escalation() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let response = |model: &str, finish_reason: &str| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "1",
+                "model": model,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "hi"},
+                    "finish_reason": finish_reason
+                }],
+                "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+            }))
+        };
+
+        let server = MockServer::start().await;
+        for (model, finish_reason) in [("llama3.1-8b", "length"), ("llama-3.3-70b", "stop")] {
+            Mock::given(method("POST"))
+                .and(body_partial_json(serde_json::json!({"model": model})))
+                .respond_with(response(model, finish_reason))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::builder()
+            .api_key("cerebras", "test")
+            .base_url("cerebras", server.uri())
+            .build()
+            .unwrap();
+        let router = CheapestModelRouter::new(["cerebras/llama-3.3-70b", "cerebras/llama3.1-8b"]);
+
+        let result = router
+            .complete(
+                &client,
+                &[Message::user("Hi")],
+                &ModelCapabilities::new(),
+                TokenBudget::new(100, 100),
+                |b| b.max_tokens(100),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.model, "llama-3.3-70b");
+        assert_eq!(result.finish_reason, FinishReason::Stop);
+    }
+}
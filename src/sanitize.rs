@@ -0,0 +1,211 @@
+//! Input sanitization for untrusted text.
+//!
+//! Scraped pages and extracted documents carry NUL bytes, terminal escapes,
+//! Unicode noncharacters, and megabytes of boilerplate that providers answer
+//! with a bare 400 deep inside a pipeline. A [`Sanitizer`] set with
+//! [`ClientBuilder::sanitize`](crate::ClientBuilder::sanitize) cleans the
+//! message text of every request before it is serialized.
+//!
+//! A Rust `String` can't hold invalid UTF-8 or lone surrogates; decode raw
+//! input with [`Sanitizer::decode`], which replaces them with U+FFFD.
+
+use crate::types::{ContentPart, Message, MessageContent};
+use std::borrow::Cow;
+
+/// Which text cleanups to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sanitizer {
+    /// Remove control characters other than tab, line feed, and carriage return.
+    pub strip_control: bool,
+    /// Replace Unicode noncharacters (U+FFFE, U+FDD0, ...) with U+FFFD.
+    pub replace_noncharacters: bool,
+    /// Cut the text of a message after this many characters.
+    pub max_message_chars: Option<usize>,
+}
+
+impl Sanitizer {
+    /// Strip control characters and replace noncharacters, without a size cap.
+    pub const fn new() -> Self {
+        Self {
+            strip_control: true,
+            replace_noncharacters: true,
+            max_message_chars: None,
+        }
+    }
+
+    /// Cut the text of each message after `max` characters; text parts past
+    /// the cap are dropped.
+    pub const fn with_max_message_chars(mut self, max: usize) -> Self {
+        self.max_message_chars = Some(max);
+        self
+    }
+
+    /// Keep control characters, e.g. for prompts about terminal output.
+    pub const fn keep_control(mut self) -> Self {
+        self.strip_control = false;
+        self
+    }
+
+    /// Decode `bytes` as UTF-8, replacing invalid sequences and encoded
+    /// surrogates with U+FFFD, then clean the text.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let text = String::from_utf8_lossy(bytes);
+        self.sanitize_text(&text, usize::MAX).into_owned()
+    }
+
+    /// Clean `messages`, borrowing them when there's nothing to change.
+    pub fn sanitize_messages<'a>(&self, messages: &'a [Message]) -> Cow<'a, [Message]> {
+        let first = messages
+            .iter()
+            .enumerate()
+            .find_map(|(i, message)| Some((i, self.sanitize_message(message)?)));
+        let Some((first, clean)) = first else {
+            return Cow::Borrowed(messages);
+        };
+
+        let mut sanitized = messages.to_vec();
+        sanitized[first] = clean;
+        for message in &mut sanitized[first + 1..] {
+            if let Some(clean) = self.sanitize_message(message) {
+                *message = clean;
+            }
+        }
+        Cow::Owned(sanitized)
+    }
+
+    /// The cleaned message, if it changed.
+    fn sanitize_message(&self, message: &Message) -> Option<Message> {
+        let mut budget = self.max_message_chars.unwrap_or(usize::MAX);
+        let mut clean = |text: &str| -> Option<String> {
+            let cleaned = self.sanitize_text(text, budget);
+            budget = budget.saturating_sub(cleaned.chars().count());
+            match cleaned {
+                Cow::Borrowed(_) => None,
+                Cow::Owned(cleaned) => Some(cleaned),
+            }
+        };
+
+        match &message.content {
+            MessageContent::Text(text) => {
+                let text = clean(text)?;
+                Some(Message {
+                    content: MessageContent::Text(text),
+                    ..message.clone()
+                })
+            }
+            MessageContent::Parts(parts) => {
+                let mut changed = false;
+                // Parts emptied by the cap are dropped: Claude rejects empty text blocks
+                let parts = parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text { text } => match clean(text) {
+                            Some(text) => {
+                                changed = true;
+                                (!text.is_empty()).then_some(ContentPart::Text { text })
+                            }
+                            None => Some(part.clone()),
+                        },
+                        ContentPart::ImageUrl { .. } => Some(part.clone()),
+                    })
+                    .collect();
+                changed.then(|| Message {
+                    content: MessageContent::Parts(parts),
+                    ..message.clone()
+                })
+            }
+        }
+    }
+
+    /// Clean `text` and cut it after `max_chars` characters.
+    fn sanitize_text<'t>(&self, text: &'t str, max_chars: usize) -> Cow<'t, str> {
+        let keep = |c: char| !(self.strip_control && is_stripped_control(c));
+        let replace = |c: char| self.replace_noncharacters && is_noncharacter(c);
+
+        let dirty = text.chars().any(|c| !keep(c) || replace(c));
+        if !dirty && (max_chars == usize::MAX || text.chars().nth(max_chars).is_none()) {
+            return Cow::Borrowed(text);
+        }
+        Cow::Owned(
+            text.chars()
+                .filter(|&c| keep(c))
+                .map(|c| {
+                    if replace(c) {
+                        char::REPLACEMENT_CHARACTER
+                    } else {
+                        c
+                    }
+                })
+                .take(max_chars)
+                .collect(),
+        )
+    }
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_stripped_control(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+}
+
+fn is_noncharacter(c: char) -> bool {
+    let c = u32::from(c);
+    (0xFDD0..=0xFDEF).contains(&c) || c & 0xFFFE == 0xFFFE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_messages() {
+        let capped = Sanitizer::new().with_max_message_chars(8);
+        let messages = vec![
+            Message::user("clean"),
+            Message::user("a\0b\x1b[0m\tc\u{FFFE}"),
+            Message {
+                content: MessageContent::Parts(vec![
+                    ContentPart::Text {
+                        text: "12345".into(),
+                    },
+                    ContentPart::Text {
+                        text: "67890".into(),
+                    },
+                    ContentPart::Text {
+                        text: "past the cap".into(),
+                    },
+                ]),
+                ..Message::user("")
+            },
+        ];
+
+        let cleaned = capped.sanitize_messages(&messages);
+        assert_eq!(cleaned[0].content.as_text(), Some("clean"));
+        assert_eq!(cleaned[1].content.as_text(), Some("ab[0m\tc\u{FFFD}"));
+        // The cap spans all parts of a message
+        let MessageContent::Parts(parts) = &cleaned[2].content else {
+            panic!("expected parts");
+        };
+        assert!(matches!(&parts[1], ContentPart::Text { text } if text == "678"));
+        // Parts with no text left are dropped
+        assert_eq!(parts.len(), 2);
+
+        let clean = [Message::user("a\tb\r\n")];
+        assert!(matches!(capped.sanitize_messages(&clean), Cow::Borrowed(_)));
+        assert!(matches!(
+            Sanitizer::new().keep_control().sanitize_messages(&messages[1..2]),
+            Cow::Owned(ref m) if m[0].content.as_text() == Some("a\0b\x1b[0m\tc\u{FFFD}")
+        ));
+    }
+
+    #[test]
+    fn test_decode() {
+        // A CESU-8 encoded surrogate, as emitted by some Java and JavaScript tooling
+        let decoded = Sanitizer::new().decode(b"ok \xED\xA0\x80 \x00done");
+        assert_eq!(decoded, "ok \u{FFFD}\u{FFFD}\u{FFFD} done");
+    }
+}
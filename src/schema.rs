@@ -0,0 +1,468 @@
+//! JSON Schema normalization for provider quirks.
+//!
+//! Providers accept different subsets of JSON Schema, and answer the rest with
+//! a bare 400:
+//!
+//! - Gemini takes an OpenAPI 3.0 subset: no `$ref`, `const`, `oneOf`,
+//!   `additionalProperties`, or type arrays (`nullable` instead).
+//! - OpenAI strict mode requires every object to be closed and to list all of
+//!   its properties as `required`; optional properties are made nullable.
+//! - Tool parameters must be an object schema, without `anyOf`/`oneOf`/`allOf`
+//!   at the top level (Claude's `input_schema`, OpenAI function parameters).
+//!
+//! Tool parameters and response schemas are normalized for each provider when
+//! the request body is built, so one [`Tool`](crate::Tool) definition works
+//! everywhere. [`normalize`] applies the same rewrites, e.g. to inspect them.
+
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+/// `$ref`s nested deeper than this (recursive schemas) are left unresolved.
+const MAX_REF_DEPTH: usize = 8;
+
+/// Keywords Gemini accepts in a schema.
+const GEMINI_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "title",
+    "description",
+    "nullable",
+    "enum",
+    "default",
+    "example",
+    "properties",
+    "required",
+    "propertyOrdering",
+    "minProperties",
+    "maxProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minimum",
+    "maximum",
+    "anyOf",
+];
+
+/// A provider's flavor of JSON Schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaDialect {
+    /// Gemini's OpenAPI 3.0 subset.
+    Gemini,
+    /// OpenAI without strict mode: an object at the top level.
+    OpenAI,
+    /// OpenAI strict mode: closed objects with every property required.
+    OpenAIStrict,
+    /// Claude `input_schema`: an object at the top level.
+    Claude,
+}
+
+/// Rewrite tool parameters `schema` in place for `dialect`.
+///
+/// Schemas that already fit are left as they are.
+pub fn normalize(schema: &mut Value, dialect: SchemaDialect) {
+    object_root(schema);
+    match dialect {
+        SchemaDialect::Gemini => {
+            let definitions = take_definitions(schema);
+            inline_refs(schema, &definitions, 0);
+            gemini(schema);
+        }
+        SchemaDialect::OpenAIStrict => strict(schema),
+        SchemaDialect::OpenAI | SchemaDialect::Claude => {}
+    }
+}
+
+/// `schema` normalized for `dialect`, borrowed when it already fits.
+pub(crate) fn normalized(schema: &Value, dialect: SchemaDialect) -> Cow<'_, Value> {
+    // These dialects only need an object root, so most schemas skip the copy
+    if matches!(dialect, SchemaDialect::OpenAI | SchemaDialect::Claude) && has_object_root(schema) {
+        return Cow::Borrowed(schema);
+    }
+    let mut normalized = schema.clone();
+    normalize(&mut normalized, dialect);
+    if normalized == *schema {
+        Cow::Borrowed(schema)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+/// Tool `parameters` normalized for `dialect`, an empty object schema if unset.
+pub(crate) fn tool_parameters(
+    parameters: Option<&Value>,
+    dialect: SchemaDialect,
+) -> Cow<'_, Value> {
+    match parameters {
+        Some(parameters) => normalized(parameters, dialect),
+        None => {
+            let mut empty = Value::Null;
+            normalize(&mut empty, dialect);
+            Cow::Owned(empty)
+        }
+    }
+}
+
+/// Whether [`object_root`] would leave `schema` as it is.
+fn has_object_root(schema: &Value) -> bool {
+    let Value::Object(map) = schema else {
+        return false;
+    };
+    if map.get("type").is_some_and(|t| t != "object") {
+        return true;
+    }
+    map.contains_key("type")
+        && map.contains_key("properties")
+        && !["allOf", "anyOf", "oneOf"]
+            .iter()
+            .any(|keyword| map.contains_key(*keyword))
+}
+
+/// Make the top level an object schema, merging the variants of a top-level
+/// `anyOf`/`oneOf`/`allOf` into one.
+fn object_root(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        *schema = serde_json::json!({"type": "object", "properties": {}});
+        return;
+    };
+    if map.get("type").is_some_and(|t| t != "object") {
+        return;
+    }
+
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        let Some(Value::Array(variants)) = map.remove(keyword) else {
+            continue;
+        };
+        let mut properties = map
+            .remove("properties")
+            .and_then(|p| match p {
+                Value::Object(p) => Some(p),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let mut required = required_names(map);
+        // Any variant may apply, so only what all of them require stays required
+        let mut shared: Option<Vec<String>> = None;
+        for variant in variants {
+            let Value::Object(mut variant) = variant else {
+                continue;
+            };
+            let names = required_names(&variant);
+            if keyword == "allOf" {
+                required.extend(names);
+            } else {
+                shared = Some(match shared {
+                    Some(shared) => shared.into_iter().filter(|n| names.contains(n)).collect(),
+                    None => names,
+                });
+            }
+            if let Some(Value::Object(props)) = variant.remove("properties") {
+                for (name, property) in props {
+                    properties.entry(name).or_insert(property);
+                }
+            }
+        }
+        required.extend(shared.unwrap_or_default());
+        required.sort();
+        required.dedup();
+        map.insert("properties".into(), Value::Object(properties));
+        if !required.is_empty() {
+            map.insert(
+                "required".into(),
+                required.into_iter().map(Value::String).collect(),
+            );
+        }
+    }
+    map.entry("type").or_insert_with(|| "object".into());
+    if !map.contains_key("properties") {
+        map.insert("properties".into(), Value::Object(Map::new()));
+    }
+}
+
+fn required_names(map: &Map<String, Value>) -> Vec<String> {
+    match map.get("required") {
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(|name| name.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Call `f` on each direct subschema of `map`.
+fn for_each_subschema(map: &mut Map<String, Value>, f: &mut impl FnMut(&mut Value)) {
+    for (keyword, value) in map.iter_mut() {
+        match (keyword.as_str(), value) {
+            ("properties" | "patternProperties" | "$defs" | "definitions", Value::Object(map)) => {
+                map.values_mut().for_each(&mut *f);
+            }
+            ("anyOf" | "oneOf" | "allOf" | "prefixItems", Value::Array(items)) => {
+                items.iter_mut().for_each(&mut *f);
+            }
+            ("items" | "additionalProperties" | "not", value @ Value::Object(_)) => f(value),
+            _ => {}
+        }
+    }
+}
+
+fn take_definitions(schema: &mut Value) -> Map<String, Value> {
+    let Value::Object(map) = schema else {
+        return Map::new();
+    };
+    let mut definitions = Map::new();
+    for keyword in ["$defs", "definitions"] {
+        if let Some(Value::Object(defs)) = map.remove(keyword) {
+            definitions.extend(defs);
+        }
+    }
+    definitions
+}
+
+/// Replace local `$ref`s with the definitions they point to.
+fn inline_refs(schema: &mut Value, definitions: &Map<String, Value>, depth: usize) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    if let Some(Value::String(reference)) = map.remove("$ref") {
+        let name = reference
+            .strip_prefix("#/$defs/")
+            .or_else(|| reference.strip_prefix("#/definitions/"));
+        if let Some(Value::Object(definition)) = name.and_then(|name| definitions.get(name)) {
+            if depth < MAX_REF_DEPTH {
+                // Keywords next to the `$ref` (e.g. a description) take precedence
+                for (keyword, value) in definition {
+                    map.entry(keyword.clone()).or_insert_with(|| value.clone());
+                }
+                inline_refs(schema, definitions, depth + 1);
+                return;
+            }
+        }
+    }
+    for_each_subschema(map, &mut |subschema| {
+        inline_refs(subschema, definitions, depth);
+    });
+}
+
+fn gemini(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+
+    if let Some(Value::Array(types)) = map.get("type") {
+        let mut types: Vec<Value> = types.iter().filter(|t| *t != "null").cloned().collect();
+        if types.len() < map["type"].as_array().map_or(0, Vec::len) {
+            map.insert("nullable".into(), true.into());
+        }
+        if types.len() == 1 {
+            map.insert("type".into(), types.remove(0));
+        } else {
+            map.remove("type");
+            let variants = types.into_iter().map(|t| serde_json::json!({"type": t}));
+            map.insert("anyOf".into(), variants.collect());
+        }
+    }
+    if let Some(value) = map.remove("const") {
+        map.insert("enum".into(), Value::Array(vec![value]));
+    }
+    // Gemini only takes string enums, declared as such
+    if let Some(Value::Array(values)) = map.get("enum") {
+        if !map.contains_key("type") && values.iter().all(Value::is_string) {
+            map.insert("type".into(), "string".into());
+        }
+    }
+    if let Some(variants) = map.remove("oneOf") {
+        map.entry("anyOf").or_insert(variants);
+    }
+    if let Some(Value::Array(variants)) = map.remove("allOf") {
+        for variant in variants {
+            let Value::Object(variant) = variant else {
+                continue;
+            };
+            for (keyword, value) in variant {
+                match (map.get_mut(&keyword), value) {
+                    (Some(Value::Object(properties)), Value::Object(more)) => {
+                        properties.extend(more);
+                    }
+                    (Some(Value::Array(required)), Value::Array(more)) => required.extend(more),
+                    (None, value) => {
+                        map.insert(keyword, value);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    // `anyOf: [{...}, {"type": "null"}]` is how nullable fields are usually spelled
+    if let Some(Value::Array(variants)) = map.get_mut("anyOf") {
+        let before = variants.len();
+        variants.retain(|variant| !variant.get("type").is_some_and(|t| t == "null"));
+        if variants.len() < before {
+            if variants.len() == 1 {
+                let Some(Value::Object(variant)) = variants.pop() else {
+                    unreachable!("filtered to object schemas");
+                };
+                map.remove("anyOf");
+                for (keyword, value) in variant {
+                    map.entry(keyword).or_insert(value);
+                }
+            }
+            map.insert("nullable".into(), true.into());
+        }
+    }
+
+    map.retain(|keyword, _| GEMINI_KEYWORDS.contains(&keyword.as_str()));
+    for_each_subschema(map, &mut gemini);
+}
+
+/// Close every object schema and require all of its properties, making the
+/// optional ones nullable.
+fn strict(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+
+    let required = required_names(map);
+    if let Some(Value::Object(properties)) = map.get_mut("properties") {
+        for (name, property) in properties.iter_mut() {
+            if !required.contains(name) {
+                make_nullable(property);
+            }
+        }
+        let names = properties.keys().cloned().map(Value::String).collect();
+        map.insert("required".into(), Value::Array(names));
+        map.insert("additionalProperties".into(), false.into());
+    } else if map.get("type").and_then(Value::as_str) == Some("object") {
+        map.insert("additionalProperties".into(), false.into());
+    }
+    for_each_subschema(map, &mut strict);
+}
+
+fn make_nullable(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    match map.get_mut("type") {
+        Some(Value::String(t)) if t != "null" => {
+            let t = std::mem::take(t);
+            map.insert("type".into(), serde_json::json!([t, "null"]));
+        }
+        Some(Value::Array(types)) if !types.iter().any(|t| t == "null") => {
+            types.push("null".into());
+        }
+        None => {
+            if let Some(Value::Array(variants)) = map.get_mut("anyOf") {
+                if !variants
+                    .iter()
+                    .any(|v| v.get("type").is_some_and(|t| t == "null"))
+                {
+                    variants.push(serde_json::json!({"type": "null"}));
+                }
+            }
+            return;
+        }
+        _ => return,
+    }
+    if let Some(Value::Array(values)) = map.get_mut("enum") {
+        if !values.contains(&Value::Null) {
+            values.push(Value::Null);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn city_schema() -> Value {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "country": {"$ref": "#/$defs/Country", "description": "Where it is"},
+                "population": {"type": ["integer", "null"]},
+                "kind": {"const": "city"}
+            },
+            "required": ["name"],
+            "additionalProperties": false,
+            "$defs": {
+                "Country": {"type": "string", "enum": ["FR", "DE"], "description": "ISO code"}
+            }
+        })
+    }
+
+    #[test]
+    fn test_gemini() {
+        let mut schema = city_schema();
+        normalize(&mut schema, SchemaDialect::Gemini);
+        assert_eq!(
+            schema,
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string", "minLength": 1},
+                    "country": {"type": "string", "enum": ["FR", "DE"], "description": "Where it is"},
+                    "population": {"type": "integer", "nullable": true},
+                    "kind": {"type": "string", "enum": ["city"]}
+                },
+                "required": ["name"]
+            })
+        );
+
+        let mut nullable = json!({"anyOf": [{"type": "string"}, {"type": "null"}]});
+        gemini(&mut nullable);
+        assert_eq!(nullable, json!({"type": "string", "nullable": true}));
+    }
+
+    #[test]
+    fn test_strict() {
+        let mut schema = city_schema();
+        normalize(&mut schema, SchemaDialect::OpenAIStrict);
+        assert_eq!(
+            schema["required"],
+            json!(["country", "kind", "name", "population"])
+        );
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["properties"]["name"]["type"], "string");
+        assert_eq!(
+            schema["properties"]["population"]["type"],
+            json!(["integer", "null"])
+        );
+
+        // Already strict schemas are left alone
+        let strict = schema.clone();
+        assert!(matches!(
+            normalized(&strict, SchemaDialect::OpenAIStrict),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_object_root() {
+        let mut schema = json!({
+            "oneOf": [
+                {"properties": {"id": {"type": "integer"}, "verbose": {"type": "boolean"}}, "required": ["id"]},
+                {"properties": {"name": {"type": "string"}}, "required": ["name"]}
+            ]
+        });
+        normalize(&mut schema, SchemaDialect::Claude);
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"].as_object().unwrap().len(), 3);
+        assert!(schema.get("required").is_none());
+
+        let mut schema = Value::Null;
+        normalize(&mut schema, SchemaDialect::OpenAI);
+        assert_eq!(schema, json!({"type": "object", "properties": {}}));
+
+        // Object roots are borrowed; others are rewritten
+        assert!(matches!(
+            normalized(&schema, SchemaDialect::Claude),
+            Cow::Borrowed(_)
+        ));
+        let schema = json!({"properties": {"id": {"type": "integer"}}});
+        assert_eq!(normalized(&schema, SchemaDialect::OpenAI)["type"], "object");
+    }
+}
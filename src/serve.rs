@@ -0,0 +1,539 @@
+//! Re-emit completion streams in a client-facing wire format.
+//!
+//! Gateways that use this crate as their backend can forward a stream to
+//! browsers and SDKs without hand-writing the wire format: [`encode`] turns a
+//! [`CompletionStream`] into the body of an OpenAI-compatible SSE response or
+//! of a Vercel AI SDK data stream, whatever provider it came from.
+//! [`Encoder`] does the same chunk by chunk, without I/O.
+//!
+//! ```no_run
+//! # #[cfg(feature = "client")]
+//! # async fn run(client: rust_ai_sdk::Client, messages: Vec<rust_ai_sdk::Message>) -> Result<(), rust_ai_sdk::Error> {
+//! use rust_ai_sdk::serve::{self, WireFormat};
+//!
+//! let stream = client.stream("claude/claude-sonnet-4-5", &messages).send().await?;
+//! let headers = WireFormat::OpenAI.headers();
+//! let body = serve::encode(stream, WireFormat::OpenAI);
+//! // Respond with `headers` and `body`, e.g. `axum::body::Body::from_stream(body)`
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
+use crate::types::{ChunkKind, FinishReason, StreamChunk, Usage};
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderValue, CACHE_CONTROL, CONTENT_TYPE};
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "client")]
+use crate::stream::CompletionStream;
+#[cfg(feature = "client")]
+use futures::Stream;
+#[cfg(feature = "client")]
+use std::convert::Infallible;
+
+/// Wire format to re-emit a stream in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    /// Server-sent `chat.completion.chunk` events ending with `data: [DONE]`,
+    /// as read by OpenAI SDKs.
+    OpenAI,
+    /// The Vercel AI SDK data stream protocol (`0:"text"` lines), as read by
+    /// `useChat` and `useCompletion`.
+    VercelDataStream,
+}
+
+impl WireFormat {
+    /// Response headers the format's readers expect.
+    pub fn headers(self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        match self {
+            Self::OpenAI => {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+            }
+            Self::VercelDataStream => {
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                );
+                headers.insert("x-vercel-ai-data-stream", HeaderValue::from_static("v1"));
+            }
+        }
+        headers
+    }
+}
+
+/// Encodes stream chunks in a [`WireFormat`].
+///
+/// Feed every chunk to [`chunk`](Self::chunk), then end with
+/// [`finish`](Self::finish) or, if the stream failed, [`error`](Self::error).
+/// Each call returns the bytes to write, empty if there's nothing to send.
+#[derive(Debug)]
+pub struct Encoder {
+    format: WireFormat,
+    id: String,
+    model: String,
+    created: u64,
+    started: bool,
+    tool_calls: Vec<ForwardedToolCall>,
+    finish_reason: Option<FinishReason>,
+    finish_sent: bool,
+    usage: Option<Usage>,
+}
+
+/// A tool call as forwarded so far.
+#[derive(Debug, Default)]
+struct ForwardedToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+    announced: bool,
+}
+
+impl Encoder {
+    /// Encoder for a stream from `model`, with a random response ID.
+    pub fn new(format: WireFormat, model: impl Into<String>) -> Self {
+        let prefix = match format {
+            WireFormat::OpenAI => "chatcmpl-",
+            WireFormat::VercelDataStream => "msg-",
+        };
+        let random: String = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(24)
+            .collect();
+        Self {
+            format,
+            id: format!("{prefix}{random}"),
+            model: model.into(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            started: false,
+            tool_calls: Vec::new(),
+            finish_reason: None,
+            finish_sent: false,
+            usage: None,
+        }
+    }
+
+    /// Use `id` as the response (OpenAI) or message (Vercel) ID.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Encode one chunk.
+    pub fn chunk(&mut self, chunk: &StreamChunk) -> Bytes {
+        if let Some(usage) = &chunk.usage {
+            self.usage.get_or_insert_with(Usage::default).merge(usage);
+        }
+        if chunk.finish_reason.is_some() {
+            self.finish_reason = chunk.finish_reason;
+        }
+
+        let mut out = String::new();
+        if !self.started && self.format == WireFormat::VercelDataStream {
+            self.started = true;
+            push_part(&mut out, 'f', &json!({ "messageId": self.id }));
+        }
+
+        let text = chunk.text();
+        let text = text.as_deref().filter(|text| !text.is_empty());
+        let tool_call = chunk
+            .tool_call_delta
+            .as_ref()
+            .map(|delta| self.forward_tool_call(delta));
+
+        match self.format {
+            WireFormat::OpenAI => {
+                let mut delta = serde_json::Map::new();
+                if let Some(text) = text {
+                    let field = if chunk.kind == ChunkKind::Thinking {
+                        "reasoning_content"
+                    } else {
+                        "content"
+                    };
+                    delta.insert(field.into(), text.into());
+                }
+                if let Some(refusal) = &chunk.refusal {
+                    delta.insert("refusal".into(), refusal.as_str().into());
+                }
+                if let Some((index, fragment)) = tool_call {
+                    delta.insert("tool_calls".into(), self.openai_tool_call(index, fragment));
+                }
+                let finish_reason = chunk.finish_reason.filter(|_| !self.finish_sent);
+                if !delta.is_empty() || finish_reason.is_some() {
+                    self.finish_sent |= finish_reason.is_some();
+                    self.push_openai_delta(&mut out, delta, finish_reason);
+                } else if chunk.kind == ChunkKind::Ping {
+                    out.push_str(": ping\n\n");
+                }
+            }
+            WireFormat::VercelDataStream => {
+                if let Some(text) = text {
+                    let part = if chunk.kind == ChunkKind::Thinking {
+                        'g'
+                    } else {
+                        '0'
+                    };
+                    push_part(&mut out, part, &Value::from(text));
+                }
+                if let Some(refusal) = &chunk.refusal {
+                    push_part(&mut out, '0', &Value::from(refusal.as_str()));
+                }
+                if let Some((index, fragment)) = tool_call {
+                    let call = &mut self.tool_calls[index];
+                    if !call.announced {
+                        call.announced = true;
+                        push_part(
+                            &mut out,
+                            'b',
+                            &json!({ "toolCallId": call.id, "toolName": call.name }),
+                        );
+                    }
+                    if let Some(fragment) = fragment {
+                        push_part(
+                            &mut out,
+                            'c',
+                            &json!({ "toolCallId": call.id, "argsTextDelta": fragment }),
+                        );
+                    }
+                }
+            }
+        }
+        Bytes::from(out)
+    }
+
+    /// Encode the end of the stream: the finish reason and usage, and the
+    /// completed tool calls (Vercel).
+    pub fn finish(&mut self) -> Bytes {
+        let mut out = String::new();
+        let usage = self.usage.clone().unwrap_or_default();
+        match self.format {
+            WireFormat::OpenAI => {
+                if !self.finish_sent {
+                    self.finish_sent = true;
+                    let reason = self.finish_reason.unwrap_or(FinishReason::Stop);
+                    self.push_openai_delta(&mut out, serde_json::Map::new(), Some(reason));
+                }
+                if self.usage.is_some() {
+                    let frame = json!({
+                        "id": self.id,
+                        "object": "chat.completion.chunk",
+                        "created": self.created,
+                        "model": self.model,
+                        "choices": [],
+                        "usage": {
+                            "prompt_tokens": usage.input_tokens,
+                            "completion_tokens": usage.output_tokens,
+                            "total_tokens": usage.total(),
+                        },
+                    });
+                    push_event(&mut out, &frame);
+                }
+                out.push_str("data: [DONE]\n\n");
+            }
+            WireFormat::VercelDataStream => {
+                if !self.started {
+                    push_part(&mut out, 'f', &json!({ "messageId": self.id }));
+                }
+                for call in &self.tool_calls {
+                    let args = serde_json::from_str::<Value>(&call.arguments)
+                        .unwrap_or_else(|_| json!({}));
+                    push_part(
+                        &mut out,
+                        '9',
+                        &json!({ "toolCallId": call.id, "toolName": call.name, "args": args }),
+                    );
+                }
+                let finish = json!({
+                    "finishReason": self.finish_reason.map_or("unknown", vercel_finish_reason),
+                    "usage": {
+                        "promptTokens": usage.input_tokens,
+                        "completionTokens": usage.output_tokens,
+                    },
+                });
+                let mut step = finish.clone();
+                step["isContinued"] = false.into();
+                push_part(&mut out, 'e', &step);
+                push_part(&mut out, 'd', &finish);
+            }
+        }
+        Bytes::from(out)
+    }
+
+    /// Encode a failure that ended the stream.
+    pub fn error(&mut self, error: &Error) -> Bytes {
+        let mut out = String::new();
+        match self.format {
+            WireFormat::OpenAI => {
+                let frame = json!({
+                    "error": {
+                        "message": error.to_string(),
+                        "type": if error.is_retryable() { "server_error" } else { "invalid_request_error" },
+                        "code": error.status(),
+                    }
+                });
+                push_event(&mut out, &frame);
+            }
+            WireFormat::VercelDataStream => push_part(&mut out, '3', &error.to_string().into()),
+        }
+        Bytes::from(out)
+    }
+
+    /// Record a tool call delta, returning the call's index and the argument
+    /// text to forward.
+    ///
+    /// Final arguments can't take back fragments already sent; only what
+    /// extends them is forwarded.
+    fn forward_tool_call(
+        &mut self,
+        delta: &crate::types::ToolCallDelta,
+    ) -> (usize, Option<String>) {
+        while self.tool_calls.len() <= delta.index {
+            self.tool_calls.push(ForwardedToolCall::default());
+        }
+        let call = &mut self.tool_calls[delta.index];
+        if call.id.is_empty() {
+            call.id = delta
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("call_{}", delta.index));
+        }
+        if call.name.is_empty() {
+            if let Some(name) = &delta.function_name {
+                call.name.clone_from(name);
+            }
+        }
+
+        let mut fragment = delta.function_arguments.clone();
+        if let Some(fragment) = &fragment {
+            call.arguments.push_str(fragment);
+        }
+        if let Some(arguments) = &delta.final_arguments {
+            fragment = arguments
+                .strip_prefix(call.arguments.as_str())
+                .filter(|rest| !rest.is_empty())
+                .map(str::to_string);
+            call.arguments.clone_from(arguments);
+        }
+        (delta.index, fragment)
+    }
+
+    fn openai_tool_call(&mut self, index: usize, fragment: Option<String>) -> Value {
+        let call = &mut self.tool_calls[index];
+        let mut function = json!({ "arguments": fragment.unwrap_or_default() });
+        let mut tool_call = json!({ "index": index });
+        if !call.announced {
+            call.announced = true;
+            function["name"] = call.name.as_str().into();
+            tool_call["id"] = call.id.as_str().into();
+            tool_call["type"] = "function".into();
+        }
+        tool_call["function"] = function;
+        json!([tool_call])
+    }
+
+    fn push_openai_delta(
+        &mut self,
+        out: &mut String,
+        mut delta: serde_json::Map<String, Value>,
+        finish_reason: Option<FinishReason>,
+    ) {
+        // The first delta carries the role
+        if !self.started {
+            self.started = true;
+            delta.insert("role".into(), "assistant".into());
+        }
+        let frame = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason.map(openai_finish_reason),
+            }],
+        });
+        push_event(out, &frame);
+    }
+}
+
+/// Encode `stream` as a response body in `format`.
+///
+/// The body doesn't fail: a stream error is encoded as the format's error
+/// event and ends the body.
+#[cfg(feature = "client")]
+pub fn encode<S>(
+    stream: CompletionStream<S>,
+    format: WireFormat,
+) -> impl Stream<Item = Result<Bytes, Infallible>>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Unpin,
+{
+    let encoder = Encoder::new(format, stream.model());
+    futures::stream::unfold(Some((stream, encoder)), |state| async move {
+        let (mut stream, mut encoder) = state?;
+        loop {
+            let frame = match stream.next().await {
+                Some(Ok(chunk)) => encoder.chunk(&chunk),
+                Some(Err(e)) => return Some((Ok(encoder.error(&e)), None)),
+                None => return Some((Ok(encoder.finish()), None)),
+            };
+            if !frame.is_empty() {
+                return Some((Ok(frame), Some((stream, encoder))));
+            }
+        }
+    })
+}
+
+fn push_event(out: &mut String, data: &Value) {
+    out.push_str("data: ");
+    out.push_str(&data.to_string());
+    out.push_str("\n\n");
+}
+
+fn push_part(out: &mut String, code: char, value: &Value) {
+    out.push(code);
+    out.push(':');
+    out.push_str(&value.to_string());
+    out.push('\n');
+}
+
+fn openai_finish_reason(reason: FinishReason) -> &'static str {
+    match reason {
+        FinishReason::Stop | FinishReason::Unknown => "stop",
+        FinishReason::Length => "length",
+        FinishReason::ToolCalls => "tool_calls",
+        FinishReason::ContentFilter => "content_filter",
+    }
+}
+
+fn vercel_finish_reason(reason: FinishReason) -> &'static str {
+    match reason {
+        FinishReason::Stop => "stop",
+        FinishReason::Length => "length",
+        FinishReason::ToolCalls => "tool-calls",
+        FinishReason::ContentFilter => "content-filter",
+        FinishReason::Unknown => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ToolCallDelta;
+
+    fn tool_delta(id: Option<&str>, arguments: &str) -> StreamChunk {
+        let mut chunk = StreamChunk::empty(ChunkKind::ToolDelta);
+        chunk.tool_call_delta = Some(ToolCallDelta {
+            id: id.map(str::to_string),
+            function_name: id.map(|_| "get_weather".to_string()),
+            function_arguments: Some(arguments.to_string()),
+            ..Default::default()
+        });
+        chunk
+    }
+
+    fn usage() -> StreamChunk {
+        StreamChunk::usage(Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_encode_openai() {
+        let mut encoder =
+            Encoder::new(WireFormat::OpenAI, "claude-sonnet-4-5").with_id("chatcmpl-1");
+        let frame = encoder.chunk(&StreamChunk::text_owned("Hi".into()));
+        let frame = std::str::from_utf8(&frame).unwrap();
+        let data: Value =
+            serde_json::from_str(frame.strip_prefix("data: ").unwrap().trim_end()).unwrap();
+        assert_eq!(data["id"], "chatcmpl-1");
+        assert_eq!(data["model"], "claude-sonnet-4-5");
+        assert_eq!(
+            data["choices"][0]["delta"],
+            json!({"role": "assistant", "content": "Hi"})
+        );
+
+        let frame = encoder.chunk(&tool_delta(Some("call_1"), "{\"city\""));
+        assert!(std::str::from_utf8(&frame).unwrap().contains(
+            r#""tool_calls":[{"function":{"arguments":"{\"city\"","name":"get_weather"},"id":"call_1","index":0,"type":"function"}]"#
+        ));
+        let frame = encoder.chunk(&tool_delta(None, ":\"Paris\"}"));
+        assert!(!std::str::from_utf8(&frame).unwrap().contains("get_weather"));
+
+        let done =
+            StreamChunk::empty(ChunkKind::Unknown).with_finish_reason(FinishReason::ToolCalls);
+        assert!(std::str::from_utf8(&encoder.chunk(&done))
+            .unwrap()
+            .contains(r#""finish_reason":"tool_calls""#));
+        assert!(encoder.chunk(&usage()).is_empty());
+        let end = encoder.finish();
+        let end = std::str::from_utf8(&end).unwrap();
+        assert!(
+            end.contains(r#""usage":{"completion_tokens":5,"prompt_tokens":10,"total_tokens":15}"#)
+        );
+        assert!(end.ends_with("data: [DONE]\n\n"));
+    }
+
+    #[test]
+    fn test_encode_vercel() {
+        let mut encoder = Encoder::new(WireFormat::VercelDataStream, "gpt-4o").with_id("msg-1");
+        let mut out = Vec::new();
+        out.extend_from_slice(&encoder.chunk(&StreamChunk::text_owned("Hi".into())));
+        out.extend_from_slice(&encoder.chunk(&tool_delta(Some("call_1"), "{\"city\"")));
+        // Final arguments only forward what extends the fragments already sent
+        let mut done = StreamChunk::empty(ChunkKind::ToolDelta);
+        done.tool_call_delta = Some(ToolCallDelta {
+            final_arguments: Some(r#"{"city":"Paris"}"#.into()),
+            ..Default::default()
+        });
+        out.extend_from_slice(&encoder.chunk(&done));
+        out.extend_from_slice(&encoder.chunk(&usage().with_finish_reason(FinishReason::ToolCalls)));
+        out.extend_from_slice(&encoder.finish());
+
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            concat!(
+                "f:{\"messageId\":\"msg-1\"}\n",
+                "0:\"Hi\"\n",
+                "b:{\"toolCallId\":\"call_1\",\"toolName\":\"get_weather\"}\n",
+                "c:{\"argsTextDelta\":\"{\\\"city\\\"\",\"toolCallId\":\"call_1\"}\n",
+                "c:{\"argsTextDelta\":\":\\\"Paris\\\"}\",\"toolCallId\":\"call_1\"}\n",
+                "9:{\"args\":{\"city\":\"Paris\"},\"toolCallId\":\"call_1\",\"toolName\":\"get_weather\"}\n",
+                "e:{\"finishReason\":\"tool-calls\",\"isContinued\":false,\"usage\":{\"completionTokens\":5,\"promptTokens\":10}}\n",
+                "d:{\"finishReason\":\"tool-calls\",\"usage\":{\"completionTokens\":5,\"promptTokens\":10}}\n",
+            )
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[tokio::test]
+    async fn test_encode_stream() {
+        use futures::StreamExt;
+
+        let body = vec![
+            Ok(Bytes::from(
+                "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+            )),
+            Err(Error::Timeout),
+        ];
+        let stream = CompletionStream::new(
+            futures::stream::iter(body),
+            Box::new(crate::providers::cerebras::CerebrasParser::new()),
+            "llama3.1-8b".to_string(),
+        );
+        let frames: Vec<Bytes> = encode(stream, WireFormat::VercelDataStream)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].ends_with(b"0:\"Hello\"\n"));
+        assert_eq!(frames[1], "3:\"timeout\"\n");
+    }
+}
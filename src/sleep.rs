@@ -0,0 +1,35 @@
+//! Pluggable timer for retry backoff.
+//!
+//! The client waits out retry backoffs through a [`Sleeper`]. The default
+//! [`TokioSleeper`] sleeps on tokio's clock; plug in another runtime's timer,
+//! or one that returns at once and records the delays so tests can check a
+//! retry schedule without waiting for it, with
+//! [`ClientBuilder::sleeper`](crate::ClientBuilder::sleeper).
+
+use futures::future::BoxFuture;
+use std::time::Duration;
+
+/// Waits for a duration.
+///
+/// Equivalent to `async fn sleep(&self, duration: Duration)`, boxed so the
+/// sleeper can be shared as `Arc<dyn Sleeper>`.
+pub trait Sleeper: Send + Sync {
+    /// Resolve after `duration`.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+impl<T: Sleeper + ?Sized> Sleeper for std::sync::Arc<T> {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        (**self).sleep(duration)
+    }
+}
+
+/// Default sleeper backed by `tokio::time::sleep`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioSleeper;
+
+impl Sleeper for TokioSleeper {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
@@ -5,9 +5,17 @@
 //! - Multi-line data fields
 //! - CRLF and LF line endings
 //! - Buffer compaction to prevent unbounded growth
+//! - Buffer reuse across parsers through the [`pool`]
+//! - Oversized events in bounded segments ([`SseParser::segmented`])
+//!
+//! [`SseStream`] adapts the parser to any byte stream, for use outside the SDK.
 
-use bytes::{Buf, BytesMut};
+use crate::pool;
+use bytes::{Buf, Bytes, BytesMut};
+use futures::Stream;
 use memchr::memchr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 /// A parsed SSE event with zero-copy views into the buffer.
 #[derive(Debug)]
@@ -15,6 +23,37 @@ pub struct SseEvent<'a> {
     pub event: Option<&'a str>,
     pub data: &'a str,
     pub id: Option<&'a str>,
+    /// Shared buffer holding exactly `data`, for single-line events.
+    ///
+    /// Parsers can keep slices of it (see
+    /// [`StreamChunk::text_shared`](crate::types::StreamChunk::text_shared))
+    /// instead of copying strings out of `data`.
+    pub frame: Option<&'a Bytes>,
+    /// More of the event's data follows in the next events; only set by
+    /// [`SseParser::segmented`] parsers.
+    pub partial: bool,
+}
+
+impl SseEvent<'_> {
+    /// Copy the event out of the parser's buffers.
+    pub fn to_owned_event(&self) -> OwnedSseEvent {
+        OwnedSseEvent {
+            event: self.event.map(str::to_string),
+            data: self.data.to_string(),
+            id: self.id.map(str::to_string),
+            partial: self.partial,
+        }
+    }
+}
+
+/// A parsed SSE event that owns its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedSseEvent {
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+    /// More of the event's data follows in the next events.
+    pub partial: bool,
 }
 
 /// Line-based SSE parser with minimal allocations.
@@ -26,8 +65,51 @@ pub struct SseParser {
     event_scratch: String,
     /// Current id being built.
     id_scratch: String,
+    /// Data of the last single-line event, split off the buffer.
+    frame: Bytes,
     /// Offset of unconsumed data in buffer.
     consumed: usize,
+    /// Set for parsers that yield data in bounded segments.
+    segmenting: Option<Segmenting>,
+}
+
+/// State of a [`SseParser::segmented`] parser within the current event.
+#[derive(Debug, Default)]
+struct Segmenting {
+    max_segment: usize,
+    /// The unconsumed buffer starts inside the value of a `data` line.
+    in_data_line: bool,
+    /// A `data` line was seen, so the next one is joined with a newline.
+    has_data: bool,
+    /// Segments of the event were returned already.
+    partial_sent: bool,
+    /// What the last call returned, to be cleared by the next.
+    returned: Returned,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Returned {
+    #[default]
+    Nothing,
+    Segment,
+    EventEnd,
+}
+
+impl Segmenting {
+    /// Enter the value of the `data` line starting `buf`, returning the bytes
+    /// before the value.
+    fn start_data_line(&mut self, buf: &[u8], data: &mut String) -> usize {
+        if self.has_data {
+            data.push('\n');
+        }
+        self.has_data = true;
+        self.in_data_line = true;
+        if buf.get(5) == Some(&b' ') {
+            6
+        } else {
+            5
+        }
+    }
 }
 
 impl SseParser {
@@ -39,14 +121,35 @@ impl SseParser {
     /// Create a new parser with specified initial capacity.
     pub fn with_capacity(cap: usize) -> Self {
         Self {
-            buffer: BytesMut::with_capacity(cap),
-            data_scratch: String::with_capacity(1024),
-            event_scratch: String::new(),
-            id_scratch: String::new(),
+            buffer: pool::take_bytes(cap),
+            data_scratch: pool::take_string(1024),
+            event_scratch: pool::take_string(0),
+            id_scratch: pool::take_string(0),
+            frame: Bytes::new(),
             consumed: 0,
+            segmenting: None,
         }
     }
 
+    /// Create a parser that yields the data of large events in segments of
+    /// about `max_segment` bytes instead of buffering whole events.
+    ///
+    /// Every segment but the last of an event is marked
+    /// [`partial`](SseEvent::partial); concatenated, they are the event's data.
+    /// Memory stays near `max_segment` plus the largest chunk fed, even for
+    /// events of many megabytes such as inline images. Segments end on
+    /// character boundaries and never share the buffer
+    /// ([`frame`](SseEvent::frame) is `None`).
+    pub fn segmented(max_segment: usize) -> Self {
+        let mut parser = Self::new();
+        parser.segmenting = Some(Segmenting {
+            // Room for any UTF-8 character
+            max_segment: max_segment.max(4),
+            ..Segmenting::default()
+        });
+        parser
+    }
+
     /// Feed bytes into the parser.
     #[inline]
     pub fn feed(&mut self, data: &[u8]) {
@@ -65,18 +168,28 @@ impl SseParser {
         }
     }
 
-    /// Try to parse the next complete event.
+    /// Try to parse the next complete event, or the next segment of one for
+    /// [`segmented`](Self::segmented) parsers.
     /// Returns `None` if more data is needed.
     pub fn next_event(&mut self) -> Option<SseEvent<'_>> {
-        // Clear scratch buffers
+        if self.segmenting.is_some() {
+            return self.next_segment();
+        }
+
+        // Clear scratch buffers; dropping the frame lets `feed` reuse its memory
+        // once chunks sharing it are gone
         self.data_scratch.clear();
         self.event_scratch.clear();
         self.id_scratch.clear();
+        self.frame = Bytes::new();
 
         let buf = &self.buffer[self.consumed..];
         let mut pos = 0;
         let mut found_blank = false;
         let mut event_end = 0;
+        // A single data line is shared from the buffer; more are joined in scratch
+        let mut data_lines = 0;
+        let mut first_data = 0..0;
 
         // Process lines until we hit a blank line
         while pos < buf.len() {
@@ -116,10 +229,19 @@ impl SseParser {
                 if let Ok(value_str) = std::str::from_utf8(value) {
                     match field {
                         b"data" => {
-                            if !self.data_scratch.is_empty() {
+                            data_lines += 1;
+                            if data_lines == 1 {
+                                first_data = pos + value_start..pos + value_start + value.len();
+                            } else {
+                                if data_lines == 2 {
+                                    // Validated when it was parsed
+                                    let first = std::str::from_utf8(&buf[first_data.clone()])
+                                        .unwrap_or_default();
+                                    self.data_scratch.push_str(first);
+                                }
                                 self.data_scratch.push('\n');
+                                self.data_scratch.push_str(value_str);
                             }
-                            self.data_scratch.push_str(value_str);
                         }
                         b"event" => {
                             self.event_scratch.clear();
@@ -142,37 +264,161 @@ impl SseParser {
             return None; // Need more data for complete event
         }
 
-        // Update consumed position
-        self.consumed += event_end;
-
         // Only return if we have data
-        if self.data_scratch.is_empty() {
+        if data_lines == 0 || (data_lines == 1 && first_data.is_empty()) {
             // Empty event, try next
+            self.consumed += event_end;
             return self.next_event();
         }
 
-        // SAFETY: We're returning references to scratch buffers that live in `self`.
-        // The returned SseEvent borrows from these scratch buffers which are cleared
-        // at the start of each next_event() call. The lifetime 'a in SseEvent<'a>
-        // is tied to the borrow of `self`, ensuring the references remain valid.
-        // The pointer casts extend the borrow to match the return lifetime.
-        #[allow(unsafe_code)]
-        Some(SseEvent {
-            event: if self.event_scratch.is_empty() {
-                None
-            } else {
-                // SAFETY: event_scratch lives in self and won't be modified until next call
-                Some(unsafe { &*(self.event_scratch.as_str() as *const str) })
-            },
-            // SAFETY: data_scratch lives in self and won't be modified until next call
-            data: unsafe { &*(self.data_scratch.as_str() as *const str) },
-            id: if self.id_scratch.is_empty() {
-                None
+        let shared = data_lines == 1;
+        if shared {
+            // Split the event off the buffer so the data can be shared
+            let base = self.consumed;
+            let event = self.buffer.split_to(base + event_end).freeze();
+            self.frame = event.slice(base + first_data.start..base + first_data.end);
+            self.consumed = 0;
+        } else {
+            self.consumed += event_end;
+        }
+
+        Some(self.current_event(shared))
+    }
+
+    /// The event in the scratch buffers, with its data in the frame if `shared`.
+    fn current_event(&self, shared: bool) -> SseEvent<'_> {
+        SseEvent {
+            event: non_empty(&self.event_scratch),
+            data: if shared {
+                // Sliced from UTF-8-validated lines
+                std::str::from_utf8(&self.frame).unwrap_or_default()
             } else {
-                // SAFETY: id_scratch lives in self and won't be modified until next call
-                Some(unsafe { &*(self.id_scratch.as_str() as *const str) })
+                &self.data_scratch
             },
-        })
+            id: non_empty(&self.id_scratch),
+            frame: shared.then_some(&self.frame),
+            partial: false,
+        }
+    }
+
+    /// [`next_event`](Self::next_event) for segmented parsers: data is copied
+    /// into the scratch buffer as it arrives and returned whenever it fills up.
+    fn next_segment(&mut self) -> Option<SseEvent<'_>> {
+        let partial = self.scan_segment()?;
+        Some(self.current_segment(partial))
+    }
+
+    /// Consume input up to the end of the next segment, returning whether
+    /// more of its event follows.
+    fn scan_segment(&mut self) -> Option<bool> {
+        let seg = self.segmenting.as_mut()?;
+        // The fields of an event last until its final segment is returned
+        match std::mem::take(&mut seg.returned) {
+            Returned::Nothing => {}
+            Returned::Segment => self.data_scratch.clear(),
+            Returned::EventEnd => {
+                *seg = Segmenting {
+                    max_segment: seg.max_segment,
+                    ..Segmenting::default()
+                };
+                self.data_scratch.clear();
+                self.event_scratch.clear();
+                self.id_scratch.clear();
+            }
+        }
+
+        loop {
+            let buf = &self.buffer[self.consumed..];
+
+            if seg.in_data_line {
+                let line_end = memchr(b'\n', buf);
+                let mut value_end = line_end.unwrap_or(buf.len());
+                // Hold back a carriage return: it may start a CRLF
+                if value_end > 0 && buf[value_end - 1] == b'\r' {
+                    value_end -= 1;
+                }
+                let room = seg.max_segment.saturating_sub(self.data_scratch.len());
+                let take = utf8_prefix(&buf[..value_end.min(room)]);
+                self.data_scratch
+                    .push_str(&String::from_utf8_lossy(&buf[..take]));
+                self.consumed += take;
+
+                match line_end {
+                    Some(line_end) if take == value_end => {
+                        self.consumed += line_end + 1 - take;
+                        seg.in_data_line = false;
+                    }
+                    // Out of room mid-line
+                    _ if value_end > room => {
+                        seg.partial_sent = true;
+                        seg.returned = Returned::Segment;
+                        return Some(true);
+                    }
+                    _ => return None,
+                }
+                continue;
+            }
+
+            let Some(line_end) = memchr(b'\n', buf) else {
+                // Stream long data lines before they end; the optional space
+                // after the colon is known from six bytes on
+                if buf.starts_with(b"data:") && buf.len() >= 6 {
+                    self.consumed += seg.start_data_line(buf, &mut self.data_scratch);
+                    continue;
+                }
+                return None;
+            };
+            let line = &buf[..line_end];
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            if line.is_empty() {
+                self.consumed += line_end + 1;
+                // Events without data, or with a lone empty data line, are
+                // skipped like unsegmented parsers do
+                if !seg.partial_sent && self.data_scratch.is_empty() {
+                    seg.has_data = false;
+                    self.event_scratch.clear();
+                    self.id_scratch.clear();
+                    continue;
+                }
+                seg.returned = Returned::EventEnd;
+                return Some(false);
+            }
+
+            if line.starts_with(b"data:") {
+                self.consumed += seg.start_data_line(buf, &mut self.data_scratch);
+                continue;
+            }
+            if let Some(colon_pos) = memchr(b':', line) {
+                let value = &line[colon_pos + 1..];
+                let value = value.strip_prefix(b" ").unwrap_or(value);
+                if let Ok(value) = std::str::from_utf8(value) {
+                    match &line[..colon_pos] {
+                        b"event" => {
+                            self.event_scratch.clear();
+                            self.event_scratch.push_str(value);
+                        }
+                        b"id" => {
+                            self.id_scratch.clear();
+                            self.id_scratch.push_str(value);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            self.consumed += line_end + 1;
+        }
+    }
+
+    /// The segment in the scratch buffers.
+    fn current_segment(&self, partial: bool) -> SseEvent<'_> {
+        SseEvent {
+            event: non_empty(&self.event_scratch),
+            data: &self.data_scratch,
+            id: non_empty(&self.id_scratch),
+            frame: None,
+            partial,
+        }
     }
 
     /// Check if the data indicates end of stream (e.g., `[DONE]`).
@@ -187,7 +433,14 @@ impl SseParser {
         self.data_scratch.clear();
         self.event_scratch.clear();
         self.id_scratch.clear();
+        self.frame = Bytes::new();
         self.consumed = 0;
+        if let Some(seg) = &mut self.segmenting {
+            *seg = Segmenting {
+                max_segment: seg.max_segment,
+                ..Segmenting::default()
+            };
+        }
     }
 
     /// Current buffer size.
@@ -196,12 +449,127 @@ impl SseParser {
     }
 }
 
+fn non_empty(s: &str) -> Option<&str> {
+    (!s.is_empty()).then_some(s)
+}
+
+/// Length of `bytes` without a UTF-8 character cut off at the end.
+fn utf8_prefix(bytes: &[u8]) -> usize {
+    // Find the last character's lead byte and check its continuation arrived
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        if byte & 0xC0 != 0x80 {
+            let width = match byte {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            return if width > back {
+                bytes.len() - back
+            } else {
+                bytes.len()
+            };
+        }
+    }
+    bytes.len()
+}
+
 impl Default for SseParser {
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl Drop for SseParser {
+    fn drop(&mut self) {
+        pool::give_bytes(std::mem::take(&mut self.buffer));
+        pool::give_string(std::mem::take(&mut self.data_scratch));
+        pool::give_string(std::mem::take(&mut self.event_scratch));
+        pool::give_string(std::mem::take(&mut self.id_scratch));
+    }
+}
+
+/// Owned SSE events parsed from a byte stream.
+///
+/// Works with any source of byte chunks, e.g. an HTTP response body:
+///
+/// ```no_run
+/// # use bytes::Bytes;
+/// # use futures::Stream;
+/// # async fn run(body: impl Stream<Item = Result<Bytes, std::io::Error>> + Unpin) -> std::io::Result<()> {
+/// use futures::StreamExt;
+/// use rust_ai_sdk::SseStream;
+///
+/// let mut events = SseStream::new(body);
+/// while let Some(event) = events.next().await {
+///     println!("{}", event?.data);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Infallible sources can be adapted with `.map(Ok::<_, std::convert::Infallible>)`.
+/// An incomplete event at the end of the stream is discarded, as the SSE spec requires.
+pub struct SseStream<S> {
+    inner: S,
+    parser: SseParser,
+    done: bool,
+}
+
+impl<S> SseStream<S> {
+    /// Parse events from `inner`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            parser: SseParser::new(),
+            done: false,
+        }
+    }
+
+    /// Parse events from `inner`, yielding large events in segments (see
+    /// [`SseParser::segmented`]).
+    pub fn segmented(inner: S, max_segment: usize) -> Self {
+        Self {
+            inner,
+            parser: SseParser::segmented(max_segment),
+            done: false,
+        }
+    }
+
+    /// Get the underlying stream back.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, B, E> Stream for SseStream<S>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    type Item = Result<OwnedSseEvent, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.parser.next_event() {
+                return Poll::Ready(Some(Ok(event.to_owned_event())));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => this.parser.feed(bytes.as_ref()),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -267,6 +635,21 @@ mod tests {
         assert_eq!(event2.data, "second");
     }
 
+    #[test]
+    fn test_frame() {
+        let mut parser = SseParser::new();
+        parser.feed(b"event: delta\ndata: single\n\ndata: multi\ndata: line\n\n");
+
+        let event = parser.next_event().unwrap();
+        assert_eq!(event.data, "single");
+        assert_eq!(event.event, Some("delta"));
+        assert_eq!(&event.frame.unwrap()[..], b"single");
+
+        let event = parser.next_event().unwrap();
+        assert_eq!(event.data, "multi\nline");
+        assert!(event.frame.is_none());
+    }
+
     #[test]
     fn test_coalesced_frames() {
         let mut parser = SseParser::new();
@@ -285,6 +668,84 @@ mod tests {
         assert!(!SseParser::is_done("data"));
     }
 
+    #[tokio::test]
+    async fn test_sse_stream() {
+        use futures::StreamExt;
+
+        let chunks = [
+            "id: 1\ndata: fir",
+            "st\n\n: comment\n\ndata: second\n\n",
+            "data: cut",
+        ];
+        let events: Vec<_> = SseStream::new(futures::stream::iter(chunks).map(Ok::<_, ()>))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].id.as_deref(), Some("1"));
+        assert_eq!(events[0].data, "first");
+        assert_eq!(events[1].data, "second");
+    }
+
+    #[test]
+    fn test_segmented() {
+        let mut parser = SseParser::segmented(8);
+        // Small events come whole, multi-line data joined
+        parser.feed(b"event: a\ndata: one\ndata: two\n\n: ping\n\n");
+        let event = parser.next_event().unwrap();
+        assert_eq!(
+            (event.event, event.data, event.partial),
+            (Some("a"), "one\ntwo", false)
+        );
+        assert!(parser.next_event().is_none());
+
+        // A large event arrives in segments as it is fed, before its line ends
+        let mut segments = Vec::new();
+        parser.feed(b"event: image\r\ndata: 0123456789ab");
+        while let Some(event) = parser.next_event() {
+            assert_eq!(event.event, Some("image"));
+            segments.push((event.data.to_string(), event.partial));
+        }
+        assert_eq!(segments, [("01234567".to_string(), true)]);
+        assert!(parser.buffer_len() <= 8);
+
+        // Segments end on character boundaries; a held-back CR ends the line
+        parser.feed("cdé€\r".as_bytes());
+        parser.feed(b"\n\r\ndata: next\n\n");
+        while let Some(event) = parser.next_event() {
+            segments.push((event.data.to_string(), event.partial));
+        }
+        assert_eq!(
+            segments,
+            [
+                ("01234567".to_string(), true),
+                ("89abcdé".to_string(), true),
+                ("€".to_string(), false),
+                ("next".to_string(), false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_segmented_stream() {
+        use futures::StreamExt;
+
+        let data = "x".repeat(100_000);
+        let body = format!("data: {data}\n\n");
+        let chunks = body.as_bytes().chunks(1400).map(Ok::<_, ()>);
+        let segments: Vec<_> = SseStream::segmented(futures::stream::iter(chunks), 4096)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert!(segments.iter().all(|s| s.data.len() <= 4096));
+        assert!(segments[..segments.len() - 1].iter().all(|s| s.partial));
+        assert!(!segments.last().unwrap().partial);
+        let joined: String = segments.iter().map(|s| s.data.as_str()).collect();
+        assert_eq!(joined, data);
+    }
+
     #[test]
     fn test_json_data() {
         let mut parser = SseParser::new();
@@ -1,30 +1,153 @@
 //! Streaming completion handler with usage accumulation.
 
 use crate::error::Error;
-use crate::sse::SseParser;
+use crate::sse::SseEvent;
 use crate::types::*;
+use crate::warning::Warning;
+
+#[cfg(feature = "prometheus")]
+use crate::metrics::RequestMetrics;
+#[cfg(feature = "client")]
+use crate::{
+    client::{ErrorHook, StallHook, UsageHook, WarningHook},
+    cost::RequestOutcome,
+    error::ErrorDetails,
+    lifecycle::InFlight,
+    sse::{OwnedSseEvent, SseParser, SseStream},
+    tokens::estimate_text,
+    usage::UsageRecorder,
+};
+#[cfg(feature = "client")]
 use bytes::Bytes;
+#[cfg(feature = "client")]
+use futures::future::{select, Either};
+#[cfg(feature = "client")]
 use futures::Stream;
-use pin_project_lite::pin_project;
+#[cfg(feature = "client")]
+use std::future::Future;
+#[cfg(feature = "client")]
+use std::pin::pin;
+#[cfg(feature = "client")]
+use std::time::{Duration, Instant};
 
-pin_project! {
-    /// A streaming completion response.
-    ///
-    /// Yields `StreamChunk` items and accumulates content/usage for finalization.
-    pub struct CompletionStream<S> {
-        #[pin]
-        inner: S,
-        parser: SseParser,
-        provider_parser: Box<dyn ProviderParser + Send>,
-        // Accumulation state
-        content: String,
-        usage: Usage,
-        finish_reason: Option<FinishReason>,
-        tool_calls: ToolCallAccumulator,
-        model: String,
-        // Stream state
-        done: bool,
-        finalized: bool,
+#[cfg(feature = "client")]
+/// A streaming completion response.
+///
+/// Yields `StreamChunk` items and accumulates content/usage for finalization.
+///
+/// Dropping the stream closes the underlying HTTP connection, which stops
+/// generation (and billing) on the provider side.
+#[allow(clippy::struct_excessive_bools)]
+pub struct CompletionStream<S> {
+    // `None` once aborted; dropping the body cancels the request
+    inner: Option<S>,
+    parser: SseParser,
+    provider_parser: Box<dyn ProviderParser + Send>,
+    // Accumulation state
+    content: String,
+    reasoning: String,
+    usage: Usage,
+    finish_reason: Option<FinishReason>,
+    tool_calls: ToolCallAccumulator,
+    server_tool_calls: Vec<ServerToolCall>,
+    logprobs: Option<Vec<TokenLogProb>>,
+    refusal: Option<String>,
+    safety_ratings: Option<Vec<SafetyRating>>,
+    citations: Option<Vec<Citation>>,
+    stop_sequence: Option<String>,
+    // Requested stop sequences, to find echoed ones at the end and trim them
+    stop_sequences: Vec<String>,
+    trim_stop: bool,
+    // Token mode: split chunks per token; split-off tokens wait here
+    token_mode: bool,
+    pending: std::collections::VecDeque<StreamChunk>,
+    // Answer bytes read, ahead of `content` while a paced stream holds chunks back
+    read_len: usize,
+    // Seen positions, when dropping resent chunks
+    dedup: Option<Dedup>,
+    // First chunk, read before the stream was handed out, or text flushed
+    // by transforms at the end; already read, so accumulated unless paced
+    peeked: Option<StreamChunk>,
+    transforms: Vec<Box<dyn ChunkTransform>>,
+    // Emission schedule, when paced
+    pace: Option<Pace>,
+    model: String,
+    request_id: Option<String>,
+    on_error: Option<ErrorHook>,
+    on_usage: Option<UsageHook>,
+    on_stall: Option<(Duration, StallHook)>,
+    on_warning: Option<WarningHook>,
+    // Last time any bytes arrived, keepalives included
+    last_activity: Instant,
+    // Client registration, released once the stream ends
+    in_flight: Option<InFlight>,
+    // Records usage once, when the stream ends or is finalized
+    usage_recorder: Option<UsageRecorder>,
+    timings: Option<RequestTimings>,
+    // Request start and response arrival, until the stream ends
+    clock: Option<(Instant, Instant)>,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<RequestMetrics>,
+    // Stream state
+    done: bool,
+    // Ended by an error or abort rather than by the provider
+    interrupted: bool,
+    // Ended by an error
+    failed: bool,
+    finalized: bool,
+}
+
+/// Rewrites chunk text before it is accumulated, so the final
+/// [`CompletionResult::content`] is the transformed text too.
+///
+/// Transforms run in the order they were added, on each chunk handed out
+/// (each token in token mode, whose byte offsets keep referring to the
+/// untransformed text).
+pub trait ChunkTransform: Send {
+    /// Rewrite the text of `chunk`, e.g. with [`StreamChunk::set_text`].
+    fn transform(&mut self, chunk: &mut StreamChunk);
+
+    /// Text held back from earlier chunks, handed out when the stream ends.
+    fn flush(&mut self) -> Option<String> {
+        None
+    }
+}
+
+/// A [`ChunkTransform`] that rewrites each chunk's text with a function.
+#[cfg(feature = "client")]
+struct MapText<F>(F);
+
+#[cfg(feature = "client")]
+impl<F: FnMut(&str) -> String + Send> ChunkTransform for MapText<F> {
+    fn transform(&mut self, chunk: &mut StreamChunk) {
+        if let Some(text) = chunk.text() {
+            let mapped = (self.0)(&text);
+            chunk.set_text(mapped);
+        }
+    }
+}
+
+/// A [`ChunkTransform`] that drops whitespace at the end of the output.
+///
+/// Trailing whitespace of each chunk is held back until more text follows.
+#[derive(Debug, Default)]
+pub struct TrimTrailingWhitespace {
+    held: String,
+}
+
+impl ChunkTransform for TrimTrailingWhitespace {
+    fn transform(&mut self, chunk: &mut StreamChunk) {
+        let Some(text) = chunk.text() else {
+            return;
+        };
+        let kept = text.trim_end();
+        let mut released = String::new();
+        if !kept.is_empty() {
+            released = std::mem::take(&mut self.held);
+            released.push_str(kept);
+        }
+        self.held.push_str(&text[kept.len()..]);
+        chunk.set_text(released);
     }
 }
 
@@ -33,34 +156,152 @@ pub trait ProviderParser: Send {
     /// Parse an SSE data payload into a StreamChunk.
     fn parse_chunk(&mut self, data: &str) -> Result<Option<StreamChunk>, Error>;
 
+    /// Parse a complete SSE event, with access to its `event:` and `id:` fields.
+    ///
+    /// Defaults to parsing the data payload with [`parse_chunk`](Self::parse_chunk).
+    fn parse_event(&mut self, event: &SseEvent<'_>) -> Result<Option<StreamChunk>, Error> {
+        self.parse_chunk(event.data)
+    }
+
     /// Check if this data indicates end of stream.
     fn is_end_of_stream(&self, data: &str) -> bool;
+
+    /// A warning raised by the last parsed event, e.g. an unknown event type
+    /// that was skipped.
+    fn take_warning(&mut self) -> Option<Warning> {
+        None
+    }
+
+    /// A further chunk parsed from the last event, for events that carry more
+    /// than one (reasoning and answer text in the same delta). Handed out
+    /// after the chunk the event returned.
+    fn take_pending(&mut self) -> Option<StreamChunk> {
+        None
+    }
 }
 
+#[cfg(feature = "client")]
 impl<S> CompletionStream<S>
 where
-    S: Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
+    S: Stream<Item = Result<Bytes, Error>> + Unpin,
 {
     /// Create a new completion stream.
     pub fn new(inner: S, parser: Box<dyn ProviderParser + Send>, model: String) -> Self {
         Self {
-            inner,
+            inner: Some(inner),
             parser: SseParser::new(),
             provider_parser: parser,
-            content: String::with_capacity(4096),
+            content: crate::pool::take_string(4096),
+            reasoning: String::new(),
             usage: Usage::default(),
             finish_reason: None,
             tool_calls: ToolCallAccumulator::default(),
+            server_tool_calls: Vec::new(),
+            logprobs: None,
+            refusal: None,
+            safety_ratings: None,
+            citations: None,
+            stop_sequence: None,
+            stop_sequences: Vec::new(),
+            trim_stop: false,
+            token_mode: false,
+            pending: std::collections::VecDeque::new(),
+            read_len: 0,
+            dedup: None,
+            peeked: None,
+            transforms: Vec::new(),
+            pace: None,
             model,
+            request_id: None,
+            on_error: None,
+            on_usage: None,
+            on_stall: None,
+            on_warning: None,
+            last_activity: Instant::now(),
+            in_flight: None,
+            usage_recorder: None,
+            timings: None,
+            clock: None,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
             done: false,
+            interrupted: false,
+            failed: false,
             finalized: false,
         }
     }
 
     /// Get the next chunk from the stream.
     pub async fn next(&mut self) -> Option<Result<StreamChunk, Error>> {
+        if self.pace.is_some() {
+            return self.next_paced().await;
+        }
+        self.read_next().await
+    }
+
+    /// Hand out chunks no faster than the pace, reading ahead meanwhile.
+    async fn next_paced(&mut self) -> Option<Result<StreamChunk, Error>> {
+        loop {
+            let token_mode = self.token_mode;
+            let pace = self.pace.as_mut()?;
+            let now = tokio::time::Instant::now();
+            if !pace.ready.is_empty() && pace.next_at <= now {
+                let item = pace.ready.pop_front()?;
+                let tokens = match &item {
+                    Ok(_) if token_mode => 1,
+                    Ok(chunk) => chunk.text().map_or(0, |text| estimate_text(&text)),
+                    Err(_) => 0,
+                };
+                pace.next_at = now + pace.per_token * tokens;
+                // Chunks count once handed out, not when read ahead
+                if pace.accumulated > 0 {
+                    pace.accumulated -= 1;
+                } else if let Ok(chunk) = &item {
+                    self.accumulate(chunk);
+                }
+                return Some(item);
+            }
+            if pace.ended {
+                if pace.ready.is_empty() {
+                    return None;
+                }
+                tokio::time::sleep_until(pace.next_at).await;
+                continue;
+            }
+
+            let deadline = (!pace.ready.is_empty()).then_some(pace.next_at);
+            let read = match deadline {
+                Some(deadline) => {
+                    let read = pin!(self.read_next());
+                    let timer = pin!(tokio::time::sleep_until(deadline));
+                    match select(read, timer).await {
+                        Either::Left((read, _)) => read,
+                        // Nothing is lost: reads only await the body between events
+                        Either::Right(_) => continue,
+                    }
+                }
+                None => self.read_next().await,
+            };
+            let pace = self.pace.as_mut()?;
+            match read {
+                Some(item) => pace.ready.push_back(item),
+                None => pace.ended = true,
+            }
+        }
+    }
+
+    /// Read the next chunk, unpaced.
+    async fn read_next(&mut self) -> Option<Result<StreamChunk, Error>> {
         use futures::StreamExt;
 
+        if let Some(chunk) = self.peeked.take() {
+            return Some(Ok(chunk));
+        }
+        if let Some(mut chunk) = self.pending.pop_front() {
+            self.apply_transforms(&mut chunk);
+            self.accumulate_read(&chunk);
+            return Some(Ok(chunk));
+        }
         if self.done {
             return None;
         }
@@ -69,86 +310,357 @@ where
             // First, try to get an event from buffered data
             if let Some(event) = self.parser.next_event() {
                 if self.provider_parser.is_end_of_stream(event.data) {
-                    self.done = true;
-                    return None;
+                    self.end();
+                    return self.peeked.take().map(Ok);
                 }
 
-                match self.provider_parser.parse_chunk(event.data) {
-                    Ok(Some(chunk)) => {
-                        self.accumulate(&chunk);
-                        return Some(Ok(chunk));
+                let parsed = self.provider_parser.parse_event(&event);
+                report_warnings(self.provider_parser.as_mut(), self.on_warning.as_ref());
+                match parsed {
+                    Ok(Some(mut chunk)) => {
+                        attach_sse_fields(&mut chunk, &event);
+                        if self.is_duplicate(&chunk) {
+                            continue;
+                        }
+                        return Some(Ok(self.emit(chunk)));
                     }
                     Ok(None) => continue, // Skip empty chunks
-                    Err(e) => return Some(Err(e)),
+                    Err(e) => {
+                        let details = ErrorDetails {
+                            status: 200,
+                            request_id: self.request_id.clone(),
+                            body: event.data.to_string(),
+                            headers: Vec::new(),
+                        };
+                        self.interrupted = true;
+                        self.failed = true;
+                        return Some(Err(self.report_error(e.with_details(details))));
+                    }
                 }
             }
 
             // Need more data from the stream
-            match self.inner.next().await {
+            let Some(inner) = self.inner.as_mut() else {
+                self.end();
+                return self.peeked.take().map(Ok);
+            };
+            let read = watch_stall(inner.next(), self.on_stall.as_ref(), self.last_activity);
+            let next = match self.in_flight.as_mut() {
+                Some(in_flight) => in_flight.or_abort(read).await,
+                None => Ok(read.await),
+            };
+            let next = match next {
+                Ok(next) => next,
+                Err(e) => {
+                    self.abort();
+                    return Some(Err(self.report_error(e)));
+                }
+            };
+            match next {
                 Some(Ok(bytes)) => {
+                    self.last_activity = Instant::now();
                     self.parser.feed(&bytes);
                 }
                 Some(Err(e)) => {
-                    self.done = true;
-                    return Some(Err(Error::Http(e)));
+                    self.interrupted = true;
+                    self.failed = true;
+                    self.mark_done();
+                    return Some(Err(self.report_error(e)));
                 }
                 None => {
                     // Stream ended - check for any remaining buffered data
                     if let Some(event) = self.parser.next_event() {
                         if !self.provider_parser.is_end_of_stream(event.data) {
-                            if let Ok(Some(chunk)) = self.provider_parser.parse_chunk(event.data) {
-                                self.accumulate(&chunk);
-                                self.done = true;
+                            let parsed = self.provider_parser.parse_event(&event);
+                            report_warnings(
+                                self.provider_parser.as_mut(),
+                                self.on_warning.as_ref(),
+                            );
+                            if let Ok(Some(mut chunk)) = parsed {
+                                attach_sse_fields(&mut chunk, &event);
+                                if self.is_duplicate(&chunk) {
+                                    self.end();
+                                    return self.peeked.take().map(Ok);
+                                }
+                                let chunk = self.emit(chunk);
+                                // Split-off tokens and queued chunks are still to come
+                                self.inner = None;
+                                if self.pending.is_empty() {
+                                    self.end();
+                                }
                                 return Some(Ok(chunk));
                             }
                         }
                     }
-                    self.done = true;
-                    return None;
+                    self.end();
+                    return self.peeked.take().map(Ok);
                 }
             }
         }
     }
 
+    /// Whether `chunk` was already handed out, when dropping resent chunks.
+    fn is_duplicate(&mut self, chunk: &StreamChunk) -> bool {
+        self.dedup
+            .as_mut()
+            .is_some_and(|dedup| dedup.is_duplicate(chunk))
+    }
+
+    /// Wait for the first chunk, which [`next`](Self::next) then returns.
+    pub(crate) async fn read_ahead(&mut self) -> Result<(), Error> {
+        match self.next().await {
+            Some(Ok(chunk)) => {
+                self.peeked = Some(chunk);
+                Ok(())
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Queue the further chunks the provider parsed from the last event.
+    fn queue_pending(&mut self) {
+        while let Some(chunk) = self.provider_parser.take_pending() {
+            if self.token_mode && chunk.kind != ChunkKind::Thinking {
+                let queued: usize = self
+                    .pending
+                    .iter()
+                    .filter(|queued| queued.kind == ChunkKind::Text)
+                    .filter_map(|queued| queued.text().map(|text| text.len()))
+                    .sum();
+                self.pending
+                    .extend(chunk.into_tokens(self.read_len + queued));
+            } else {
+                self.pending.push_back(chunk);
+            }
+        }
+    }
+
+    /// Hand out a parsed chunk, split per token in token mode, and queue the
+    /// further chunks its event carried.
+    fn emit(&mut self, chunk: StreamChunk) -> StreamChunk {
+        let mut chunk = if self.token_mode && chunk.kind != ChunkKind::Thinking {
+            self.pending.extend(chunk.into_tokens(self.read_len));
+            self.pending
+                .pop_front()
+                .unwrap_or_else(|| StreamChunk::empty(ChunkKind::Unknown))
+        } else {
+            chunk
+        };
+        self.apply_transforms(&mut chunk);
+        self.accumulate_read(&chunk);
+        self.queue_pending();
+        chunk
+    }
+
+    fn apply_transforms(&mut self, chunk: &mut StreamChunk) {
+        // Transforms rewrite the answer; reasoning passes through as is
+        if chunk.kind == ChunkKind::Thinking {
+            return;
+        }
+        for transform in &mut self.transforms {
+            transform.transform(chunk);
+        }
+    }
+
+    /// Rewrite chunk text with `transform` before it is accumulated.
+    #[must_use]
+    pub fn transform(mut self, transform: impl ChunkTransform + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Rewrite the text of each chunk with `f`, e.g. to mask words.
+    #[must_use]
+    pub fn map_text(self, f: impl FnMut(&str) -> String + Send + 'static) -> Self {
+        self.transform(MapText(f))
+    }
+
+    /// Hand out chunks at about `tokens_per_second`, e.g. for teleprompter-style
+    /// UIs or to demo a slower model. The response is still read as fast as it
+    /// arrives; chunks wait in memory until their turn. Chunk sizes are
+    /// estimated with [`estimate_text`], or one token each in token mode. A rate
+    /// that isn't positive doesn't slow the stream.
+    #[must_use]
+    pub fn pace(mut self, tokens_per_second: f64) -> Self {
+        // A chunk read ahead (hedged requests) was accumulated already
+        let ready: std::collections::VecDeque<_> = self.peeked.take().map(Ok).into_iter().collect();
+        self.pace = Some(Pace {
+            per_token: Duration::try_from_secs_f64(tokens_per_second.recip())
+                .unwrap_or(Duration::ZERO),
+            next_at: tokio::time::Instant::now(),
+            accumulated: ready.len(),
+            ready,
+            ended: false,
+        });
+        self
+    }
+
+    /// Note a chunk as read: accumulate it, unless the stream is paced and
+    /// accumulates it when handing it out.
+    fn accumulate_read(&mut self, chunk: &StreamChunk) {
+        if chunk.kind != ChunkKind::Thinking {
+            self.read_len += chunk.text().map_or(0, |text| text.len());
+        }
+        if self.pace.is_none() {
+            self.accumulate(chunk);
+        }
+    }
+
     /// Accumulate chunk data for final result.
     fn accumulate(&mut self, chunk: &StreamChunk) {
-        // Accumulate text
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &mut self.metrics {
+            metrics.chunk();
+        }
+
+        // Accumulate text, keeping reasoning apart from the answer
         if let Some(text) = chunk.text() {
-            self.content.push_str(&text);
+            if chunk.kind == ChunkKind::Thinking {
+                self.reasoning.push_str(&text);
+            } else {
+                self.content.push_str(&text);
+            }
         }
 
         // Update usage (keep latest/max)
         if let Some(usage) = &chunk.usage {
             self.usage.merge(usage);
+            if let Some(on_usage) = &self.on_usage {
+                on_usage(&self.usage);
+            }
         }
 
         // Update finish reason
         if chunk.finish_reason.is_some() {
             self.finish_reason = chunk.finish_reason;
         }
+        if chunk.stop_sequence.is_some() {
+            self.stop_sequence.clone_from(&chunk.stop_sequence);
+        }
 
         // Accumulate tool calls
         if let Some(delta) = &chunk.tool_call_delta {
             self.tool_calls.apply(delta);
         }
+
+        // A call comes again with its results; keep the latest of each
+        if let Some(call) = &chunk.server_tool_call {
+            match self.server_tool_calls.iter_mut().find(|c| c.id == call.id) {
+                Some(existing) => existing.clone_from(call),
+                None => self.server_tool_calls.push(call.clone()),
+            }
+        }
+
+        if let Some(refusal) = &chunk.refusal {
+            self.refusal
+                .get_or_insert_with(String::new)
+                .push_str(refusal);
+        }
+
+        // Ratings are reported for the output so far; keep the latest
+        if chunk.safety_ratings.is_some() {
+            self.safety_ratings.clone_from(&chunk.safety_ratings);
+        }
+        if let Some(citations) = &chunk.citations {
+            self.citations
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(citations);
+        }
+
+        if let Some(logprobs) = &chunk.logprobs {
+            self.logprobs
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(logprobs);
+        }
     }
 
     /// Finalize the stream and get the accumulated result.
     ///
-    /// Must be called after the stream is exhausted.
+    /// Must be called after the stream is exhausted; otherwise the result is
+    /// flagged [`incomplete`](CompletionResult::incomplete).
     pub fn finalize(mut self) -> Result<CompletionResult, Error> {
         if self.finalized {
             return Err(Error::StreamConsumed);
         }
         self.finalized = true;
+        self.record_usage();
+
+        let finish_reason = self.finish_reason.unwrap_or(FinishReason::Stop);
+        Ok(self.take_result(finish_reason))
+    }
+
+    /// Finalize whatever state the stream is in: after an error, an abort, or
+    /// breaking out of the read loop early.
+    ///
+    /// Unlike [`finalize_drained`](Self::finalize_drained), reads nothing more.
+    /// Unless the stream ran to its end, the result is flagged
+    /// [`incomplete`](CompletionResult::incomplete) and, without a finish reason
+    /// from the provider, reports [`FinishReason::Unknown`].
+    pub fn finalize_lossy(mut self) -> CompletionResult {
+        self.finalized = true;
+        self.record_usage();
+
+        let fallback = if self.is_incomplete() {
+            FinishReason::Unknown
+        } else {
+            FinishReason::Stop
+        };
+        let finish_reason = self.finish_reason.unwrap_or(fallback);
+        self.take_result(finish_reason)
+    }
 
-        Ok(CompletionResult {
+    /// Take everything accumulated so far, e.g. to salvage a long generation
+    /// after a mid-stream error.
+    ///
+    /// Returns the content, tool calls (arguments possibly cut off), and usage
+    /// received up to now, and resets the accumulation. Without a finish reason
+    /// from the provider, the result reports [`FinishReason::Unknown`].
+    pub fn take_partial(&mut self) -> CompletionResult {
+        // Usage leaves with the result; record it before it's gone
+        self.record_usage();
+        let finish_reason = self.finish_reason.take().unwrap_or(FinishReason::Unknown);
+        self.take_result(finish_reason)
+    }
+
+    fn take_result(&mut self, finish_reason: FinishReason) -> CompletionResult {
+        // What a paced stream still holds back stays ahead of the content
+        self.read_len = self.read_len.saturating_sub(self.content.len());
+        let mut result = CompletionResult {
             content: std::mem::take(&mut self.content),
+            reasoning: Some(std::mem::take(&mut self.reasoning)).filter(|r| !r.is_empty()),
             usage: std::mem::take(&mut self.usage),
-            model: std::mem::take(&mut self.model),
-            finish_reason: self.finish_reason.unwrap_or(FinishReason::Stop),
-            tool_calls: self.tool_calls.finalize(),
-        })
+            model: self.model.clone(),
+            finish_reason,
+            tool_calls: std::mem::take(&mut self.tool_calls).finalize(),
+            server_tool_calls: std::mem::take(&mut self.server_tool_calls),
+            logprobs: self.logprobs.take(),
+            refusal: self.refusal.take(),
+            safety_ratings: self.safety_ratings.take(),
+            citations: self.citations.take(),
+            stop_sequence: self.stop_sequence.take(),
+            incomplete: self.is_incomplete(),
+            timings: self.timings(),
+        };
+        result.resolve_stop_sequence(&self.stop_sequences, self.trim_stop);
+        result
+    }
+
+    /// Consume any unread chunks, then finalize.
+    ///
+    /// Use this instead of [`finalize`](Self::finalize) when the caller may
+    /// have stopped reading early: providers often report usage in a trailing
+    /// chunk, which `finalize` would otherwise miss.
+    pub async fn finalize_drained(mut self) -> Result<CompletionResult, Error> {
+        while let Some(chunk) = self.next().await {
+            chunk?;
+        }
+        self.finalize()
+    }
+
+    /// Model the stream is from.
+    pub fn model(&self) -> &str {
+        &self.model
     }
 
     /// Get current accumulated content without finalizing.
@@ -156,17 +668,398 @@ where
         &self.content
     }
 
+    /// Get the reasoning received so far without finalizing.
+    pub fn current_reasoning(&self) -> &str {
+        &self.reasoning
+    }
+
+    /// Get the tool calls finished so far without finalizing, e.g. to start
+    /// planning while the rest of the response streams in. Calls whose
+    /// arguments are still arriving are left out until the stream ends.
+    pub fn current_tool_calls(&self) -> Vec<ToolCall> {
+        if self.done {
+            self.tool_calls.current()
+        } else {
+            self.tool_calls.completed()
+        }
+    }
+
     /// Get current accumulated usage without finalizing.
     pub fn current_usage(&self) -> &Usage {
         &self.usage
     }
 
+    /// When the stream last received data, including keepalives such as Claude
+    /// `ping` events and SSE comments.
+    ///
+    /// Recent activity without new chunks means the model is still working.
+    pub fn last_activity(&self) -> Instant {
+        self.last_activity
+    }
+
+    /// Provider request ID from the response headers, if any.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Record the provider request ID so mid-stream errors can report it.
+    pub(crate) fn set_request_id(&mut self, request_id: Option<String>) {
+        self.request_id = request_id;
+    }
+
+    /// Record the phases up to the response headers, which arrived just now.
+    pub(crate) fn set_timings(&mut self, timings: RequestTimings, started: Instant) {
+        self.timings = Some(timings);
+        self.clock = Some((started, Instant::now()));
+    }
+
+    /// Register the client's error hook for mid-stream failures.
+    pub(crate) fn set_error_hook(&mut self, hook: Option<ErrorHook>) {
+        self.on_error = hook;
+    }
+
+    /// Register the client's warning hook for warnings raised mid-stream.
+    pub(crate) fn set_warning_hook(&mut self, hook: Option<WarningHook>) {
+        self.on_warning = hook;
+    }
+
+    /// Register a callback for usage updates.
+    pub(crate) fn set_usage_hook(&mut self, hook: Option<UsageHook>) {
+        self.on_usage = hook;
+    }
+
+    /// Requested stop sequences, to detect and optionally trim an echoed one.
+    pub(crate) fn set_stop_sequences(&mut self, stop: Vec<String>, trim: bool) {
+        self.stop_sequences = stop;
+        self.trim_stop = trim;
+    }
+
+    /// Emit one chunk per model token, using the chunks' logprobs.
+    pub(crate) fn set_token_mode(&mut self, enabled: bool) {
+        self.token_mode = enabled;
+    }
+
+    /// Drop chunks resent by a gateway, recognized by their sequence number or SSE id.
+    pub(crate) fn set_dedup(&mut self, enabled: bool) {
+        self.dedup = enabled.then(Dedup::default);
+    }
+
+    /// Register a callback for periods without data.
+    pub(crate) fn set_stall_hook(&mut self, after: Duration, hook: StallHook) {
+        self.on_stall = Some((after, hook));
+    }
+
+    /// Notify the error hook, if any, and hand the error back.
+    fn report_error(&self, error: Error) -> Error {
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &self.metrics {
+            metrics.error(&error);
+        }
+        if let Some(on_error) = &self.on_error {
+            on_error(&error);
+        }
+        error
+    }
+
+    /// Cancel the request immediately.
+    ///
+    /// Closes the connection without reading the rest of the response, so the
+    /// provider stops generating. Content received so far stays available via
+    /// [`current_content`](Self::current_content) and [`finalize`](Self::finalize).
+    pub fn abort(&mut self) {
+        if !self.done {
+            self.interrupted = true;
+        }
+        self.inner = None;
+        self.pending.clear();
+        self.mark_done();
+    }
+
+    /// Mark the stream as ended normally; text held back by transforms is
+    /// handed out next.
+    fn end(&mut self) {
+        let mut text = String::new();
+        for transform in &mut self.transforms {
+            if !text.is_empty() {
+                let mut chunk = StreamChunk::text_owned(std::mem::take(&mut text));
+                transform.transform(&mut chunk);
+                text = chunk.into_text().unwrap_or_default();
+            }
+            text.extend(transform.flush());
+        }
+        if !text.is_empty() {
+            let chunk = StreamChunk::text_owned(text);
+            self.accumulate_read(&chunk);
+            self.peeked = Some(chunk);
+        }
+        self.mark_done();
+    }
+
+    /// End the stream and release its client registration.
+    fn mark_done(&mut self) {
+        self.done = true;
+        self.in_flight = None;
+        // Stop the clock
+        self.timings = self.timings();
+        self.clock = None;
+        self.record_usage();
+    }
+
+    /// Maintain the client's Prometheus metrics for this stream.
+    #[cfg(feature = "prometheus")]
+    pub(crate) fn set_metrics(&mut self, metrics: Option<RequestMetrics>) {
+        self.metrics = metrics;
+    }
+
+    /// Record usage in the client's usage store when the stream ends.
+    pub(crate) fn set_usage_recorder(&mut self, recorder: Option<UsageRecorder>) {
+        self.usage_recorder = recorder;
+    }
+
+    /// Register the stream as in flight until it ends or is dropped.
+    pub(crate) fn set_in_flight(&mut self, in_flight: InFlight) {
+        self.in_flight = Some(in_flight);
+    }
+
     /// Check if stream is done.
     pub fn is_done(&self) -> bool {
         self.done
     }
 }
 
+#[cfg(feature = "client")]
+impl<S> CompletionStream<S> {
+    /// Where the request's time went, for streams sent by the client; while the
+    /// stream runs, its duration and the total count up to now.
+    pub fn timings(&self) -> Option<RequestTimings> {
+        let mut timings = self.timings?;
+        if let Some((started, response_at)) = self.clock {
+            timings.stream = response_at.elapsed();
+            timings.total = started.elapsed();
+        }
+        Some(timings)
+    }
+
+    fn is_incomplete(&self) -> bool {
+        self.interrupted || !self.done
+    }
+
+    fn record_usage(&mut self) {
+        // Chunks a paced stream holds back were generated, and are billed, anyway
+        let mut usage = self.usage.clone();
+        let mut finish_reason = self.finish_reason;
+        let held_back = self
+            .pace
+            .iter()
+            .flat_map(|pace| pace.ready.iter().skip(pace.accumulated))
+            .flatten();
+        for chunk in held_back {
+            if let Some(held) = &chunk.usage {
+                usage.merge(held);
+            }
+            finish_reason = chunk.finish_reason.or(finish_reason);
+        }
+
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = self.metrics.take() {
+            metrics.usage(&usage);
+            if let Some(timings) = self.timings() {
+                metrics.timings(&timings);
+            }
+        }
+        if let Some(recorder) = self.usage_recorder.take() {
+            let outcome = if self.failed {
+                RequestOutcome::Error
+            } else if self.is_incomplete() {
+                RequestOutcome::Cancelled
+            } else {
+                RequestOutcome::Success
+            };
+            recorder.record(
+                &self.model,
+                &usage,
+                finish_reason,
+                self.request_id.as_deref(),
+                true,
+                outcome,
+            );
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl<S> Drop for CompletionStream<S> {
+    fn drop(&mut self) {
+        // A stream dropped before its end is still billed for what it generated
+        self.record_usage();
+        // Finalized content has moved into the result; reuse what's left
+        crate::pool::give_string(std::mem::take(&mut self.content));
+    }
+}
+
+/// Pass the parser's warnings to the hook, or drop them.
+#[cfg(feature = "client")]
+fn report_warnings(parser: &mut dyn ProviderParser, on_warning: Option<&WarningHook>) {
+    while let Some(warning) = parser.take_warning() {
+        if let Some(on_warning) = on_warning {
+            on_warning(&warning);
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+/// Emission schedule of a paced stream.
+struct Pace {
+    per_token: Duration,
+    // When the next chunk may be handed out
+    next_at: tokio::time::Instant,
+    // Chunks read ahead, waiting for their turn
+    ready: std::collections::VecDeque<Result<StreamChunk, Error>>,
+    // How many chunks at the front of `ready` were accumulated when read
+    accumulated: usize,
+    ended: bool,
+}
+
+#[cfg(feature = "client")]
+/// Await `read`, calling the stall hook for every `after` without data.
+async fn watch_stall<F: Future>(
+    read: F,
+    on_stall: Option<&(Duration, StallHook)>,
+    last_activity: Instant,
+) -> F::Output {
+    let Some((after, hook)) = on_stall else {
+        return read.await;
+    };
+
+    let mut read = pin!(read);
+    let mut deadline = last_activity + *after;
+    loop {
+        let timer = pin!(tokio::time::sleep_until(deadline.into()));
+        match select(read.as_mut(), timer).await {
+            Either::Left((output, _)) => return output,
+            Either::Right(_) => {
+                hook(last_activity.elapsed());
+                deadline = Instant::now() + *after;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+/// Positions of the chunks handed out so far.
+///
+/// Gateways that reconnect upstream may resend events from before the break.
+/// Numbered events (OpenAI `sequence_number`) are dropped unless they come
+/// after the last one; otherwise events with an SSE id already seen are
+/// dropped. Chunks with neither pass through.
+#[derive(Default)]
+struct Dedup {
+    last_sequence: Option<u64>,
+    seen_ids: std::collections::HashSet<String>,
+}
+
+#[cfg(feature = "client")]
+impl Dedup {
+    fn is_duplicate(&mut self, chunk: &StreamChunk) -> bool {
+        if let Some(sequence) = chunk.sequence_number {
+            if self.last_sequence.is_some_and(|last| sequence <= last) {
+                return true;
+            }
+            self.last_sequence = Some(sequence);
+            return false;
+        }
+        chunk
+            .sse_id
+            .as_ref()
+            .is_some_and(|id| !self.seen_ids.insert(id.clone()))
+    }
+}
+
+#[cfg(feature = "client")]
+/// Copy the SSE `event:`/`id:` fields onto a chunk, unless the parser set them.
+fn attach_sse_fields(chunk: &mut StreamChunk, event: &SseEvent<'_>) {
+    if chunk.sse_event.is_none() {
+        chunk.sse_event = event.event.map(str::to_string);
+    }
+    if chunk.sse_id.is_none() {
+        chunk.sse_id = event.id.map(str::to_string);
+    }
+}
+
+#[cfg(feature = "client")]
+/// SSE events of a streaming response, without provider interpretation.
+///
+/// Returned by [`Client::stream_raw`](crate::Client::stream_raw). Dropping the
+/// stream closes the connection.
+pub struct RawStream<S> {
+    // `None` once ended or aborted
+    inner: Option<SseStream<S>>,
+    request_id: Option<String>,
+    in_flight: Option<InFlight>,
+}
+
+#[cfg(feature = "client")]
+impl<S> RawStream<S>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Unpin,
+{
+    /// Create a raw stream over a response body.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner: Some(SseStream::new(inner)),
+            request_id: None,
+            in_flight: None,
+        }
+    }
+
+    /// Get the next event from the stream.
+    pub async fn next(&mut self) -> Option<Result<OwnedSseEvent, Error>> {
+        use futures::StreamExt;
+
+        let inner = self.inner.as_mut()?;
+        let next = match self.in_flight.as_mut() {
+            Some(in_flight) => in_flight.or_abort(inner.next()).await,
+            None => Ok(inner.next().await),
+        };
+        match next {
+            Ok(Some(Ok(event))) => Some(Ok(event)),
+            Ok(Some(Err(e))) => {
+                self.abort();
+                Some(Err(e))
+            }
+            Ok(None) => {
+                self.abort();
+                None
+            }
+            Err(e) => {
+                self.abort();
+                Some(Err(e))
+            }
+        }
+    }
+
+    /// Provider request ID from the response headers, if any.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    pub(crate) fn set_request_id(&mut self, request_id: Option<String>) {
+        self.request_id = request_id;
+    }
+
+    /// Register the stream as in flight until it ends or is dropped.
+    pub(crate) fn set_in_flight(&mut self, in_flight: InFlight) {
+        self.in_flight = Some(in_flight);
+    }
+
+    /// Close the connection immediately.
+    pub fn abort(&mut self) {
+        self.inner = None;
+        self.in_flight = None;
+    }
+}
+
+#[cfg(feature = "client")]
 /// Builder for CompletionStream that allows custom configuration.
 pub struct StreamBuilder<S> {
     inner: S,
@@ -175,9 +1068,10 @@ pub struct StreamBuilder<S> {
     content_capacity: usize,
 }
 
+#[cfg(feature = "client")]
 impl<S> StreamBuilder<S>
 where
-    S: Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
+    S: Stream<Item = Result<Bytes, Error>> + Unpin,
 {
     /// Create a new stream builder.
     pub fn new(inner: S, parser: Box<dyn ProviderParser + Send>, model: String) -> Self {
@@ -198,12 +1092,12 @@ where
     /// Build the CompletionStream.
     pub fn build(self) -> CompletionStream<S> {
         let mut stream = CompletionStream::new(self.inner, self.parser, self.model);
-        stream.content = String::with_capacity(self.content_capacity);
+        stream.content.reserve(self.content_capacity);
         stream
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client"))]
 mod tests {
     use super::*;
 
@@ -257,4 +1151,455 @@ mod tests {
         assert_eq!(result.usage.input_tokens, 10);
         assert_eq!(result.usage.output_tokens, 5);
     }
+
+    #[tokio::test]
+    async fn test_token_mode() {
+        let chunks = vec![
+            Ok(Bytes::from(concat!(
+                r#"data: {"choices":[{"delta":{"content":"Hello world"},"logprobs":{"content":["#,
+                r#"{"token":"Hello","logprob":-0.1},{"token":" world","logprob":-0.7}]},"#,
+                r#""finish_reason":"stop"}]}"#,
+                "\n\n"
+            ))),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let mut completion = CompletionStream::new(
+            futures::stream::iter(chunks),
+            Box::new(crate::providers::cerebras::CerebrasParser::new()),
+            "test-model".to_string(),
+        );
+        completion.set_token_mode(true);
+
+        let mut tokens = Vec::new();
+        while let Some(chunk) = completion.next().await {
+            let chunk = chunk.unwrap();
+            tokens.push((
+                chunk.text().unwrap().into_owned(),
+                chunk.byte_offset.unwrap(),
+                chunk.finish_reason,
+            ));
+        }
+        assert_eq!(
+            tokens,
+            [
+                ("Hello".to_string(), 0, None),
+                (" world".to_string(), 5, Some(FinishReason::Stop)),
+            ]
+        );
+
+        let result = completion.finalize().unwrap();
+        assert_eq!(result.content, "Hello world");
+        assert_eq!(result.logprobs.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_kept_apart() {
+        let chunks = vec![
+            Ok(Bytes::from(concat!(
+                r#"data: {"choices":[{"delta":{"content":null,"reasoning_content":"Think "}}]}"#,
+                "\n\n",
+                r#"data: {"choices":[{"delta":{"content":null,"reasoning_content":"twice"}}]}"#,
+                "\n\n",
+                r#"data: {"choices":[{"delta":{"content":"Done ","reasoning_content":null},"finish_reason":"stop"}]}"#,
+                "\n\n",
+            ))),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let mut completion = CompletionStream::new(
+            futures::stream::iter(chunks),
+            Box::new(crate::providers::cerebras::CerebrasParser::new()),
+            "test-model".to_string(),
+        )
+        .transform(TrimTrailingWhitespace::default());
+
+        let mut kinds = Vec::new();
+        while let Some(chunk) = completion.next().await {
+            kinds.push(chunk.unwrap().kind);
+        }
+        assert_eq!(kinds[..2], [ChunkKind::Thinking, ChunkKind::Thinking]);
+        assert_eq!(completion.current_reasoning(), "Think twice");
+
+        let result = completion.finalize().unwrap();
+        // The transform only trims the answer
+        assert_eq!(result.content, "Done");
+        assert_eq!(result.reasoning.as_deref(), Some("Think twice"));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_and_answer_in_one_delta() {
+        let chunks = vec![
+            Ok(Bytes::from(concat!(
+                r#"data: {"choices":[{"delta":{"reasoning_content":"Think"}}]}"#,
+                "\n\n",
+                r#"data: {"choices":[{"delta":{"content":"Done","reasoning_content":" twice"}}]}"#,
+                "\n\n",
+                r#"data: {"choices":[{"delta":{"content":"!"},"finish_reason":"stop"}]}"#,
+                "\n\n",
+            ))),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let mut completion = CompletionStream::new(
+            futures::stream::iter(chunks),
+            Box::new(crate::providers::cerebras::CerebrasParser::new()),
+            "test-model".to_string(),
+        );
+
+        let mut kinds = Vec::new();
+        while let Some(chunk) = completion.next().await {
+            kinds.push(chunk.unwrap().kind);
+        }
+        assert_eq!(
+            kinds,
+            [
+                ChunkKind::Thinking,
+                ChunkKind::Thinking,
+                ChunkKind::Text,
+                ChunkKind::Text
+            ]
+        );
+
+        let result = completion.finalize().unwrap();
+        assert_eq!(result.reasoning.as_deref(), Some("Think twice"));
+        assert_eq!(result.content, "Done!");
+    }
+
+    #[tokio::test]
+    async fn test_take_partial_after_error() {
+        let chunks = vec![
+            Ok(Bytes::from("data: text:Hello\n\n")),
+            Ok(Bytes::from("data: usage\n\n")),
+            Err(Error::Timeout),
+        ];
+        let mut completion = CompletionStream::new(
+            futures::stream::iter(chunks),
+            Box::new(TestParser),
+            "test-model".to_string(),
+        );
+
+        let err = loop {
+            match completion.next().await.unwrap() {
+                Ok(_) => {}
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(err, Error::Timeout));
+
+        let partial = completion.take_partial();
+        assert_eq!(partial.content, "Hello");
+        assert_eq!(partial.usage.output_tokens, 5);
+        assert_eq!(partial.finish_reason, FinishReason::Unknown);
+        assert!(partial.incomplete);
+        assert!(completion.current_content().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_current_tool_calls() {
+        let delta = |tool_call: &str| {
+            Ok(Bytes::from(format!(
+                "data: {{\"choices\":[{{\"index\":0,\"delta\":{{\"tool_calls\":[{tool_call}]}}}}]}}\n\n"
+            )))
+        };
+        let chunks = vec![
+            delta(r#"{"index":0,"id":"call_1","function":{"name":"lookup","arguments":""}}"#),
+            delta(r#"{"index":0,"function":{"arguments":"{\"q\":"}}"#),
+            delta(r#"{"index":0,"function":{"arguments":"\"a\"}"}}"#),
+            delta(r#"{"index":1,"id":"call_2","function":{"name":"now","arguments":""}}"#),
+            Ok(Bytes::from(
+                "data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\ndata: [DONE]\n\n",
+            )),
+        ];
+        let mut completion = CompletionStream::new(
+            futures::stream::iter(chunks),
+            Box::new(crate::providers::cerebras::CerebrasParser::new()),
+            "test-model".to_string(),
+        );
+
+        completion.next().await.unwrap().unwrap();
+        completion.next().await.unwrap().unwrap();
+        assert!(completion.current_tool_calls().is_empty());
+
+        // Whole JSON arguments: finished before the next call starts
+        completion.next().await.unwrap().unwrap();
+        let calls = completion.current_tool_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].function.arguments, r#"{"q":"a"}"#);
+
+        // A call without arguments yet is held back until the stream ends
+        completion.next().await.unwrap().unwrap();
+        assert_eq!(completion.current_tool_calls().len(), 1);
+        while completion.next().await.is_some() {}
+        let calls = completion.current_tool_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1].id, "call_2");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pace() {
+        let chunks = vec![
+            Ok(Bytes::from("data: text:Hello\n\n")),
+            Ok(Bytes::from("data: text: big\n\n")),
+            Ok(Bytes::from("data: text: world\n\n")),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let mut completion = CompletionStream::new(
+            futures::stream::iter(chunks),
+            Box::new(TestParser),
+            "test-model".to_string(),
+        )
+        .pace(4.0);
+
+        let start = tokio::time::Instant::now();
+        let mut at = Vec::new();
+        while let Some(chunk) = completion.next().await {
+            chunk.unwrap();
+            at.push(start.elapsed());
+            // The rest was read ahead, but only what was handed out counts
+            if at.len() == 2 {
+                assert_eq!(completion.current_content(), "Hello big");
+            }
+        }
+        // "Hello" is two tokens at four per second, " big" one
+        assert_eq!(
+            at,
+            [
+                Duration::ZERO,
+                Duration::from_millis(500),
+                Duration::from_millis(750)
+            ]
+        );
+        assert_eq!(completion.finalize().unwrap().content, "Hello big world");
+    }
+
+    #[tokio::test]
+    async fn test_finalize_lossy() {
+        let chunks = || {
+            futures::stream::iter(vec![
+                Ok(Bytes::from("data: text:Hello\n\n")),
+                Ok(Bytes::from("data: text: World\n\n")),
+                Ok(Bytes::from("data: [DONE]\n\n")),
+            ])
+        };
+
+        // Early break
+        let mut completion =
+            CompletionStream::new(chunks(), Box::new(TestParser), "test-model".to_string());
+        completion.next().await.unwrap().unwrap();
+        let result = completion.finalize_lossy();
+        assert_eq!(result.content, "Hello");
+        assert!(result.incomplete);
+        assert_eq!(result.finish_reason, FinishReason::Unknown);
+
+        // Fully drained
+        let mut completion =
+            CompletionStream::new(chunks(), Box::new(TestParser), "test-model".to_string());
+        while completion.next().await.is_some() {}
+        let result = completion.finalize_lossy();
+        assert_eq!(result.content, "Hello World");
+        assert!(!result.incomplete);
+        assert_eq!(result.finish_reason, FinishReason::Stop);
+    }
+
+    #[tokio::test]
+    async fn test_finalize_drained() {
+        let chunks = vec![
+            Ok(Bytes::from("data: text:Hello\n\n")),
+            Ok(Bytes::from("data: text: World\n\n")),
+            Ok(Bytes::from("data: usage\n\n")),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let stream = futures::stream::iter(chunks);
+
+        let mut completion =
+            CompletionStream::new(stream, Box::new(TestParser), "test-model".to_string());
+
+        // Stop after the first chunk
+        completion.next().await.unwrap().unwrap();
+
+        let result = completion.finalize_drained().await.unwrap();
+        assert_eq!(result.content, "Hello World");
+        assert_eq!(result.usage.input_tokens, 10);
+    }
+
+    #[tokio::test]
+    async fn test_sse_fields() {
+        let chunks = vec![
+            Ok(Bytes::from("event: delta\nid: 41\ndata: text:Hi\n\n")),
+            Ok(Bytes::from("data: text: there\n\n")),
+        ];
+        let stream = futures::stream::iter(chunks);
+
+        let mut completion =
+            CompletionStream::new(stream, Box::new(TestParser), "test-model".to_string());
+
+        let chunk = completion.next().await.unwrap().unwrap();
+        assert_eq!(chunk.sse_event.as_deref(), Some("delta"));
+        assert_eq!(chunk.sse_id.as_deref(), Some("41"));
+
+        let chunk = completion.next().await.unwrap().unwrap();
+        assert!(chunk.sse_event.is_none() && chunk.sse_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dedup() {
+        // The gateway reconnected after event 2 and resent events 1 and 2
+        let chunks = || {
+            futures::stream::iter(vec![
+                Ok(Bytes::from("id: 1\ndata: text:Hello\n\n")),
+                Ok(Bytes::from("id: 2\ndata: text: big\n\n")),
+                Ok(Bytes::from("id: 1\ndata: text:Hello\n\n")),
+                Ok(Bytes::from("id: 2\ndata: text: big\n\n")),
+                Ok(Bytes::from("id: 3\ndata: text: world\n\n")),
+                Ok(Bytes::from("data: [DONE]\n\n")),
+            ])
+        };
+
+        let mut completion =
+            CompletionStream::new(chunks(), Box::new(TestParser), "test-model".to_string());
+        completion.set_dedup(true);
+        while completion.next().await.is_some() {}
+        assert_eq!(completion.current_content(), "Hello big world");
+
+        let mut completion =
+            CompletionStream::new(chunks(), Box::new(TestParser), "test-model".to_string());
+        while completion.next().await.is_some() {}
+        assert_eq!(completion.current_content(), "Hello bigHello big world");
+    }
+
+    #[tokio::test]
+    async fn test_transforms() {
+        let chunks = vec![
+            Ok(Bytes::from("data: text:Hello darn \n\n")),
+            Ok(Bytes::from("data: text: \n\n")),
+            Ok(Bytes::from("data: text:world \n\n")),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+
+        let mut completion = CompletionStream::new(
+            futures::stream::iter(chunks),
+            Box::new(TestParser),
+            "test-model".to_string(),
+        )
+        .map_text(|text| text.replace("darn", "****"))
+        .transform(TrimTrailingWhitespace::default());
+
+        let mut streamed = String::new();
+        while let Some(chunk) = completion.next().await {
+            streamed.push_str(&chunk.unwrap().text().unwrap_or_default());
+        }
+        assert_eq!(streamed, "Hello ****  world");
+        assert_eq!(completion.finalize().unwrap().content, streamed);
+    }
+
+    #[tokio::test]
+    async fn test_usage_hook() {
+        let chunks = vec![
+            Ok(Bytes::from("data: text:Hello\n\n")),
+            Ok(Bytes::from("data: usage\n\n")),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let stream = futures::stream::iter(chunks);
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut completion =
+            CompletionStream::new(stream, Box::new(TestParser), "test-model".to_string());
+        completion.set_usage_hook(Some(std::sync::Arc::new({
+            let seen = seen.clone();
+            move |usage: &Usage| seen.lock().unwrap().push(usage.output_tokens)
+        })));
+
+        // Only the chunk carrying usage triggers the hook, before the stream ends
+        completion.next().await.unwrap().unwrap();
+        assert!(seen.lock().unwrap().is_empty());
+        completion.next().await.unwrap().unwrap();
+        assert_eq!(*seen.lock().unwrap(), [5]);
+    }
+
+    #[tokio::test]
+    async fn test_stall_hook() {
+        // A keepalive, then a gap long enough to count as a stall
+        let body = futures::stream::unfold(0, |step| async move {
+            let bytes = match step {
+                0 => ": keepalive\n\n",
+                1 => "data: text:Hi\n\n",
+                2 => {
+                    tokio::time::sleep(Duration::from_millis(150)).await;
+                    "data: text: there\n\n"
+                }
+                _ => return None,
+            };
+            Some((Ok(Bytes::from(bytes)), step + 1))
+        });
+
+        let stalls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut completion = CompletionStream::new(
+            Box::pin(body),
+            Box::new(TestParser),
+            "test-model".to_string(),
+        );
+        completion.set_stall_hook(Duration::from_millis(50), {
+            let stalls = stalls.clone();
+            std::sync::Arc::new(move |_| {
+                stalls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            })
+        });
+
+        completion.next().await.unwrap().unwrap();
+        assert_eq!(stalls.load(std::sync::atomic::Ordering::SeqCst), 0);
+        let before = completion.last_activity();
+
+        completion.next().await.unwrap().unwrap();
+        assert!(stalls.load(std::sync::atomic::Ordering::SeqCst) >= 1);
+        assert!(completion.last_activity() > before);
+    }
+
+    #[tokio::test]
+    async fn test_raw_stream() {
+        let chunks = vec![
+            Ok(Bytes::from("event: message_start\ndata: {\"a\"")),
+            Ok(Bytes::from(":1}\n\n: ping\n\ndata: [DONE]\n\n")),
+        ];
+        let mut raw = RawStream::new(futures::stream::iter(chunks));
+
+        let event = raw.next().await.unwrap().unwrap();
+        assert_eq!(event.event.as_deref(), Some("message_start"));
+        assert_eq!(event.data, r#"{"a":1}"#);
+        assert_eq!(raw.next().await.unwrap().unwrap().data, "[DONE]");
+        assert!(raw.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_abort_drops_connection() {
+        struct Body(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+        impl Stream for Body {
+            type Item = Result<Bytes, Error>;
+
+            fn poll_next(
+                self: std::pin::Pin<&mut Self>,
+                _cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Option<Self::Item>> {
+                std::task::Poll::Ready(Some(Ok(Bytes::from("data: text:Hi\n\n"))))
+            }
+        }
+
+        impl Drop for Body {
+            fn drop(&mut self) {
+                self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        let dropped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut completion = CompletionStream::new(
+            Body(dropped.clone()),
+            Box::new(TestParser),
+            "test-model".to_string(),
+        );
+
+        completion.next().await.unwrap().unwrap();
+        completion.abort();
+
+        assert!(dropped.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(completion.next().await.is_none());
+        assert_eq!(completion.finalize().unwrap().content, "Hi");
+    }
 }
@@ -0,0 +1,120 @@
+//! Per-tenant scoping for multi-tenant services.
+//!
+//! [`Client::for_tenant`](crate::Client::for_tenant) returns a handle that
+//! shares the client's connection pool, rate limits, and settings, optionally
+//! with the tenant's own API keys ([`Client::with_api_key`](crate::Client::with_api_key)).
+//! Usage and cost of its requests accumulate in a [`Tenant`] shared by every
+//! handle for the same tenant ID; once a tenant with a budget has spent it,
+//! further requests fail with [`Error::BudgetExceeded`].
+//!
+//! Costs are known only for models with pricing; others count tokens but no cost.
+
+use crate::cost::{Cost, CostTracker, RequestOutcome};
+use crate::error::Error;
+use crate::types::Usage;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Usage, cost, and budget of one tenant.
+#[derive(Debug)]
+pub struct Tenant {
+    id: String,
+    state: Mutex<TenantState>,
+}
+
+#[derive(Debug, Default)]
+struct TenantState {
+    budget: Option<f64>,
+    costs: CostTracker,
+}
+
+impl Tenant {
+    fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            state: Mutex::new(TenantState::default()),
+        }
+    }
+
+    /// Tenant ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Limit the tenant's spending, in USD (`None` removes the limit).
+    ///
+    /// A request in progress when the budget runs out still completes, so
+    /// spending can overshoot by the cost of the requests running at the time.
+    pub fn set_budget(&self, budget_usd: Option<f64>) {
+        self.lock().budget = budget_usd;
+    }
+
+    /// Spending limit in USD, if any.
+    pub fn budget(&self) -> Option<f64> {
+        self.lock().budget
+    }
+
+    /// USD spent so far.
+    pub fn spent(&self) -> f64 {
+        self.lock().costs.total_cost()
+    }
+
+    /// USD left of the budget, if there is one.
+    pub fn remaining(&self) -> Option<f64> {
+        let state = self.lock();
+        state
+            .budget
+            .map(|budget| (budget - state.costs.total_cost()).max(0.0))
+    }
+
+    /// Usage and cost accumulated so far.
+    pub fn costs(&self) -> CostTracker {
+        self.lock().costs.clone()
+    }
+
+    /// Clear the accumulated usage and cost, e.g. at the start of a billing period.
+    pub fn reset(&self) {
+        self.lock().costs.reset();
+    }
+
+    /// Fail if the tenant has spent its budget.
+    pub(crate) fn check_budget(&self) -> Result<(), Error> {
+        let state = self.lock();
+        match state.budget {
+            Some(budget) if state.costs.total_cost() >= budget => Err(Error::BudgetExceeded {
+                tenant: self.id.clone(),
+                budget,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn record(&self, usage: &Usage, cost: Option<&Cost>, outcome: RequestOutcome) {
+        self.lock().costs.record_outcome(usage, cost, outcome);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TenantState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Tenants of a client, created on first use.
+#[derive(Debug, Default)]
+pub(crate) struct Tenants(Mutex<HashMap<String, Arc<Tenant>>>);
+
+impl Tenants {
+    pub(crate) fn get(&self, id: &str) -> Arc<Tenant> {
+        let mut tenants = self
+            .0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(tenant) = tenants.get(id) {
+            return Arc::clone(tenant);
+        }
+        let tenant = Arc::new(Tenant::new(id));
+        tenants.insert(id.to_string(), Arc::clone(&tenant));
+        tenant
+    }
+}
@@ -0,0 +1,257 @@
+//! Token estimates without a tokenizer.
+//!
+//! Byte-pair tokenizers average about four characters per token on English
+//! text and code, and close to one token per character in CJK scripts. The
+//! estimates follow that rule and err high: good enough to catch prompts that
+//! won't fit a model's context window before they are sent, not to bill by.
+//!
+//! [`truncate`] uses them to fit a conversation into a token budget.
+
+use crate::error::Error;
+use crate::providers::RequestConfig;
+use crate::types::{ContentPart, Message, MessageContent, Role};
+use std::borrow::Cow;
+
+/// Framing per message: role markers and separators.
+const MESSAGE_OVERHEAD: u32 = 4;
+
+/// An image at high detail, roughly what OpenAI and Claude charge for ~1 megapixel.
+const IMAGE_TOKENS: u32 = 1_000;
+
+/// An image at `detail: "low"` (OpenAI's fixed rate).
+const LOW_DETAIL_IMAGE_TOKENS: u32 = 85;
+
+/// Estimate the tokens of `text`.
+pub fn estimate_text(text: &str) -> u32 {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+        if c.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    u32::try_from(ascii.div_ceil(4) + other).unwrap_or(u32::MAX)
+}
+
+/// Estimate the input tokens of `messages`.
+pub fn estimate_messages(messages: &[Message]) -> u32 {
+    messages.iter().fold(0u32, |total, message| {
+        total.saturating_add(estimate_message(message))
+    })
+}
+
+/// Estimate the input tokens of a request: its messages plus the system
+/// prompt, tool definitions, and documents from `config`.
+pub fn estimate_request(messages: &[Message], config: &RequestConfig) -> u32 {
+    let mut total = estimate_messages(messages);
+    if let Some(system) = &config.system {
+        total = total.saturating_add(MESSAGE_OVERHEAD + estimate_text(system));
+    }
+    for tool in config.tools.iter().flatten() {
+        // Providers render tool definitions into the prompt, close to their JSON form
+        let definition = serde_json::to_string(&tool.function).unwrap_or_default();
+        total = total.saturating_add(estimate_text(&definition));
+    }
+    for document in config.documents.iter().flatten() {
+        total = total.saturating_add(MESSAGE_OVERHEAD + estimate_text(&document.content));
+    }
+    total
+}
+
+/// How to fit a request's input into the model's context window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncateStrategy {
+    /// Fail with [`Error::ContextLengthExceeded`].
+    Error,
+    /// Drop the oldest messages after the leading system messages, keeping the
+    /// last message and starting what remains with a user message.
+    DropOldest,
+    /// Cut the middle out of the longest texts, keeping their start and end.
+    TrimMiddle,
+}
+
+/// Replaces the text cut by [`TruncateStrategy::TrimMiddle`].
+pub const TRIM_MARKER: &str = "\n[...]\n";
+
+/// Fit `messages` into `available` input tokens, counting the system prompt,
+/// tools, and documents of `config` as fixed.
+///
+/// Returns the messages as they are if they already fit, and
+/// [`Error::ContextLengthExceeded`] if the strategy can't make them fit.
+pub fn truncate<'a>(
+    messages: &'a [Message],
+    config: &RequestConfig,
+    available: u32,
+    strategy: TruncateStrategy,
+) -> Result<Cow<'a, [Message]>, Error> {
+    let estimated = estimate_request(messages, config);
+    if estimated <= available {
+        return Ok(Cow::Borrowed(messages));
+    }
+
+    let mut fitted = messages.to_vec();
+    let fits = match strategy {
+        TruncateStrategy::Error => false,
+        TruncateStrategy::DropOldest => drop_oldest(&mut fitted, estimated - available),
+        TruncateStrategy::TrimMiddle => trim_middle(&mut fitted, config, available),
+    };
+    if fits {
+        Ok(Cow::Owned(fitted))
+    } else {
+        Err(Error::ContextLengthExceeded {
+            estimated,
+            available,
+        })
+    }
+}
+
+fn drop_oldest(messages: &mut Vec<Message>, mut excess: u32) -> bool {
+    let first = messages
+        .iter()
+        .position(|message| message.role != Role::System)
+        .unwrap_or(messages.len());
+    while excess > 0 {
+        if first + 1 >= messages.len() {
+            return false;
+        }
+        let dropped = messages.remove(first);
+        excess = excess.saturating_sub(estimate_message(&dropped));
+    }
+    // Don't open with an assistant turn or tool results whose call is gone
+    while first + 1 < messages.len() && messages[first].role != Role::User {
+        messages.remove(first);
+    }
+    true
+}
+
+fn trim_middle(messages: &mut [Message], config: &RequestConfig, available: u32) -> bool {
+    let marker = estimate_text(TRIM_MARKER);
+    let mut estimated = estimate_request(messages, config);
+    while estimated > available {
+        let excess = estimated - available;
+        let Some(text) = longest_text(messages) else {
+            return false;
+        };
+        let tokens = estimate_text(text);
+        if tokens <= excess + marker {
+            return false;
+        }
+
+        // Cut a share of the characters proportional to the tokens to shed
+        let chars = text.chars().count();
+        let cut = ((excess + marker) as usize * chars).div_ceil(tokens as usize);
+        let keep = chars.saturating_sub(cut);
+        let byte_at = |n: usize| text.char_indices().nth(n).map_or(text.len(), |(i, _)| i);
+        let (head, tail) = (byte_at(keep / 2), byte_at(chars - (keep - keep / 2)));
+        *text = format!("{}{TRIM_MARKER}{}", &text[..head], &text[tail..]);
+
+        let trimmed = estimate_request(messages, config);
+        if trimmed >= estimated {
+            return false;
+        }
+        estimated = trimmed;
+    }
+    true
+}
+
+/// The longest text of `messages`, in a message or a content part.
+fn longest_text(messages: &mut [Message]) -> Option<&mut String> {
+    messages
+        .iter_mut()
+        .flat_map(|message| -> Box<dyn Iterator<Item = &mut String>> {
+            match &mut message.content {
+                MessageContent::Text(text) => Box::new(std::iter::once(text)),
+                MessageContent::Parts(parts) => {
+                    Box::new(parts.iter_mut().filter_map(|part| match part {
+                        ContentPart::Text { text } => Some(text),
+                        ContentPart::ImageUrl { .. } => None,
+                    }))
+                }
+            }
+        })
+        .max_by_key(|text| text.len())
+}
+
+fn estimate_message(message: &Message) -> u32 {
+    let mut total = MESSAGE_OVERHEAD;
+    match &message.content {
+        MessageContent::Text(text) => total = total.saturating_add(estimate_text(text)),
+        MessageContent::Parts(parts) => {
+            for part in parts {
+                total = total.saturating_add(match part {
+                    ContentPart::Text { text } => estimate_text(text),
+                    ContentPart::ImageUrl { image_url } => {
+                        if image_url.detail.as_deref() == Some("low") {
+                            LOW_DETAIL_IMAGE_TOKENS
+                        } else {
+                            IMAGE_TOKENS
+                        }
+                    }
+                });
+            }
+        }
+    }
+    for call in message.tool_calls.iter().flatten() {
+        total = total
+            .saturating_add(estimate_text(&call.function.name))
+            .saturating_add(estimate_text(&call.function.arguments));
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates() {
+        assert_eq!(estimate_text(""), 0);
+        assert_eq!(estimate_text("Hello, world"), 3);
+        // CJK text is about a token per character
+        assert_eq!(estimate_text("你好世界"), 4);
+
+        let messages = vec![Message::system("Be brief."), Message::user("Hello, world")];
+        assert_eq!(estimate_messages(&messages), 2 * MESSAGE_OVERHEAD + 3 + 3);
+
+        let config = RequestConfig {
+            system: Some("Be brief.".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_request(&messages[1..], &config),
+            estimate_messages(&messages)
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let config = RequestConfig::default();
+        let long = "lorem ipsum ".repeat(100);
+        let messages = vec![
+            Message::system("Be brief."),
+            Message::user(long.clone()),
+            Message::assistant("Noted."),
+            Message::user("Summarize."),
+        ];
+        let all = estimate_messages(&messages);
+
+        // Fitting input is borrowed as is
+        let fitted = truncate(&messages, &config, all, TruncateStrategy::Error).unwrap();
+        assert!(matches!(fitted, Cow::Borrowed(_)));
+        let err = truncate(&messages, &config, all - 1, TruncateStrategy::Error).unwrap_err();
+        assert!(matches!(err, Error::ContextLengthExceeded { estimated, .. } if estimated == all));
+
+        // Dropping the long message would leave an assistant turn first
+        let fitted = truncate(&messages, &config, all - 1, TruncateStrategy::DropOldest).unwrap();
+        let roles: Vec<Role> = fitted.iter().map(|m| m.role).collect();
+        assert_eq!(roles, [Role::System, Role::User]);
+        assert!(truncate(&messages, &config, 10, TruncateStrategy::DropOldest).is_err());
+
+        let fitted = truncate(&messages, &config, all - 100, TruncateStrategy::TrimMiddle).unwrap();
+        assert!(estimate_messages(&fitted) <= all - 100);
+        let text = fitted[1].content.as_text().unwrap();
+        assert!(text.starts_with("lorem ipsum") && text.contains(TRIM_MARKER));
+        assert!(text.ends_with("ipsum "));
+        assert_eq!(fitted[3].content.as_text(), Some("Summarize."));
+    }
+}
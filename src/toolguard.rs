@@ -0,0 +1,200 @@
+//! Limits for tool-calling loops.
+//!
+//! The SDK doesn't run tools itself: the caller sends a request, runs the
+//! returned [`ToolCall`]s, appends their results and sends again until the
+//! model answers. A model that keeps calling tools, often the same one with
+//! the same arguments, keeps that loop going and burns tokens. Pass each
+//! turn's calls to a [`ToolCallGuard`] before running them; it fails with
+//! [`Error::AgentLoopDetected`] once a limit is hit.
+//!
+//! ```
+//! use rust_ai_sdk::{Error, FunctionCall, ToolCall, ToolCallGuard};
+//!
+//! let call = ToolCall {
+//!     id: "call_1".into(),
+//!     tool_type: "function".into(),
+//!     function: FunctionCall {
+//!         name: "search".into(),
+//!         arguments: r#"{"q": "rust"}"#.into(),
+//!     },
+//! };
+//! let mut guard = ToolCallGuard::new().max_calls(10).max_repeats(2);
+//! assert!(guard.check(std::slice::from_ref(&call)).is_ok());
+//! assert!(guard.check(std::slice::from_ref(&call)).is_ok());
+//! // A third identical call
+//! assert!(matches!(
+//!     guard.check(&[call]),
+//!     Err(Error::AgentLoopDetected(_))
+//! ));
+//! ```
+
+use crate::error::Error;
+use crate::types::ToolCall;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Call limits for one run of a tool-calling loop.
+///
+/// No limits are set by default.
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallGuard {
+    max_calls: Option<u32>,
+    tool_limits: HashMap<String, u32>,
+    max_repeats: Option<u32>,
+    calls: u32,
+    tool_calls: HashMap<String, u32>,
+    // Distinct calls seen, as (tool, parsed arguments, times called)
+    seen: Vec<(String, Value, u32)>,
+}
+
+impl ToolCallGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `max` tool calls in the run.
+    pub fn max_calls(mut self, max: u32) -> Self {
+        self.max_calls = Some(max);
+        self
+    }
+
+    /// Allow at most `max` calls to `tool` in the run.
+    pub fn max_calls_to(mut self, tool: impl Into<String>, max: u32) -> Self {
+        self.tool_limits.insert(tool.into(), max);
+        self
+    }
+
+    /// Allow the same call, same tool with equal JSON arguments, at most `max` times.
+    pub fn max_repeats(mut self, max: u32) -> Self {
+        self.max_repeats = Some(max);
+        self
+    }
+
+    /// Count a turn's tool calls against the limits, before running them.
+    ///
+    /// Fails with [`Error::AgentLoopDetected`] if running them would exceed a
+    /// limit; the calls are counted either way.
+    pub fn check(&mut self, calls: &[ToolCall]) -> Result<(), Error> {
+        let mut exceeded = None;
+        for call in calls {
+            let name = &call.function.name;
+            self.calls += 1;
+            let tool_calls = self.tool_calls.entry(name.clone()).or_insert(0);
+            *tool_calls += 1;
+            let tool_calls = *tool_calls;
+            let repeats = self.record(call);
+
+            exceeded = exceeded.or_else(|| {
+                if let Some(max) = self.max_calls.filter(|&max| self.calls > max) {
+                    Some(format!("more than {max} tool calls"))
+                } else if let Some(max) =
+                    self.tool_limits.get(name).filter(|&&max| tool_calls > max)
+                {
+                    Some(format!("{name} called more than {max} times"))
+                } else if self.max_repeats.is_some_and(|max| repeats > max) {
+                    Some(format!(
+                        "{name} called {repeats} times with arguments {}",
+                        call.function.arguments
+                    ))
+                } else {
+                    None
+                }
+            });
+        }
+        exceeded.map_or(Ok(()), |reason| Err(Error::AgentLoopDetected(reason)))
+    }
+
+    /// Tool calls counted so far.
+    pub fn calls(&self) -> u32 {
+        self.calls
+    }
+
+    /// Calls to `tool` counted so far.
+    pub fn calls_to(&self, tool: &str) -> u32 {
+        self.tool_calls.get(tool).copied().unwrap_or(0)
+    }
+
+    /// Forget the counts, keeping the limits, to start another run.
+    pub fn reset(&mut self) {
+        self.calls = 0;
+        self.tool_calls.clear();
+        self.seen.clear();
+    }
+
+    /// Count `call` among the distinct calls; returns how often it was made.
+    fn record(&mut self, call: &ToolCall) -> u32 {
+        // Compare parsed arguments so key order and whitespace don't matter
+        let arguments = serde_json::from_str(&call.function.arguments)
+            .unwrap_or_else(|_| Value::String(call.function.arguments.clone()));
+        let name = &call.function.name;
+        match self
+            .seen
+            .iter_mut()
+            .find(|(tool, args, _)| tool == name && *args == arguments)
+        {
+            Some((_, _, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                self.seen.push((name.clone(), arguments, 1));
+                1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FunctionCall;
+
+    fn call(name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: format!("call_{name}"),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut guard = ToolCallGuard::new()
+            .max_calls(4)
+            .max_calls_to("fetch", 1)
+            .max_repeats(2);
+
+        // Same arguments in another key order
+        guard
+            .check(&[call("search", r#"{"q":"a","n":1}"#)])
+            .unwrap();
+        let err = guard
+            .check(&[
+                call("search", r#"{"n": 1, "q": "a"}"#),
+                call("search", r#"{"n":1,"q":"a"}"#),
+            ])
+            .unwrap_err();
+        assert!(err.to_string().contains("search called 3 times"), "{err}");
+        assert_eq!(guard.calls_to("search"), 3);
+
+        guard.reset();
+        guard.check(&[call("fetch", "{}")]).unwrap();
+        let err = guard.check(&[call("fetch", r#"{"url":"b"}"#)]).unwrap_err();
+        assert!(
+            err.to_string().contains("fetch called more than 1 times"),
+            "{err}"
+        );
+
+        guard.reset();
+        let turn: Vec<_> = (0..5)
+            .map(|i| call("search", &format!("{{\"q\":{i}}}")))
+            .collect();
+        let err = guard.check(&turn).unwrap_err();
+        assert!(
+            matches!(err, Error::AgentLoopDetected(ref reason) if reason == "more than 4 tool calls")
+        );
+    }
+}
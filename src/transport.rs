@@ -0,0 +1,242 @@
+//! Pluggable HTTP transport.
+//!
+//! The client sends every request through an [`HttpTransport`]: a POST with a
+//! JSON body that resolves to a status, headers, and a streaming body. The
+//! default [`ReqwestTransport`] is built from the
+//! [`ClientBuilder`](crate::ClientBuilder) connection settings; plug in another
+//! stack (hyper, isahc, custom mTLS) or canned responses for tests with
+//! [`ClientBuilder::transport`](crate::ClientBuilder::transport).
+//!
+//! To route requests through a relay gateway that shouldn't see them in the
+//! clear, encrypt bodies on the way out and decrypt them on the way back with
+//! a [`PayloadCipher`], set with
+//! [`ClientBuilder::encryption`](crate::ClientBuilder::encryption) or wrapped
+//! around a transport with [`EncryptedTransport`].
+//!
+//! With the `unix-socket` feature, the default transport also serves base URLs
+//! like `unix:///var/run/llama.sock` over Unix domain sockets.
+
+#[cfg(all(unix, feature = "unix-socket"))]
+mod unix;
+
+use crate::error::Error;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::{Stream, StreamExt};
+use reqwest::header::HeaderMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Response body as a stream of byte chunks.
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>;
+
+/// A request to send: always a POST with a JSON body.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub url: String,
+    /// Provider headers, including authentication and content type.
+    pub headers: HeaderMap,
+    /// Serialized JSON body.
+    pub body: Bytes,
+    /// Time allowed for the whole exchange, reading the body included.
+    pub timeout: Duration,
+}
+
+/// A response whose body is read incrementally.
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: ByteStream,
+}
+
+impl HttpResponse {
+    /// Build a response from a complete body, e.g. in tests.
+    pub fn from_bytes(status: u16, headers: HeaderMap, body: impl Into<Bytes>) -> Self {
+        let body = body.into();
+        Self {
+            status,
+            headers,
+            body: Box::pin(futures::stream::once(async move { Ok(body) })),
+        }
+    }
+
+    /// Whether the status is 2xx.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Read the whole body as (lossy) UTF-8 text.
+    pub async fn text(mut self) -> Result<String, Error> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.body.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+impl std::fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends HTTP requests for a [`Client`](crate::Client).
+///
+/// Equivalent to `async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error>`,
+/// boxed so the transport can be shared as `Arc<dyn HttpTransport>`.
+///
+/// Return error statuses as responses; the client turns them into errors and
+/// decides on retries. Report failures before a response as [`Error::Timeout`]
+/// or [`Error::Transport`] (`retryable` when the request never reached the server).
+pub trait HttpTransport: Send + Sync {
+    /// Send one request.
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>>;
+
+    /// Open a pooled connection to `url`'s host ahead of the first request,
+    /// resolving DNS and completing the TLS handshake.
+    ///
+    /// Any HTTP response counts as success. The default does nothing, for
+    /// transports without connection reuse.
+    fn warmup<'a>(&'a self, _url: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for std::sync::Arc<T> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        (**self).send(request)
+    }
+
+    fn warmup<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        (**self).warmup(url)
+    }
+}
+
+/// Encrypts request payloads before they're sent and decrypts response
+/// payloads before they're parsed.
+///
+/// Both methods see the whole message, so a cipher can add headers (a key ID,
+/// a nonce) to the request and read them from the response. Requests are
+/// encrypted per attempt, so retries don't reuse a nonce. Every response goes
+/// through [`decrypt`](Self::decrypt), error statuses included; return it
+/// unchanged if the gateway sends those in the clear.
+///
+/// Streaming responses arrive in chunks whose boundaries the gateway and the
+/// network choose: a cipher that seals frames should buffer the body stream
+/// until a whole frame is in, and hand out the decrypted SSE bytes.
+pub trait PayloadCipher: Send + Sync {
+    /// Encrypt the body of `request` in place.
+    fn encrypt(&self, request: &mut HttpRequest) -> Result<(), Error>;
+
+    /// Decrypt the body of `response`, typically by wrapping its stream.
+    fn decrypt(&self, response: HttpResponse) -> Result<HttpResponse, Error>;
+}
+
+impl<T: PayloadCipher + ?Sized> PayloadCipher for Arc<T> {
+    fn encrypt(&self, request: &mut HttpRequest) -> Result<(), Error> {
+        (**self).encrypt(request)
+    }
+
+    fn decrypt(&self, response: HttpResponse) -> Result<HttpResponse, Error> {
+        (**self).decrypt(response)
+    }
+}
+
+/// A transport that encrypts requests and decrypts responses of another
+/// transport with a [`PayloadCipher`].
+pub struct EncryptedTransport<T> {
+    inner: T,
+    cipher: Arc<dyn PayloadCipher>,
+}
+
+impl<T: HttpTransport> EncryptedTransport<T> {
+    pub fn new(inner: T, cipher: impl PayloadCipher + 'static) -> Self {
+        Self {
+            inner,
+            cipher: Arc::new(cipher),
+        }
+    }
+}
+
+impl<T: HttpTransport> HttpTransport for EncryptedTransport<T> {
+    fn send(&self, mut request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            self.cipher.encrypt(&mut request)?;
+            let response = self.inner.send(request).await?;
+            self.cipher.decrypt(response)
+        })
+    }
+
+    fn warmup<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        self.inner.warmup(url)
+    }
+}
+
+/// Default transport backed by a `reqwest::Client`.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Use an existing `reqwest` client.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            if request.url.starts_with("unix://") {
+                #[cfg(all(unix, feature = "unix-socket"))]
+                return unix::send(request).await;
+                #[cfg(not(all(unix, feature = "unix-socket")))]
+                return Err(Error::Config(
+                    "unix socket URLs require the unix-socket feature".into(),
+                ));
+            }
+
+            let resp = self
+                .client
+                .post(request.url)
+                .headers(request.headers)
+                .body(request.body)
+                .timeout(request.timeout)
+                .send()
+                .await
+                .map_err(send_error)?;
+
+            Ok(HttpResponse {
+                status: resp.status().as_u16(),
+                headers: resp.headers().clone(),
+                body: Box::pin(resp.bytes_stream().map(|chunk| chunk.map_err(Error::Http))),
+            })
+        })
+    }
+
+    fn warmup<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            // A local socket has no handshake worth doing early
+            if url.starts_with("unix://") {
+                return Ok(());
+            }
+            // The bodiless response hands the connection back to the pool
+            self.client.head(url).send().await.map_err(send_error)?;
+            Ok(())
+        })
+    }
+}
+
+fn send_error(e: reqwest::Error) -> Error {
+    if e.is_timeout() {
+        Error::Timeout
+    } else {
+        Error::Http(e)
+    }
+}
@@ -0,0 +1,134 @@
+//! HTTP/1.1 over Unix domain sockets (`unix-socket` feature).
+//!
+//! A base URL like `unix:///var/run/llama.sock` targets the server listening on
+//! that socket. Provider paths are appended as usual, so the socket is the
+//! shortest prefix of the URL path that exists and isn't a directory:
+//! `unix:///var/run/llama.sock/v1/chat/completions` sends `POST /v1/chat/completions`
+//! to `/var/run/llama.sock`.
+
+use super::{HttpRequest, HttpResponse};
+use crate::error::Error;
+use futures::StreamExt;
+use http_body_util::{BodyDataStream, Full};
+use hyper_util::rt::TokioIo;
+use std::path::PathBuf;
+
+/// URL scheme of Unix domain socket base URLs.
+pub(crate) const SCHEME: &str = "unix://";
+
+/// Send a request to the socket named by its `unix://` URL.
+pub(crate) async fn send(request: HttpRequest) -> Result<HttpResponse, Error> {
+    let (socket, path) = split_url(&request.url)?;
+
+    let stream = tokio::net::UnixStream::connect(&socket)
+        .await
+        .map_err(|e| Error::Transport {
+            message: format!("{}: {e}", socket.display()),
+            retryable: true,
+        })?;
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| transport_error(&e))?;
+    // Drives the connection until the response body is read or dropped
+    tokio::spawn(conn);
+
+    let mut req = http::Request::post(path)
+        .header(http::header::HOST, "localhost")
+        .body(Full::new(request.body))
+        .map_err(|e| Error::Config(format!("unix socket request: {e}")))?;
+    req.headers_mut().extend(request.headers);
+
+    let resp = sender
+        .send_request(req)
+        .await
+        .map_err(|e| transport_error(&e))?;
+    let (parts, body) = resp.into_parts();
+
+    Ok(HttpResponse {
+        status: parts.status.as_u16(),
+        headers: parts.headers,
+        body: Box::pin(
+            BodyDataStream::new(body).map(|chunk| chunk.map_err(|e| transport_error(&e))),
+        ),
+    })
+}
+
+/// Split `unix:///path/to.sock/http/path` into the socket path and the HTTP path.
+fn split_url(url: &str) -> Result<(PathBuf, String), Error> {
+    let full = url
+        .strip_prefix(SCHEME)
+        .ok_or_else(|| Error::Config(format!("not a unix socket URL: {url}")))?;
+
+    let mut socket = PathBuf::from("/");
+    for (i, segment) in full.split('/').enumerate().filter(|(_, s)| !s.is_empty()) {
+        socket.push(segment);
+        if !socket.is_dir() {
+            if !socket.exists() {
+                break;
+            }
+            let rest = full.split('/').skip(i + 1).collect::<Vec<_>>().join("/");
+            return Ok((socket, format!("/{rest}")));
+        }
+    }
+    Err(Error::Config(format!("no unix socket found in {url}")))
+}
+
+fn transport_error(e: &hyper::Error) -> Error {
+    Error::Transport {
+        message: e.to_string(),
+        retryable: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_url() {
+        let dir = std::env::temp_dir().join(format!("uds-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("llm.sock");
+        std::fs::write(&socket, b"").unwrap();
+
+        let url = format!("unix://{}/v1/chat/completions", socket.display());
+        let (path, http_path) = split_url(&url).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(path, socket);
+        assert_eq!(http_path, "/v1/chat/completions");
+        assert!(split_url("unix:///nonexistent/llm.sock/v1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let socket = std::env::temp_dir().join(format!("uds-{}.sock", fastrand::u64(..)));
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = conn.read(&mut buf).await.unwrap();
+            conn.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+
+        let resp = send(HttpRequest {
+            url: format!("unix://{}/v1/chat/completions", socket.display()),
+            headers: http::HeaderMap::new(),
+            body: bytes::Bytes::from_static(b"{}"),
+            timeout: std::time::Duration::from_secs(5),
+        })
+        .await
+        .unwrap();
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.text().await.unwrap(), "ok");
+
+        let request = server.await.unwrap();
+        std::fs::remove_file(&socket).unwrap();
+        assert!(request.starts_with("POST /v1/chat/completions HTTP/1.1"));
+    }
+}
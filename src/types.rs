@@ -1,6 +1,8 @@
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use std::borrow::Cow;
+use std::time::Duration;
 
 /// Message role in conversation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -23,6 +25,10 @@ pub struct Message {
     pub tool_call_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// Set by [`Message::assistant_prefill`]: the model continues this message
+    /// instead of answering after it.
+    #[serde(skip)]
+    pub prefill: bool,
 }
 
 impl Message {
@@ -34,6 +40,7 @@ impl Message {
             name: None,
             tool_call_id: None,
             tool_calls: None,
+            prefill: false,
         }
     }
 
@@ -45,6 +52,7 @@ impl Message {
             name: None,
             tool_call_id: None,
             tool_calls: None,
+            prefill: false,
         }
     }
 
@@ -56,6 +64,35 @@ impl Message {
             name: None,
             tool_call_id: None,
             tool_calls: None,
+            prefill: false,
+        }
+    }
+
+    /// Create the start of the assistant's reply for the model to continue,
+    /// e.g. `{"` to force JSON. It must be the last message.
+    ///
+    /// Claude and vLLM continue it directly; other providers are asked to
+    /// continue it by a final user instruction. Either way the response holds
+    /// only the continuation.
+    pub fn assistant_prefill(content: impl Into<String>) -> Self {
+        Self {
+            prefill: true,
+            ..Self::assistant(content)
+        }
+    }
+
+    /// Create an assistant message that called tools, for replaying a tool-use turn.
+    pub fn assistant_with_tool_calls(
+        content: impl Into<String>,
+        tool_calls: Vec<ToolCall>,
+    ) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: MessageContent::Text(content.into()),
+            name: None,
+            tool_call_id: None,
+            tool_calls: Some(tool_calls),
+            prefill: false,
         }
     }
 
@@ -67,6 +104,7 @@ impl Message {
             name: None,
             tool_call_id: Some(tool_call_id.into()),
             tool_calls: None,
+            prefill: false,
         }
     }
 }
@@ -102,6 +140,10 @@ pub enum ContentPart {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageUrl {
     pub url: String,
+    /// Fidelity: `"low"`, `"high"` or `"auto"`. Lower detail costs fewer input tokens.
+    ///
+    /// Sent as `detail` to OpenAI-compatible providers and as the request's
+    /// `mediaResolution` to Gemini; Claude has no equivalent and ignores it.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detail: Option<String>,
 }
@@ -117,6 +159,9 @@ pub struct Usage {
     /// Tokens written to cache (Anthropic).
     #[serde(default)]
     pub cache_creation_input_tokens: u32,
+    /// Web searches the provider ran (Claude `server_tool_use`).
+    #[serde(default)]
+    pub web_search_requests: u32,
 }
 
 impl Usage {
@@ -136,6 +181,7 @@ impl Usage {
         self.cache_creation_input_tokens = self
             .cache_creation_input_tokens
             .max(other.cache_creation_input_tokens);
+        self.web_search_requests = self.web_search_requests.max(other.web_search_requests);
     }
 }
 
@@ -155,10 +201,182 @@ pub enum FinishReason {
 #[derive(Debug, Clone)]
 pub struct CompletionResult {
     pub content: String,
+    /// The model's reasoning before its answer, when the provider returns it
+    /// (DeepSeek `reasoning_content`).
+    pub reasoning: Option<String>,
     pub usage: Usage,
     pub model: String,
     pub finish_reason: FinishReason,
     pub tool_calls: Vec<ToolCall>,
+    /// Tools the provider ran itself, e.g. web searches, in call order.
+    pub server_tool_calls: Vec<ServerToolCall>,
+    /// Log probabilities of the generated tokens, when requested and supported.
+    pub logprobs: Option<Vec<TokenLogProb>>,
+    /// Explanation given when the model refused to answer (OpenAI structured outputs).
+    pub refusal: Option<String>,
+    /// Safety classifier verdicts on the output (Gemini).
+    pub safety_ratings: Option<Vec<SafetyRating>>,
+    /// Sources spans of the content draw on: web pages (Claude, OpenAI), files
+    /// found by file search (OpenAI) or recited sources (Gemini).
+    pub citations: Option<Vec<Citation>>,
+    /// The stop sequence that ended the output, as reported by the provider
+    /// (Claude, vLLM) or found echoed at the end of the content.
+    pub stop_sequence: Option<String>,
+    /// The stream failed, was aborted, or wasn't read to the end; the other
+    /// fields hold only what arrived before that.
+    pub incomplete: bool,
+    /// Where the request's time went; set for requests sent by the client.
+    pub timings: Option<RequestTimings>,
+}
+
+impl CompletionResult {
+    /// Note which of `stop` ends the content, if the provider echoed it, and
+    /// with `trim` remove it from the content.
+    ///
+    /// The client does this for its own requests; call it on results parsed
+    /// with a provider directly.
+    pub fn resolve_stop_sequence(&mut self, stop: &[String], trim: bool) {
+        if self.finish_reason != FinishReason::Stop {
+            return;
+        }
+        let echoed = stop
+            .iter()
+            .filter(|sequence| !sequence.is_empty() && self.content.ends_with(sequence.as_str()))
+            .max_by_key(|sequence| sequence.len());
+        if let Some(sequence) = echoed {
+            if trim {
+                self.content.truncate(self.content.len() - sequence.len());
+            }
+            self.stop_sequence.get_or_insert_with(|| sequence.clone());
+        }
+    }
+}
+
+/// Phases of one request, for telling client-side waits, network and
+/// provider latency apart.
+///
+/// Connection setup (DNS, TCP, TLS) has no phase of its own: the transport
+/// doesn't report it, so it lands in [`time_to_first_byte`](Self::time_to_first_byte)
+/// of requests that open a new connection. Pre-open connections with
+/// [`Client::warmup`](crate::Client::warmup) to keep it off the first request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestTimings {
+    /// Waiting for a slot from the client's rate limiter.
+    pub queued: Duration,
+    /// Failed attempts before the one that succeeded.
+    pub retries: u32,
+    /// Time spent on failed attempts and the backoff between them.
+    pub retry_time: Duration,
+    /// From sending the successful attempt to its response headers: connection
+    /// setup if any, the round trip, and the provider's queueing and prefill.
+    pub time_to_first_byte: Duration,
+    /// From the response headers to the end of the body: generation, for streams.
+    /// Still running for a stream that hasn't ended.
+    pub stream: Duration,
+    /// From sending the request to the end of the body, including time spent in
+    /// the SDK itself.
+    pub total: Duration,
+}
+
+impl RequestTimings {
+    /// Time in none of the phases: building the request, parsing the response,
+    /// and other work in the SDK.
+    pub fn overhead(&self) -> Duration {
+        self.total
+            .saturating_sub(self.queued)
+            .saturating_sub(self.retry_time)
+            .saturating_sub(self.time_to_first_byte)
+            .saturating_sub(self.stream)
+    }
+}
+
+/// Safety classifier verdict for one harm category.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafetyRating {
+    /// Harm category, e.g. `HARM_CATEGORY_DANGEROUS_CONTENT`.
+    pub category: String,
+    /// Likelihood of harm, e.g. `NEGLIGIBLE` or `HIGH`.
+    pub probability: String,
+    /// Whether this rating caused content to be blocked.
+    pub blocked: bool,
+}
+
+/// Source attributed to a span of the output.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Citation {
+    /// Start of the cited span, in bytes of the output.
+    pub start_index: Option<u32>,
+    /// End of the cited span (exclusive); the start again for a citation of
+    /// one position (OpenAI file citations).
+    pub end_index: Option<u32>,
+    pub uri: Option<String>,
+    pub title: Option<String>,
+    pub license: Option<String>,
+    /// File the span draws on, found by file search (OpenAI).
+    pub file_id: Option<String>,
+}
+
+/// A call to a tool the provider runs itself, with its outcome.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerToolCall {
+    pub id: String,
+    /// Tool name, e.g. `web_search`.
+    pub name: String,
+    /// Arguments the model chose, e.g. `{"query": "..."}`.
+    pub input: serde_json::Value,
+    /// Pages a web search found; empty until the results arrive.
+    #[serde(default)]
+    pub results: Vec<WebSearchResult>,
+    /// Passages a file search retrieved, when requested.
+    #[serde(default)]
+    pub files: Vec<FileSearchResult>,
+    /// Error code when the tool failed, e.g. `max_uses_exceeded`.
+    pub error: Option<String>,
+}
+
+/// A page found by a web search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebSearchResult {
+    pub url: String,
+    pub title: String,
+    /// How old the page is, as reported by the search (`"2 days ago"`).
+    pub page_age: Option<String>,
+}
+
+/// A passage retrieved from a file by a file search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSearchResult {
+    pub file_id: String,
+    pub filename: Option<String>,
+    /// Relevance of the passage to the query.
+    pub score: Option<f64>,
+    pub text: Option<String>,
+}
+
+/// Log probability of one generated token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogProb {
+    pub token: String,
+    pub logprob: f64,
+    /// Most likely tokens at this position, most likely first.
+    #[serde(default, alias = "top_logprobs")]
+    pub top_alternatives: Vec<TopLogProb>,
+}
+
+/// A candidate token at a position, with its log probability.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopLogProb {
+    pub token: String,
+    pub logprob: f64,
+}
+
+/// Result of an embedding request.
+#[derive(Debug, Clone)]
+pub struct EmbeddingResult {
+    /// One vector per input, in input order.
+    pub embeddings: Vec<Vec<f32>>,
+    pub usage: Usage,
+    pub model: String,
 }
 
 /// Kind of streaming chunk.
@@ -169,6 +387,15 @@ pub enum ChunkKind {
     Ping,
     ToolDelta,
     Thinking,
+    /// Spoken output; see [`StreamChunk::audio`].
+    Audio,
+    /// Part of a refusal; see [`StreamChunk::refusal`].
+    Refusal,
+    /// A frame of a generated image; see [`StreamChunk::image`].
+    ImageDelta,
+    /// A tool the provider runs itself was called, or returned its results;
+    /// see [`StreamChunk::server_tool_call`].
+    ServerTool,
     Unknown,
 }
 
@@ -176,24 +403,63 @@ pub enum ChunkKind {
 #[derive(Debug, Clone)]
 pub struct StreamChunk {
     pub kind: ChunkKind,
-    /// Text content - may be borrowed (zero-copy) or owned (after unescape).
+    /// Text content - shared with the parse buffer or owned (after unescape).
     text_data: TextData,
     pub finish_reason: Option<FinishReason>,
     pub usage: Option<Usage>,
     pub tool_call_delta: Option<ToolCallDelta>,
+    /// Audio output fragment of [`ChunkKind::Audio`] chunks.
+    pub audio: Option<AudioDelta>,
+    /// Refusal text fragment of [`ChunkKind::Refusal`] chunks.
+    pub refusal: Option<String>,
+    /// Image frame of [`ChunkKind::ImageDelta`] chunks.
+    pub image: Option<ImageDelta>,
+    /// Server tool call of [`ChunkKind::ServerTool`] chunks.
+    pub server_tool_call: Option<ServerToolCall>,
+    /// The stop sequence that ended the output, on the chunk with the finish reason.
+    pub stop_sequence: Option<String>,
+    /// Latest safety classifier verdicts (Gemini).
+    pub safety_ratings: Option<Vec<SafetyRating>>,
+    /// Sources spans of the content draw on, as the provider reports them:
+    /// with the text (Gemini), at the end of a text block (Claude) or once
+    /// per citation (OpenAI).
+    pub citations: Option<Vec<Citation>>,
+    /// Log probabilities of the tokens in this chunk, when requested.
+    pub logprobs: Option<Vec<TokenLogProb>>,
+    /// Byte offset of this chunk's text in the accumulated content (token mode).
+    pub byte_offset: Option<usize>,
+    /// Provider-specific data without a common field (e.g. Groq `x_groq`,
+    /// Cerebras `time_info`, OpenAI `obfuscation`).
+    pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+    /// SSE `event:` field of the event this chunk was parsed from.
+    pub sse_event: Option<String>,
+    /// SSE `id:` field of the event this chunk was parsed from.
+    pub sse_id: Option<String>,
+    /// Position of the event in the stream, when the provider numbers events
+    /// (OpenAI `sequence_number`).
+    pub sequence_number: Option<u64>,
+    /// Which of the response's outputs this chunk belongs to: OpenAI
+    /// `output_index`, Claude content block `index`, Gemini candidate or
+    /// Chat Completions choice `index`.
+    pub output_index: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
-#[allow(dead_code)] // Borrowed reserved for future zero-copy optimization
 enum TextData {
     Empty,
-    /// Zero-copy view into the original buffer (future optimization).
-    Borrowed {
-        start: usize,
-        len: usize,
-    },
-    /// Owned after JSON unescape.
-    Owned(String),
+    /// Non-empty UTF-8: a slice of the SSE frame when the JSON string needed no
+    /// unescaping, otherwise the unescaped `String`'s buffer.
+    Shared(Bytes),
+}
+
+impl TextData {
+    fn from_string(text: String) -> Self {
+        if text.is_empty() {
+            TextData::Empty
+        } else {
+            TextData::Shared(Bytes::from(text))
+        }
+    }
 }
 
 impl StreamChunk {
@@ -205,6 +471,20 @@ impl StreamChunk {
             finish_reason: None,
             usage: None,
             tool_call_delta: None,
+            audio: None,
+            refusal: None,
+            image: None,
+            server_tool_call: None,
+            stop_sequence: None,
+            safety_ratings: None,
+            citations: None,
+            logprobs: None,
+            byte_offset: None,
+            extensions: None,
+            sse_event: None,
+            sse_id: None,
+            sequence_number: None,
+            output_index: None,
         }
     }
 
@@ -212,17 +492,39 @@ impl StreamChunk {
     pub fn text_owned(text: String) -> Self {
         Self {
             kind: ChunkKind::Text,
-            text_data: if text.is_empty() {
-                TextData::Empty
-            } else {
-                TextData::Owned(text)
-            },
+            text_data: TextData::from_string(text),
             finish_reason: None,
             usage: None,
             tool_call_delta: None,
+            audio: None,
+            refusal: None,
+            image: None,
+            server_tool_call: None,
+            stop_sequence: None,
+            safety_ratings: None,
+            citations: None,
+            logprobs: None,
+            byte_offset: None,
+            extensions: None,
+            sse_event: None,
+            sse_id: None,
+            sequence_number: None,
+            output_index: None,
         }
     }
 
+    /// Set the event's position in the stream.
+    pub fn with_sequence_number(mut self, sequence_number: Option<u64>) -> Self {
+        self.sequence_number = sequence_number;
+        self
+    }
+
+    /// Set the output the chunk belongs to.
+    pub fn with_output_index(mut self, output_index: Option<u32>) -> Self {
+        self.output_index = output_index;
+        self
+    }
+
     /// Create a usage-only chunk.
     pub fn usage(usage: Usage) -> Self {
         Self {
@@ -231,30 +533,96 @@ impl StreamChunk {
             finish_reason: None,
             usage: Some(usage),
             tool_call_delta: None,
+            audio: None,
+            refusal: None,
+            image: None,
+            server_tool_call: None,
+            stop_sequence: None,
+            safety_ratings: None,
+            citations: None,
+            logprobs: None,
+            byte_offset: None,
+            extensions: None,
+            sse_event: None,
+            sse_id: None,
+            sequence_number: None,
+            output_index: None,
         }
     }
 
+    /// Create a text chunk from `text` found inside `frame`, sharing the
+    /// frame's memory instead of copying.
+    ///
+    /// Parsers use this with [`SseEvent::frame`](crate::sse::SseEvent::frame)
+    /// for strings that needed no unescaping. Text outside `frame` is copied.
+    pub fn text_shared(frame: &Bytes, text: &str) -> Self {
+        let range = frame.as_ptr_range();
+        let inside =
+            range.start <= text.as_ptr() && text.as_bytes().as_ptr_range().end <= range.end;
+        let mut chunk = Self::empty(ChunkKind::Text);
+        chunk.text_data = if text.is_empty() {
+            TextData::Empty
+        } else if inside {
+            TextData::Shared(frame.slice_ref(text.as_bytes()))
+        } else {
+            TextData::Shared(Bytes::copy_from_slice(text.as_bytes()))
+        };
+        chunk
+    }
+
     /// Get text content if any. Returns Cow for zero-copy when possible.
     #[inline]
     pub fn text(&self) -> Option<Cow<'_, str>> {
         match &self.text_data {
             TextData::Empty => None,
-            TextData::Borrowed { .. } => None, // Would need buffer reference
-            TextData::Owned(s) if s.is_empty() => None,
-            TextData::Owned(s) => Some(Cow::Borrowed(s.as_str())),
+            // SAFETY: `Shared` only ever holds bytes copied or sliced from a `&str`
+            #[allow(unsafe_code)]
+            TextData::Shared(bytes) => Some(Cow::Borrowed(unsafe {
+                std::str::from_utf8_unchecked(bytes)
+            })),
+        }
+    }
+
+    /// Replace the text content; an empty string removes it.
+    pub fn set_text(&mut self, text: String) {
+        self.text_data = TextData::from_string(text);
+    }
+
+    /// Text content as a shared byte buffer, e.g. to forward it without copying.
+    pub fn text_bytes(&self) -> Option<&Bytes> {
+        match &self.text_data {
+            TextData::Empty => None,
+            TextData::Shared(bytes) => Some(bytes),
         }
     }
 
     /// Get owned text, consuming the chunk.
+    ///
+    /// Reuses the buffer of text created from a `String`; text shared with a
+    /// parse buffer is copied.
     pub fn into_text(self) -> Option<String> {
         match self.text_data {
             TextData::Empty => None,
-            TextData::Borrowed { .. } => None,
-            TextData::Owned(s) if s.is_empty() => None,
-            TextData::Owned(s) => Some(s),
+            // SAFETY: `Shared` only ever holds bytes copied or sliced from a `&str`
+            #[allow(unsafe_code)]
+            TextData::Shared(bytes) => Some(unsafe { String::from_utf8_unchecked(bytes.into()) }),
         }
     }
 
+    /// Create a refusal chunk.
+    pub fn refusal(refusal: String) -> Self {
+        let mut chunk = Self::empty(ChunkKind::Refusal);
+        chunk.refusal = Some(refusal);
+        chunk
+    }
+
+    /// Create an image frame chunk.
+    pub fn image(image: ImageDelta) -> Self {
+        let mut chunk = Self::empty(ChunkKind::ImageDelta);
+        chunk.image = Some(image);
+        chunk
+    }
+
     /// Set finish reason.
     pub fn with_finish_reason(mut self, reason: FinishReason) -> Self {
         self.finish_reason = Some(reason);
@@ -266,6 +634,101 @@ impl StreamChunk {
         self.usage = Some(usage);
         self
     }
+
+    /// Add a provider-specific extension value.
+    pub fn with_extension(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extensions
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value);
+        self
+    }
+
+    /// Get a provider-specific extension value.
+    pub fn extension(&self, key: &str) -> Option<&serde_json::Value> {
+        self.extensions.as_ref()?.get(key)
+    }
+
+    /// Split into one text chunk per token of [`logprobs`](Self::logprobs), with
+    /// byte offsets counted from `offset`.
+    ///
+    /// Each chunk's text is its token's part of the chunk text; the last one
+    /// keeps the remaining fields (finish reason, usage, ...). From the first
+    /// token that isn't the next part of the text, e.g. a byte-fallback token
+    /// (`\xe2\x80`) of a character split across tokens, the rest of the text
+    /// stays in one chunk with the remaining logprobs. Chunks without logprobs
+    /// are returned whole.
+    pub fn into_tokens(mut self, offset: usize) -> Vec<StreamChunk> {
+        let text = match self.text() {
+            Some(text) if self.logprobs.as_ref().is_some_and(|l| !l.is_empty()) => {
+                text.into_owned()
+            }
+            text => {
+                if text.is_some() {
+                    self.byte_offset = Some(offset);
+                }
+                return vec![self];
+            }
+        };
+
+        let mut offset = offset;
+        let mut rest = text.as_str();
+        let mut logprobs = self.logprobs.take().unwrap_or_default().into_iter();
+        let mut tokens = Vec::with_capacity(logprobs.len());
+        while let Some(logprob) = logprobs.next() {
+            if logprobs.len() == 0 || !rest.starts_with(logprob.token.as_str()) {
+                self.kind = ChunkKind::Text;
+                self.byte_offset = Some(offset);
+                self.text_data = TextData::from_string(rest.to_string());
+                self.logprobs = Some(std::iter::once(logprob).chain(logprobs).collect());
+                tokens.push(self);
+                break;
+            }
+            let (part, tail) = rest.split_at(logprob.token.len());
+            rest = tail;
+            let mut token = StreamChunk::empty(ChunkKind::Text);
+            token.byte_offset = Some(offset);
+            offset += part.len();
+            token.text_data = TextData::from_string(part.to_string());
+            token.logprobs = Some(vec![logprob]);
+            tokens.push(token);
+        }
+        tokens
+    }
+}
+
+/// A grounding document supplied alongside the conversation (AI21 `documents`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<std::collections::BTreeMap<String, String>>,
+}
+
+impl Document {
+    /// Create a document from its text content.
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            id: None,
+            metadata: None,
+        }
+    }
+
+    /// Set the document ID.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Add a metadata key/value pair.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
 }
 
 /// Tool/function definition.
@@ -289,9 +752,25 @@ impl Tool {
                 name: name.into(),
                 description: Some(description.into()),
                 parameters: Some(parameters),
+                strict: None,
             },
         }
     }
+
+    /// Enable strict schema adherence (OpenAI structured tool calling).
+    ///
+    /// Strict mode requires every object in the schema to list all of its
+    /// properties as `required` and to set `additionalProperties: false`;
+    /// the parameters schema is rewritten accordingly, with the optional
+    /// properties made nullable.
+    #[must_use]
+    pub fn strict(mut self) -> Self {
+        if let Some(parameters) = &mut self.function.parameters {
+            crate::schema::normalize(parameters, crate::schema::SchemaDialect::OpenAIStrict);
+        }
+        self.function.strict = Some(true);
+        self
+    }
 }
 
 /// Function definition for tool calling.
@@ -302,6 +781,9 @@ pub struct FunctionDef {
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parameters: Option<serde_json::Value>,
+    /// Guarantee arguments match `parameters` exactly (OpenAI strict mode).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 /// A tool call in the response.
@@ -327,13 +809,51 @@ impl FunctionCall {
     }
 }
 
+/// Fragment of streamed audio output.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct AudioDelta {
+    /// Audio response ID, for referring to the audio in later turns.
+    pub id: Option<String>,
+    /// Base64-encoded audio bytes in the requested format.
+    pub data: Option<String>,
+    /// Transcript of the spoken output.
+    pub transcript: Option<String>,
+    /// Unix time after which `id` can no longer be referenced.
+    pub expires_at: Option<u64>,
+}
+
+/// Frame of a streamed generated image.
+///
+/// Each frame is a complete image, refined over the previous one: render the
+/// latest as a preview until the final frame arrives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageDelta {
+    /// Image generation call ID, shared by the frames of one image.
+    pub id: String,
+    /// Base64-encoded image in the requested output format.
+    pub data: String,
+    /// Position of a preview frame, starting at 0; `None` for the final image.
+    pub partial_index: Option<u32>,
+}
+
+impl ImageDelta {
+    /// Whether this is the finished image rather than a preview.
+    pub fn is_final(&self) -> bool {
+        self.partial_index.is_none()
+    }
+}
+
 /// Delta for streaming tool calls.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ToolCallDelta {
     pub index: usize,
     pub id: Option<String>,
     pub function_name: Option<String>,
     pub function_arguments: Option<String>,
+    /// Complete arguments, sent by some providers when the call is done
+    /// (OpenAI's `function_call_arguments.done`). They replace the
+    /// accumulated fragments, which a lost or repeated delta corrupts.
+    pub final_arguments: Option<String>,
 }
 
 /// Accumulator for building tool calls from deltas.
@@ -347,6 +867,21 @@ struct ToolCallBuilder {
     id: String,
     name: String,
     arguments: String,
+    // No more argument fragments will come
+    closed: bool,
+}
+
+impl ToolCallBuilder {
+    fn to_tool_call(&self) -> ToolCall {
+        ToolCall {
+            id: self.id.clone(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: self.name.clone(),
+                arguments: self.arguments.clone(),
+            },
+        }
+    }
 }
 
 impl ToolCallAccumulator {
@@ -356,17 +891,55 @@ impl ToolCallAccumulator {
         while self.calls.len() <= delta.index {
             self.calls.push(ToolCallBuilder::default());
         }
+        // Calls stream one after another: a later one starting ends the earlier ones
+        for earlier in &mut self.calls[..delta.index] {
+            earlier.closed = true;
+        }
 
         let builder = &mut self.calls[delta.index];
+        // Providers send the ID and name whole, once or repeated on every delta
         if let Some(id) = &delta.id {
-            builder.id.push_str(id);
+            if builder.id.is_empty() {
+                builder.id.push_str(id);
+            }
         }
         if let Some(name) = &delta.function_name {
-            builder.name.push_str(name);
+            if builder.name.is_empty() {
+                builder.name.push_str(name);
+            }
         }
         if let Some(args) = &delta.function_arguments {
             builder.arguments.push_str(args);
         }
+        if let Some(args) = &delta.final_arguments {
+            if builder.arguments != *args {
+                builder.arguments.clone_from(args);
+            }
+            builder.closed = true;
+        }
+    }
+
+    /// Tool calls whose arguments are complete, without consuming the accumulator:
+    /// those followed by another call or sent with their final arguments, and
+    /// those whose arguments already form a whole JSON value.
+    pub fn completed(&self) -> Vec<ToolCall> {
+        self.calls
+            .iter()
+            .filter(|b| !b.id.is_empty())
+            .filter(|b| {
+                b.closed || serde_json::from_str::<serde::de::IgnoredAny>(&b.arguments).is_ok()
+            })
+            .map(ToolCallBuilder::to_tool_call)
+            .collect()
+    }
+
+    /// All tool calls so far, the last one possibly with partial arguments.
+    pub fn current(&self) -> Vec<ToolCall> {
+        self.calls
+            .iter()
+            .filter(|b| !b.id.is_empty())
+            .map(ToolCallBuilder::to_tool_call)
+            .collect()
     }
 
     /// Finalize into completed tool calls.
@@ -442,4 +1015,101 @@ mod tests {
         assert_eq!(a.input_tokens, 10);
         assert_eq!(a.output_tokens, 20);
     }
+
+    #[test]
+    fn test_into_tokens_multibyte() {
+        // The emoji is split across two byte-fallback tokens
+        let logprobs = ["Hi", " ", r"\xf0\x9f\x98", r"\x80", "!"]
+            .map(|token| TokenLogProb {
+                token: token.to_string(),
+                logprob: -0.5,
+                top_alternatives: Vec::new(),
+            })
+            .to_vec();
+        let mut chunk = StreamChunk::text_owned("Hi \u{1F600}!".to_string());
+        chunk.logprobs = Some(logprobs);
+        chunk.finish_reason = Some(FinishReason::Stop);
+
+        let tokens = chunk.into_tokens(10);
+        let parts: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.text().unwrap().into_owned(), t.byte_offset.unwrap()))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("Hi".to_string(), 10),
+                (" ".to_string(), 12),
+                ("\u{1F600}!".to_string(), 13),
+            ]
+        );
+        let last = tokens.last().unwrap();
+        assert_eq!(last.logprobs.as_ref().unwrap().len(), 3);
+        assert_eq!(last.finish_reason, Some(FinishReason::Stop));
+    }
+
+    #[test]
+    fn test_resolve_stop_sequence() {
+        let mut result = CompletionResult {
+            content: "SELECT 1;\n###".to_string(),
+            reasoning: None,
+            usage: Usage::default(),
+            model: String::new(),
+            finish_reason: FinishReason::Stop,
+            tool_calls: Vec::new(),
+            server_tool_calls: Vec::new(),
+            logprobs: None,
+            refusal: None,
+            safety_ratings: None,
+            citations: None,
+            stop_sequence: None,
+            incomplete: false,
+            timings: None,
+        };
+        let stop = ["#".to_string(), "###".to_string()];
+
+        result.resolve_stop_sequence(&stop, false);
+        assert_eq!(result.stop_sequence.as_deref(), Some("###"));
+        assert_eq!(result.content, "SELECT 1;\n###");
+
+        result.resolve_stop_sequence(&stop, true);
+        assert_eq!(result.content, "SELECT 1;\n");
+    }
+
+    #[test]
+    fn test_strict_tool_schema() {
+        let tool = Tool::function(
+            "search",
+            "Search",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string"},
+                    "filter": {
+                        "type": "object",
+                        "properties": {"lang": {"type": "string"}}
+                    }
+                },
+                "required": ["query"]
+            }),
+        )
+        .strict();
+
+        let params = tool.function.parameters.as_ref().unwrap();
+        assert_eq!(tool.function.strict, Some(true));
+        assert_eq!(params["additionalProperties"], false);
+        assert_eq!(params["required"], serde_json::json!(["filter", "query"]));
+        assert_eq!(
+            params["properties"]["filter"]["additionalProperties"],
+            false
+        );
+        assert_eq!(
+            params["properties"]["filter"]["type"],
+            serde_json::json!(["object", "null"])
+        );
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap()["function"]["strict"],
+            true
+        );
+    }
 }
@@ -0,0 +1,302 @@
+//! Durable usage recording.
+//!
+//! A [`UsageStore`] registered with
+//! [`ClientBuilder::usage_store`](crate::ClientBuilder::usage_store) receives
+//! one [`RequestRecord`] per completed request or stream, with its token usage
+//! and cost. Streams that are cancelled or fail mid-way are recorded too,
+//! with the usage reported before they stopped. Bundled stores append JSON
+//! lines to a file ([`FileUsageStore`]) or insert rows into SQLite
+//! (`SqliteUsageStore`, `sqlite` feature).
+
+use crate::client::{ErrorHook, WarningHook};
+use crate::cost::{PricingRegistry, RequestOutcome};
+use crate::error::Error;
+use crate::tenant::Tenant;
+use crate::types::{FinishReason, Usage};
+use crate::warning::Warning;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Usage and cost of one request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestRecord {
+    /// Completion time in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub provider: String,
+    pub model: String,
+    pub usage: Usage,
+    /// Cost in USD, if the model has known pricing.
+    pub cost_usd: Option<f64>,
+    pub finish_reason: Option<FinishReason>,
+    /// Provider request ID, if the response carried one.
+    pub request_id: Option<String>,
+    pub streamed: bool,
+    /// How the request ended; records written before outcomes were tracked read as successes.
+    #[serde(default)]
+    pub outcome: RequestOutcome,
+}
+
+/// Destination for [`RequestRecord`]s.
+///
+/// Equivalent to `async fn append(&self, record: RequestRecord) -> Result<(), Error>`,
+/// boxed so the store can be shared as `Arc<dyn UsageStore>`.
+///
+/// The future runs on the client's runtime. Stores doing blocking I/O should
+/// move it off the async worker threads (e.g. `tokio::task::spawn_blocking`),
+/// as the bundled stores do.
+pub trait UsageStore: Send + Sync {
+    /// Persist one record.
+    fn append(&self, record: RequestRecord) -> BoxFuture<'_, Result<(), Error>>;
+}
+
+/// Appends records as JSON lines to a file.
+#[derive(Debug)]
+pub struct FileUsageStore {
+    file: Arc<Mutex<File>>,
+}
+
+impl FileUsageStore {
+    /// Open (or create) `path` for appending.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::Config(format!("usage store: {e}")))?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+}
+
+impl UsageStore for FileUsageStore {
+    fn append(&self, record: RequestRecord) -> BoxFuture<'_, Result<(), Error>> {
+        let file = Arc::clone(&self.file);
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(&record).map_err(|e| Error::parse(e.to_string()))?;
+            line.push(b'\n');
+
+            blocking(move || {
+                // One small write per record; a single `write_all` keeps lines whole
+                let mut file = file
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                file.write_all(&line)
+                    .map_err(|e| Error::Config(format!("usage store: {e}")))
+            })
+            .await
+        })
+    }
+}
+
+/// Inserts records into a `requests` table of a SQLite database.
+#[cfg(feature = "sqlite")]
+pub struct SqliteUsageStore {
+    conn: Arc<Mutex<rusqlite::Connection>>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteUsageStore {
+    /// Open (or create) the database at `path` and create the table if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let conn = rusqlite::Connection::open(path).map_err(|e| sqlite_error(&e))?;
+        Self::from_connection(conn)
+    }
+
+    /// Use an existing connection, creating the table if needed.
+    pub fn from_connection(conn: rusqlite::Connection) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS requests (
+                timestamp_ms INTEGER NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                input_tokens INTEGER NOT NULL,
+                output_tokens INTEGER NOT NULL,
+                cache_read_input_tokens INTEGER NOT NULL,
+                cache_creation_input_tokens INTEGER NOT NULL,
+                cost_usd REAL,
+                finish_reason TEXT,
+                request_id TEXT,
+                streamed INTEGER NOT NULL,
+                outcome TEXT NOT NULL DEFAULT 'success'
+            )",
+        )
+        .map_err(|e| sqlite_error(&e))?;
+        // Tables created before outcomes were tracked lack the column
+        let has_outcome = conn
+            .prepare("SELECT 1 FROM pragma_table_info('requests') WHERE name = 'outcome'")
+            .and_then(|mut statement| statement.exists([]))
+            .map_err(|e| sqlite_error(&e))?;
+        if !has_outcome {
+            conn.execute_batch(
+                "ALTER TABLE requests ADD COLUMN outcome TEXT NOT NULL DEFAULT 'success'",
+            )
+            .map_err(|e| sqlite_error(&e))?;
+        }
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl UsageStore for SqliteUsageStore {
+    fn append(&self, record: RequestRecord) -> BoxFuture<'_, Result<(), Error>> {
+        let conn = Arc::clone(&self.conn);
+        Box::pin(blocking(move || {
+            let finish_reason = record
+                .finish_reason
+                .and_then(|r| serde_json::to_value(r).ok())
+                .and_then(|v| v.as_str().map(str::to_string));
+            // A busy database blocks here, off the async worker threads
+            let conn = conn
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            conn.execute(
+                "INSERT INTO requests VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                rusqlite::params![
+                    i64::try_from(record.timestamp_ms).unwrap_or(i64::MAX),
+                    record.provider,
+                    record.model,
+                    record.usage.input_tokens,
+                    record.usage.output_tokens,
+                    record.usage.cache_read_input_tokens,
+                    record.usage.cache_creation_input_tokens,
+                    record.cost_usd,
+                    finish_reason,
+                    record.request_id,
+                    record.streamed,
+                    record.outcome.as_str(),
+                ],
+            )
+            .map_err(|e| sqlite_error(&e))?;
+            Ok(())
+        }))
+    }
+}
+
+/// Run a store's blocking I/O on tokio's blocking thread pool.
+async fn blocking(io: impl FnOnce() -> Result<(), Error> + Send + 'static) -> Result<(), Error> {
+    tokio::task::spawn_blocking(io)
+        .await
+        .map_err(|e| Error::Config(format!("usage store: {e}")))?
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_error(e: &rusqlite::Error) -> Error {
+    Error::Config(format!("usage store: {e}"))
+}
+
+/// Builds and submits records for one request, and charges its tenant.
+#[derive(Clone)]
+pub(crate) struct UsageRecorder {
+    pub(crate) store: Option<Arc<dyn UsageStore>>,
+    pub(crate) tenant: Option<Arc<Tenant>>,
+    pub(crate) pricing: Arc<PricingRegistry>,
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) on_warning: Option<WarningHook>,
+    pub(crate) provider: String,
+}
+
+impl UsageRecorder {
+    /// Charge the tenant and submit a record in the background; store failures
+    /// go to the error hook.
+    pub(crate) fn record(
+        &self,
+        model: &str,
+        usage: &Usage,
+        finish_reason: Option<FinishReason>,
+        request_id: Option<&str>,
+        streamed: bool,
+        outcome: RequestOutcome,
+    ) {
+        let key = format!("{}/{model}", self.provider);
+        let cost = self.pricing.calculate_cost(&key, usage);
+        if cost.is_none() {
+            if let Some(on_warning) = &self.on_warning {
+                on_warning(&Warning::PricingMissing { model: key });
+            }
+        }
+        if let Some(tenant) = &self.tenant {
+            tenant.record(usage, cost.as_ref(), outcome);
+        }
+        let Some(store) = &self.store else {
+            return;
+        };
+
+        let record = RequestRecord {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX)),
+            provider: self.provider.clone(),
+            model: model.to_string(),
+            usage: usage.clone(),
+            cost_usd: cost.map(|cost| cost.total()),
+            finish_reason,
+            request_id: request_id.map(str::to_string),
+            streamed,
+            outcome,
+        };
+
+        // Streams may end outside a runtime (e.g. dropped during teardown)
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let store = Arc::clone(store);
+        let on_error = self.on_error.clone();
+        runtime.spawn(async move {
+            if let Err(e) = store.append(record).await {
+                if let Some(on_error) = on_error {
+                    on_error(&e);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_store() {
+        let path = std::env::temp_dir().join(format!("usage-{}.jsonl", fastrand::u64(..)));
+        let store = FileUsageStore::open(&path).unwrap();
+
+        for model in ["gpt-4o", "gpt-4o-mini"] {
+            store
+                .append(RequestRecord {
+                    timestamp_ms: 0,
+                    provider: "openai".to_string(),
+                    model: model.to_string(),
+                    usage: Usage {
+                        input_tokens: 10,
+                        output_tokens: 5,
+                        ..Default::default()
+                    },
+                    cost_usd: Some(0.001),
+                    finish_reason: Some(FinishReason::Stop),
+                    request_id: None,
+                    streamed: false,
+                    outcome: RequestOutcome::Success,
+                })
+                .await
+                .unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<RequestRecord> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].model, "gpt-4o-mini");
+        assert_eq!(records[1].usage.output_tokens, 5);
+    }
+}
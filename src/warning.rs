@@ -0,0 +1,42 @@
+//! Non-fatal conditions worth knowing about.
+//!
+//! Some things go wrong without failing the request: a parameter the provider
+//! doesn't support is left out, a stream event of a new type is skipped, a
+//! cost can't be computed. Register
+//! [`ClientBuilder::on_warning`](crate::ClientBuilder::on_warning) to log or
+//! count them.
+
+use crate::providers::Parameter;
+use std::fmt;
+
+/// A condition that didn't fail the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A request parameter the provider doesn't support was left out of the request.
+    ParameterDropped {
+        provider: String,
+        parameter: Parameter,
+    },
+    /// A stream event of an unknown type was skipped.
+    UnknownEvent { provider: String, event: String },
+    /// No pricing is known for the model, so its cost isn't recorded.
+    PricingMissing { model: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParameterDropped {
+                provider,
+                parameter,
+            } => write!(
+                f,
+                "{provider} does not support {parameter}; it was not sent"
+            ),
+            Self::UnknownEvent { provider, event } => {
+                write!(f, "skipped unknown {provider} stream event {event:?}")
+            }
+            Self::PricingMissing { model } => write!(f, "no pricing for {model}; cost unknown"),
+        }
+    }
+}